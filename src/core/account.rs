@@ -0,0 +1,700 @@
+//! Folds the account, portfolio, position and P&L callbacks into a state per account and model
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Error, Formatter};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::account_value::{AccountValue, AccountValueName};
+use crate::core::client::POISONED_MUTEX;
+use crate::core::common::UNSET_DOUBLE;
+use crate::core::contract::Contract;
+use crate::core::fa::FaGroup;
+
+//----------------------------------------------------------------------------------------------
+/// Returns None for the values TWS sends as UNSET_DOUBLE when they are not available
+fn available(value: f64) -> Option<f64> {
+    if value == UNSET_DOUBLE || value.is_nan() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+//==================================================================================================
+/// Identifies the state of an account, or of a model of an account.  The model code is empty for
+/// the callbacks that are not for a model.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccountKey {
+    pub account: String,
+    pub model_code: String,
+}
+
+impl AccountKey {
+    pub fn new(account: &str, model_code: &str) -> Self {
+        AccountKey {
+            account: account.to_string(),
+            model_code: model_code.to_string(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Identifies an account, without model
+    pub fn account(account: &str) -> Self {
+        AccountKey::new(account, "")
+    }
+}
+
+impl Display for AccountKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        if self.model_code.is_empty() {
+            write!(f, "{}", self.account)
+        } else {
+            write!(f, "{}/{}", self.account, self.model_code)
+        }
+    }
+}
+
+//==================================================================================================
+/// A position of an account.  The market values are only known for the accounts subscribed to with
+/// req_account_updates, or the positions subscribed to with req_pnl_single.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountPosition {
+    pub contract: Contract,
+    pub position: f64,
+    pub average_cost: f64,
+    pub market_price: Option<f64>,
+    pub market_value: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+    pub realized_pnl: Option<f64>,
+    pub daily_pnl: Option<f64>,
+}
+
+impl AccountPosition {
+    //----------------------------------------------------------------------------------------------
+    /// Creates a position from the values of the update_portfolio callback
+    pub fn from_portfolio(
+        contract: Contract,
+        position: f64,
+        market_price: f64,
+        market_value: f64,
+        average_cost: f64,
+        unrealized_pnl: f64,
+        realized_pnl: f64,
+    ) -> Self {
+        AccountPosition {
+            contract,
+            position,
+            average_cost,
+            market_price: available(market_price),
+            market_value: available(market_value),
+            unrealized_pnl: available(unrealized_pnl),
+            realized_pnl: available(realized_pnl),
+            daily_pnl: None,
+        }
+    }
+}
+
+//==================================================================================================
+/// Profit and loss of an account or model, from the pnl callback
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AccountPnl {
+    pub daily: Option<f64>,
+    pub unrealized: Option<f64>,
+    pub realized: Option<f64>,
+}
+
+//==================================================================================================
+/// The state of an account or model
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountSnapshot {
+    pub key: AccountKey,
+    /// Values by key, such as "NetLiquidation", then by currency.  Values without currency, such as
+    /// "AccountType", are under the empty currency.
+    pub values: BTreeMap<String, BTreeMap<String, String>>,
+    /// Open positions by contract id
+    pub positions: BTreeMap<i32, AccountPosition>,
+    pub pnl: AccountPnl,
+    /// Time of the last account update, as sent to update_account_time, such as "15:32"
+    pub account_time: String,
+    /// When the store last received an update of the account
+    pub last_update: Option<DateTime<Utc>>,
+    /// Whether account_download_end was received for the account
+    pub download_complete: bool,
+}
+
+impl AccountSnapshot {
+    pub fn new(key: AccountKey) -> Self {
+        AccountSnapshot {
+            key,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a value of the account, such as value("NetLiquidation", "USD")
+    pub fn value(&self, key: &str, currency: &str) -> Option<&str> {
+        self.values.get(key)?.get(currency).map(String::as_str)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a numeric value of the account, such as balance("CashBalance", "EUR")
+    pub fn balance(&self, key: &str, currency: &str) -> Option<f64> {
+        self.value(key, currency)?.parse::<f64>().ok()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the numeric values of a key by currency, such as the cash balance in each currency
+    pub fn balances(&self, key: &str) -> BTreeMap<String, f64> {
+        self.values
+            .get(key)
+            .map(|by_currency| {
+                by_currency
+                    .iter()
+                    .filter_map(|(currency, value)| {
+                        Some((currency.clone(), value.parse::<f64>().ok()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a parsed value of the account, such as
+    /// typed_value(&AccountValueName::parse("NetLiquidation-S"), "USD")
+    pub fn typed_value(&self, name: &AccountValueName, currency: &str) -> Option<AccountValue> {
+        let value = self.value(&name.to_string(), currency)?;
+        Some(AccountValue::parse(name, value, currency))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns all the values of the account parsed, with their currency.  Values of unknown keys
+    /// are included.
+    pub fn typed_values(&self) -> Vec<(AccountValueName, String, AccountValue)> {
+        self.values
+            .iter()
+            .flat_map(|(name, by_currency)| {
+                let name = AccountValueName::parse(name);
+                by_currency.iter().map(move |(currency, value)| {
+                    let parsed = AccountValue::parse(&name, value, currency);
+                    (name.clone(), currency.clone(), parsed)
+                })
+            })
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn position(&self, con_id: i32) -> Option<&AccountPosition> {
+        self.positions.get(&con_id)
+    }
+}
+
+//==================================================================================================
+/// A change of the state of an account
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum AccountEvent {
+    Value {
+        key: AccountKey,
+        name: AccountValueName,
+        currency: String,
+        value: AccountValue,
+    },
+    /// A position opened or changed.  Closed positions are sent with a position of 0, and removed.
+    Position {
+        key: AccountKey,
+        position: Box<AccountPosition>,
+    },
+    Pnl {
+        key: AccountKey,
+        pnl: AccountPnl,
+    },
+    Time {
+        key: AccountKey,
+        account_time: String,
+    },
+    DownloadEnd {
+        key: AccountKey,
+    },
+}
+
+//----------------------------------------------------------------------------------------------
+/// Adds two values that may be unavailable, treating unavailable values as 0 unless both are
+fn add_available(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (None, None) => None,
+        _ => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+    }
+}
+
+//==================================================================================================
+/// Positions and P&L of several accounts or models added together
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountRollup {
+    /// Account, model or household name
+    pub name: String,
+    /// Accounts and models added, ordered
+    pub keys: Vec<AccountKey>,
+    /// Positions by contract id.  The average cost is weighted by position, and positions that net
+    /// to 0 are kept.
+    pub positions: BTreeMap<i32, AccountPosition>,
+    pub pnl: AccountPnl,
+}
+
+impl AccountRollup {
+    pub fn new(name: &str) -> Self {
+        AccountRollup {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds the positions and P&L of an account or model
+    pub fn add(&mut self, snapshot: &AccountSnapshot) {
+        if let Err(index) = self.keys.binary_search(&snapshot.key) {
+            self.keys.insert(index, snapshot.key.clone());
+        }
+        for (con_id, position) in &snapshot.positions {
+            let total = self
+                .positions
+                .entry(*con_id)
+                .or_insert_with(|| AccountPosition {
+                    contract: position.contract.clone(),
+                    ..Default::default()
+                });
+            let cost =
+                total.average_cost * total.position + position.average_cost * position.position;
+            total.position += position.position;
+            total.average_cost = if total.position == 0.0 {
+                0.0
+            } else {
+                cost / total.position
+            };
+            total.market_price = total.market_price.or(position.market_price);
+            total.market_value = add_available(total.market_value, position.market_value);
+            total.unrealized_pnl = add_available(total.unrealized_pnl, position.unrealized_pnl);
+            total.realized_pnl = add_available(total.realized_pnl, position.realized_pnl);
+            total.daily_pnl = add_available(total.daily_pnl, position.daily_pnl);
+        }
+        self.pnl = AccountPnl {
+            daily: add_available(self.pnl.daily, snapshot.pnl.daily),
+            unrealized: add_available(self.pnl.unrealized, snapshot.pnl.unrealized),
+            realized: add_available(self.pnl.realized, snapshot.pnl.realized),
+        };
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn position(&self, con_id: i32) -> Option<&AccountPosition> {
+        self.positions.get(&con_id)
+    }
+}
+
+//==================================================================================================
+/// Positions and P&L rolled up by account, by model and by household
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountRollups {
+    /// Rollups by account.  The state of an account without model is used if there is one, else
+    /// the states of its models are added, so that positions are not counted twice.
+    pub by_account: BTreeMap<String, AccountRollup>,
+    /// Rollups of the states with a model, by model code
+    pub by_model: BTreeMap<String, AccountRollup>,
+    /// Rollups of the accounts of each FA group, by group name
+    pub by_household: BTreeMap<String, AccountRollup>,
+}
+
+impl AccountRollups {
+    //----------------------------------------------------------------------------------------------
+    /// Rolls up the states of accounts and models
+    ///
+    /// # Arguments
+    /// * snapshots - the states of the accounts and models
+    /// * households - the FA groups, as sent by receive_fa for FaDataType::GROUPS
+    pub fn build(snapshots: &[AccountSnapshot], households: &[FaGroup]) -> Self {
+        let mut by_account_snapshots: BTreeMap<&str, Vec<&AccountSnapshot>> = BTreeMap::new();
+        for snapshot in snapshots {
+            by_account_snapshots
+                .entry(snapshot.key.account.as_str())
+                .or_default()
+                .push(snapshot);
+        }
+
+        let mut rollups = AccountRollups::default();
+        for (account, account_snapshots) in &mut by_account_snapshots {
+            if account_snapshots
+                .iter()
+                .any(|snapshot| snapshot.key.model_code.is_empty())
+            {
+                account_snapshots.retain(|snapshot| snapshot.key.model_code.is_empty());
+            }
+            let rollup = rollups
+                .by_account
+                .entry(account.to_string())
+                .or_insert_with(|| AccountRollup::new(account));
+            account_snapshots
+                .iter()
+                .for_each(|snapshot| rollup.add(snapshot));
+        }
+
+        for snapshot in snapshots {
+            let model_code = &snapshot.key.model_code;
+            if !model_code.is_empty() {
+                rollups
+                    .by_model
+                    .entry(model_code.clone())
+                    .or_insert_with(|| AccountRollup::new(model_code))
+                    .add(snapshot);
+            }
+        }
+
+        for household in households {
+            let mut rollup = AccountRollup::new(&household.name);
+            household
+                .accounts
+                .iter()
+                .filter_map(|account| by_account_snapshots.get(account.as_str()))
+                .flatten()
+                .for_each(|snapshot| rollup.add(snapshot));
+            rollups.by_household.insert(household.name.clone(), rollup);
+        }
+        rollups
+    }
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct AccountState {
+    accounts: HashMap<AccountKey, AccountSnapshot>,
+    /// Account, model and contract id of the req_pnl and req_pnl_single requests
+    pnl_requests: HashMap<i32, (AccountKey, Option<i32>)>,
+    /// Account of the last update_account_value or update_portfolio, which update_account_time
+    /// applies to
+    current_account: String,
+    /// FA groups of the last receive_fa for FaDataType::GROUPS, which define the households
+    fa_groups: Vec<FaGroup>,
+    subscribers: Vec<Sender<AccountEvent>>,
+}
+
+impl AccountState {
+    //----------------------------------------------------------------------------------------------
+    /// Applies an update to the state of an account and sends the event it returns to the
+    /// subscribers
+    fn update<F: FnOnce(&mut AccountSnapshot) -> AccountEvent>(
+        &mut self,
+        key: AccountKey,
+        update: F,
+    ) {
+        let snapshot = self
+            .accounts
+            .entry(key.clone())
+            .or_insert_with(|| AccountSnapshot::new(key));
+        snapshot.last_update = Some(Utc::now());
+        let event = update(snapshot);
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_value(&mut self, key: AccountKey, name: &str, value: &str, currency: &str) {
+        self.update(key, |snapshot| {
+            snapshot
+                .values
+                .entry(name.to_string())
+                .or_default()
+                .insert(currency.to_string(), value.to_string());
+            let name = AccountValueName::parse(name);
+            let value = AccountValue::parse(&name, value, currency);
+            AccountEvent::Value {
+                key: snapshot.key.clone(),
+                name,
+                currency: currency.to_string(),
+                value,
+            }
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Updates a position with a function, removing it if it is closed
+    fn update_position<F: FnOnce(&mut AccountPosition)>(
+        &mut self,
+        key: AccountKey,
+        con_id: i32,
+        update: F,
+    ) {
+        self.update(key, |snapshot| {
+            let mut position = snapshot.positions.remove(&con_id).unwrap_or_default();
+            position.contract.con_id = con_id;
+            update(&mut position);
+            if position.position != 0.0 {
+                snapshot.positions.insert(con_id, position.clone());
+            }
+            AccountEvent::Position {
+                key: snapshot.key.clone(),
+                position: Box::new(position),
+            }
+        });
+    }
+}
+
+//==================================================================================================
+/// Thread safe store of the state of each account and model, built from the account, portfolio,
+/// position, account summary and P&L callbacks.  The decoder updates it before the matching
+/// Wrapper callback is called.
+#[derive(Debug, Default)]
+pub struct AccountStore {
+    state: Mutex<AccountState>,
+}
+
+impl AccountStore {
+    pub fn new() -> Self {
+        AccountStore {
+            state: Mutex::new(AccountState::default()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the state of an account or model
+    pub fn snapshot(&self, key: &AccountKey) -> Option<AccountSnapshot> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .accounts
+            .get(key)
+            .cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the states of all the accounts and models, taken at the same time and
+    /// ordered by key
+    pub fn snapshots(&self) -> Vec<AccountSnapshot> {
+        let mut snapshots: Vec<AccountSnapshot> = self
+            .state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .accounts
+            .values()
+            .cloned()
+            .collect();
+        snapshots.sort_by(|a, b| a.key.cmp(&b.key));
+        snapshots
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the keys of the accounts and models with a state, ordered
+    pub fn accounts(&self) -> Vec<AccountKey> {
+        let mut keys: Vec<AccountKey> = self
+            .state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .accounts
+            .keys()
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Rolls up the positions and P&L of the accounts and models by account, by model and by
+    /// household, the households being the FA groups received
+    pub fn rollups(&self) -> AccountRollups {
+        let state = self.state.lock().expect(POISONED_MUTEX);
+        let mut snapshots: Vec<AccountSnapshot> = state.accounts.values().cloned().collect();
+        snapshots.sort_by(|a, b| a.key.cmp(&b.key));
+        AccountRollups::build(&snapshots, &state.fa_groups)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn fa_groups(&self) -> Vec<FaGroup> {
+        self.state.lock().expect(POISONED_MUTEX).fa_groups.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the FA groups which define the households
+    pub fn update_fa_groups(&self, groups: Vec<FaGroup>) {
+        self.state.lock().expect(POISONED_MUTEX).fa_groups = groups;
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a channel receiving every change of the accounts
+    pub fn subscribe(&self) -> Receiver<AccountEvent> {
+        let (sender, receiver) = channel();
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .subscribers
+            .push(sender);
+        receiver
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the account of a req_account_updates subscription, which the update times apply to
+    pub fn account_updates_requested(&self, account: &str) {
+        self.state.lock().expect(POISONED_MUTEX).current_account = account.to_string();
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the account, model and contract id of a req_pnl or req_pnl_single request
+    pub(crate) fn expect_pnl(&self, req_id: i32, key: AccountKey, con_id: Option<i32>) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .pnl_requests
+            .insert(req_id, (key, con_id));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub(crate) fn forget_pnl(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .pnl_requests
+            .remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a value of the update_account_value callback
+    pub fn update_account_value(&self, name: &str, value: &str, currency: &str, account: &str) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.current_account = account.to_string();
+        state.update_value(AccountKey::account(account), name, value, currency);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a value of the account_update_multi callback
+    pub fn update_account_multi(
+        &self,
+        account: &str,
+        model_code: &str,
+        name: &str,
+        value: &str,
+        currency: &str,
+    ) {
+        self.state.lock().expect(POISONED_MUTEX).update_value(
+            AccountKey::new(account, model_code),
+            name,
+            value,
+            currency,
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a value of the account_summary callback
+    pub fn update_account_summary(&self, account: &str, tag: &str, value: &str, currency: &str) {
+        self.state.lock().expect(POISONED_MUTEX).update_value(
+            AccountKey::account(account),
+            tag,
+            value,
+            currency,
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a position of the update_portfolio callback, with its market values
+    pub fn update_portfolio(&self, account: &str, position: AccountPosition) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.current_account = account.to_string();
+        let con_id = position.contract.con_id;
+        state.update_position(AccountKey::account(account), con_id, |current| {
+            let daily_pnl = current.daily_pnl;
+            *current = position;
+            current.daily_pnl = daily_pnl;
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a position of the position or position_multi callbacks, keeping the market values
+    /// already known
+    pub fn update_position(
+        &self,
+        key: AccountKey,
+        contract: Contract,
+        position: f64,
+        average_cost: f64,
+    ) {
+        let con_id = contract.con_id;
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .update_position(key, con_id, |current| {
+                current.contract = contract;
+                current.position = position;
+                current.average_cost = average_cost;
+            });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the time of the update_account_time callback, for the account being updated
+    pub fn update_account_time(&self, account_time: &str) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let key = AccountKey::account(&state.current_account.clone());
+        state.update(key, |snapshot| {
+            snapshot.account_time = account_time.to_string();
+            AccountEvent::Time {
+                key: snapshot.key.clone(),
+                account_time: account_time.to_string(),
+            }
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Marks the values and positions of an account as complete
+    pub fn account_download_end(&self, account: &str) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .update(AccountKey::account(account), |snapshot| {
+                snapshot.download_complete = true;
+                AccountEvent::DownloadEnd {
+                    key: snapshot.key.clone(),
+                }
+            });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the values of the pnl callback, for the account or model of its request
+    pub fn update_pnl(&self, req_id: i32, daily: f64, unrealized: f64, realized: f64) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let key = match state.pnl_requests.get(&req_id) {
+            Some((key, None)) => key.clone(),
+            _ => return,
+        };
+        state.update(key, |snapshot| {
+            snapshot.pnl = AccountPnl {
+                daily: available(daily),
+                unrealized: available(unrealized),
+                realized: available(realized),
+            };
+            AccountEvent::Pnl {
+                key: snapshot.key.clone(),
+                pnl: snapshot.pnl,
+            }
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the values of the pnl_single callback, for the position of its request
+    pub fn update_pnl_single(
+        &self,
+        req_id: i32,
+        position: i32,
+        daily: f64,
+        unrealized: f64,
+        realized: f64,
+        value: f64,
+    ) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let (key, con_id) = match state.pnl_requests.get(&req_id) {
+            Some((key, Some(con_id))) => (key.clone(), *con_id),
+            _ => return,
+        };
+        state.update_position(key, con_id, |current| {
+            current.position = position as f64;
+            current.daily_pnl = available(daily);
+            current.unrealized_pnl = available(unrealized);
+            current.realized_pnl = available(realized);
+            current.market_value = available(value);
+        });
+    }
+}
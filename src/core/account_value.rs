@@ -0,0 +1,364 @@
+//! Typed names and values of the update_account_value, account_update_multi and account_summary
+//! callbacks
+use std::fmt::{Display, Error, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+//==================================================================================================
+/// How the value of an account key is parsed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AccountValueKind {
+    /// An amount of money in the currency sent with the value
+    Amount,
+    Number,
+    Boolean,
+    Text,
+}
+
+macro_rules! account_value_keys {
+    ($($(#[$meta:meta])* $variant:ident = $name:literal => $kind:ident,)*) => {
+        //==========================================================================================
+        /// Keys of the account values sent by req_account_updates, req_account_updates_multi and
+        /// req_account_summary, including the keys of the $LEDGER tags.  Keys this enum does not
+        /// know are kept as Other.
+        #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum AccountValueKey {
+            $(
+                $(#[$meta])*
+                $variant,
+            )*
+            Other(String),
+        }
+
+        impl AccountValueKey {
+            //--------------------------------------------------------------------------------------
+            /// Returns the key of a name without segment suffix
+            pub fn from_name(name: &str) -> Self {
+                match name {
+                    $($name => AccountValueKey::$variant,)*
+                    other => AccountValueKey::Other(other.to_string()),
+                }
+            }
+
+            //--------------------------------------------------------------------------------------
+            pub fn name(&self) -> &str {
+                match self {
+                    $(AccountValueKey::$variant => $name,)*
+                    AccountValueKey::Other(name) => name,
+                }
+            }
+
+            //--------------------------------------------------------------------------------------
+            /// Returns how the values of the key are parsed.  Values of unknown keys are parsed
+            /// as amounts if they are numbers sent with a currency.
+            pub fn kind(&self) -> Option<AccountValueKind> {
+                match self {
+                    $(AccountValueKey::$variant => Some(AccountValueKind::$kind),)*
+                    AccountValueKey::Other(_) => None,
+                }
+            }
+        }
+    };
+}
+
+account_value_keys! {
+    /// The account id
+    AccountCode = "AccountCode" => Text,
+    /// "All" for the values of all the accounts
+    AccountOrGroup = "AccountOrGroup" => Text,
+    /// Whether the values are up to date
+    AccountReady = "AccountReady" => Boolean,
+    /// Structure of the account, such as INDIVIDUAL
+    AccountType = "AccountType" => Text,
+    /// Accrued interest
+    AccruedCash = "AccruedCash" => Amount,
+    /// Dividends accrued but not paid
+    AccruedDividend = "AccruedDividend" => Amount,
+    /// Equity with loan value less initial margin
+    AvailableFunds = "AvailableFunds" => Amount,
+    /// Fees billable to the account
+    Billable = "Billable" => Amount,
+    /// Value of the securities that can be bought
+    BuyingPower = "BuyingPower" => Amount,
+    /// Cash balance in a currency
+    CashBalance = "CashBalance" => Amount,
+    /// Display order of the segment in TWS
+    ColumnPrio = "ColumnPrio" => Number,
+    /// Value of the corporate bonds
+    CorporateBondValue = "CorporateBondValue" => Amount,
+    /// Currency of the values sent with it
+    Currency = "Currency" => Text,
+    /// Excess liquidity as a fraction of the net liquidation value
+    Cushion = "Cushion" => Number,
+    /// Day trades left, -1 for unlimited
+    DayTradesRemaining = "DayTradesRemaining" => Number,
+    /// Day trades left tomorrow
+    DayTradesRemainingT1 = "DayTradesRemainingT+1" => Number,
+    /// Day trades left in 2 days
+    DayTradesRemainingT2 = "DayTradesRemainingT+2" => Number,
+    /// Day trades left in 3 days
+    DayTradesRemainingT3 = "DayTradesRemainingT+3" => Number,
+    /// Day trades left in 4 days
+    DayTradesRemainingT4 = "DayTradesRemainingT+4" => Number,
+    /// Cash and marginable securities
+    EquityWithLoanValue = "EquityWithLoanValue" => Amount,
+    /// Equity with loan value less maintenance margin
+    ExcessLiquidity = "ExcessLiquidity" => Amount,
+    /// Rate of the currency to the base currency
+    ExchangeRate = "ExchangeRate" => Number,
+    /// Available funds without discounts
+    FullAvailableFunds = "FullAvailableFunds" => Amount,
+    /// Excess liquidity without discounts
+    FullExcessLiquidity = "FullExcessLiquidity" => Amount,
+    /// Initial margin without discounts
+    FullInitMarginReq = "FullInitMarginReq" => Amount,
+    /// Maintenance margin without discounts
+    FullMaintMarginReq = "FullMaintMarginReq" => Amount,
+    /// Value of the funds
+    FundValue = "FundValue" => Amount,
+    /// Value of the futures options
+    FutureOptionValue = "FutureOptionValue" => Amount,
+    /// Profit and loss of the futures since the last settlement
+    FuturesPnl = "FuturesPNL" => Amount,
+    /// Cash balance of the forex positions
+    FxCashBalance = "FxCashBalance" => Amount,
+    /// Sum of the absolute position values
+    GrossPositionValue = "GrossPositionValue" => Amount,
+    /// Guarantee of the account
+    Guarantee = "Guarantee" => Amount,
+    /// How close the account is to liquidation
+    HighestSeverity = "HighestSeverity" => Number,
+    /// Margin haircut of Indian stocks
+    IndianStockHaircut = "IndianStockHaircut" => Amount,
+    /// Initial margin requirement
+    InitMarginReq = "InitMarginReq" => Amount,
+    /// Value of the structured products
+    IssuerOptionValue = "IssuerOptionValue" => Amount,
+    /// Gross position value over net liquidation value
+    Leverage = "Leverage" => Number,
+    /// Available funds at the next margin change
+    LookAheadAvailableFunds = "LookAheadAvailableFunds" => Amount,
+    /// Excess liquidity at the next margin change
+    LookAheadExcessLiquidity = "LookAheadExcessLiquidity" => Amount,
+    /// Initial margin at the next margin change
+    LookAheadInitMarginReq = "LookAheadInitMarginReq" => Amount,
+    /// Maintenance margin at the next margin change
+    LookAheadMaintMarginReq = "LookAheadMaintMarginReq" => Amount,
+    /// Time of the next margin change, in seconds since the epoch
+    LookAheadNextChange = "LookAheadNextChange" => Number,
+    /// Maintenance margin requirement
+    MaintMarginReq = "MaintMarginReq" => Amount,
+    /// Value of the money market funds
+    MoneyMarketFundValue = "MoneyMarketFundValue" => Amount,
+    /// Value of the mutual funds
+    MutualFundValue = "MutualFundValue" => Amount,
+    /// Dividends receivable less payable
+    NetDividend = "NetDividend" => Amount,
+    /// Value of the account if it was liquidated
+    NetLiquidation = "NetLiquidation" => Amount,
+    /// Net liquidation value in a currency
+    NetLiquidationByCurrency = "NetLiquidationByCurrency" => Amount,
+    /// Uncertainty of the net liquidation value
+    NetLiquidationUncertainty = "NetLiquidationUncertainty" => Amount,
+    /// Whether the values are under review
+    NlvAndMarginInReview = "NLVAndMarginInReview" => Boolean,
+    /// Value of the options
+    OptionMarketValue = "OptionMarketValue" => Amount,
+    /// Value of the shares of pooled accounts
+    PaSharesValue = "PASharesValue" => Amount,
+    /// Value of the physical certificates
+    PhysicalCertificateValue = "PhysicalCertificateValue" => Amount,
+    /// Excess liquidity after the options expire
+    PostExpirationExcess = "PostExpirationExcess" => Amount,
+    /// Margin after the options expire
+    PostExpirationMargin = "PostExpirationMargin" => Amount,
+    /// Equity with loan value of the previous day
+    PreviousDayEquityWithLoanValue = "PreviousDayEquityWithLoanValue" => Amount,
+    /// Equity with loan value of the previous day, as sent by account_summary
+    PreviousEquityWithLoanValue = "PreviousEquityWithLoanValue" => Amount,
+    /// Currency of a $LEDGER value sent with the BASE currency
+    RealCurrency = "RealCurrency" => Text,
+    /// Realized profit and loss
+    RealizedPnl = "RealizedPnL" => Amount,
+    /// Regulation T equity
+    RegTEquity = "RegTEquity" => Amount,
+    /// Regulation T margin
+    RegTMargin = "RegTMargin" => Amount,
+    /// Name of the segment
+    SegmentTitle = "SegmentTitle" => Text,
+    /// Settled cash
+    SettledCash = "SettledCash" => Amount,
+    /// Special memorandum account
+    Sma = "SMA" => Amount,
+    /// Value of the stocks
+    StockMarketValue = "StockMarketValue" => Amount,
+    /// Value of the treasury bills
+    TBillValue = "TBillValue" => Amount,
+    /// Value of the treasury bonds
+    TBondValue = "TBondValue" => Amount,
+    /// Total cash balance, in all currencies
+    TotalCashBalance = "TotalCashBalance" => Amount,
+    /// Cash and futures profit and loss
+    TotalCashValue = "TotalCashValue" => Amount,
+    /// Debit card charges not settled yet
+    TotalDebitCardPendingCharges = "TotalDebitCardPendingCharges" => Amount,
+    /// Trading permissions of the segment, such as STKNOPT
+    TradingType = "TradingType" => Text,
+    /// Unrealized profit and loss
+    UnrealizedPnl = "UnrealizedPnL" => Amount,
+    /// Value of the warrants
+    WarrantValue = "WarrantValue" => Amount,
+    /// Whether portfolio margin what-if is enabled
+    WhatIfPmEnabled = "WhatIfPMEnabled" => Boolean,
+}
+
+impl Display for AccountValueKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.name())
+    }
+}
+
+//==================================================================================================
+/// Segment of an account value, given by the suffix of its name
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AccountSegment {
+    /// The whole account, for names without suffix
+    Total,
+    /// The commodities segment, for names ending with -C
+    Commodities,
+    /// The securities segment, for names ending with -S
+    Securities,
+}
+
+impl AccountSegment {
+    //----------------------------------------------------------------------------------------------
+    pub fn suffix(&self) -> &str {
+        match self {
+            AccountSegment::Total => "",
+            AccountSegment::Commodities => "-C",
+            AccountSegment::Securities => "-S",
+        }
+    }
+}
+
+//==================================================================================================
+/// The name of an account value, such as "NetLiquidation-S", split into its key and segment
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccountValueName {
+    pub key: AccountValueKey,
+    pub segment: AccountSegment,
+}
+
+impl AccountValueName {
+    pub fn new(key: AccountValueKey, segment: AccountSegment) -> Self {
+        AccountValueName { key, segment }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses a name sent by TWS.  Names with an unknown key are kept as AccountValueKey::Other,
+    /// without their suffix.
+    pub fn parse(name: &str) -> Self {
+        let (base, segment) = if let Some(base) = name.strip_suffix("-C") {
+            (base, AccountSegment::Commodities)
+        } else if let Some(base) = name.strip_suffix("-S") {
+            (base, AccountSegment::Securities)
+        } else {
+            (name, AccountSegment::Total)
+        };
+        AccountValueName {
+            key: AccountValueKey::from_name(base),
+            segment,
+        }
+    }
+}
+
+impl From<AccountValueKey> for AccountValueName {
+    fn from(key: AccountValueKey) -> Self {
+        AccountValueName::new(key, AccountSegment::Total)
+    }
+}
+
+impl Display for AccountValueName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}{}", self.key, self.segment.suffix())
+    }
+}
+
+//==================================================================================================
+/// A parsed account value
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AccountValue {
+    Amount {
+        value: f64,
+        currency: String,
+    },
+    Number(f64),
+    Boolean(bool),
+    /// Text values, and values that could not be parsed as their key expects
+    Text(String),
+}
+
+impl AccountValue {
+    //----------------------------------------------------------------------------------------------
+    /// Parses a value according to the kind of its key
+    ///
+    /// # Arguments
+    /// * name - the name of the value
+    /// * value - the value as sent by TWS
+    /// * currency - the currency sent with the value, empty if there is none
+    pub fn parse(name: &AccountValueName, value: &str, currency: &str) -> Self {
+        let trimmed = value.trim();
+        let number = trimmed.parse::<f64>().ok();
+        let kind = name.key.kind().unwrap_or(match number {
+            Some(_) => AccountValueKind::Amount,
+            None => AccountValueKind::Text,
+        });
+        match (kind, number) {
+            (AccountValueKind::Amount, Some(amount)) if !currency.is_empty() => {
+                AccountValue::Amount {
+                    value: amount,
+                    currency: currency.to_string(),
+                }
+            }
+            (AccountValueKind::Amount, Some(number)) | (AccountValueKind::Number, Some(number)) => {
+                AccountValue::Number(number)
+            }
+            (AccountValueKind::Boolean, _) if trimmed.eq_ignore_ascii_case("true") => {
+                AccountValue::Boolean(true)
+            }
+            (AccountValueKind::Boolean, _) if trimmed.eq_ignore_ascii_case("false") => {
+                AccountValue::Boolean(false)
+            }
+            _ => AccountValue::Text(value.to_string()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the value of amounts and numbers
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            AccountValue::Amount { value, .. } => Some(*value),
+            AccountValue::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            AccountValue::Boolean(flag) => Some(*flag),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AccountValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            AccountValue::Amount { value, currency } => write!(f, "{} {}", value, currency),
+            AccountValue::Number(number) => write!(f, "{}", number),
+            AccountValue::Boolean(flag) => write!(f, "{}", flag),
+            AccountValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
@@ -1,4 +1,5 @@
-//! Utility functions that illustrate setting fields related to algo parameters
+//! Utility functions that illustrate setting fields related to algo parameters.  See
+//! algo_strategy for typed parameters that are validated before they are sent
 use crate::core::common::TagValue;
 use crate::core::order::Order;
 
@@ -0,0 +1,1149 @@
+//! Typed, validated parameters for the IB algos, converted to and from the tag values sent in
+//! Order::algo_params
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{TagValue, NO_VALID_ID};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::order::Order;
+
+/// Largest participation rate accepted by the volume based algos, as a fraction of volume
+pub const MAX_PCT_VOL: f64 = 0.5;
+
+//----------------------------------------------------------------------------------------------
+fn invalid_param(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(NO_VALID_ID, &message)
+}
+
+//==================================================================================================
+/// How aggressively the arrival price, close price and balance impact risk algos trade
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RiskAversion {
+    GetDone,
+    Aggressive,
+    Neutral,
+    Passive,
+}
+
+impl Display for RiskAversion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            RiskAversion::GetDone => write!(f, "Get Done"),
+            RiskAversion::Aggressive => write!(f, "Aggressive"),
+            RiskAversion::Neutral => write!(f, "Neutral"),
+            RiskAversion::Passive => write!(f, "Passive"),
+        }
+    }
+}
+
+impl FromStr for RiskAversion {
+    type Err = IBKRApiLibError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "Get Done" => Ok(RiskAversion::GetDone),
+            "Aggressive" => Ok(RiskAversion::Aggressive),
+            "Neutral" => Ok(RiskAversion::Neutral),
+            "Passive" => Ok(RiskAversion::Passive),
+            _ => Err(invalid_param(format!("Unknown risk aversion: {}", text))),
+        }
+    }
+}
+
+//==================================================================================================
+/// Priority of the adaptive algo
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AdaptivePriority {
+    Urgent,
+    Normal,
+    Patient,
+}
+
+impl Display for AdaptivePriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            AdaptivePriority::Urgent => write!(f, "Urgent"),
+            AdaptivePriority::Normal => write!(f, "Normal"),
+            AdaptivePriority::Patient => write!(f, "Patient"),
+        }
+    }
+}
+
+impl FromStr for AdaptivePriority {
+    type Err = IBKRApiLibError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "Urgent" => Ok(AdaptivePriority::Urgent),
+            "Normal" => Ok(AdaptivePriority::Normal),
+            "Patient" => Ok(AdaptivePriority::Patient),
+            _ => Err(invalid_param(format!(
+                "Unknown adaptive priority: {}",
+                text
+            ))),
+        }
+    }
+}
+
+//==================================================================================================
+/// How the TWAP algo prices its child orders
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TwapStrategyType {
+    Marketable,
+    MatchingMidpoint,
+    MatchingSameSide,
+    MatchingLast,
+}
+
+impl Display for TwapStrategyType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            TwapStrategyType::Marketable => write!(f, "Marketable"),
+            TwapStrategyType::MatchingMidpoint => write!(f, "Matching Midpoint"),
+            TwapStrategyType::MatchingSameSide => write!(f, "Matching Same Side"),
+            TwapStrategyType::MatchingLast => write!(f, "Matching Last"),
+        }
+    }
+}
+
+impl FromStr for TwapStrategyType {
+    type Err = IBKRApiLibError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "Marketable" => Ok(TwapStrategyType::Marketable),
+            "Matching Midpoint" => Ok(TwapStrategyType::MatchingMidpoint),
+            "Matching Same Side" => Ok(TwapStrategyType::MatchingSameSide),
+            "Matching Last" => Ok(TwapStrategyType::MatchingLast),
+            _ => Err(invalid_param(format!(
+                "Unknown TWAP strategy type: {}",
+                text
+            ))),
+        }
+    }
+}
+
+//==================================================================================================
+/// Start or end time of an algo, either a time of day ("09:00:00 US/Eastern") or a date and
+/// time ("20161010-12:00:00 GMT").  When time_zone is None TWS uses its own time zone.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AlgoTime {
+    pub date: Option<NaiveDate>,
+    pub time: NaiveTime,
+    pub time_zone: Option<String>,
+}
+
+impl AlgoTime {
+    pub fn new(date: Option<NaiveDate>, time: NaiveTime, time_zone: Option<String>) -> Self {
+        AlgoTime {
+            date,
+            time,
+            time_zone,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns true if this time is known to be strictly before other.  Times in different time
+    /// zones, or where only one of them has a date, cannot be compared and return false.
+    pub fn is_before(&self, other: &AlgoTime) -> bool {
+        self.time_zone == other.time_zone
+            && self.date.is_some() == other.date.is_some()
+            && (self.date, self.time) < (other.date, other.time)
+    }
+}
+
+impl Display for AlgoTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        if let Some(date) = self.date {
+            write!(f, "{}-", date.format("%Y%m%d"))?;
+        }
+        write!(f, "{}", self.time.format("%H:%M:%S"))?;
+        if let Some(time_zone) = &self.time_zone {
+            write!(f, " {}", time_zone)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AlgoTime {
+    type Err = IBKRApiLibError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid_time = || invalid_param(format!("Invalid algo time: {}", text));
+        let mut parts = text.trim().splitn(2, ' ');
+        let date_time = parts.next().unwrap_or_default();
+        let time_zone = parts
+            .next()
+            .map(|time_zone| time_zone.trim().to_string())
+            .filter(|time_zone| !time_zone.is_empty());
+
+        if date_time.contains('-') {
+            let date_time = NaiveDateTime::parse_from_str(date_time, "%Y%m%d-%H:%M:%S")
+                .map_err(|_| invalid_time())?;
+            Ok(AlgoTime::new(
+                Some(date_time.date()),
+                date_time.time(),
+                time_zone,
+            ))
+        } else {
+            let time =
+                NaiveTime::parse_from_str(date_time, "%H:%M:%S").map_err(|_| invalid_time())?;
+            Ok(AlgoTime::new(None, time, time_zone))
+        }
+    }
+}
+
+//==================================================================================================
+/// Reads the tag values of an algo by tag name
+struct TagReader<'a> {
+    strategy: &'static str,
+    values: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> TagReader<'a> {
+    fn new(strategy: &'static str, params: &'a [TagValue]) -> Self {
+        TagReader {
+            strategy,
+            values: params
+                .iter()
+                .map(|param| (param.tag.as_str(), param.value.as_str()))
+                .collect(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses a tag, returning None if it is missing or empty
+    fn optional<T: FromStr>(&self, tag: &str) -> Result<Option<T>, IBKRApiLibError> {
+        match self.values.get(tag).map(|value| value.trim()) {
+            None | Some("") => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                invalid_param(format!(
+                    "{} has an invalid {} value: {}",
+                    self.strategy, tag, value
+                ))
+            }),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn required<T: FromStr>(&self, tag: &str) -> Result<T, IBKRApiLibError> {
+        self.optional(tag)?
+            .ok_or_else(|| invalid_param(format!("{} is missing its {} value", self.strategy, tag)))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Flags are sent as 1/0 but true/false is accepted too.  A missing flag is false.
+    fn flag(&self, tag: &str) -> Result<bool, IBKRApiLibError> {
+        match self.values.get(tag).map(|value| value.trim()) {
+            None | Some("") | Some("0") => Ok(false),
+            Some("1") => Ok(true),
+            Some(value) if value.eq_ignore_ascii_case("true") => Ok(true),
+            Some(value) if value.eq_ignore_ascii_case("false") => Ok(false),
+            Some(value) => Err(invalid_param(format!(
+                "{} has an invalid {} value: {}",
+                self.strategy, tag, value
+            ))),
+        }
+    }
+}
+
+//==================================================================================================
+/// Collects the tag values of an algo, in the order TWS documents them
+#[derive(Default)]
+struct TagWriter {
+    params: Vec<TagValue>,
+}
+
+impl TagWriter {
+    fn value<T: ToString>(mut self, tag: &str, value: T) -> Self {
+        self.params
+            .push(TagValue::new(tag.to_string(), value.to_string()));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn optional<T: ToString>(self, tag: &str, value: &Option<T>) -> Self {
+        match value {
+            Some(value) => self.value(tag, value.to_string()),
+            None => self,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn flag(self, tag: &str, value: bool) -> Self {
+        self.value(tag, value as i32)
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn check_pct_vol(strategy: &str, tag: &str, value: f64) -> Result<(), IBKRApiLibError> {
+    if value > 0.0 && value <= MAX_PCT_VOL {
+        Ok(())
+    } else {
+        Err(invalid_param(format!(
+            "{} {} must be a fraction of volume greater than 0 and at most {}, got {}",
+            strategy, tag, MAX_PCT_VOL, value
+        )))
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn check_optional_pct_vol(
+    strategy: &str,
+    tag: &str,
+    value: Option<f64>,
+) -> Result<(), IBKRApiLibError> {
+    value.map_or(Ok(()), |value| check_pct_vol(strategy, tag, value))
+}
+
+//----------------------------------------------------------------------------------------------
+fn check_positive(strategy: &str, tag: &str, value: f64) -> Result<(), IBKRApiLibError> {
+    if value > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid_param(format!(
+            "{} {} must be greater than 0, got {}",
+            strategy, tag, value
+        )))
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn check_monetary_value(strategy: &str, value: Option<f64>) -> Result<(), IBKRApiLibError> {
+    match value {
+        Some(value) if value < 0.0 => Err(invalid_param(format!(
+            "{} monetaryValue cannot be negative, got {}",
+            strategy, value
+        ))),
+        _ => Ok(()),
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn check_time_range(
+    strategy: &str,
+    start_time: &Option<AlgoTime>,
+    end_time: &Option<AlgoTime>,
+) -> Result<(), IBKRApiLibError> {
+    if let (Some(start_time), Some(end_time)) = (start_time, end_time) {
+        if end_time.is_before(start_time) || end_time == start_time {
+            return Err(invalid_param(format!(
+                "{} end time {} must be after start time {}",
+                strategy, end_time, start_time
+            )));
+        }
+    }
+    Ok(())
+}
+
+//==================================================================================================
+/// Parameters of one algo strategy
+pub trait AlgoParams: Sized {
+    /// Value of Order::algo_strategy for this algo
+    const STRATEGY: &'static str;
+
+    /// Checks that the parameters will be accepted by TWS
+    fn validate(&self) -> Result<(), IBKRApiLibError>;
+
+    /// Converts the parameters to the tag values sent in Order::algo_params
+    fn to_tag_values(&self) -> Vec<TagValue>;
+
+    /// Parses and validates the tag values of Order::algo_params.  Tags that do not belong to
+    /// the algo are ignored.
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError>;
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the parameters if they are valid
+    fn validated(self) -> Result<Self, IBKRApiLibError> {
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+//==================================================================================================
+/// Adaptive algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AdaptiveParams {
+    pub priority: AdaptivePriority,
+}
+
+impl AlgoParams for AdaptiveParams {
+    const STRATEGY: &'static str = "Adaptive";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        Ok(())
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("adaptivePriority", self.priority)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        AdaptiveParams {
+            priority: reader.required("adaptivePriority")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Arrival price algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArrivalPriceParams {
+    pub max_pct_vol: Option<f64>,
+    pub risk_aversion: RiskAversion,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub force_completion: bool,
+    pub allow_past_end_time: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for ArrivalPriceParams {
+    const STRATEGY: &'static str = "ArrivalPx";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_optional_pct_vol(Self::STRATEGY, "maxPctVol", self.max_pct_vol)?;
+        check_time_range(Self::STRATEGY, &self.start_time, &self.end_time)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .optional("maxPctVol", &self.max_pct_vol)
+            .value("riskAversion", self.risk_aversion)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("forceCompletion", self.force_completion)
+            .flag("allowPastEndTime", self.allow_past_end_time)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        ArrivalPriceParams {
+            max_pct_vol: reader.optional("maxPctVol")?,
+            risk_aversion: reader.required("riskAversion")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            force_completion: reader.flag("forceCompletion")?,
+            allow_past_end_time: reader.flag("allowPastEndTime")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Close price algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClosePriceParams {
+    pub max_pct_vol: Option<f64>,
+    pub risk_aversion: RiskAversion,
+    pub start_time: Option<AlgoTime>,
+    pub force_completion: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for ClosePriceParams {
+    const STRATEGY: &'static str = "ClosePx";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_optional_pct_vol(Self::STRATEGY, "maxPctVol", self.max_pct_vol)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .optional("maxPctVol", &self.max_pct_vol)
+            .value("riskAversion", self.risk_aversion)
+            .optional("startTime", &self.start_time)
+            .flag("forceCompletion", self.force_completion)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        ClosePriceParams {
+            max_pct_vol: reader.optional("maxPctVol")?,
+            risk_aversion: reader.required("riskAversion")?,
+            start_time: reader.optional("startTime")?,
+            force_completion: reader.flag("forceCompletion")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Dark ice algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DarkIceParams {
+    pub display_size: i32,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub allow_past_end_time: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for DarkIceParams {
+    const STRATEGY: &'static str = "DarkIce";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_positive(Self::STRATEGY, "displaySize", self.display_size as f64)?;
+        check_time_range(Self::STRATEGY, &self.start_time, &self.end_time)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("displaySize", self.display_size)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("allowPastEndTime", self.allow_past_end_time)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        DarkIceParams {
+            display_size: reader.required("displaySize")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            allow_past_end_time: reader.flag("allowPastEndTime")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Percentage of volume algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PctVolParams {
+    pub pct_vol: f64,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub no_take_liq: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for PctVolParams {
+    const STRATEGY: &'static str = "PctVol";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_pct_vol(Self::STRATEGY, "pctVol", self.pct_vol)?;
+        check_time_range(Self::STRATEGY, &self.start_time, &self.end_time)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("pctVol", self.pct_vol)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("noTakeLiq", self.no_take_liq)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        PctVolParams {
+            pct_vol: reader.required("pctVol")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            no_take_liq: reader.flag("noTakeLiq")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Price variant percentage of volume algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PriceVariantPctVolParams {
+    pub pct_vol: f64,
+    pub delta_pct_vol: Option<f64>,
+    pub min_pct_vol_4px: Option<f64>,
+    pub max_pct_vol_4px: Option<f64>,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub no_take_liq: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for PriceVariantPctVolParams {
+    const STRATEGY: &'static str = "PctVolPx";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_pct_vol(Self::STRATEGY, "pctVol", self.pct_vol)?;
+        check_optional_pct_vol(Self::STRATEGY, "deltaPctVol", self.delta_pct_vol)?;
+        check_optional_pct_vol(Self::STRATEGY, "minPctVol4Px", self.min_pct_vol_4px)?;
+        check_optional_pct_vol(Self::STRATEGY, "maxPctVol4Px", self.max_pct_vol_4px)?;
+        if let (Some(min), Some(max)) = (self.min_pct_vol_4px, self.max_pct_vol_4px) {
+            if min > max {
+                return Err(invalid_param(format!(
+                    "{} minPctVol4Px {} is greater than maxPctVol4Px {}",
+                    Self::STRATEGY,
+                    min,
+                    max
+                )));
+            }
+        }
+        check_time_range(Self::STRATEGY, &self.start_time, &self.end_time)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("pctVol", self.pct_vol)
+            .optional("deltaPctVol", &self.delta_pct_vol)
+            .optional("minPctVol4Px", &self.min_pct_vol_4px)
+            .optional("maxPctVol4Px", &self.max_pct_vol_4px)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("noTakeLiq", self.no_take_liq)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        PriceVariantPctVolParams {
+            pct_vol: reader.required("pctVol")?,
+            delta_pct_vol: reader.optional("deltaPctVol")?,
+            min_pct_vol_4px: reader.optional("minPctVol4Px")?,
+            max_pct_vol_4px: reader.optional("maxPctVol4Px")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            no_take_liq: reader.flag("noTakeLiq")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Parameters of the size and time variant percentage of volume algos, which move the
+/// participation rate from start_pct_vol to end_pct_vol
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VariantPctVolParams {
+    pub start_pct_vol: f64,
+    pub end_pct_vol: f64,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub no_take_liq: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl VariantPctVolParams {
+    fn validate_for(&self, strategy: &str) -> Result<(), IBKRApiLibError> {
+        check_pct_vol(strategy, "startPctVol", self.start_pct_vol)?;
+        check_pct_vol(strategy, "endPctVol", self.end_pct_vol)?;
+        check_time_range(strategy, &self.start_time, &self.end_time)?;
+        check_monetary_value(strategy, self.monetary_value)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("startPctVol", self.start_pct_vol)
+            .value("endPctVol", self.end_pct_vol)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("noTakeLiq", self.no_take_liq)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn read(reader: &TagReader) -> Result<Self, IBKRApiLibError> {
+        Ok(VariantPctVolParams {
+            start_pct_vol: reader.required("startPctVol")?,
+            end_pct_vol: reader.required("endPctVol")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            no_take_liq: reader.flag("noTakeLiq")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        })
+    }
+}
+
+//==================================================================================================
+/// Size variant percentage of volume algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SizeVariantPctVolParams(pub VariantPctVolParams);
+
+impl AlgoParams for SizeVariantPctVolParams {
+    const STRATEGY: &'static str = "PctVolSz";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        self.0.validate_for(Self::STRATEGY)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        self.0.to_tag_values()
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        SizeVariantPctVolParams(VariantPctVolParams::read(&reader)?).validated()
+    }
+}
+
+//==================================================================================================
+/// Time variant percentage of volume algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimeVariantPctVolParams(pub VariantPctVolParams);
+
+impl AlgoParams for TimeVariantPctVolParams {
+    const STRATEGY: &'static str = "PctVolTm";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        self.0.validate_for(Self::STRATEGY)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        self.0.to_tag_values()
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        TimeVariantPctVolParams(VariantPctVolParams::read(&reader)?).validated()
+    }
+}
+
+//==================================================================================================
+/// TWAP algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TwapParams {
+    pub strategy_type: TwapStrategyType,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub allow_past_end_time: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for TwapParams {
+    const STRATEGY: &'static str = "Twap";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_time_range(Self::STRATEGY, &self.start_time, &self.end_time)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("strategyType", self.strategy_type)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("allowPastEndTime", self.allow_past_end_time)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        TwapParams {
+            strategy_type: reader.required("strategyType")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            allow_past_end_time: reader.flag("allowPastEndTime")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// VWAP algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VwapParams {
+    pub max_pct_vol: Option<f64>,
+    pub start_time: Option<AlgoTime>,
+    pub end_time: Option<AlgoTime>,
+    pub allow_past_end_time: bool,
+    pub no_take_liq: bool,
+    pub monetary_value: Option<f64>,
+}
+
+impl AlgoParams for VwapParams {
+    const STRATEGY: &'static str = "Vwap";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_optional_pct_vol(Self::STRATEGY, "maxPctVol", self.max_pct_vol)?;
+        check_time_range(Self::STRATEGY, &self.start_time, &self.end_time)?;
+        check_monetary_value(Self::STRATEGY, self.monetary_value)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .optional("maxPctVol", &self.max_pct_vol)
+            .optional("startTime", &self.start_time)
+            .optional("endTime", &self.end_time)
+            .flag("allowPastEndTime", self.allow_past_end_time)
+            .flag("noTakeLiq", self.no_take_liq)
+            .optional("monetaryValue", &self.monetary_value)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        VwapParams {
+            max_pct_vol: reader.optional("maxPctVol")?,
+            start_time: reader.optional("startTime")?,
+            end_time: reader.optional("endTime")?,
+            allow_past_end_time: reader.flag("allowPastEndTime")?,
+            no_take_liq: reader.flag("noTakeLiq")?,
+            monetary_value: reader.optional("monetaryValue")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Accumulate/distribute algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccumulateDistributeParams {
+    pub component_size: i32,
+    /// Seconds between child orders
+    pub time_between_orders: i32,
+    pub randomize_time_20: bool,
+    pub randomize_size_55: bool,
+    pub give_up: Option<i32>,
+    pub catch_up: bool,
+    pub wait_for_fill: bool,
+    pub active_time_start: Option<AlgoTime>,
+    pub active_time_end: Option<AlgoTime>,
+}
+
+impl AlgoParams for AccumulateDistributeParams {
+    const STRATEGY: &'static str = "AD";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_positive(Self::STRATEGY, "ComponentSize", self.component_size as f64)?;
+        check_positive(
+            Self::STRATEGY,
+            "TimeBetweenOrders",
+            self.time_between_orders as f64,
+        )?;
+        if let Some(give_up) = self.give_up {
+            if give_up < 0 {
+                return Err(invalid_param(format!(
+                    "{} GiveUp cannot be negative, got {}",
+                    Self::STRATEGY,
+                    give_up
+                )));
+            }
+        }
+        check_time_range(
+            Self::STRATEGY,
+            &self.active_time_start,
+            &self.active_time_end,
+        )
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("ComponentSize", self.component_size)
+            .value("TimeBetweenOrders", self.time_between_orders)
+            .flag("RandomizeTime20", self.randomize_time_20)
+            .flag("RandomizeSize55", self.randomize_size_55)
+            .optional("GiveUp", &self.give_up)
+            .flag("CatchUp", self.catch_up)
+            .flag("WaitForFill", self.wait_for_fill)
+            .optional("activeTimeStart", &self.active_time_start)
+            .optional("activeTimeEnd", &self.active_time_end)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        AccumulateDistributeParams {
+            component_size: reader.required("ComponentSize")?,
+            time_between_orders: reader.required("TimeBetweenOrders")?,
+            randomize_time_20: reader.flag("RandomizeTime20")?,
+            randomize_size_55: reader.flag("RandomizeSize55")?,
+            give_up: reader.optional("GiveUp")?,
+            catch_up: reader.flag("CatchUp")?,
+            wait_for_fill: reader.flag("WaitForFill")?,
+            active_time_start: reader.optional("activeTimeStart")?,
+            active_time_end: reader.optional("activeTimeEnd")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Balance impact risk algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BalanceImpactRiskParams {
+    pub max_pct_vol: Option<f64>,
+    pub risk_aversion: RiskAversion,
+    pub force_completion: bool,
+}
+
+impl AlgoParams for BalanceImpactRiskParams {
+    const STRATEGY: &'static str = "BalanceImpactRisk";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_optional_pct_vol(Self::STRATEGY, "maxPctVol", self.max_pct_vol)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .optional("maxPctVol", &self.max_pct_vol)
+            .value("riskAversion", self.risk_aversion)
+            .flag("forceCompletion", self.force_completion)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        BalanceImpactRiskParams {
+            max_pct_vol: reader.optional("maxPctVol")?,
+            risk_aversion: reader.required("riskAversion")?,
+            force_completion: reader.flag("forceCompletion")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// Minimise impact algo parameters
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MinImpactParams {
+    pub max_pct_vol: f64,
+}
+
+impl AlgoParams for MinImpactParams {
+    const STRATEGY: &'static str = "MinImpact";
+
+    fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_pct_vol(Self::STRATEGY, "maxPctVol", self.max_pct_vol)
+    }
+
+    fn to_tag_values(&self) -> Vec<TagValue> {
+        TagWriter::default()
+            .value("maxPctVol", self.max_pct_vol)
+            .params
+    }
+
+    fn from_tag_values(params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        let reader = TagReader::new(Self::STRATEGY, params);
+        MinImpactParams {
+            max_pct_vol: reader.required("maxPctVol")?,
+        }
+        .validated()
+    }
+}
+
+//==================================================================================================
+/// An algo strategy with its parameters.  Strategies this library does not know about, such as
+/// third party algos, are kept as Other with their raw tag values.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AlgoStrategy {
+    Adaptive(AdaptiveParams),
+    ArrivalPrice(ArrivalPriceParams),
+    ClosePrice(ClosePriceParams),
+    DarkIce(DarkIceParams),
+    PctVol(PctVolParams),
+    PriceVariantPctVol(PriceVariantPctVolParams),
+    SizeVariantPctVol(SizeVariantPctVolParams),
+    TimeVariantPctVol(TimeVariantPctVolParams),
+    Twap(TwapParams),
+    Vwap(VwapParams),
+    AccumulateDistribute(AccumulateDistributeParams),
+    BalanceImpactRisk(BalanceImpactRiskParams),
+    MinImpact(MinImpactParams),
+    Other {
+        strategy: String,
+        params: Vec<TagValue>,
+    },
+}
+
+impl AlgoStrategy {
+    //----------------------------------------------------------------------------------------------
+    /// Returns the value of Order::algo_strategy for this algo
+    pub fn strategy(&self) -> &str {
+        match self {
+            AlgoStrategy::Adaptive(_) => AdaptiveParams::STRATEGY,
+            AlgoStrategy::ArrivalPrice(_) => ArrivalPriceParams::STRATEGY,
+            AlgoStrategy::ClosePrice(_) => ClosePriceParams::STRATEGY,
+            AlgoStrategy::DarkIce(_) => DarkIceParams::STRATEGY,
+            AlgoStrategy::PctVol(_) => PctVolParams::STRATEGY,
+            AlgoStrategy::PriceVariantPctVol(_) => PriceVariantPctVolParams::STRATEGY,
+            AlgoStrategy::SizeVariantPctVol(_) => SizeVariantPctVolParams::STRATEGY,
+            AlgoStrategy::TimeVariantPctVol(_) => TimeVariantPctVolParams::STRATEGY,
+            AlgoStrategy::Twap(_) => TwapParams::STRATEGY,
+            AlgoStrategy::Vwap(_) => VwapParams::STRATEGY,
+            AlgoStrategy::AccumulateDistribute(_) => AccumulateDistributeParams::STRATEGY,
+            AlgoStrategy::BalanceImpactRisk(_) => BalanceImpactRiskParams::STRATEGY,
+            AlgoStrategy::MinImpact(_) => MinImpactParams::STRATEGY,
+            AlgoStrategy::Other { strategy, .. } => strategy,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks that the parameters will be accepted by TWS.  Other strategies are not checked.
+    pub fn validate(&self) -> Result<(), IBKRApiLibError> {
+        match self {
+            AlgoStrategy::Adaptive(params) => params.validate(),
+            AlgoStrategy::ArrivalPrice(params) => params.validate(),
+            AlgoStrategy::ClosePrice(params) => params.validate(),
+            AlgoStrategy::DarkIce(params) => params.validate(),
+            AlgoStrategy::PctVol(params) => params.validate(),
+            AlgoStrategy::PriceVariantPctVol(params) => params.validate(),
+            AlgoStrategy::SizeVariantPctVol(params) => params.validate(),
+            AlgoStrategy::TimeVariantPctVol(params) => params.validate(),
+            AlgoStrategy::Twap(params) => params.validate(),
+            AlgoStrategy::Vwap(params) => params.validate(),
+            AlgoStrategy::AccumulateDistribute(params) => params.validate(),
+            AlgoStrategy::BalanceImpactRisk(params) => params.validate(),
+            AlgoStrategy::MinImpact(params) => params.validate(),
+            AlgoStrategy::Other { .. } => Ok(()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Converts the parameters to the tag values sent in Order::algo_params
+    pub fn to_tag_values(&self) -> Vec<TagValue> {
+        match self {
+            AlgoStrategy::Adaptive(params) => params.to_tag_values(),
+            AlgoStrategy::ArrivalPrice(params) => params.to_tag_values(),
+            AlgoStrategy::ClosePrice(params) => params.to_tag_values(),
+            AlgoStrategy::DarkIce(params) => params.to_tag_values(),
+            AlgoStrategy::PctVol(params) => params.to_tag_values(),
+            AlgoStrategy::PriceVariantPctVol(params) => params.to_tag_values(),
+            AlgoStrategy::SizeVariantPctVol(params) => params.to_tag_values(),
+            AlgoStrategy::TimeVariantPctVol(params) => params.to_tag_values(),
+            AlgoStrategy::Twap(params) => params.to_tag_values(),
+            AlgoStrategy::Vwap(params) => params.to_tag_values(),
+            AlgoStrategy::AccumulateDistribute(params) => params.to_tag_values(),
+            AlgoStrategy::BalanceImpactRisk(params) => params.to_tag_values(),
+            AlgoStrategy::MinImpact(params) => params.to_tag_values(),
+            AlgoStrategy::Other { params, .. } => params.clone(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses and validates the tag values of an algo strategy
+    ///
+    /// # Arguments
+    /// * strategy - The value of Order::algo_strategy
+    /// * params - The value of Order::algo_params
+    pub fn from_tag_values(strategy: &str, params: &[TagValue]) -> Result<Self, IBKRApiLibError> {
+        Ok(match strategy {
+            AdaptiveParams::STRATEGY => {
+                AlgoStrategy::Adaptive(AdaptiveParams::from_tag_values(params)?)
+            }
+            ArrivalPriceParams::STRATEGY => {
+                AlgoStrategy::ArrivalPrice(ArrivalPriceParams::from_tag_values(params)?)
+            }
+            ClosePriceParams::STRATEGY => {
+                AlgoStrategy::ClosePrice(ClosePriceParams::from_tag_values(params)?)
+            }
+            DarkIceParams::STRATEGY => {
+                AlgoStrategy::DarkIce(DarkIceParams::from_tag_values(params)?)
+            }
+            PctVolParams::STRATEGY => AlgoStrategy::PctVol(PctVolParams::from_tag_values(params)?),
+            PriceVariantPctVolParams::STRATEGY => {
+                AlgoStrategy::PriceVariantPctVol(PriceVariantPctVolParams::from_tag_values(params)?)
+            }
+            SizeVariantPctVolParams::STRATEGY => {
+                AlgoStrategy::SizeVariantPctVol(SizeVariantPctVolParams::from_tag_values(params)?)
+            }
+            TimeVariantPctVolParams::STRATEGY => {
+                AlgoStrategy::TimeVariantPctVol(TimeVariantPctVolParams::from_tag_values(params)?)
+            }
+            TwapParams::STRATEGY => AlgoStrategy::Twap(TwapParams::from_tag_values(params)?),
+            VwapParams::STRATEGY => AlgoStrategy::Vwap(VwapParams::from_tag_values(params)?),
+            AccumulateDistributeParams::STRATEGY => AlgoStrategy::AccumulateDistribute(
+                AccumulateDistributeParams::from_tag_values(params)?,
+            ),
+            BalanceImpactRiskParams::STRATEGY => {
+                AlgoStrategy::BalanceImpactRisk(BalanceImpactRiskParams::from_tag_values(params)?)
+            }
+            MinImpactParams::STRATEGY => {
+                AlgoStrategy::MinImpact(MinImpactParams::from_tag_values(params)?)
+            }
+            _ => AlgoStrategy::Other {
+                strategy: strategy.to_string(),
+                params: params.to_vec(),
+            },
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the typed algo of an order, such as one received in Wrapper::open_order or
+    /// Wrapper::completed_order, or None if the order does not use an algo
+    pub fn from_order(order: &Order) -> Result<Option<Self>, IBKRApiLibError> {
+        if order.algo_strategy.is_empty() {
+            return Ok(None);
+        }
+        AlgoStrategy::from_tag_values(&order.algo_strategy, &order.algo_params).map(Some)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Validates the parameters and sets the algo strategy and parameters of an order
+    pub fn apply_to(&self, order: &mut Order) -> Result<(), IBKRApiLibError> {
+        self.validate()?;
+        order.algo_strategy = self.strategy().to_string();
+        order.algo_params = self.to_tag_values();
+        Ok(())
+    }
+}
+
+impl Display for AlgoStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.strategy())?;
+        let params = self.to_tag_values();
+        if !params.is_empty() {
+            write!(
+                f,
+                " ({})",
+                params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect::<String>()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! algo_strategy_from_params {
+    ($params:ident, $variant:ident) => {
+        impl From<$params> for AlgoStrategy {
+            fn from(params: $params) -> Self {
+                AlgoStrategy::$variant(params)
+            }
+        }
+    };
+}
+
+algo_strategy_from_params!(AdaptiveParams, Adaptive);
+algo_strategy_from_params!(ArrivalPriceParams, ArrivalPrice);
+algo_strategy_from_params!(ClosePriceParams, ClosePrice);
+algo_strategy_from_params!(DarkIceParams, DarkIce);
+algo_strategy_from_params!(PctVolParams, PctVol);
+algo_strategy_from_params!(PriceVariantPctVolParams, PriceVariantPctVol);
+algo_strategy_from_params!(SizeVariantPctVolParams, SizeVariantPctVol);
+algo_strategy_from_params!(TimeVariantPctVolParams, TimeVariantPctVol);
+algo_strategy_from_params!(TwapParams, Twap);
+algo_strategy_from_params!(VwapParams, Vwap);
+algo_strategy_from_params!(AccumulateDistributeParams, AccumulateDistribute);
+algo_strategy_from_params!(BalanceImpactRiskParams, BalanceImpactRisk);
+algo_strategy_from_params!(MinImpactParams, MinImpact);
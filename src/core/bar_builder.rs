@@ -0,0 +1,253 @@
+//! Aggregates trades and real time bars into time, tick, volume or dollar bars
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+
+use crate::core::common::{BarData, HistoricalTickLast, RealTimeBar};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::timestamps::from_epoch_seconds;
+use crate::core::trading_hours::{local_to_utc, SessionCalendar};
+
+//==================================================================================================
+/// When a bar is complete
+#[derive(Clone, Debug, PartialEq)]
+pub enum BarSize {
+    /// Bars covering a fixed time span, aligned to the start of the session or, without a
+    /// session calendar, to midnight in the exchange time zone
+    Time(Duration),
+    /// Bars of a number of trades
+    Ticks(i32),
+    /// Bars of at least a traded volume
+    Volume(i64),
+    /// Bars of at least a traded notional value, price times size
+    Dollar(f64),
+}
+
+//==================================================================================================
+/// A trade, as received from tick_by_tick_all_last, historical ticks or time and sales
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trade {
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub size: i64,
+}
+
+impl Trade {
+    pub fn new(time: DateTime<Utc>, price: f64, size: i64) -> Self {
+        Trade { time, price, size }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Creates a trade from the time, in seconds since the epoch, price and size of a
+    /// tick_by_tick_all_last callback
+    pub fn from_tick_by_tick(time: i64, price: f64, size: i32) -> Result<Self, IBKRApiLibError> {
+        Ok(Trade::new(from_epoch_seconds(time)?, price, size as i64))
+    }
+}
+
+impl From<&HistoricalTickLast> for Trade {
+    fn from(tick: &HistoricalTickLast) -> Self {
+        Trade::new(tick.time, tick.price, tick.size as i64)
+    }
+}
+
+//==================================================================================================
+/// A bar being built
+#[derive(Clone, Debug)]
+struct PartialBar {
+    start: DateTime<Utc>,
+    /// Time at which a time bar, or any bar when sessions are known, is complete
+    end: Option<DateTime<Utc>>,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: i64,
+    trade_count: i32,
+    notional: f64,
+}
+
+impl PartialBar {
+    //----------------------------------------------------------------------------------------------
+    fn merge(&mut self, other: &PartialBar) {
+        self.high = self.high.max(other.high);
+        self.low = self.low.min(other.low);
+        self.close = other.close;
+        self.volume += other.volume;
+        self.trade_count += other.trade_count;
+        self.notional += other.notional;
+    }
+}
+
+//==================================================================================================
+/// Builds bars from trades or from the 5 second bars of req_real_time_bars.  Bars never span
+/// two trading sessions, and trades outside the sessions of the calendar are ignored.
+///
+/// The bars are BarData with date holding the start of the bar, bar_count the number of trades
+/// and average the volume weighted average price.
+#[derive(Clone, Debug)]
+pub struct BarBuilder {
+    bar_size: BarSize,
+    time_zone: Tz,
+    calendar: Option<SessionCalendar>,
+    current: Option<PartialBar>,
+}
+
+impl BarBuilder {
+    //----------------------------------------------------------------------------------------------
+    /// # Arguments
+    /// * bar_size - when a bar is complete
+    /// * time_zone - time zone of the exchange, see trading_hours::parse_time_zone
+    pub fn new(bar_size: BarSize, time_zone: Tz) -> Self {
+        BarBuilder {
+            bar_size,
+            time_zone,
+            calendar: None,
+            current: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Creates a builder using the sessions and time zone of a calendar
+    pub fn with_calendar(bar_size: BarSize, calendar: SessionCalendar) -> Self {
+        BarBuilder {
+            bar_size,
+            time_zone: calendar.time_zone,
+            calendar: Some(calendar),
+            current: None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a trade.  Returns the bars it completed.
+    pub fn add_trade(&mut self, trade: &Trade) -> Vec<BarData> {
+        self.add(PartialBar {
+            start: trade.time,
+            end: None,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.size,
+            trade_count: 1,
+            notional: trade.price * trade.size as f64,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a bar of req_real_time_bars.  The bar is not split, so it counts towards the bar
+    /// containing its start time.  Returns the bars it completed.
+    pub fn add_real_time_bar(&mut self, bar: &RealTimeBar) -> Vec<BarData> {
+        self.add(PartialBar {
+            start: bar.date_time,
+            end: None,
+            open: bar.open,
+            high: bar.high,
+            low: bar.low,
+            close: bar.close,
+            volume: bar.volume,
+            trade_count: bar.count,
+            notional: bar.wap * bar.volume as f64,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Completes the current bar if its time span ended before now, so that a time bar does
+    /// not have to wait for the next trade
+    pub fn update_time(&mut self, now: &DateTime<Utc>) -> Option<BarData> {
+        match self.current.as_ref().and_then(|bar| bar.end) {
+            Some(end) if end <= *now => self.flush(),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Completes and returns the current bar, if any
+    pub fn flush(&mut self) -> Option<BarData> {
+        let bar = self.current.take()?;
+        Some(self.to_bar_data(&bar))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a trade or real time bar, given as a bar starting at its time
+    fn add(&mut self, mut piece: PartialBar) -> Vec<BarData> {
+        let time = &piece.start;
+        let mut completed = vec![];
+        let session_end = match &self.calendar {
+            Some(calendar) => match calendar.session_at(time) {
+                Some(session) => Some(session.end),
+                None => return completed,
+            },
+            None => None,
+        };
+
+        let expired = match self.current.as_ref().and_then(|bar| bar.end) {
+            Some(end) => end <= *time,
+            None => false,
+        };
+        if expired {
+            completed.extend(self.flush());
+        }
+
+        match self.current.as_mut() {
+            Some(bar) => bar.merge(&piece),
+            None => {
+                match &self.bar_size {
+                    BarSize::Time(span) => {
+                        let start = self.time_bar_start(time, span);
+                        let end = start + *span;
+                        piece.start = start;
+                        piece.end = Some(session_end.map_or(end, |session| session.min(end)));
+                    }
+                    _ => piece.end = session_end,
+                }
+                self.current = Some(piece);
+            }
+        }
+
+        let full = match (&self.bar_size, self.current.as_ref()) {
+            (BarSize::Ticks(ticks), Some(bar)) => bar.trade_count >= *ticks,
+            (BarSize::Volume(volume), Some(bar)) => bar.volume >= *volume,
+            (BarSize::Dollar(value), Some(bar)) => bar.notional >= *value,
+            _ => false,
+        };
+        if full {
+            completed.extend(self.flush());
+        }
+        completed
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the start of the time bar containing a time.  Bars are counted from the start of
+    /// the session, or from midnight in the exchange time zone.
+    fn time_bar_start(&self, time: &DateTime<Utc>, span: &Duration) -> DateTime<Utc> {
+        let anchor = match self.calendar.as_ref().and_then(|c| c.session_at(time)) {
+            Some(session) => session.start,
+            None => {
+                let local_date = time.with_timezone(&self.time_zone).date_naive();
+                local_to_utc(&self.time_zone, &local_date.and_hms_opt(0, 0, 0).unwrap())
+            }
+        };
+        let span_seconds = span.num_seconds().max(1);
+        let elapsed = (*time - anchor).num_seconds();
+        anchor + Duration::seconds(elapsed - elapsed.rem_euclid(span_seconds))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn to_bar_data(&self, bar: &PartialBar) -> BarData {
+        let average = if bar.volume > 0 {
+            bar.notional / bar.volume as f64
+        } else {
+            bar.close
+        };
+        BarData::new(
+            bar.start,
+            bar.open,
+            bar.high,
+            bar.low,
+            bar.close,
+            bar.volume,
+            bar.trade_count,
+            average,
+        )
+    }
+}
@@ -7,33 +7,49 @@ use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{thread, result::Result};
 use std::fmt::{Debug, Display};
 use std::fmt;
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use from_ascii::FromAscii;
 use log::*;
 
 use num_derive::FromPrimitive;
 
 use super::streamer::{Streamer, TcpStreamer};
+use crate::core::account::{AccountKey, AccountStore};
 use crate::core::common::*;
 use crate::core::contract::Contract;
-use crate::core::decoder::Decoder;
+use crate::core::decoder::{Decoder, SharedStores};
 use crate::core::errors::{IBKRApiLibError, TwsApiReportableError, TwsError};
 use crate::core::execution::ExecutionFilter;
+use crate::core::fa::FaConfig;
+use crate::core::generic_tick::GenericTickList;
 use crate::core::messages::make_field;
 use crate::core::messages::{make_field_handle_empty, read_msg};
 use crate::core::messages::{make_message, read_fields, OutgoingMessageIds};
-use crate::core::order::Order;
+use crate::core::option_chain::{OptionChain, OptionChainStore};
+use crate::core::order::{Order, OrderStatus};
 use crate::core::order_condition::Condition;
+use crate::core::historical_data::HistoricalDataTracker;
+use crate::core::market_data::MarketDataCache;
+use crate::core::market_depth::{DepthSubscription, MarketDepthCache};
+use crate::core::order_tracker::{OpenOrderSnapshot, OrderTracker, PendingOrderPreview};
 use crate::core::reader::Reader;
-use crate::core::scanner::ScannerSubscription;
+use crate::core::scanner::{ScannerCache, ScannerSubscription};
 use crate::core::server_versions::*;
+use crate::core::timestamps::{connection_time_zone, format_optional_tws_time};
+use crate::core::trade_blotter::TradeBlotter;
 use crate::core::wrapper::Wrapper;
 
 pub(crate) static POISONED_MUTEX: &str = "Mutex was poisoned";
 
+/// How long preview_order waits for TWS to answer a what-if order
+pub const PREVIEW_ORDER_TIMEOUT: Duration = Duration::from_secs(10);
+
 //==================================================================================================
 /// Connection status
 #[repr(i32)]
@@ -78,9 +94,18 @@ where
     client_id: i32,
     pub(crate) server_version: i32,
     conn_time: String,
+    time_zone: Tz,
     pub conn_state: Arc<Mutex<ConnStatus>>,
     opt_capab: String,
     disconnect_requested: Arc<AtomicBool>,
+    pub(crate) order_tracker: Arc<OrderTracker>,
+    pub(crate) market_data: Arc<MarketDataCache>,
+    pub(crate) market_depth: Arc<MarketDepthCache>,
+    pub(crate) historical_data: Arc<HistoricalDataTracker>,
+    pub(crate) scanner: Arc<ScannerCache>,
+    pub(crate) accounts: Arc<AccountStore>,
+    pub(crate) trade_blotter: Arc<TradeBlotter>,
+    pub(crate) option_chains: Arc<OptionChainStore>,
 }
 
 impl<T> EClient<T>
@@ -97,9 +122,18 @@ where
             client_id: 0,
             server_version: 0,
             conn_time: "".to_string(),
+            time_zone: Tz::UTC,
             conn_state: Arc::new(Mutex::new(ConnStatus::DISCONNECTED)),
             opt_capab: "".to_string(),
             disconnect_requested: Arc::new(AtomicBool::new(false)),
+            order_tracker: Arc::new(OrderTracker::new()),
+            market_data: Arc::new(MarketDataCache::new()),
+            market_depth: Arc::new(MarketDepthCache::new()),
+            historical_data: Arc::new(HistoricalDataTracker::new()),
+            scanner: Arc::new(ScannerCache::new()),
+            accounts: Arc::new(AccountStore::new()),
+            trade_blotter: Arc::new(TradeBlotter::new()),
+            option_chains: Arc::new(OptionChainStore::new()),
         }
     }
     fn send_request(&mut self, request: &str) -> Result<(), IBKRApiLibError> {
@@ -166,6 +200,16 @@ where
             rx,
             self.server_version,
             self.conn_state.clone(),
+            SharedStores {
+                order_tracker: self.order_tracker.clone(),
+                market_data: self.market_data.clone(),
+                market_depth: self.market_depth.clone(),
+                historical_data: self.historical_data.clone(),
+                scanner: self.scanner.clone(),
+                accounts: self.accounts.clone(),
+                trade_blotter: self.trade_blotter.clone(),
+                option_chains: self.option_chains.clone(),
+            },
         );
 
         //An Interactive Broker's developer's note: "sometimes I get news before the server version, thus the loop"
@@ -191,7 +235,15 @@ where
         info!("Server version: {}", self.server_version);
 
         self.conn_time = fields.get(1).unwrap().to_string();
+        self.time_zone = match connection_time_zone(&self.conn_time) {
+            Ok(time_zone) => time_zone,
+            Err(err) => {
+                warn!("Using UTC as the time zone of TWS: {}", err);
+                Tz::UTC
+            }
+        };
         decoder.server_version = self.server_version;
+        decoder.time_zone = self.time_zone;
 
         thread::spawn(move || {
             reader.run();
@@ -260,6 +312,13 @@ where
         self.conn_time.clone()
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Gets the time zone of TWS, read from the connection time.  Dates sent by TWS without a
+    /// time zone are in this time zone.
+    pub fn tws_time_zone(&self) -> Tz {
+        self.time_zone
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Request the current time according to TWS or IB Gateway
     pub fn req_current_time(&mut self) -> Result<(), IBKRApiLibError> {
@@ -323,11 +382,9 @@ where
     ///            also used when canceling the market data.
     /// * contract - This structure contains a description of the
     ///              Contract for which market data is being requested.
-    /// * generic_tick_list - A commma delimited list of generic tick types.
-    ///                       Tick types can be found in the Generic Tick Types page.
-    ///                       Prefixing w/ 'mdoff' indicates that top mkt data shouldn't tick.
-    ///                       You can specify the news source by postfixing w/ ':<source>.
-    ///                       Example: "mdoff, 292: FLY + BRF"
+    /// * generic_tick_list - The generic tick types to request, in addition to the top of book
+    ///                       ticks unless market_data_off is set.  News sources can be chosen
+    ///                       with GenericTickList::news.
     /// * snapshot - Check to return a single snapshot of Market data and
     ///                    have the market data subscription cancel. Do not enter any
     ///                    generic_tick_list values if you use snapshots.
@@ -338,7 +395,7 @@ where
         &mut self,
         req_id: i32,
         contract: &Contract,
-        generic_tick_list: &str,
+        generic_tick_list: &GenericTickList,
         snapshot: bool,
         regulatory_snapshot: bool,
         mkt_data_options: Vec<TagValue>,
@@ -442,7 +499,7 @@ where
                 msg.push_str(&make_field(&false)?);
             }
 
-            msg.push_str(&make_field(&String::from(generic_tick_list))?); // srv v31 and above
+            msg.push_str(&make_field(&generic_tick_list.to_string())?); // srv v31 and above
             msg.push_str(&make_field(&snapshot)?); // srv v35 and above
         }
 
@@ -470,6 +527,7 @@ where
             msg.push_str(&make_field(&mkt_data_options_str)?);
         }
 
+        self.market_data.reset(req_id);
         self.send_request(msg.as_str())?;
         Ok(())
     }
@@ -491,6 +549,7 @@ where
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.market_data.remove(req_id);
         Ok(())
     }
 
@@ -1968,6 +2027,141 @@ where
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Returns the contract, order and order state from the last Wrapper::open_order event
+    /// received for the order, if any
+    pub fn open_order_snapshot(&self, order_id: i32) -> Option<OpenOrderSnapshot> {
+        self.order_tracker.snapshot(order_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the last status received for the order from Wrapper::order_status or
+    /// Wrapper::open_order events, if any
+    pub fn last_order_status(&self, order_id: i32) -> Option<OrderStatus> {
+        self.order_tracker.order_status(order_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the cache holding a Ticker for each req_mkt_data subscription.  It is updated by
+    /// the decoder thread and can be shared with other threads to query or subscribe to tickers.
+    pub fn market_data_cache(&self) -> Arc<MarketDataCache> {
+        self.market_data.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the cache holding an OrderBook for each req_mkt_depth subscription
+    pub fn market_depth_cache(&self) -> Arc<MarketDepthCache> {
+        self.market_depth.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the cache holding a ScannerSession for each req_scanner_subscription
+    pub fn scanner_cache(&self) -> Arc<ScannerCache> {
+        self.scanner.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the store holding the state of each account and model, built from the account,
+    /// position, account summary and P&L subscriptions
+    pub fn account_store(&self) -> Arc<AccountStore> {
+        self.accounts.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the blotter joining the executions to their commission reports
+    pub fn trade_blotter(&self) -> Arc<TradeBlotter> {
+        self.trade_blotter.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the store holding the OptionChain of each req_sec_def_opt_params request and the
+    /// con_ids of their options
+    pub fn option_chain_store(&self) -> Arc<OptionChainStore> {
+        self.option_chains.clone()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Call this function to modify a working order.  The order is copied from the last
+    /// Wrapper::open_order event received for order_id, the modify function is applied to
+    /// the copy, and the result is resent with place_order.  Only the fields TWS accepts
+    /// changes to for the order's type (see Order::modifiable_fields) may be changed.
+    ///
+    /// # Arguments
+    /// * order_id - The order id of the working order to modify
+    /// * modify - Function that applies the changes to a copy of the live order
+    pub fn modify_order<F>(&mut self, order_id: i32, modify: F) -> Result<(), IBKRApiLibError>
+    where
+        F: FnOnce(&mut Order),
+    {
+        let snapshot = match self.order_tracker.snapshot(order_id) {
+            Some(snapshot) => snapshot,
+            None => {
+                return Err(IBKRApiLibError::invalid_argument(
+                    order_id,
+                    "No open order has been received for this order id.",
+                ));
+            }
+        };
+
+        let mut order = snapshot.order.clone();
+        modify(&mut order);
+
+        let modifiable = snapshot.order.modifiable_fields();
+        let not_modifiable = snapshot
+            .order
+            .changed_fields(&order)?
+            .into_iter()
+            .filter(|field| !modifiable.contains(&field.as_str()))
+            .collect::<Vec<String>>();
+
+        if !not_modifiable.is_empty() {
+            return Err(IBKRApiLibError::invalid_argument(
+                order_id,
+                &format!(
+                    "Fields cannot be modified on a {} order: {}",
+                    snapshot.order.order_type,
+                    not_modifiable.join(", ")
+                ),
+            ));
+        }
+
+        self.place_order(order_id, &snapshot.contract, &order)?;
+        self.order_tracker.update_order(order_id, &order);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Call this function to calculate the margin and commission impact of an order without
+    /// placing it.  A what-if copy of the order is sent, and the PendingOrderPreview returned
+    /// waits for TWS to answer with the matching open_order event or an error.
+    ///
+    /// Note: The answer is delivered by the decoder thread, so the PendingOrderPreview must not
+    ///       be waited on from inside a Wrapper callback.  Release the client, such as by
+    ///       dropping the lock of its Mutex, before waiting.
+    ///
+    /// # Arguments
+    /// * order_id - The order id of the what-if order, unique as for place_order.
+    /// * contract - Description of the contract which is being traded.
+    /// * order - The order to preview.  It is not modified.
+    pub fn preview_order(
+        &mut self,
+        order_id: i32,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<PendingOrderPreview, IBKRApiLibError> {
+        self.check_connected(order_id)?;
+
+        let mut what_if_order = order.clone();
+        what_if_order.order_id = order_id;
+        what_if_order.what_if = true;
+        what_if_order.transmit = true;
+
+        self.order_tracker.expect_open_order(order_id);
+        let pending = PendingOrderPreview::new(order_id, self.order_tracker.clone());
+        self.place_order(order_id, contract, &what_if_order)?;
+        Ok(pending)
+    }
+
     //----------------------------------------------------------------------------------------------
     /// Call this function to cancel an order.
     /// # Arguments
@@ -2152,6 +2346,9 @@ where
         msg.push_str(&make_field(&subscribe)?); // TRUE = subscribe, FALSE = unsubscribe
         msg.push_str(&make_field(&String::from(acct_code))?); // srv v9 and above, the account code.This will only be used for FA clients
 
+        if subscribe {
+            self.accounts.account_updates_requested(acct_code);
+        }
         self.send_request(msg.as_str())?;
 
         Ok(())
@@ -2481,6 +2678,8 @@ where
         msg.push_str(&make_field(&String::from(account))?);
         msg.push_str(&make_field(&String::from(model_code))?);
 
+        self.accounts
+            .expect_pnl(req_id, AccountKey::new(account, model_code), None);
         self.send_request(msg.as_str())
     }
 
@@ -2511,6 +2710,7 @@ where
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&req_id)?);
 
+        self.accounts.forget_pnl(req_id);
         self.send_request(msg.as_str())
     }
 
@@ -2553,6 +2753,8 @@ where
         msg.push_str(&make_field(&String::from(model_code))?);
         msg.push_str(&make_field(&con_id)?);
 
+        self.accounts
+            .expect_pnl(req_id, AccountKey::new(account, model_code), Some(con_id));
         self.send_request(msg.as_str())
     }
 
@@ -2583,6 +2785,7 @@ where
         msg.push_str(&make_field(&message_id)?);
         msg.push_str(&make_field(&req_id)?);
 
+        self.accounts.forget_pnl(req_id);
         self.send_request(msg.as_str())
     }
 
@@ -2596,14 +2799,15 @@ where
     /// Trade Log in TWS and, while the Trade Log is displayed, request
     /// the executions again from the API.
     ///
+    /// The executions which do not match the filter are not passed to exec_details, as TWS
+    /// applies it loosely.
+    ///
     /// # Arguments
     /// * req_id - The ID of the data request. Ensures that responses are
     ///            matched to requests if several requests are in process.
     /// * exec_filter - This object contains attributes that
     ///                 describe the filter criteria used to determine which execution
     ///                 reports are returned.
-    ///
-    /// NOTE: Time format must be 'yyyymmdd-hh:mm:ss' Eg: '20030702-14:55'
     pub fn req_executions(
         &mut self,
         req_id: i32,
@@ -2621,13 +2825,19 @@ where
         }
         msg.push_str(&make_field(&exec_filter.client_id)?);
         msg.push_str(&make_field(&exec_filter.acct_code)?);
-        msg.push_str(&make_field(&exec_filter.time)?);
+        msg.push_str(&make_field(&exec_filter.encoded_time())?);
         msg.push_str(&make_field(&exec_filter.symbol)?);
         msg.push_str(&make_field(&exec_filter.sec_type)?);
         msg.push_str(&make_field(&exec_filter.exchange)?);
-        msg.push_str(&make_field(&exec_filter.side)?);
+        msg.push_str(&make_field(&exec_filter.encoded_side())?);
 
-        self.send_request(msg.as_str())
+        self.trade_blotter
+            .expect_executions(req_id, exec_filter.clone());
+        if let Err(err) = self.send_request(msg.as_str()) {
+            self.trade_blotter.forget_executions(req_id);
+            return Err(err);
+        }
+        Ok(())
     }
 
     //#########################################################################
@@ -2750,6 +2960,30 @@ where
         self.send_request(msg.as_str())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Resolves the con_id of an option built by an OptionChain.  The con_id is returned at once if
+    /// already resolved, else it is requested with req_contract_details and recorded by the
+    /// OptionChainStore once the contract details are received.
+    ///
+    /// # Arguments
+    /// * req_id - the ID of the req_contract_details request, if one is needed
+    /// * contract - the option, such as from OptionChain::contract
+    pub fn req_option_con_id(
+        &mut self,
+        req_id: i32,
+        contract: &Contract,
+    ) -> Result<Option<i32>, IBKRApiLibError> {
+        if let Some(con_id) = self.option_chains.con_id(contract) {
+            return Ok(Some(con_id));
+        }
+        self.option_chains.expect_con_id(req_id);
+        if let Err(err) = self.req_contract_details(req_id, contract) {
+            self.option_chains.forget_con_id(req_id);
+            return Err(err);
+        }
+        Ok(None)
+    }
+
     //#########################################################################
     //################## Market Depth
     //#########################################################################
@@ -2910,6 +3144,15 @@ where
             let mkt_data_options_str = "";
             msg.push_str(&make_field(&mkt_data_options_str)?);
         }
+        self.market_depth.subscribe(
+            req_id,
+            DepthSubscription {
+                contract: contract.clone(),
+                num_rows,
+                is_smart_depth,
+                mkt_depth_options,
+            },
+        );
         self.send_request(msg.as_str())
     }
 
@@ -2954,7 +3197,36 @@ where
             msg.push_str(&make_field(&is_smart_depth)?);
         }
 
-        self.send_request(msg.as_str())
+        self.send_request(msg.as_str())?;
+        self.market_depth.remove(req_id);
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Cancels and requests again the market depth of every order book that received an update
+    /// it could not apply, for instance the deletion of a row that does not exist.  Books are not
+    /// requested again automatically: call it when the error() event reports a
+    /// TwsError::InvalidOrderBook error.  The books are requested under the same ids, and ignore
+    /// the updates still in flight from the cancelled requests until the first row of the new
+    /// stream.  Returns the ids of the requests that were sent again.
+    pub fn resubscribe_invalid_mkt_depth(&mut self) -> Result<Vec<i32>, IBKRApiLibError> {
+        let mut req_ids = vec![];
+        for req_id in self.market_depth.invalid_books() {
+            let subscription = match self.market_depth.subscription(req_id) {
+                Some(subscription) => subscription,
+                None => continue,
+            };
+            self.cancel_mkt_depth(req_id, subscription.is_smart_depth)?;
+            self.req_mkt_depth(
+                req_id,
+                &subscription.contract,
+                subscription.num_rows,
+                subscription.is_smart_depth,
+                subscription.mkt_depth_options,
+            )?;
+            req_ids.push(req_id);
+        }
+        Ok(req_ids)
     }
 
     //#########################################################################
@@ -3042,15 +3314,12 @@ where
     /// Call this function to modify FA configuration information from the
     /// API. Note that this can also be done manually in TWS itself.
     ///
-    /// * fa_data - See the FaDataType enum. Specifies the type of Financial Advisor
-    ///             configuration data beingingg requested. Valid values include:
-    ///     * 1 = GROUPS
-    ///     * 2 = PROFILE
-    ///     * 3 = ACCOUNT ALIASES
-    /// *cxml - The XML string containing the new FA configuration
-    ///         information.
-    pub fn replace_fa(&mut self, fa_data: FaDataType, cxml: &str) -> Result<(), IBKRApiLibError> {
+    /// # Arguments
+    /// * config - The groups, allocation profiles or account aliases replacing those of the
+    ///            same type.  They are validated, then sent as XML.
+    pub fn replace_fa(&mut self, config: &FaConfig) -> Result<(), IBKRApiLibError> {
         self.check_connected(NO_VALID_ID)?;
+        config.validate()?;
 
         let version = 1;
         let message_id: i32 = OutgoingMessageIds::ReplaceFa as i32;
@@ -3058,8 +3327,8 @@ where
         msg.push_str(&make_field(&message_id)?);
 
         msg.push_str(&make_field(&version)?);
-        msg.push_str(&make_field(&fa_data)?);
-        msg.push_str(&make_field(&String::from(cxml))?);
+        msg.push_str(&make_field(&(config.data_type() as i32))?);
+        msg.push_str(&make_field(&config.to_xml())?);
 
         self.send_request(msg.as_str())
     }
@@ -3077,9 +3346,8 @@ where
     ///            used when canceling the market data.
     /// * contract - This object contains a description of the contract for which
     ///              market data is being requested.
-    /// * end_date_time - Defines a query end date and time at any point during the past 6 mos.
-    ///                   Valid values include any date/time within the past six months in the format:
-    ///                   yyyymmdd HH:mm:ss ttt where "ttt" is the optional time zone.
+    /// * end_date_time - Defines a query end date and time at any point during the past 6 mos,
+    ///                   or None for the current time.
     /// * duration_str - Set the query duration up to one week, using a time unit
     ///                  of seconds, days or weeks. Valid values include any integer followed by a space
     ///                  and then S (seconds)); D (days) or W (week). If no unit is specified, seconds is used.
@@ -3122,7 +3390,7 @@ where
         &mut self,
         req_id: i32,
         contract: &Contract,
-        end_date_time: &str,
+        end_date_time: Option<DateTime<Utc>>,
         duration_str: &str,
         bar_size_setting: &str,
         what_to_show: &str,
@@ -3181,7 +3449,7 @@ where
         }
         msg.push_str(&make_field(&contract.include_expired)?); // srv v31 and above
 
-        msg.push_str(&make_field(&String::from(end_date_time))?); // srv v20 and above
+        msg.push_str(&make_field(&format_optional_tws_time(&end_date_time))?); // srv v20 and above
         msg.push_str(&make_field(&String::from(bar_size_setting))?); // srv v20 and above
         msg.push_str(&make_field(&String::from(duration_str))?);
         msg.push_str(&make_field(&use_rth)?);
@@ -3210,7 +3478,11 @@ where
             msg.push_str(&make_field(&chart_options_str)?);
         }
 
-        self.send_request(msg.as_str())?;
+        self.historical_data.expect_format_date(req_id, format_date);
+        if let Err(err) = self.send_request(msg.as_str()) {
+            self.historical_data.forget_format_date(req_id);
+            return Err(err);
+        }
         Ok(())
     }
 
@@ -3234,6 +3506,7 @@ where
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.historical_data.forget_format_date(req_id);
 
         Ok(())
     }
@@ -3425,8 +3698,8 @@ where
     /// # Arguments
     /// * req_id - id of the request
     /// * contract - Contract object that is subject of query
-    /// * start_date_time,i.e.	"20170701 12:01:00". Uses TWS timezone specified at login.
-    /// * end_date_time,i.e.	"20170701 13:01:00". In TWS timezone. Exactly one of start time and end time has to be defined.
+    /// * start_date_time - start of the ticks requested.
+    /// * end_date_time - end of the ticks requested. Exactly one of start time and end time has to be defined.
     /// * number_of_ticks - Number of distinct data points. Max currently 1000 per request.
     /// * what_to_show - (Bid_Ask, Midpoint, Trades) Type of data requested.
     /// * use_rth - Data from regular trading hours (1), or all available hours (0)
@@ -3436,8 +3709,8 @@ where
         &mut self,
         req_id: i32,
         contract: &Contract,
-        start_date_time: &str,
-        end_date_time: &str,
+        start_date_time: Option<DateTime<Utc>>,
+        end_date_time: Option<DateTime<Utc>>,
         number_of_ticks: i32,
         what_to_show: &str,
         use_rth: i32,
@@ -3478,8 +3751,8 @@ where
         msg.push_str(&make_field(&contract.local_symbol)?);
         msg.push_str(&make_field(&contract.trading_class)?);
         msg.push_str(&make_field(&contract.include_expired)?);
-        msg.push_str(&make_field(&String::from(start_date_time))?);
-        msg.push_str(&make_field(&String::from(end_date_time))?);
+        msg.push_str(&make_field(&format_optional_tws_time(&start_date_time))?);
+        msg.push_str(&make_field(&format_optional_tws_time(&end_date_time))?);
         msg.push_str(&make_field(&number_of_ticks)?);
         msg.push_str(&make_field(&String::from(what_to_show))?);
         msg.push_str(&make_field(&use_rth)?);
@@ -3601,6 +3874,7 @@ where
         }
         error!("req_scanner_subscription");
         error!("{}", msg);
+        self.scanner.reset(req_id);
         self.send_request(msg.as_str())?;
         Ok(())
     }
@@ -3625,6 +3899,7 @@ where
         msg.push_str(&make_field(&req_id)?);
 
         self.send_request(msg.as_str())?;
+        self.scanner.remove(req_id);
         Ok(())
     }
 
@@ -4370,6 +4645,8 @@ where
     /// * fut_fop_exchange - The exchange on which the returned options are trading. Can be set to the empty string "" for all exchanges.
     /// * underlying_sec_type - The type of the underlying security, i.e. STK
     /// * underlying_con_id - the contract ID of the underlying security
+    ///
+    /// The parameters are gathered into an OptionChain by the OptionChainStore.
     pub fn req_sec_def_opt_params(
         &mut self,
         req_id: i32,
@@ -4404,7 +4681,16 @@ where
         msg.push_str(&make_field(&String::from(underlying_sec_type))?);
         msg.push_str(&make_field(&underlying_con_id)?);
 
-        self.send_request(msg.as_str())?;
+        self.option_chains.expect_chain(OptionChain::new(
+            req_id,
+            underlying_symbol,
+            underlying_sec_type,
+            underlying_con_id,
+        ));
+        if let Err(err) = self.send_request(msg.as_str()) {
+            self.option_chains.remove(req_id);
+            return Err(err);
+        }
         Ok(())
     }
 
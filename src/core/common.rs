@@ -2,6 +2,7 @@
 use std::fmt::Display;
 use std::fmt::{self, Error, Formatter};
 
+use chrono::{DateTime, Utc};
 use num_derive::FromPrimitive;
 
 use serde::{Deserialize, Serialize};
@@ -16,7 +17,7 @@ pub const UNSET_LONG: i64 = std::i64::MAX;
 //==================================================================================================
 /// Tick types
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, Debug, FromPrimitive, Copy)]
+#[derive(Serialize, Deserialize, Clone, Debug, FromPrimitive, Copy, PartialEq, Eq, Hash)]
 pub enum TickType {
     BidSize = 0,
     Bid = 1,
@@ -212,7 +213,7 @@ impl fmt::Display for TickType {
 //==================================================================================================
 /// Financial advisor data types
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq)]
 pub enum FaDataType {
     NA = 0,
     GROUPS = 1,
@@ -222,7 +223,12 @@ pub enum FaDataType {
 
 impl fmt::Display for FaDataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Type: {}", self)
+        match self {
+            FaDataType::NA => write!(f, "N/A"),
+            FaDataType::GROUPS => write!(f, "GROUPS"),
+            FaDataType::PROFILES => write!(f, "PROFILES"),
+            FaDataType::ALIASES => write!(f, "ALIASES"),
+        }
     }
 }
 
@@ -264,7 +270,9 @@ impl fmt::Display for TickByTickType {
 /// average - average price of the bar
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct BarData {
-    pub date: String,
+    /// Start of the bar, in UTC.  TWS sends it in its own time zone, or as seconds since the
+    /// epoch, and it is converted so that bars of any contract and session compare directly.
+    pub date: DateTime<Utc>,
     pub open: f64,
     pub high: f64,
     pub low: f64,
@@ -276,7 +284,7 @@ pub struct BarData {
 
 impl BarData {
     pub fn new(
-        date: String,
+        date: DateTime<Utc>,
         open: f64,
         high: f64,
         low: f64,
@@ -319,7 +327,8 @@ impl fmt::Display for BarData {
 /// count - running count of the bars for this request
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct RealTimeBar {
-    pub date_time: String,
+    /// Start of the bar, in UTC
+    pub date_time: DateTime<Utc>,
     pub open: f64,
     pub high: f64,
     pub low: f64,
@@ -331,7 +340,7 @@ pub struct RealTimeBar {
 
 impl RealTimeBar {
     pub fn new(
-        date_time: String,
+        date_time: DateTime<Utc>,
         open: f64,
         high: f64,
         low: f64,
@@ -598,13 +607,13 @@ impl fmt::Display for PriceIncrement {
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct HistoricalTick {
-    pub time: i32,
+    pub time: DateTime<Utc>,
     pub price: f64,
     pub size: i32,
 }
 
 impl HistoricalTick {
-    pub fn new(time: i32, price: f64, size: i32) -> Self {
+    pub fn new(time: DateTime<Utc>, price: f64, size: i32) -> Self {
         HistoricalTick { time, price, size }
     }
 }
@@ -622,7 +631,7 @@ impl fmt::Display for HistoricalTick {
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct HistoricalTickBidAsk {
-    pub time: i32,
+    pub time: DateTime<Utc>,
     pub tick_attrib_bid_ask: TickAttribBidAsk,
     pub price_bid: f64,
     pub price_ask: f64,
@@ -632,7 +641,7 @@ pub struct HistoricalTickBidAsk {
 
 impl HistoricalTickBidAsk {
     pub fn new(
-        time: i32,
+        time: DateTime<Utc>,
         tick_attrib_bid_ask: TickAttribBidAsk,
         price_bid: f64,
         price_ask: f64,
@@ -668,7 +677,7 @@ impl fmt::Display for HistoricalTickBidAsk {
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct HistoricalTickLast {
-    pub time: i32,
+    pub time: DateTime<Utc>,
     pub tick_attrib_last: TickAttribLast,
     pub price: f64,
     pub size: i32,
@@ -678,7 +687,7 @@ pub struct HistoricalTickLast {
 
 impl HistoricalTickLast {
     pub fn new(
-        time: i32,
+        time: DateTime<Utc>,
         tick_attrib_last: TickAttribLast,
         price: f64,
         size: i32,
@@ -771,7 +780,7 @@ impl fmt::Display for NewsProvider {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct TagValue {
     pub tag: String,
     pub value: String,
@@ -931,7 +940,7 @@ impl Display for TriggerMethod {
 
 //==================================================================================================
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq)]
 pub enum Action {
     BUY,
     SELL,
@@ -1337,8 +1346,9 @@ impl Display for SecType {
 
 //==================================================================================================
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq, Default)]
 pub enum MarketDataTypeEnum {
+    #[default]
     Unknown,
     Realtime,
     Frozen,
@@ -10,25 +10,37 @@ use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
 
 use bigdecimal::BigDecimal;
-use float_cmp::*;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use log::*;
-use num_traits::float::FloatCore;
 use num_traits::FromPrimitive;
 
+use crate::core::account::{AccountKey, AccountPosition, AccountStore};
 use crate::core::client::ConnStatus;
 use crate::core::common::{
-    BarData, CommissionReport, DepthMktDataDescription, FamilyCode, HistogramData, HistoricalTick,
-    HistoricalTickBidAsk, HistoricalTickLast, NewsProvider, PriceIncrement, RealTimeBar,
-    SmartComponent, TagValue, TickAttrib, TickAttribBidAsk, TickAttribLast, TickType, MAX_MSG_LEN,
-    NO_VALID_ID, UNSET_DOUBLE, UNSET_INTEGER,
+    BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode, HistogramData,
+    HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, NewsProvider, PriceIncrement,
+    RealTimeBar, SmartComponent, TagValue, TickAttrib, TickAttribBidAsk, TickAttribLast, TickType,
+    MAX_MSG_LEN, NO_VALID_ID, UNSET_DOUBLE, UNSET_INTEGER,
 };
 use crate::core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract};
 use crate::core::errors::{IBKRApiLibError, TwsError};
 use crate::core::execution::Execution;
+use crate::core::fa::FaConfig;
+use crate::core::historical_data::HistoricalDataTracker;
+use crate::core::historical_ticks::HistoricalTicks;
+use crate::core::market_data::{EfpComputation, MarketDataCache, OptionComputation};
+use crate::core::market_depth::{DepthRow, MarketDepthCache};
 use crate::core::messages::{read_fields, IncomingMessageIds};
-use crate::core::order::{Order, OrderState, SoftDollarTier};
+use crate::core::order::{Order, OrderState, OrderStatus, SoftDollarTier};
 use crate::core::order_decoder::OrderDecoder;
-use crate::core::scanner::ScanData;
+use crate::core::option_chain::{OptionChainParameters, OptionChainStore};
+use crate::core::order_tracker::OrderTracker;
+use crate::core::scanner::{ScanData, ScannerCache};
+use crate::core::timestamps::{
+    from_epoch_seconds, parse_tws_time, FORMAT_DATE_EPOCH, FORMAT_DATE_TEXT,
+};
+use crate::core::trade_blotter::TradeBlotter;
 use crate::core::server_versions::{
     MIN_SERVER_VER_AGG_GROUP, MIN_SERVER_VER_FRACTIONAL_POSITIONS, MIN_SERVER_VER_LAST_LIQUIDITY,
     MIN_SERVER_VER_MARKET_CAP_PRICE, MIN_SERVER_VER_MARKET_RULES,
@@ -98,12 +110,37 @@ pub fn decode_bool(iter: &mut Iter<String>) -> Result<bool, IBKRApiLibError> {
     Ok(retval != 0)
 }
 
+//==================================================================================================
+/// Stores shared by the client and the decoder.  The decoder updates them as messages arrive,
+/// before calling the matching Wrapper callback.
+#[derive(Clone, Debug, Default)]
+pub struct SharedStores {
+    pub order_tracker: Arc<OrderTracker>,
+    pub market_data: Arc<MarketDataCache>,
+    pub market_depth: Arc<MarketDepthCache>,
+    pub historical_data: Arc<HistoricalDataTracker>,
+    pub scanner: Arc<ScannerCache>,
+    pub accounts: Arc<AccountStore>,
+    pub trade_blotter: Arc<TradeBlotter>,
+    pub option_chains: Arc<OptionChainStore>,
+}
+
 //==================================================================================================
 pub struct Decoder<T: Wrapper> {
     msg_queue: Receiver<String>,
     pub wrapper: Arc<Mutex<T>>,
     pub server_version: i32,
+    /// Time zone of TWS, in which the dates without time zone it sends are
+    pub time_zone: Tz,
     conn_state: Arc<Mutex<ConnStatus>>,
+    order_tracker: Arc<OrderTracker>,
+    market_data: Arc<MarketDataCache>,
+    market_depth: Arc<MarketDepthCache>,
+    historical_data: Arc<HistoricalDataTracker>,
+    scanner: Arc<ScannerCache>,
+    accounts: Arc<AccountStore>,
+    trade_blotter: Arc<TradeBlotter>,
+    option_chains: Arc<OptionChainStore>,
 }
 
 impl<T> Decoder<T>
@@ -115,12 +152,56 @@ where
         msg_queue: Receiver<String>,
         server_version: i32,
         conn_state: Arc<Mutex<ConnStatus>>,
+        stores: SharedStores,
     ) -> Self {
         Decoder {
             wrapper: the_wrapper,
             msg_queue: msg_queue,
             server_version,
+            time_zone: Tz::UTC,
             conn_state,
+            order_tracker: stores.order_tracker,
+            market_data: stores.market_data,
+            market_depth: stores.market_depth,
+            historical_data: stores.historical_data,
+            scanner: stores.scanner,
+            accounts: stores.accounts,
+            trade_blotter: stores.trade_blotter,
+            option_chains: stores.option_chains,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses a date and time of a message.  A date that cannot be parsed is reported to the
+    /// wrapper as an error of the request, and None is returned so that the caller skips the bar
+    /// or execution carrying it rather than delivering a made up time.
+    fn parse_time(&self, req_id: i32, text: &str, format_date: i32) -> Option<DateTime<Utc>> {
+        match parse_tws_time(text, format_date, &self.time_zone) {
+            Ok(time) => Some(time),
+            Err(err) => {
+                warn!("Skipping the date and time {:?}: {}", text, err);
+                if let IBKRApiLibError::ApiError(err) = &err {
+                    self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).error(
+                        req_id,
+                        TwsError::InvalidArgument.code(),
+                        &err.description,
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Reports a status the order tracker rejected as out of order to the wrapper
+    fn report_out_of_order_status(&self, order_id: i32, tracked: Result<(), IBKRApiLibError>) {
+        if let Err(IBKRApiLibError::ApiError(err)) = tracked {
+            warn!("{}", err.description);
+            self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).error(
+                order_id,
+                TwsError::OutOfOrderStatus.code(),
+                &err.description,
+            );
         }
     }
 
@@ -297,6 +378,10 @@ where
         if self.server_version >= MIN_SERVER_VER_PRE_OPEN_BID_ASK {
             tick_arrtibute.pre_open = attr_mask & 4 != 0;
         }
+        if let Some(tick_type) = FromPrimitive::from_i32(tick_type) {
+            self.market_data
+                .update_price(req_id, tick_type, price, &tick_arrtibute);
+        }
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -320,6 +405,7 @@ where
         };
 
         if size_tick_type as i32 != TickType::NotSet as i32 {
+            self.market_data.update_size(req_id, size_tick_type, size);
             self.wrapper
                 .lock()
                 .expect(WRAPPER_POISONED_MUTEX)
@@ -341,6 +427,9 @@ where
         let tick_type: i32 = decode_i32(&mut fields_itr)?;
         let value = decode_string(&mut fields_itr)?;
 
+        if let Some(tick_type) = FromPrimitive::from_i32(tick_type) {
+            self.market_data.update_string(req_id, tick_type, &value);
+        }
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -361,15 +450,23 @@ where
         //throw away version
         fields_itr.next();
 
+        let req_id = decode_i32(&mut fields_itr)?;
+        let account = decode_string(&mut fields_itr)?;
+        let tag = decode_string(&mut fields_itr)?;
+        let value = decode_string(&mut fields_itr)?;
+        let currency = decode_string(&mut fields_itr)?;
+
+        self.accounts
+            .update_account_summary(&account, &tag, &value, &currency);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
             .account_summary(
-                decode_i32(&mut fields_itr)?,
-                decode_string(&mut fields_itr)?.as_ref(),
-                decode_string(&mut fields_itr)?.as_ref(),
-                decode_string(&mut fields_itr)?.as_ref(),
-                decode_string(&mut fields_itr)?.as_ref(),
+                req_id,
+                account.as_ref(),
+                tag.as_ref(),
+                value.as_ref(),
+                currency.as_ref(),
             );
         Ok(())
     }
@@ -406,6 +503,8 @@ where
         let value = decode_string(&mut fields_itr)?;
         let currency = decode_string(&mut fields_itr)?;
 
+        self.accounts
+            .update_account_multi(&account, &model_code, &key, &value, &currency);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -450,10 +549,13 @@ where
         //throw away version
         fields_itr.next();
 
+        let account = decode_string(&mut fields_itr)?;
+
+        self.accounts.account_download_end(&account);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
-            .account_download_end(decode_string(&mut fields_itr)?.as_ref());
+            .account_download_end(account.as_ref());
         Ok(())
     }
 
@@ -466,10 +568,13 @@ where
         //throw away version
         fields_itr.next();
 
+        let time_stamp = decode_string(&mut fields_itr)?;
+
+        self.accounts.update_account_time(&time_stamp);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
-            .update_account_time(decode_string(&mut fields_itr)?.as_ref());
+            .update_account_time(time_stamp.as_ref());
         Ok(())
     }
 
@@ -482,14 +587,21 @@ where
         //throw away version
         fields_itr.next();
 
+        let key = decode_string(&mut fields_itr)?;
+        let value = decode_string(&mut fields_itr)?;
+        let currency = decode_string(&mut fields_itr)?;
+        let account_name = decode_string(&mut fields_itr)?;
+
+        self.accounts
+            .update_account_value(&key, &value, &currency, &account_name);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
             .update_account_value(
-                decode_string(&mut fields_itr)?.as_ref(),
-                decode_string(&mut fields_itr)?.as_ref(),
-                decode_string(&mut fields_itr)?.as_ref(),
-                decode_string(&mut fields_itr)?.as_ref(),
+                key.as_ref(),
+                value.as_ref(),
+                currency.as_ref(),
+                account_name.as_ref(),
             );
         Ok(())
     }
@@ -592,6 +704,8 @@ where
 
         commission_report.yield_redemption_date = decode_string(&mut fields_itr)?;
 
+        self.trade_blotter
+            .add_commission_report(commission_report.clone());
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -725,6 +839,7 @@ where
             contract.real_expiration_date = decode_string(&mut fields_itr)?;
         }
 
+        self.option_chains.add_contract_details(req_id, &contract);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -743,6 +858,7 @@ where
 
         let req_id = decode_i32(&mut fields_itr)?;
 
+        self.option_chains.forget_con_id(req_id);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -840,10 +956,19 @@ where
         //throw away version
         fields_itr.next();
 
+        let req_id = decode_i32(&mut fields_itr)?;
+        let error_code = decode_i32(&mut fields_itr)?;
+        let error_string = decode_string(&mut fields_itr)?;
+
+        self.order_tracker
+            .record_error(req_id, error_code, error_string.as_ref());
+        self.historical_data
+            .record_error(req_id, error_code, error_string.as_ref());
+
         self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).error(
-            decode_i32(&mut fields_itr)?,
-            decode_i32(&mut fields_itr)?,
-            decode_string(&mut fields_itr)?.as_ref(),
+            req_id,
+            error_code,
+            error_string.as_ref(),
         );
         Ok(())
     }
@@ -891,7 +1016,11 @@ where
         let mut execution = Execution::default();
         execution.order_id = order_id;
         execution.exec_id = decode_string(&mut fields_itr)?;
-        execution.time = decode_string(&mut fields_itr)?;
+        execution.time =
+            match self.parse_time(req_id, &decode_string(&mut fields_itr)?, FORMAT_DATE_TEXT) {
+                Some(time) => time,
+                None => return Ok(()),
+            };
         execution.acct_number = decode_string(&mut fields_itr)?;
         execution.exchange = decode_string(&mut fields_itr)?;
         execution.side = decode_string(&mut fields_itr)?;
@@ -934,10 +1063,15 @@ where
             execution.last_liquidity = decode_i32(&mut fields_itr)?;
         }
 
-        self.wrapper
-            .lock()
-            .expect(WRAPPER_POISONED_MUTEX)
-            .exec_details(req_id, contract, execution);
+        self.trade_blotter
+            .add_execution(contract.clone(), execution.clone());
+        // The filters of req_executions are applied loosely by TWS
+        if self.trade_blotter.accepts(req_id, &contract, &execution) {
+            self.wrapper
+                .lock()
+                .expect(WRAPPER_POISONED_MUTEX)
+                .exec_details(req_id, contract, execution);
+        }
         Ok(())
     }
 
@@ -952,6 +1086,7 @@ where
 
         let req_id = decode_i32(&mut fields_itr)?;
 
+        self.trade_blotter.forget_executions(req_id);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1009,10 +1144,11 @@ where
 
         let req_id = decode_i32(&mut fields_itr)?;
         let timestamp = decode_string(&mut fields_itr)?;
+        self.historical_data.head_timestamp(req_id, timestamp.as_ref());
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
-            .fundamental_data(req_id, timestamp.as_ref());
+            .head_timestamp(req_id, timestamp.as_ref());
         Ok(())
     }
 
@@ -1058,10 +1194,12 @@ where
         let _peek = *(fields_itr.clone()).peekable().peek().unwrap();
 
         let bar_count = decode_i32(&mut fields_itr)?;
+        let format_date = self.historical_data.format_date(req_id);
 
         for _ in 0..bar_count {
             let mut bar = BarData::default();
-            bar.date = decode_string(&mut fields_itr)?;
+            // The fields of a bar with an unparsable date are still consumed, for the next bars
+            let date = self.parse_time(req_id, &decode_string(&mut fields_itr)?, format_date);
             bar.open = decode_f64(&mut fields_itr)?;
             bar.high = decode_f64(&mut fields_itr)?;
             bar.low = decode_f64(&mut fields_itr)?;
@@ -1079,6 +1217,11 @@ where
 
             bar.bar_count = decode_i32(&mut fields_itr)?; // ver 3 field
 
+            bar.date = match date {
+                Some(date) => date,
+                None => continue,
+            };
+            self.historical_data.add_bar(req_id, &bar);
             self.wrapper
                 .lock()
                 .expect(WRAPPER_POISONED_MUTEX)
@@ -1086,6 +1229,7 @@ where
         }
 
         // send end of dataset marker
+        self.historical_data.end(req_id);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1104,7 +1248,14 @@ where
 
         let mut bar = BarData::default();
         bar.bar_count = decode_i32(&mut fields_itr)?;
-        bar.date = decode_string(&mut fields_itr)?;
+        bar.date = match self.parse_time(
+            req_id,
+            &decode_string(&mut fields_itr)?,
+            self.historical_data.format_date(req_id),
+        ) {
+            Some(date) => date,
+            None => return Ok(()),
+        };
         bar.open = decode_f64(&mut fields_itr)?;
         bar.close = decode_f64(&mut fields_itr)?;
         bar.high = decode_f64(&mut fields_itr)?;
@@ -1174,7 +1325,7 @@ where
 
         for _ in 0..tick_count {
             let mut historical_tick = HistoricalTick::default();
-            historical_tick.time = decode_i32(&mut fields_itr)?;
+            historical_tick.time = from_epoch_seconds(decode_i64(&mut fields_itr)?)?;
             fields_itr.next(); // for consistency
             historical_tick.price = decode_f64(&mut fields_itr)?;
             historical_tick.size = decode_i32(&mut fields_itr)?;
@@ -1183,6 +1334,8 @@ where
 
         let done = decode_bool(&mut fields_itr)?;
 
+        self.historical_data
+            .add_ticks(req_id, HistoricalTicks::Midpoint(ticks.clone()), done);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1207,7 +1360,7 @@ where
 
         for _ in 0..tick_count {
             let mut historical_tick_bid_ask = HistoricalTickBidAsk::default();
-            historical_tick_bid_ask.time = decode_i32(&mut fields_itr)?;
+            historical_tick_bid_ask.time = from_epoch_seconds(decode_i64(&mut fields_itr)?)?;
             let mask = decode_i32(&mut fields_itr)?;
             let mut tick_attrib_bid_ask = TickAttribBidAsk::default();
             tick_attrib_bid_ask.ask_past_high = mask & 1 != 0;
@@ -1222,6 +1375,8 @@ where
 
         let done = decode_bool(&mut fields_itr)?;
 
+        self.historical_data
+            .add_ticks(req_id, HistoricalTicks::BidAsk(ticks.clone()), done);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1243,7 +1398,7 @@ where
 
         for _ in 0..tick_count {
             let mut historical_tick_last = HistoricalTickLast::default();
-            historical_tick_last.time = decode_i32(&mut fields_itr)?;
+            historical_tick_last.time = from_epoch_seconds(decode_i64(&mut fields_itr)?)?;
             let mask = decode_i32(&mut fields_itr)?;
             let mut tick_attrib_last = TickAttribLast::default();
             tick_attrib_last.past_limit = mask & 1 != 0;
@@ -1258,6 +1413,8 @@ where
 
         let done = decode_bool(&mut fields_itr)?;
 
+        self.historical_data
+            .add_ticks(req_id, HistoricalTicks::Last(ticks.clone()), done);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1293,6 +1450,8 @@ where
         fields_itr.next();
         let req_id = decode_i32(&mut fields_itr)?;
         let market_data_type = decode_i32(&mut fields_itr)?;
+        self.market_data
+            .update_market_data_type(req_id, market_data_type);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1316,6 +1475,14 @@ where
         let price = decode_f64(&mut fields_itr)?;
         let size = decode_i32(&mut fields_itr)?;
 
+        self.apply_market_depth(
+            req_id,
+            position,
+            operation,
+            side,
+            DepthRow::new(price, size, "".to_string()),
+            false,
+        );
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1346,6 +1513,14 @@ where
             is_smart_depth = decode_bool(&mut fields_itr)?;
         }
 
+        self.apply_market_depth(
+            req_id,
+            position,
+            operation,
+            side,
+            DepthRow::new(price, size, market_maker.clone()),
+            is_smart_depth,
+        );
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1388,6 +1563,32 @@ where
         Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
+    /// Applies a depth update to the order book of the request.  When the update does not match
+    /// the book, the book is marked invalid and the wrapper receives a TwsError::InvalidOrderBook
+    /// error, upon which EClient::resubscribe_invalid_mkt_depth requests it again.
+    fn apply_market_depth(
+        &self,
+        req_id: i32,
+        position: i32,
+        operation: i32,
+        side: i32,
+        row: DepthRow,
+        is_smart_depth: bool,
+    ) {
+        if let Err(err) =
+            self.market_depth
+                .apply(req_id, position, operation, side, row, is_smart_depth)
+        {
+            warn!("Order book {} needs to be requested again: {}", req_id, err);
+            self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).error(
+                req_id,
+                TwsError::InvalidOrderBook.code(),
+                &format!("{} {}", TwsError::InvalidOrderBook.message(), err),
+            );
+        }
+    }
+
     //----------------------------------------------------------------------------------------------
     fn process_market_depth_exchanges(&mut self, fields: &[String]) -> Result<(), IBKRApiLibError> {
         let mut fields_itr = fields.iter();
@@ -1528,6 +1729,12 @@ where
 
         order_decoder.decode_open(&mut fields_itr)?;
 
+        // A stale status is passed on as received, after an error telling it was ignored
+        let tracked = self
+            .order_tracker
+            .update_open_order(order.order_id, &contract, &order, &order_state);
+        self.report_out_of_order_status(order.order_id, tracked);
+
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1575,7 +1782,7 @@ where
 
         let order_id = decode_i32(&mut fields_itr)?;
 
-        let status = decode_string(&mut fields_itr)?;
+        let status = OrderStatus::from(decode_string(&mut fields_itr)?.as_str());
 
         let filled;
         if self.server_version >= MIN_SERVER_VER_FRACTIONAL_POSITIONS {
@@ -1605,12 +1812,16 @@ where
             mkt_cap_price = decode_f64(&mut fields_itr)?;
         }
 
+        // A stale status is still passed on as received, after an error telling it was ignored
+        let tracked = self.order_tracker.update_status(order_id, &status);
+        self.report_out_of_order_status(order_id, tracked);
+
         self.wrapper
             .try_lock()
             .expect(WRAPPER_POISONED_MUTEX)
             .order_status(
                 order_id,
-                status.as_ref(),
+                status,
                 filled,
                 remaining,
                 avg_fill_price,
@@ -1644,6 +1855,8 @@ where
             realized_pnl = decode_f64(&mut fields_itr)?;
         }
 
+        self.accounts
+            .update_pnl(req_id, daily_pnl, unrealized_pnl, realized_pnl);
         self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).pnl(
             req_id,
             daily_pnl,
@@ -1676,6 +1889,14 @@ where
 
         let value = decode_f64(&mut fields_itr)?;
 
+        self.accounts.update_pnl_single(
+            req_id,
+            pos,
+            daily_pnl,
+            unrealized_pnl,
+            realized_pnl,
+            value,
+        );
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1731,6 +1952,18 @@ where
             contract.primary_exchange = decode_string(&mut fields_itr)?;
         }
 
+        self.accounts.update_portfolio(
+            &account_name,
+            AccountPosition::from_portfolio(
+                contract.clone(),
+                position,
+                market_price,
+                market_value,
+                average_cost,
+                unrealized_pnl,
+                realized_pnl,
+            ),
+        );
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1786,6 +2019,12 @@ where
             avg_cost = decode_f64(&mut fields_itr)?;
         }
 
+        self.accounts.update_position(
+            AccountKey::account(&account),
+            contract.clone(),
+            position,
+            avg_cost,
+        );
         self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).position(
             account.as_ref(),
             contract,
@@ -1835,6 +2074,12 @@ where
         let avg_cost = decode_f64(&mut fields_itr)?;
         let model_code = decode_string(&mut fields_itr)?;
 
+        self.accounts.update_position(
+            AccountKey::new(&account, &model_code),
+            contract.clone(),
+            position,
+            avg_cost,
+        );
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -1879,7 +2124,11 @@ where
         let req_id = decode_i32(&mut fields_itr)?;
 
         let mut bar = RealTimeBar::default();
-        bar.date_time = decode_string(&mut fields_itr)?;
+        bar.date_time =
+            match self.parse_time(req_id, &decode_string(&mut fields_itr)?, FORMAT_DATE_EPOCH) {
+                Some(date_time) => date_time,
+                None => return Ok(()),
+            };
         bar.open = decode_f64(&mut fields_itr)?;
         bar.high = decode_f64(&mut fields_itr)?;
         bar.low = decode_f64(&mut fields_itr)?;
@@ -1907,10 +2156,31 @@ where
         let fa_data_type = decode_i32(&mut fields_itr)?;
         let xml = decode_string(&mut fields_itr)?;
 
+        let fa_data: FaDataType = FromPrimitive::from_i32(fa_data_type).unwrap();
+        // XML that cannot be parsed is still passed to the wrapper, without configuration, after
+        // an error telling why.  The households of the AccountStore are then left unchanged.
+        let config = match FaConfig::parse(fa_data, &xml) {
+            Ok(config) => Some(config),
+            Err(err) => {
+                warn!("Cannot parse the FA configuration: {}", err);
+                if let IBKRApiLibError::ApiError(err) = &err {
+                    self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).error(
+                        NO_VALID_ID,
+                        TwsError::InvalidArgument.code(),
+                        &err.description,
+                    );
+                }
+                None
+            }
+        };
+        if let Some(FaConfig::Groups(groups)) = &config {
+            self.accounts.update_fa_groups(groups.clone());
+        }
+
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
-            .receive_fa(FromPrimitive::from_i32(fa_data_type).unwrap(), xml.as_ref());
+            .receive_fa(fa_data, xml.as_ref(), config);
         Ok(())
     }
 
@@ -1984,6 +2254,7 @@ where
             data.benchmark = decode_string(&mut fields_itr)?;
             data.projection = decode_string(&mut fields_itr)?;
             data.legs = decode_string(&mut fields_itr)?;
+            self.scanner.add_row(req_id, data.clone());
             self.wrapper
                 .lock()
                 .expect(WRAPPER_POISONED_MUTEX)
@@ -1998,6 +2269,7 @@ where
                 );
         }
 
+        self.scanner.end(req_id);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -2048,12 +2320,26 @@ where
 
         let strike_count = decode_i32(&mut fields_itr)?;
         let mut strikes = HashSet::new();
+        let mut strike_values = vec![];
         for _ in 0..strike_count {
             let strike = decode_f64(&mut fields_itr)?;
             let big_strike = BigDecimal::from_f64(strike).unwrap();
             strikes.insert(big_strike);
+            strike_values.push(strike);
         }
 
+        self.option_chains.add_parameters(
+            req_id,
+            OptionChainParameters::new(
+                &exchange,
+                underlying_con_id,
+                &trading_class,
+                &multiplier,
+                expirations.iter().cloned(),
+                strike_values,
+            ),
+        );
+
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -2080,6 +2366,7 @@ where
         fields_itr.next();
 
         let req_id = decode_i32(&mut fields_itr)?;
+        self.option_chains.end(req_id);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -2271,6 +2558,21 @@ where
         let future_last_trade_date = decode_string(&mut fields_itr)?;
         let dividend_impact = decode_f64(&mut fields_itr)?;
         let dividends_to_last_trade_date = decode_f64(&mut fields_itr)?;
+        if let Some(tick_type) = FromPrimitive::from_i32(tick_type) {
+            self.market_data.update_efp(
+                ticker_id,
+                tick_type,
+                EfpComputation::new(
+                    basis_points,
+                    formatted_basis_points.clone(),
+                    implied_futures_price,
+                    hold_days,
+                    future_last_trade_date.clone(),
+                    dividend_impact,
+                    dividends_to_last_trade_date,
+                ),
+            );
+        }
         self.wrapper.lock().expect(WRAPPER_POISONED_MUTEX).tick_efp(
             ticker_id,
             FromPrimitive::from_i32(tick_type).unwrap(),
@@ -2298,6 +2600,9 @@ where
         let tick_type = decode_i32(&mut fields_itr)?;
         let value = decode_f64(&mut fields_itr)?;
 
+        if let Some(tick_type) = FromPrimitive::from_i32(tick_type) {
+            self.market_data.update_generic(ticker_id, tick_type, value);
+        }
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -2348,76 +2653,48 @@ where
         let version = decode_i32(&mut fields_itr)?;
         let ticker_id = decode_i32(&mut fields_itr)?;
         let tick_type = decode_i32(&mut fields_itr)?;
-        let mut implied_vol = decode_f64(&mut fields_itr)?;
-        if approx_eq!(f64, implied_vol, -1.0, ulps = 2) {
-            // -1 is the "not yet computed" indicator
-            implied_vol = f64::max_value();
-        }
-
-        let mut delta = decode_f64(&mut fields_itr)?;
-        if approx_eq!(f64, delta, -2.0, ulps = 2) {
-            // -2 is the "not yet computed" indicator
-            delta = f64::max_value();
-        }
-        let mut opt_price = f64::max_value();
-        let mut pv_dividend = f64::max_value();
-        let mut gamma = f64::max_value();
-        let mut vega = f64::max_value();
-        let mut theta = f64::max_value();
-        let mut und_price = f64::max_value();
+        // -1 and -2 are the "not yet computed" indicators
+        let mut computation = OptionComputation {
+            implied_vol: OptionComputation::computed(decode_f64(&mut fields_itr)?, -1.0),
+            delta: OptionComputation::computed(decode_f64(&mut fields_itr)?, -2.0),
+            ..Default::default()
+        };
         if version >= 6
             || tick_type == TickType::ModelOption as i32
             || tick_type == TickType::DelayedModelOption as i32
         {
             // introduced in version == 5
-            opt_price = decode_f64(&mut fields_itr)?;
-            if approx_eq!(f64, opt_price, -1.0, ulps = 2) {
-                // -1 is the "not yet computed" indicator
-                opt_price = f64::max_value();
-            }
-            pv_dividend = decode_f64(&mut fields_itr)?;
-            if approx_eq!(f64, pv_dividend, -1.0, ulps = 2) {
-                // -1 is the "not yet computed" indicator
-                pv_dividend = f64::max_value();
-            }
+            computation.opt_price = OptionComputation::computed(decode_f64(&mut fields_itr)?, -1.0);
+            computation.pv_dividend =
+                OptionComputation::computed(decode_f64(&mut fields_itr)?, -1.0);
         }
         if version >= 6 {
-            gamma = decode_f64(&mut fields_itr)?;
-            if approx_eq!(f64, gamma, -2.0, ulps = 2) {
-                // -2 is the "not yet computed" indicator
-                gamma = f64::max_value();
-            }
-            vega = decode_f64(&mut fields_itr)?;
-            if approx_eq!(f64, vega, -2.0, ulps = 2) {
-                // -2 is the "not yet computed" indicator
-                vega = f64::max_value();
-            }
-            theta = decode_f64(&mut fields_itr)?;
-            if approx_eq!(f64, theta, -2.0, ulps = 2) {
-                // -2 is the "not yet computed" indicator
-                theta = f64::max_value();
-            }
-            und_price = decode_f64(&mut fields_itr)?;
-            if approx_eq!(f64, und_price, -1.0, ulps = 2) {
-                // -1 is the "not yet computed" indicator
-                und_price = f64::max_value();
-            }
+            computation.gamma = OptionComputation::computed(decode_f64(&mut fields_itr)?, -2.0);
+            computation.vega = OptionComputation::computed(decode_f64(&mut fields_itr)?, -2.0);
+            computation.theta = OptionComputation::computed(decode_f64(&mut fields_itr)?, -2.0);
+            computation.und_price = OptionComputation::computed(decode_f64(&mut fields_itr)?, -1.0);
         }
 
+        if let Some(tick_type) = FromPrimitive::from_i32(tick_type) {
+            self.market_data
+                .update_option_computation(ticker_id, tick_type, computation);
+        }
+        // Values not computed are passed as f64::MAX
+        let value = |value: Option<f64>| value.unwrap_or(f64::MAX);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
             .tick_option_computation(
                 ticker_id,
                 FromPrimitive::from_i32(tick_type).unwrap(),
-                implied_vol,
-                delta,
-                opt_price,
-                pv_dividend,
-                gamma,
-                vega,
-                theta,
-                und_price,
+                value(computation.implied_vol),
+                value(computation.delta),
+                value(computation.opt_price),
+                value(computation.pv_dividend),
+                value(computation.gamma),
+                value(computation.vega),
+                value(computation.theta),
+                value(computation.und_price),
             );
         Ok(())
     }
@@ -2458,6 +2735,9 @@ where
         let tick_type = decode_i32(&mut fields_itr)?;
         let size = decode_i32(&mut fields_itr)?;
 
+        if let Some(tick_type) = FromPrimitive::from_i32(tick_type) {
+            self.market_data.update_size(ticker_id, tick_type, size);
+        }
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -2476,6 +2756,7 @@ where
 
         let req_id = decode_i32(&mut fields_itr)?;
 
+        self.market_data.snapshot_end(req_id);
         self.wrapper
             .lock()
             .expect(WRAPPER_POISONED_MUTEX)
@@ -18,6 +18,10 @@ const BAD_MESSAGE: (i32, &str) = (508, "Bad message.");
 const SOCKET_EXCEPTION: (i32, &str) = (509, "Exception caught while reading socket.");
 const FAIL_CREATE_SOCK: (i32, &str) = (520, "Failed to create socket.");
 const SSL_FAIL: (i32, &str) = (530, "SSL specific TwsError.");
+// Client side validation errors.  These are raised locally and never sent by TWS
+const INVALID_ARGUMENT: (i32, &str) = (600, "Invalid argument.");
+const OUT_OF_ORDER_STATUS: (i32, &str) = (601, "Out of order status ignored.");
+const INVALID_ORDER_BOOK: (i32, &str) = (602, "Order book invalid, it has to be requested again.");
 
 #[derive(Clone, Debug)]
 pub enum TwsError {
@@ -32,6 +36,9 @@ pub enum TwsError {
     SocketException,
     FailCreateSock,
     SslFail,
+    InvalidArgument,
+    OutOfOrderStatus,
+    InvalidOrderBook,
 }
 
 impl TwsError {
@@ -48,6 +55,9 @@ impl TwsError {
             TwsError::SocketException => SOCKET_EXCEPTION.0,
             TwsError::FailCreateSock => FAIL_CREATE_SOCK.0,
             TwsError::SslFail => SSL_FAIL.0,
+            TwsError::InvalidArgument => INVALID_ARGUMENT.0,
+            TwsError::OutOfOrderStatus => OUT_OF_ORDER_STATUS.0,
+            TwsError::InvalidOrderBook => INVALID_ORDER_BOOK.0,
         }
     }
     pub fn message(&self) -> &'static str {
@@ -63,6 +73,9 @@ impl TwsError {
             TwsError::SocketException => SOCKET_EXCEPTION.1,
             TwsError::FailCreateSock => FAIL_CREATE_SOCK.1,
             TwsError::SslFail => SSL_FAIL.1,
+            TwsError::InvalidArgument => INVALID_ARGUMENT.1,
+            TwsError::OutOfOrderStatus => OUT_OF_ORDER_STATUS.1,
+            TwsError::InvalidOrderBook => INVALID_ORDER_BOOK.1,
         }
     }
 }
@@ -85,6 +98,22 @@ pub enum IBKRApiLibError {
     ApiError(TwsApiReportableError),
 }
 
+impl IBKRApiLibError {
+    //----------------------------------------------------------------------------------------------
+    /// Returns a TwsError::InvalidArgument error, raised when a value is checked on the client side
+    ///
+    /// # Arguments
+    /// * id - the req_id or order_id the value belongs to, or NO_VALID_ID
+    /// * message - what is invalid
+    pub fn invalid_argument(id: i32, message: &str) -> Self {
+        IBKRApiLibError::ApiError(TwsApiReportableError::new(
+            id,
+            TwsError::InvalidArgument.code().to_string(),
+            format!("{} {}", TwsError::InvalidArgument.message(), message),
+        ))
+    }
+}
+
 impl fmt::Display for IBKRApiLibError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -1,13 +1,21 @@
 //! Types related to executions
 use std::fmt::{Display, Error, Formatter};
 
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
+use crate::core::common::Action;
+use crate::core::contract::Contract;
+use crate::core::timestamps::{format_optional_tws_time, format_tws_local_time};
+
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Execution {
     pub exec_id: String,
-    pub time: String,
+    /// Time of the execution, in UTC.  TWS sends it in its own time zone, and it is converted so
+    /// that executions compare directly with the times of ExecutionFilter.
+    pub time: DateTime<Utc>,
     pub acct_number: String,
     pub exchange: String,
     pub side: String,
@@ -29,7 +37,7 @@ pub struct Execution {
 impl Execution {
     pub fn new(
         exec_id: String,
-        time: String,
+        time: DateTime<Utc>,
         acct_number: String,
         exchange: String,
         side: String,
@@ -115,35 +123,161 @@ impl Display for Execution {
 }
 
 //==================================================================================================
+/// Criteria of the executions returned by req_executions.  Empty or None criteria match every
+/// execution.
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ExecutionFilter {
+    /// Id of the client which placed the orders, 0 for every client
     pub client_id: i32,
     pub acct_code: String,
-    pub time: String,
+    /// Only executions at or after this time are returned
+    pub time: Option<DateTime<Utc>>,
+    /// Time zone in which the time is sent, in UTC if None
+    pub time_zone: Option<Tz>,
     pub symbol: String,
     pub sec_type: String,
     pub exchange: String,
-    pub side: String,
+    /// Side of the executions, SSHORT matching the executions of sell orders
+    pub side: Option<Action>,
 }
 
 impl ExecutionFilter {
+    /// Creates a filter sending its time in UTC, see ExecutionFilterBuilder::time_zone to send it
+    /// in another time zone
     pub fn new(
         client_id: i32,
         acct_code: String,
-        time: String,
+        time: Option<DateTime<Utc>>,
         symbol: String,
         sec_type: String,
         exchange: String,
-        side: String,
+        side: Option<Action>,
     ) -> Self {
         ExecutionFilter {
             client_id,
             acct_code,
             time,
+            time_zone: None,
             symbol,
             sec_type,
             exchange,
             side,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts a filter matching every execution
+    pub fn builder() -> ExecutionFilterBuilder {
+        ExecutionFilterBuilder {
+            filter: ExecutionFilter::default(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the time as sent to TWS: "yyyymmdd hh:mm:ss" followed by the time zone if there is
+    /// one, else "yyyymmdd-hh:mm:ss" in UTC, or an empty string if there is no time
+    pub fn encoded_time(&self) -> String {
+        match (&self.time, &self.time_zone) {
+            (Some(time), Some(time_zone)) => {
+                format_tws_local_time(&time.with_timezone(time_zone).naive_local(), time_zone)
+            }
+            (time, None) => format_optional_tws_time(time),
+            (None, Some(_)) => "".to_string(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the side as sent to TWS, which only knows BUY and SELL
+    pub fn encoded_side(&self) -> String {
+        match self.side {
+            Some(Action::BUY) => "BUY".to_string(),
+            Some(Action::SELL) | Some(Action::SSHORT) => "SELL".to_string(),
+            None => "".to_string(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns whether an execution received by exec_details matches the filter.  TWS applies
+    /// some criteria loosely, so the executions it returns may not.
+    pub fn matches(&self, contract: &Contract, execution: &Execution) -> bool {
+        let matches_text = |criterion: &str, value: &str| {
+            criterion.is_empty() || criterion.eq_ignore_ascii_case(value)
+        };
+        (self.client_id == 0 || self.client_id == execution.client_id)
+            && matches_text(&self.acct_code, &execution.acct_number)
+            && self.time.map_or(true, |time| execution.time >= time)
+            && matches_text(&self.symbol, &contract.symbol)
+            && matches_text(&self.sec_type, &contract.sec_type)
+            && matches_text(&self.exchange, &execution.exchange)
+            && match self.side {
+                Some(Action::BUY) => execution.side == "BOT",
+                Some(Action::SELL) | Some(Action::SSHORT) => execution.side == "SLD",
+                None => true,
+            }
+    }
+}
+
+//==================================================================================================
+/// Builds an ExecutionFilter, e.g.
+/// `ExecutionFilter::builder().symbol("ES").side(Action::BUY).since(time).build()`
+#[derive(Clone, Debug)]
+pub struct ExecutionFilterBuilder {
+    filter: ExecutionFilter,
+}
+
+impl ExecutionFilterBuilder {
+    //----------------------------------------------------------------------------------------------
+    pub fn client_id(mut self, client_id: i32) -> Self {
+        self.filter.client_id = client_id;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn account(mut self, acct_code: &str) -> Self {
+        self.filter.acct_code = acct_code.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Only returns the executions at or after a time
+    pub fn since<T: TimeZone>(mut self, time: DateTime<T>) -> Self {
+        self.filter.time = Some(time.with_timezone(&Utc));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sends the time in a time zone rather than in UTC
+    pub fn time_zone(mut self, time_zone: Tz) -> Self {
+        self.filter.time_zone = Some(time_zone);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.filter.symbol = symbol.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn sec_type(mut self, sec_type: &str) -> Self {
+        self.filter.sec_type = sec_type.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn exchange(mut self, exchange: &str) -> Self {
+        self.filter.exchange = exchange.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn side(mut self, side: Action) -> Self {
+        self.filter.side = Some(side);
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn build(self) -> ExecutionFilter {
+        self.filter
+    }
 }
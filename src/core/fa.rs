@@ -0,0 +1,440 @@
+//! Typed financial advisor configuration, exchanged as XML by request_fa, replace_fa and receive_fa
+use std::collections::HashSet;
+use std::fmt::{Display, Error, Formatter, Write};
+use std::str::FromStr;
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{FaDataType, NO_VALID_ID};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::xml::{child, child_text, children, escape, invalid_xml, parse_document, text};
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>";
+
+//----------------------------------------------------------------------------------------------
+fn invalid_fa(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(
+        NO_VALID_ID,
+        &format!("Invalid FA configuration: {}", message),
+    )
+}
+
+//----------------------------------------------------------------------------------------------
+fn required_text(node: Node, name: &str) -> Result<String, IBKRApiLibError> {
+    child_text(node, name)
+        .ok_or_else(|| invalid_xml(format!("{} has no {}", node.tag_name().name(), name)))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Checks that a name is set and that the accounts are set and not repeated
+fn check_accounts<'a, I: Iterator<Item = &'a String>>(
+    kind: &str,
+    name: &str,
+    accounts: I,
+) -> Result<(), IBKRApiLibError> {
+    if name.trim().is_empty() {
+        return Err(invalid_fa(format!("{} without name", kind)));
+    }
+    let mut seen = HashSet::new();
+    for account in accounts {
+        if account.trim().is_empty() {
+            return Err(invalid_fa(format!(
+                "{} {} has an empty account",
+                kind, name
+            )));
+        }
+        if !seen.insert(account) {
+            return Err(invalid_fa(format!(
+                "{} {} has account {} twice",
+                kind, name, account
+            )));
+        }
+    }
+    if seen.is_empty() {
+        return Err(invalid_fa(format!("{} {} has no account", kind, name)));
+    }
+    Ok(())
+}
+
+//==================================================================================================
+/// How the orders placed for an FA group are allocated to its accounts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FaAllocationMethod {
+    /// The same quantity for each account
+    EqualQuantity,
+    /// A change of each position by the percentage set in Order::fa_percentage
+    PctChange,
+    /// In proportion to the net liquidation value of each account
+    NetLiq,
+    /// In proportion to the available equity of each account
+    AvailableEquity,
+    /// A method not listed above, such as those of newer TWS versions, kept as sent
+    Other(String),
+}
+
+impl Display for FaAllocationMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            FaAllocationMethod::EqualQuantity => write!(f, "EqualQuantity"),
+            FaAllocationMethod::PctChange => write!(f, "PctChange"),
+            FaAllocationMethod::NetLiq => write!(f, "NetLiq"),
+            FaAllocationMethod::AvailableEquity => write!(f, "AvailableEquity"),
+            FaAllocationMethod::Other(method) => write!(f, "{}", method),
+        }
+    }
+}
+
+impl FromStr for FaAllocationMethod {
+    type Err = IBKRApiLibError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "EqualQuantity" => Ok(FaAllocationMethod::EqualQuantity),
+            "PctChange" => Ok(FaAllocationMethod::PctChange),
+            "NetLiq" => Ok(FaAllocationMethod::NetLiq),
+            "AvailableEquity" => Ok(FaAllocationMethod::AvailableEquity),
+            "" => Err(invalid_fa("Empty allocation method".to_string())),
+            method => Ok(FaAllocationMethod::Other(method.to_string())),
+        }
+    }
+}
+
+//==================================================================================================
+/// A group of accounts of a financial advisor, such as a household
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaGroup {
+    pub name: String,
+    pub accounts: Vec<String>,
+    pub default_method: FaAllocationMethod,
+}
+
+impl FaGroup {
+    pub fn new(name: &str, accounts: &[&str], default_method: FaAllocationMethod) -> Self {
+        FaGroup {
+            name: name.to_string(),
+            accounts: accounts.iter().map(|account| account.to_string()).collect(),
+            default_method,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks that the group has a name and accounts, each listed once
+    pub fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_accounts("Group", &self.name, self.accounts.iter())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses the ListOfGroups document sent by receive_fa for FaDataType::GROUPS
+    pub fn parse_list(xml: &str) -> Result<Vec<FaGroup>, IBKRApiLibError> {
+        let document = parse_document(xml, "ListOfGroups")?;
+        children(document.root_element(), "Group")
+            .map(|group| {
+                Ok(FaGroup {
+                    name: required_text(group, "name")?,
+                    accounts: child(group, "ListOfAccts")
+                        .map(|accounts| children(accounts, "String").filter_map(text).collect())
+                        .unwrap_or_default(),
+                    default_method: required_text(group, "defaultMethod")?.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Writes groups as the ListOfGroups document expected by replace_fa
+    pub fn list_to_xml(groups: &[FaGroup]) -> String {
+        let mut xml = format!("{}\n<ListOfGroups>\n", XML_DECLARATION);
+        for group in groups {
+            xml.push_str("<Group>\n");
+            writeln!(xml, "<name>{}</name>", escape(&group.name)).unwrap();
+            xml.push_str("<ListOfAccts varName=\"list\">\n");
+            for account in &group.accounts {
+                writeln!(xml, "<String>{}</String>", escape(account)).unwrap();
+            }
+            xml.push_str("</ListOfAccts>\n");
+            writeln!(
+                xml,
+                "<defaultMethod>{}</defaultMethod>",
+                group.default_method
+            )
+            .unwrap();
+            xml.push_str("</Group>\n");
+        }
+        xml.push_str("</ListOfGroups>");
+        xml
+    }
+}
+
+//==================================================================================================
+/// How the amounts of an allocation profile are read
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq)]
+pub enum FaProfileType {
+    /// Percentages of the order quantity, adding up to 100
+    Percentages = 1,
+    /// Ratios of the order quantity
+    FinancialRatios = 2,
+    /// Numbers of shares
+    Shares = 3,
+}
+
+impl Display for FaProfileType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            FaProfileType::Percentages => write!(f, "Percentages"),
+            FaProfileType::FinancialRatios => write!(f, "FinancialRatios"),
+            FaProfileType::Shares => write!(f, "Shares"),
+        }
+    }
+}
+
+//==================================================================================================
+/// The share of the orders of an allocation profile given to an account
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaAllocation {
+    pub account: String,
+    pub amount: f64,
+}
+
+impl FaAllocation {
+    pub fn new(account: &str, amount: f64) -> Self {
+        FaAllocation {
+            account: account.to_string(),
+            amount,
+        }
+    }
+}
+
+//==================================================================================================
+/// An allocation profile of a financial advisor, splitting the orders placed for it between
+/// accounts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaProfile {
+    pub name: String,
+    pub profile_type: FaProfileType,
+    pub allocations: Vec<FaAllocation>,
+}
+
+impl FaProfile {
+    pub fn new(name: &str, profile_type: FaProfileType, allocations: Vec<FaAllocation>) -> Self {
+        FaProfile {
+            name: name.to_string(),
+            profile_type,
+            allocations,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks that the profile has a name and accounts, each listed once with a positive amount,
+    /// and that percentages add up to 100
+    pub fn validate(&self) -> Result<(), IBKRApiLibError> {
+        check_accounts(
+            "Profile",
+            &self.name,
+            self.allocations
+                .iter()
+                .map(|allocation| &allocation.account),
+        )?;
+        if let Some(allocation) = self
+            .allocations
+            .iter()
+            .find(|allocation| allocation.amount.is_nan() || allocation.amount <= 0.0)
+        {
+            return Err(invalid_fa(format!(
+                "Profile {} allocates {} to account {}",
+                self.name, allocation.amount, allocation.account
+            )));
+        }
+        let total: f64 = self
+            .allocations
+            .iter()
+            .map(|allocation| allocation.amount)
+            .sum();
+        if self.profile_type == FaProfileType::Percentages && (total - 100.0).abs() > 1e-6 {
+            return Err(invalid_fa(format!(
+                "Percentages of profile {} add up to {}, not 100",
+                self.name, total
+            )));
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses the ListOfAllocationProfiles document sent by receive_fa for FaDataType::PROFILES
+    pub fn parse_list(xml: &str) -> Result<Vec<FaProfile>, IBKRApiLibError> {
+        let document = parse_document(xml, "ListOfAllocationProfiles")?;
+        children(document.root_element(), "AllocationProfile")
+            .map(|profile| {
+                let name = required_text(profile, "name")?;
+                let profile_type = required_text(profile, "type")?;
+                let profile_type = profile_type
+                    .parse::<i32>()
+                    .ok()
+                    .and_then(FromPrimitive::from_i32)
+                    .ok_or_else(|| {
+                        invalid_xml(format!("unknown type {} of profile {}", profile_type, name))
+                    })?;
+                let allocations = match child(profile, "ListOfAllocations") {
+                    Some(allocations) => children(allocations, "Allocation")
+                        .map(|allocation| {
+                            let amount = required_text(allocation, "amount")?;
+                            Ok(FaAllocation {
+                                account: required_text(allocation, "acct")?,
+                                amount: amount.parse().map_err(|_| {
+                                    invalid_xml(format!("invalid amount {}", amount))
+                                })?,
+                            })
+                        })
+                        .collect::<Result<Vec<FaAllocation>, IBKRApiLibError>>()?,
+                    None => vec![],
+                };
+                Ok(FaProfile {
+                    name,
+                    profile_type,
+                    allocations,
+                })
+            })
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Writes profiles as the ListOfAllocationProfiles document expected by replace_fa
+    pub fn list_to_xml(profiles: &[FaProfile]) -> String {
+        let mut xml = format!("{}\n<ListOfAllocationProfiles>\n", XML_DECLARATION);
+        for profile in profiles {
+            xml.push_str("<AllocationProfile>\n");
+            writeln!(xml, "<name>{}</name>", escape(&profile.name)).unwrap();
+            writeln!(xml, "<type>{}</type>", profile.profile_type as i32).unwrap();
+            xml.push_str("<ListOfAllocations varName=\"listOfAllocations\">\n");
+            for allocation in &profile.allocations {
+                writeln!(
+                    xml,
+                    "<Allocation>\n<acct>{}</acct>\n<amount>{:?}</amount>\n</Allocation>",
+                    escape(&allocation.account),
+                    allocation.amount
+                )
+                .unwrap();
+            }
+            xml.push_str("</ListOfAllocations>\n</AllocationProfile>\n");
+        }
+        xml.push_str("</ListOfAllocationProfiles>");
+        xml
+    }
+}
+
+//==================================================================================================
+/// A name given to an account of a financial advisor
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaAlias {
+    pub account: String,
+    pub alias: String,
+}
+
+impl FaAlias {
+    pub fn new(account: &str, alias: &str) -> Self {
+        FaAlias {
+            account: account.to_string(),
+            alias: alias.to_string(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses the ListOfAccountAliases document sent by receive_fa for FaDataType::ALIASES
+    pub fn parse_list(xml: &str) -> Result<Vec<FaAlias>, IBKRApiLibError> {
+        let document = parse_document(xml, "ListOfAccountAliases")?;
+        children(document.root_element(), "AccountAlias")
+            .map(|alias| {
+                Ok(FaAlias {
+                    account: required_text(alias, "account")?,
+                    alias: child_text(alias, "alias").unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Writes aliases as the ListOfAccountAliases document expected by replace_fa
+    pub fn list_to_xml(aliases: &[FaAlias]) -> String {
+        let mut xml = format!("{}\n<ListOfAccountAliases>\n", XML_DECLARATION);
+        for alias in aliases {
+            writeln!(
+                xml,
+                "<AccountAlias>\n<account>{}</account>\n<alias>{}</alias>\n</AccountAlias>",
+                escape(&alias.account),
+                escape(&alias.alias)
+            )
+            .unwrap();
+        }
+        xml.push_str("</ListOfAccountAliases>");
+        xml
+    }
+}
+
+//==================================================================================================
+/// A financial advisor configuration, as exchanged by replace_fa and receive_fa
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FaConfig {
+    Groups(Vec<FaGroup>),
+    Profiles(Vec<FaProfile>),
+    Aliases(Vec<FaAlias>),
+}
+
+impl FaConfig {
+    //----------------------------------------------------------------------------------------------
+    /// Parses the XML document of a type of configuration
+    pub fn parse(fa_data: FaDataType, xml: &str) -> Result<Self, IBKRApiLibError> {
+        match fa_data {
+            FaDataType::GROUPS => Ok(FaConfig::Groups(FaGroup::parse_list(xml)?)),
+            FaDataType::PROFILES => Ok(FaConfig::Profiles(FaProfile::parse_list(xml)?)),
+            FaDataType::ALIASES => Ok(FaConfig::Aliases(FaAlias::parse_list(xml)?)),
+            FaDataType::NA => Err(invalid_fa("No FA data type".to_string())),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn data_type(&self) -> FaDataType {
+        match self {
+            FaConfig::Groups(_) => FaDataType::GROUPS,
+            FaConfig::Profiles(_) => FaDataType::PROFILES,
+            FaConfig::Aliases(_) => FaDataType::ALIASES,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks the groups or profiles, and that their names are unique
+    pub fn validate(&self) -> Result<(), IBKRApiLibError> {
+        let mut names = HashSet::new();
+        let mut check_name = |name: &str| {
+            if names.insert(name.to_string()) {
+                Ok(())
+            } else {
+                Err(invalid_fa(format!("{} is defined twice", name)))
+            }
+        };
+        match self {
+            FaConfig::Groups(groups) => groups.iter().try_for_each(|group| {
+                group.validate()?;
+                check_name(&group.name)
+            }),
+            FaConfig::Profiles(profiles) => profiles.iter().try_for_each(|profile| {
+                profile.validate()?;
+                check_name(&profile.name)
+            }),
+            FaConfig::Aliases(aliases) => aliases
+                .iter()
+                .try_for_each(|alias| check_name(&alias.account)),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn to_xml(&self) -> String {
+        match self {
+            FaConfig::Groups(groups) => FaGroup::list_to_xml(groups),
+            FaConfig::Profiles(profiles) => FaProfile::list_to_xml(profiles),
+            FaConfig::Aliases(aliases) => FaAlias::list_to_xml(aliases),
+        }
+    }
+}
@@ -0,0 +1,661 @@
+//! Typed parsers of the XML reports sent to fundamental_data for req_fundamental_data
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{FundamentalType, NO_VALID_ID};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::xml::{child, child_text, children, parse_document, text};
+
+//----------------------------------------------------------------------------------------------
+fn invalid_report(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(
+        NO_VALID_ID,
+        &format!("Invalid fundamental data: {}", message),
+    )
+}
+
+//----------------------------------------------------------------------------------------------
+fn descendant<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.descendants().find(|child| child.has_tag_name(name))
+}
+
+//----------------------------------------------------------------------------------------------
+fn number(node: Node) -> Option<f64> {
+    text(node)?.parse::<f64>().ok()
+}
+
+//----------------------------------------------------------------------------------------------
+fn attr_string(node: Node, name: &str) -> String {
+    node.attribute(name).unwrap_or_default().to_string()
+}
+
+//----------------------------------------------------------------------------------------------
+fn attr_i32(node: Node, name: &str) -> Option<i32> {
+    node.attribute(name)?.trim().parse::<i32>().ok()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Parses a date such as "2019-09-28" or "2019-09-28T00:00:00"
+fn date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text.get(..10).unwrap_or(text), "%Y-%m-%d").ok()
+}
+
+//----------------------------------------------------------------------------------------------
+fn attr_date(node: Node, name: &str) -> Option<NaiveDate> {
+    date(node.attribute(name)?)
+}
+
+//==================================================================================================
+/// A company officer
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Officer {
+    pub rank: Option<i32>,
+    pub first_name: String,
+    pub last_name: String,
+    pub age: Option<i32>,
+    pub title: String,
+}
+
+//==================================================================================================
+/// An industry classification of a company, such as TRBC, NAICS or SIC
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Industry {
+    pub classification: String,
+    pub code: String,
+    pub description: String,
+}
+
+//==================================================================================================
+/// Identification and general information of a company, sent in most reports
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CompanyInfo {
+    /// Reuters company number
+    pub rep_no: Option<String>,
+    pub name: Option<String>,
+    pub ticker: Option<String>,
+    pub exchange: Option<String>,
+    pub employees: Option<i64>,
+    pub shares_outstanding: Option<f64>,
+    pub float_shares: Option<f64>,
+    pub reporting_currency: Option<String>,
+    pub latest_annual: Option<NaiveDate>,
+    pub latest_interim: Option<NaiveDate>,
+    pub business_summary: Option<String>,
+    pub web_site: Option<String>,
+    pub industries: Vec<Industry>,
+    pub officers: Vec<Officer>,
+}
+
+impl CompanyInfo {
+    //----------------------------------------------------------------------------------------------
+    /// Reads the company sections found under a node.  Missing sections are left empty.
+    fn parse(node: Node) -> Self {
+        let mut info = CompanyInfo::default();
+        if let Some(co_ids) = descendant(node, "CoIDs") {
+            for co_id in children(co_ids, "CoID") {
+                match co_id.attribute("Type") {
+                    Some("RepNo") => info.rep_no = text(co_id),
+                    Some("CompanyName") => info.name = text(co_id),
+                    _ => (),
+                }
+            }
+        }
+
+        let primary_issue = descendant(node, "Issues").and_then(|issues| {
+            children(issues, "Issue")
+                .find(|issue| issue.attribute("Order") == Some("1"))
+                .or_else(|| child(issues, "Issue"))
+        });
+        if let Some(issue) = primary_issue {
+            info.ticker = children(issue, "IssueID")
+                .find(|id| id.attribute("Type") == Some("Ticker"))
+                .and_then(text);
+            info.exchange = child(issue, "Exchange")
+                .and_then(|exchange| exchange.attribute("Code"))
+                .map(str::to_string);
+        }
+        if info.ticker.is_none() {
+            info.ticker = node
+                .descendants()
+                .find(|id| id.has_tag_name("SecId") && id.attribute("type") == Some("TICKER"))
+                .and_then(text);
+        }
+
+        if let Some(general) = descendant(node, "CoGeneralInfo") {
+            info.employees = child_text(general, "Employees").and_then(|e| e.parse().ok());
+            if let Some(shares) = child(general, "SharesOut") {
+                info.shares_outstanding = number(shares);
+                info.float_shares = shares
+                    .attribute("TotalFloat")
+                    .and_then(|float| float.parse().ok());
+            }
+            info.reporting_currency = child(general, "ReportingCurrency")
+                .and_then(|currency| currency.attribute("Code"))
+                .map(str::to_string);
+            info.latest_annual =
+                child_text(general, "LatestAvailableAnnual").and_then(|d| date(&d));
+            info.latest_interim =
+                child_text(general, "LatestAvailableInterim").and_then(|d| date(&d));
+        }
+
+        info.business_summary = descendant(node, "TextInfo").and_then(|text_info| {
+            children(text_info, "Text")
+                .find(|text| text.attribute("Type") == Some("Business Summary"))
+                .and_then(text)
+        });
+        info.web_site = descendant(node, "webLinks").and_then(|links| child_text(links, "webSite"));
+
+        if let Some(industries) = descendant(node, "IndustryInfo") {
+            info.industries = children(industries, "Industry")
+                .map(|industry| Industry {
+                    classification: attr_string(industry, "type"),
+                    code: attr_string(industry, "code"),
+                    description: text(industry).unwrap_or_default(),
+                })
+                .collect();
+        }
+
+        if let Some(officers) = descendant(node, "officers") {
+            info.officers = children(officers, "officer")
+                .map(|officer| Officer {
+                    rank: attr_i32(officer, "rank"),
+                    first_name: child_text(officer, "firstName").unwrap_or_default(),
+                    last_name: child_text(officer, "lastName").unwrap_or_default(),
+                    age: child_text(officer, "age").and_then(|age| age.parse().ok()),
+                    title: child_text(officer, "title").unwrap_or_default(),
+                })
+                .collect();
+        }
+        info
+    }
+}
+
+//==================================================================================================
+/// Value of a ratio, typed by the Type attribute of the Ratio element
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RatioValue {
+    Number(f64),
+    Date(NaiveDate),
+    Text(String),
+}
+
+//==================================================================================================
+/// Ratios of a company by field name, such as NPRICE, PEEXCLXOR or TTMREV
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Ratios {
+    pub price_currency: Option<String>,
+    pub reporting_currency: Option<String>,
+    pub values: HashMap<String, RatioValue>,
+}
+
+impl Ratios {
+    //----------------------------------------------------------------------------------------------
+    /// Reads the Ratio elements of the groups of a Ratios element
+    fn parse(node: Node) -> Self {
+        let mut ratios = Ratios {
+            price_currency: node.attribute("PriceCurrency").map(str::to_string),
+            reporting_currency: node.attribute("ReportingCurrency").map(str::to_string),
+            values: HashMap::new(),
+        };
+        for ratio in node
+            .descendants()
+            .filter(|ratio| ratio.has_tag_name("Ratio"))
+        {
+            let (name, value) = match (ratio.attribute("FieldName"), text(ratio)) {
+                (Some(name), Some(value)) => (name, value),
+                _ => continue,
+            };
+            let value = match ratio.attribute("Type") {
+                Some("N") => value.parse::<f64>().ok().map(RatioValue::Number),
+                Some("D") => date(&value).map(RatioValue::Date),
+                _ => Some(RatioValue::Text(value)),
+            };
+            if let Some(value) = value {
+                ratios.values.insert(name.to_string(), value);
+            }
+        }
+        ratios
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a numeric ratio
+    pub fn ratio(&self, name: &str) -> Option<f64> {
+        match self.values.get(name)? {
+            RatioValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+//==================================================================================================
+/// The ReportSnapshot report: company overview, ratios and consensus forecasts
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CompanySnapshot {
+    pub company: CompanyInfo,
+    pub ratios: Ratios,
+    /// Current consensus values by field name, such as ConsRecom, TargetPrice or ProjEPS
+    pub forecasts: HashMap<String, f64>,
+}
+
+impl CompanySnapshot {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = parse_document(xml, "ReportSnapshot")?;
+        let root = document.root_element();
+        let mut forecasts = HashMap::new();
+        if let Some(forecast_data) = child(root, "ForecastData") {
+            for ratio in children(forecast_data, "Ratio") {
+                let value = children(ratio, "Value")
+                    .find(|value| value.attribute("PeriodType") == Some("CURR"))
+                    .and_then(number);
+                if let (Some(name), Some(value)) = (ratio.attribute("FieldName"), value) {
+                    forecasts.insert(name.to_string(), value);
+                }
+            }
+        }
+        Ok(CompanySnapshot {
+            company: CompanyInfo::parse(root),
+            ratios: child(root, "Ratios").map(Ratios::parse).unwrap_or_default(),
+            forecasts,
+        })
+    }
+}
+
+//==================================================================================================
+/// The ReportRatios report
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RatiosReport {
+    pub company: CompanyInfo,
+    pub ratios: Ratios,
+}
+
+impl RatiosReport {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = parse_document(xml, "XMLNTWSRatios")?;
+        let root = document.root_element();
+        Ok(RatiosReport {
+            company: CompanyInfo::parse(root),
+            ratios: descendant(root, "Ratios")
+                .map(Ratios::parse)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+//==================================================================================================
+/// A value of the financial summary, such as the revenue of a quarter
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SummaryValue {
+    pub as_of_date: Option<NaiveDate>,
+    /// A for actual, R for restated, P for preliminary, TTM for trailing twelve months
+    pub report_type: String,
+    /// Length of the period, such as 3M or 12M
+    pub period: String,
+    pub value: f64,
+}
+
+//==================================================================================================
+/// A dividend of the financial summary
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DividendEvent {
+    /// Type of dividend, such as CD for cash dividend
+    pub kind: String,
+    pub ex_date: Option<NaiveDate>,
+    pub record_date: Option<NaiveDate>,
+    pub pay_date: Option<NaiveDate>,
+    pub declaration_date: Option<NaiveDate>,
+    pub amount: f64,
+}
+
+//==================================================================================================
+/// The ReportsFinSummary report
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FinancialSummary {
+    pub currency: Option<String>,
+    pub total_revenues: Vec<SummaryValue>,
+    pub eps: Vec<SummaryValue>,
+    pub dividends_per_share: Vec<SummaryValue>,
+    pub dividends: Vec<DividendEvent>,
+}
+
+impl FinancialSummary {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = parse_document(xml, "FinancialSummary")?;
+        let root = document.root_element();
+        let values = |list: &str, item: &str| -> Vec<SummaryValue> {
+            child(root, list)
+                .map(|list| {
+                    children(list, item)
+                        .filter_map(|value| {
+                            Some(SummaryValue {
+                                as_of_date: attr_date(value, "asofDate"),
+                                report_type: attr_string(value, "reportType"),
+                                period: attr_string(value, "period"),
+                                value: number(value)?,
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let dividends = child(root, "Dividends")
+            .map(|list| {
+                children(list, "Dividend")
+                    .filter_map(|dividend| {
+                        Some(DividendEvent {
+                            kind: attr_string(dividend, "type"),
+                            ex_date: attr_date(dividend, "exDate"),
+                            record_date: attr_date(dividend, "recordDate"),
+                            pay_date: attr_date(dividend, "payDate"),
+                            declaration_date: attr_date(dividend, "declarationDate"),
+                            amount: number(dividend)?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(FinancialSummary {
+            currency: child(root, "TotalRevenues")
+                .and_then(|revenues| revenues.attribute("currency"))
+                .map(str::to_string),
+            total_revenues: values("TotalRevenues", "TotalRevenue"),
+            eps: values("EPSs", "EPS"),
+            dividends_per_share: values("DividendPerShares", "DividendPerShare"),
+            dividends,
+        })
+    }
+}
+
+//==================================================================================================
+/// Kind of financial statement
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum StatementType {
+    Income,
+    BalanceSheet,
+    CashFlow,
+    Other(String),
+}
+
+impl StatementType {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "INC" => StatementType::Income,
+            "BAL" => StatementType::BalanceSheet,
+            "CAS" => StatementType::CashFlow,
+            other => StatementType::Other(other.to_string()),
+        }
+    }
+}
+
+//==================================================================================================
+/// A financial statement of a fiscal period, its line items keyed by chart of account code
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub statement_type: StatementType,
+    pub statement_date: Option<NaiveDate>,
+    /// Length of the period, in period_unit
+    pub period_length: Option<i32>,
+    /// M for months, W for weeks
+    pub period_unit: Option<String>,
+    pub line_items: HashMap<String, f64>,
+}
+
+//==================================================================================================
+/// A fiscal year or quarter with its statements
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FiscalPeriod {
+    pub end_date: Option<NaiveDate>,
+    pub fiscal_year: Option<i32>,
+    /// Number of the quarter, for interim periods
+    pub period_number: Option<i32>,
+    pub statements: Vec<Statement>,
+}
+
+impl FiscalPeriod {
+    //----------------------------------------------------------------------------------------------
+    fn parse(node: Node) -> Self {
+        FiscalPeriod {
+            end_date: attr_date(node, "EndDate"),
+            fiscal_year: attr_i32(node, "FiscalYear"),
+            period_number: attr_i32(node, "FiscalPeriodNumber"),
+            statements: children(node, "Statement")
+                .map(|statement| {
+                    let header = child(statement, "FPHeader");
+                    Statement {
+                        statement_type: StatementType::from_code(
+                            statement.attribute("Type").unwrap_or_default(),
+                        ),
+                        statement_date: header
+                            .and_then(|h| child_text(h, "StatementDate"))
+                            .and_then(|d| date(&d)),
+                        period_length: header
+                            .and_then(|h| child_text(h, "PeriodLength"))
+                            .and_then(|length| length.parse().ok()),
+                        period_unit: header
+                            .and_then(|h| child(h, "periodType"))
+                            .and_then(|unit| unit.attribute("Code"))
+                            .map(str::to_string),
+                        line_items: children(statement, "lineItem")
+                            .filter_map(|item| {
+                                Some((item.attribute("coaCode")?.to_string(), number(item)?))
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the statement of a kind, if the period has one
+    pub fn statement(&self, statement_type: &StatementType) -> Option<&Statement> {
+        self.statements
+            .iter()
+            .find(|statement| statement.statement_type == *statement_type)
+    }
+}
+
+//==================================================================================================
+/// The ReportsFinStatements report
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FinancialStatements {
+    pub company: CompanyInfo,
+    /// Labels of the chart of account codes, such as SREV for Revenue
+    pub labels: HashMap<String, String>,
+    /// Fiscal years, most recent first
+    pub annual: Vec<FiscalPeriod>,
+    /// Fiscal quarters, most recent first
+    pub interim: Vec<FiscalPeriod>,
+}
+
+impl FinancialStatements {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = parse_document(xml, "ReportFinancialStatements")?;
+        let root = document.root_element();
+        let statements = child(root, "FinancialStatements")
+            .ok_or_else(|| invalid_report("no FinancialStatements element".to_string()))?;
+        let periods = |name: &str| -> Vec<FiscalPeriod> {
+            child(statements, name)
+                .map(|periods| {
+                    children(periods, "FiscalPeriod")
+                        .map(FiscalPeriod::parse)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let labels = child(statements, "COAMap")
+            .map(|map| {
+                children(map, "mapItem")
+                    .filter_map(|item| Some((item.attribute("coaItem")?.to_string(), text(item)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(FinancialStatements {
+            company: CompanyInfo::parse(root),
+            labels,
+            annual: periods("AnnualPeriods"),
+            interim: periods("InterimPeriods"),
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the label of a chart of account code
+    pub fn label(&self, coa_code: &str) -> Option<&str> {
+        self.labels.get(coa_code).map(String::as_str)
+    }
+}
+
+//==================================================================================================
+/// A reported value of the estimates report
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Actual {
+    /// Measure, such as EPS, REVENUE or DPS
+    pub measure: String,
+    /// A for annual, Q for quarterly
+    pub period_type: String,
+    pub fiscal_year: Option<i32>,
+    pub end_month: Option<i32>,
+    pub value: f64,
+}
+
+//==================================================================================================
+/// The current consensus of the analysts for a measure and period
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// Measure, such as EPS, REVENUE or DPS
+    pub measure: String,
+    /// A for annual, Q for quarterly
+    pub period_type: String,
+    pub fiscal_year: Option<i32>,
+    pub end_month: Option<i32>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub std_dev: Option<f64>,
+    pub num_estimates: Option<i32>,
+}
+
+//==================================================================================================
+/// The RESC report of analyst estimates
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Estimates {
+    pub company: CompanyInfo,
+    pub actuals: Vec<Actual>,
+    pub estimates: Vec<Estimate>,
+}
+
+impl Estimates {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = parse_document(xml, "REarnEstCons")?;
+        let root = document.root_element();
+
+        let mut actuals = vec![];
+        for actual in root
+            .descendants()
+            .filter(|node| node.has_tag_name("FYActual"))
+        {
+            for period in children(actual, "FYPeriod") {
+                if let Some(value) = child(period, "ActValue").and_then(number) {
+                    actuals.push(Actual {
+                        measure: attr_string(actual, "type"),
+                        period_type: attr_string(period, "periodType"),
+                        fiscal_year: attr_i32(period, "fYear"),
+                        end_month: attr_i32(period, "endMonth"),
+                        value,
+                    });
+                }
+            }
+        }
+
+        let mut estimates = vec![];
+        for estimate in root
+            .descendants()
+            .filter(|node| node.has_tag_name("FYEstimate"))
+        {
+            for period in children(estimate, "FYPeriod") {
+                let consensus = |kind: &str| -> Option<f64> {
+                    children(period, "ConsEstimate")
+                        .find(|cons| cons.attribute("type") == Some(kind))
+                        .and_then(|cons| {
+                            children(cons, "ConsValue")
+                                .find(|value| value.attribute("dateType") == Some("CURR"))
+                        })
+                        .and_then(number)
+                };
+                estimates.push(Estimate {
+                    measure: attr_string(estimate, "type"),
+                    period_type: attr_string(period, "periodType"),
+                    fiscal_year: attr_i32(period, "fYear"),
+                    end_month: attr_i32(period, "endMonth"),
+                    mean: consensus("Mean"),
+                    median: consensus("Median"),
+                    high: consensus("High"),
+                    low: consensus("Low"),
+                    std_dev: consensus("StdDev"),
+                    num_estimates: consensus("NumOfEst").map(|count| count as i32),
+                });
+            }
+        }
+
+        Ok(Estimates {
+            company: CompanyInfo::parse(root),
+            actuals,
+            estimates,
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the estimate of a measure for a fiscal year
+    pub fn estimate(&self, measure: &str, fiscal_year: i32) -> Option<&Estimate> {
+        self.estimates.iter().find(|estimate| {
+            estimate.measure == measure
+                && estimate.period_type == "A"
+                && estimate.fiscal_year == Some(fiscal_year)
+        })
+    }
+}
+
+//==================================================================================================
+/// A parsed fundamental data report
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FundamentalReport {
+    Snapshot(CompanySnapshot),
+    FinancialSummary(FinancialSummary),
+    Ratios(RatiosReport),
+    FinancialStatements(FinancialStatements),
+    Estimates(Estimates),
+}
+
+//----------------------------------------------------------------------------------------------
+/// Parses the data sent to fundamental_data for a request of a report type
+pub fn parse_fundamental_data(
+    report_type: &FundamentalType,
+    xml: &str,
+) -> Result<FundamentalReport, IBKRApiLibError> {
+    match report_type {
+        FundamentalType::ReportSnapshot => {
+            CompanySnapshot::parse(xml).map(FundamentalReport::Snapshot)
+        }
+        FundamentalType::ReportsFinSummary => {
+            FinancialSummary::parse(xml).map(FundamentalReport::FinancialSummary)
+        }
+        FundamentalType::ReportRatios => RatiosReport::parse(xml).map(FundamentalReport::Ratios),
+        FundamentalType::ReportsFinStatements => {
+            FinancialStatements::parse(xml).map(FundamentalReport::FinancialStatements)
+        }
+        FundamentalType::RESC => Estimates::parse(xml).map(FundamentalReport::Estimates),
+        other => Err(invalid_report(format!(
+            "{} reports are not supported",
+            other
+        ))),
+    }
+}
@@ -0,0 +1,305 @@
+//! Generic tick types requested with req_mkt_data, and the values they send as strings
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use num_derive::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::{TickType, NO_VALID_ID};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::timestamps::from_epoch_seconds;
+
+//----------------------------------------------------------------------------------------------
+fn invalid_tick(tick_type: TickType, value: &str) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(
+        NO_VALID_ID,
+        &format!("Invalid {} tick: {}", tick_type, value),
+    )
+}
+
+//==================================================================================================
+/// Generic tick types, requesting ticks that are not sent by default.  The tick types each one
+/// sends are listed with it.
+#[repr(i32)]
+#[derive(
+    Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+pub enum GenericTick {
+    /// OptionCallVolume and OptionPutVolume
+    OptionVolume = 100,
+    /// OptionCallOpenInterest and OptionPutOpenInterest
+    OptionOpenInterest = 101,
+    /// OptionHistoricalVol
+    HistoricalVolatility = 104,
+    /// AvgOptVolume
+    AverageOptionVolume = 105,
+    /// OptionImpliedVol
+    ImpliedVolatility = 106,
+    /// IndexFuturePremium
+    IndexFuturePremium = 162,
+    /// Low13Week to High52Week and AvgVolume
+    MiscellaneousStats = 165,
+    /// MarkPrice
+    MarkPrice = 221,
+    /// AuctionVolume, AuctionPrice, AuctionImbalance and RegulatoryImbalance
+    AuctionValues = 225,
+    /// RtVolume, see RtVolume
+    RtVolume = 233,
+    /// Shortable and ShortableShares
+    Shortable = 236,
+    /// Inventory of shares available for short selling
+    Inventory = 256,
+    /// FundamentalRatios, see FundamentalRatios
+    FundamentalRatios = 258,
+    /// NewsTick, from all the news sources subscribed to unless GenericTickList::news is used
+    News = 292,
+    /// TradeCount
+    TradeCount = 293,
+    /// TradeRate
+    TradeRate = 294,
+    /// VolumeRate
+    VolumeRate = 295,
+    /// LastRthTrade
+    LastRthTrade = 318,
+    /// RtTrdVolume, see RtVolume
+    RtTradeVolume = 375,
+    /// RtHistoricalVol
+    RealTimeHistoricalVolatility = 411,
+    /// IbDividends, see Dividends
+    Dividends = 456,
+    /// BondFactorMultiplier
+    BondFactorMultiplier = 460,
+    /// Net asset value of ETFs, bid and ask
+    EtfNavBidAsk = 576,
+    /// Net asset value of ETFs, last
+    EtfNavLast = 577,
+    /// Net asset value of ETFs, close
+    EtfNavClose = 578,
+    /// Estimated and final IPO prices
+    IpoPrices = 586,
+    /// FuturesOpenInterest
+    FuturesOpenInterest = 588,
+    /// ShortTermVolume3Min, ShortTermVolume5Min and ShortTermVolume10Min
+    ShortTermVolume = 595,
+    /// Net asset value of ETFs, high and low
+    EtfNavHighLow = 614,
+    /// CreditmanSlowMarkPrice
+    CreditmanSlowMarkPrice = 619,
+    /// Net asset value of ETFs, frozen last
+    EtfFrozenNavLast = 623,
+}
+
+impl fmt::Display for GenericTick {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", *self as i32)
+    }
+}
+
+//==================================================================================================
+/// The generic_tick_list of req_mkt_data, encoded as "mdoff,233,292:BRFG+DJNL"
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GenericTickList {
+    ticks: BTreeSet<GenericTick>,
+    news_sources: Vec<String>,
+    market_data_off: bool,
+}
+
+impl GenericTickList {
+    //----------------------------------------------------------------------------------------------
+    /// Creates a list of generic ticks.  Duplicates are ignored.
+    pub fn new(ticks: &[GenericTick]) -> Self {
+        GenericTickList {
+            ticks: ticks.iter().cloned().collect(),
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests news from some sources only, such as "BRFG" or "DJNL"
+    pub fn news(mut self, sources: &[&str]) -> Self {
+        self.ticks.insert(GenericTick::News);
+        self.news_sources = sources.iter().map(|source| source.to_string()).collect();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops the top of book ticks, so that only the generic ticks are sent
+    pub fn market_data_off(mut self) -> Self {
+        self.market_data_off = true;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn ticks(&self) -> &BTreeSet<GenericTick> {
+        &self.ticks
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty() && !self.market_data_off
+    }
+}
+
+impl fmt::Display for GenericTickList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut items = vec![];
+        if self.market_data_off {
+            items.push("mdoff".to_string());
+        }
+        for tick in self.ticks.iter() {
+            if *tick == GenericTick::News && !self.news_sources.is_empty() {
+                items.push(format!("{}:{}", tick, self.news_sources.join("+")));
+            } else {
+                items.push(tick.to_string());
+            }
+        }
+        write!(f, "{}", items.join(","))
+    }
+}
+
+//==================================================================================================
+/// Value of the RtVolume and RtTrdVolume string ticks, such as
+/// "701.28;1;1348075471534;67854;701.46918464;true".  Price and size are empty for ticks that
+/// only update the volume.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RtVolume {
+    pub price: Option<f64>,
+    pub size: Option<f64>,
+    pub time: DateTime<Utc>,
+    /// Volume of the day
+    pub total_volume: f64,
+    /// Volume weighted average price of the day
+    pub vwap: f64,
+    /// Whether the trade was filled by a single market maker
+    pub single_trade: bool,
+}
+
+impl RtVolume {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(value: &str) -> Result<Self, IBKRApiLibError> {
+        let invalid = || invalid_tick(TickType::RtVolume, value);
+        let fields: Vec<&str> = value.split(';').map(str::trim).collect();
+        if fields.len() < 6 {
+            return Err(invalid());
+        }
+        let optional = |field: &str| -> Result<Option<f64>, IBKRApiLibError> {
+            if field.is_empty() {
+                Ok(None)
+            } else {
+                field.parse::<f64>().map(Some).map_err(|_| invalid())
+            }
+        };
+        let number = |field: &str| field.parse::<f64>().map_err(|_| invalid());
+
+        let millis = fields[2].parse::<i64>().map_err(|_| invalid())?;
+        let time = from_epoch_seconds(millis.div_euclid(1000))?
+            + chrono::Duration::milliseconds(millis.rem_euclid(1000));
+        Ok(RtVolume {
+            price: optional(fields[0])?,
+            size: optional(fields[1])?,
+            time,
+            total_volume: number(fields[3])?,
+            vwap: number(fields[4])?,
+            single_trade: fields[5].eq_ignore_ascii_case("true"),
+        })
+    }
+}
+
+//==================================================================================================
+/// Value of the IbDividends string tick, such as "0.83,0.92,20130219,0.23"
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Dividends {
+    /// Sum of the dividends of the past 12 months
+    pub past_12_months: Option<f64>,
+    /// Sum of the expected dividends of the next 12 months
+    pub next_12_months: Option<f64>,
+    pub next_date: Option<NaiveDate>,
+    pub next_amount: Option<f64>,
+}
+
+impl Dividends {
+    //----------------------------------------------------------------------------------------------
+    /// Parses the value.  Empty fields, sent when there are no dividends, are None.
+    pub fn parse(value: &str) -> Result<Self, IBKRApiLibError> {
+        let invalid = || invalid_tick(TickType::IbDividends, value);
+        let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+        if fields.len() != 4 {
+            return Err(invalid());
+        }
+        let amount = |field: &str| -> Result<Option<f64>, IBKRApiLibError> {
+            if field.is_empty() {
+                Ok(None)
+            } else {
+                field.parse::<f64>().map(Some).map_err(|_| invalid())
+            }
+        };
+        let next_date = if fields[2].is_empty() {
+            None
+        } else {
+            Some(NaiveDate::parse_from_str(fields[2], "%Y%m%d").map_err(|_| invalid())?)
+        };
+        Ok(Dividends {
+            past_12_months: amount(fields[0])?,
+            next_12_months: amount(fields[1])?,
+            next_date,
+            next_amount: amount(fields[3])?,
+        })
+    }
+}
+
+//==================================================================================================
+/// Value of the FundamentalRatios string tick, such as "TTMNPMGN=16.1298;NLOW=80.6;CURRENCY=USD"
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FundamentalRatios {
+    pub values: HashMap<String, String>,
+}
+
+impl FundamentalRatios {
+    //----------------------------------------------------------------------------------------------
+    pub fn parse(value: &str) -> Self {
+        FundamentalRatios {
+            values: value
+                .split(';')
+                .filter_map(|pair| {
+                    let mut parts = pair.splitn(2, '=');
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(value)) if !name.trim().is_empty() => {
+                            Some((name.trim().to_string(), value.trim().to_string()))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a ratio, if it was sent and is a number
+    pub fn ratio(&self, name: &str) -> Option<f64> {
+        self.values.get(name)?.parse::<f64>().ok()
+    }
+}
+
+//==================================================================================================
+/// Availability of shares to short sell, sent in the Shortable generic tick
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Shortability {
+    /// At least 1000 shares are available
+    Available,
+    /// Shares are available, but must be located first
+    HardToBorrow,
+    NotAvailable,
+}
+
+impl Shortability {
+    pub fn from_tick_value(value: f64) -> Self {
+        if value > 2.5 {
+            Shortability::Available
+        } else if value > 1.5 {
+            Shortability::HardToBorrow
+        } else {
+            Shortability::NotAvailable
+        }
+    }
+}
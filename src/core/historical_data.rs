@@ -0,0 +1,812 @@
+//! Downloads long ranges of historical bars in chunks, within the historical data pacing limits
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::*;
+
+use crate::core::client::{EClient, POISONED_MUTEX};
+use crate::core::common::{BarData, NO_VALID_ID};
+use crate::core::contract::Contract;
+use crate::core::errors::{IBKRApiLibError, TwsApiReportableError};
+use crate::core::historical_ticks::HistoricalTicks;
+use crate::core::timestamps::{
+    format_tws_time, parse_tws_time, FORMAT_DATE_EPOCH, FORMAT_DATE_TEXT,
+};
+use crate::core::wrapper::Wrapper;
+
+/// Historical data requests allowed in PACING_WINDOW
+pub const MAX_REQUESTS_PER_WINDOW: usize = 60;
+pub const PACING_WINDOW: Duration = Duration::from_secs(600);
+/// Minimum time between two identical requests
+pub const IDENTICAL_REQUEST_INTERVAL: Duration = Duration::from_secs(15);
+/// Requests allowed for the same contract, exchange and tick type in SAME_CONTRACT_WINDOW
+pub const MAX_SAME_CONTRACT_REQUESTS: usize = 5;
+pub const SAME_CONTRACT_WINDOW: Duration = Duration::from_secs(2);
+/// Error sent when a historical data query fails, for instance because it returned no data or
+/// violated the pacing rules
+const HISTORICAL_DATA_ERROR_CODE: &str = "162";
+/// Text of the historical data error sent when a query returns no data
+const NO_DATA_MESSAGE: &str = "HMDS query returned no data";
+/// Text of the historical data error sent when a query violates the pacing rules
+const PACING_VIOLATION_MESSAGE: &str = "pacing violation";
+
+//----------------------------------------------------------------------------------------------
+/// Returns true if the error is the historical data error sent when a query returned no data,
+/// such as for a chunk without any trading
+pub fn is_no_data_error(err: &IBKRApiLibError) -> bool {
+    match err {
+        IBKRApiLibError::ApiError(err) => {
+            err.code == HISTORICAL_DATA_ERROR_CODE && err.description.contains(NO_DATA_MESSAGE)
+        }
+        _ => false,
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns true if the error is the historical data error sent when a query violated the
+/// pacing rules
+pub fn is_pacing_violation(err: &IBKRApiLibError) -> bool {
+    match err {
+        IBKRApiLibError::ApiError(err) => {
+            err.code == HISTORICAL_DATA_ERROR_CODE
+                && err
+                    .description
+                    .to_lowercase()
+                    .contains(PACING_VIOLATION_MESSAGE)
+        }
+        _ => false,
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns true if a request that failed with the error may succeed when sent again, which is
+/// the case of pacing violations and timeouts only
+pub fn is_retryable(err: &IBKRApiLibError) -> bool {
+    match err {
+        IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout) => true,
+        err => is_pacing_violation(err),
+    }
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct HistoricalState {
+    awaited: HashSet<i32>,
+    bars: HashMap<i32, Vec<BarData>>,
+    ticks: HashMap<i32, HistoricalTicks>,
+    finished: HashSet<i32>,
+    head_timestamps: HashMap<i32, String>,
+    errors: HashMap<i32, TwsApiReportableError>,
+    format_dates: HashMap<i32, i32>,
+}
+
+//==================================================================================================
+/// Thread safe store of the historical bars, ticks and head timestamps received for the requests
+/// the client is waiting on.  The decoder updates it every time a historical_data,
+/// historical_ticks, head_timestamp or error message arrives.
+#[derive(Debug, Default)]
+pub struct HistoricalDataTracker {
+    state: Mutex<HistoricalState>,
+    changed: Condvar,
+}
+
+impl HistoricalDataTracker {
+    pub fn new() -> Self {
+        HistoricalDataTracker {
+            state: Mutex::new(HistoricalState::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a bar of a historical data request, if the request is awaited
+    pub fn add_bar(&self, req_id: i32, bar: &BarData) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if state.awaited.contains(&req_id) {
+            state.bars.entry(req_id).or_default().push(bar.clone());
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the end of a historical data request
+    pub fn end(&self, req_id: i32) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if state.awaited.contains(&req_id) {
+            state.finished.insert(req_id);
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records ticks of a req_historical_ticks request.  done is set on the last message sent for
+    /// the request.
+    pub fn add_ticks(&self, req_id: i32, ticks: HistoricalTicks, done: bool) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if !state.awaited.contains(&req_id) {
+            return;
+        }
+        match state.ticks.get_mut(&req_id) {
+            Some(received) => received.extend(ticks),
+            None => {
+                state.ticks.insert(req_id, ticks);
+            }
+        }
+        if done {
+            state.finished.insert(req_id);
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the answer to req_head_time_stamp
+    pub fn head_timestamp(&self, req_id: i32, head_timestamp: &str) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if state.awaited.contains(&req_id) {
+            state
+                .head_timestamps
+                .insert(req_id, head_timestamp.to_string());
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the format_date of a req_historical_data request, which the dates of its bars are
+    /// sent in
+    pub(crate) fn expect_format_date(&self, req_id: i32, format_date: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .format_dates
+            .insert(req_id, format_date);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub(crate) fn forget_format_date(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .format_dates
+            .remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the format_date a historical data request was sent with, FORMAT_DATE_TEXT if the
+    /// request is unknown
+    pub fn format_date(&self, req_id: i32) -> i32 {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .format_dates
+            .get(&req_id)
+            .copied()
+            .unwrap_or(FORMAT_DATE_TEXT)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records an error sent by TWS for an awaited request.  Informational warnings are ignored.
+    pub fn record_error(&self, req_id: i32, error_code: i32, error_string: &str) {
+        if error_code == 399 || (2100..2200).contains(&error_code) {
+            return;
+        }
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if state.awaited.contains(&req_id) {
+            state.errors.insert(
+                req_id,
+                TwsApiReportableError::new(
+                    req_id,
+                    error_code.to_string(),
+                    error_string.to_string(),
+                ),
+            );
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns true if someone is waiting on the request
+    pub fn is_awaited(&self, req_id: i32) -> bool {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .awaited
+            .contains(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Marks a request as awaited so that the data and errors sent for it are kept.  Must be
+    /// called before the request is sent.
+    pub(crate) fn expect(&self, req_id: i32) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.awaited.insert(req_id);
+        state.bars.remove(&req_id);
+        state.ticks.remove(&req_id);
+        state.finished.remove(&req_id);
+        state.head_timestamps.remove(&req_id);
+        state.errors.remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops waiting on a request and drops what was received for it
+    pub(crate) fn forget(&self, req_id: i32) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.awaited.remove(&req_id);
+        state.bars.remove(&req_id);
+        state.ticks.remove(&req_id);
+        state.finished.remove(&req_id);
+        state.head_timestamps.remove(&req_id);
+        state.errors.remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until a result, as extracted by take, or an error arrives for an awaited request,
+    /// or until the timeout elapses.  The request is no longer awaited afterwards.
+    fn wait<R, F>(&self, req_id: i32, timeout: Duration, take: F) -> Result<R, IBKRApiLibError>
+    where
+        F: Fn(&mut HistoricalState) -> Option<R>,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let result = loop {
+            if let Some(err) = state.errors.remove(&req_id) {
+                break Err(IBKRApiLibError::ApiError(err));
+            }
+            if let Some(result) = take(&mut state) {
+                break Ok(result);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout));
+            }
+            state = self
+                .changed
+                .wait_timeout(state, deadline - now)
+                .expect(POISONED_MUTEX)
+                .0;
+        };
+        drop(state);
+        self.forget(req_id);
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until historical_data_end or an error arrives for an awaited request
+    pub(crate) fn wait_for_bars(
+        &self,
+        req_id: i32,
+        timeout: Duration,
+    ) -> Result<Vec<BarData>, IBKRApiLibError> {
+        self.wait(req_id, timeout, |state| {
+            if state.finished.remove(&req_id) {
+                Some(state.bars.remove(&req_id).unwrap_or_default())
+            } else {
+                None
+            }
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until the ticks flagged done or an error arrive for an awaited request
+    pub(crate) fn wait_for_ticks(
+        &self,
+        req_id: i32,
+        timeout: Duration,
+    ) -> Result<Option<HistoricalTicks>, IBKRApiLibError> {
+        self.wait(req_id, timeout, |state| {
+            if state.finished.remove(&req_id) {
+                Some(state.ticks.remove(&req_id))
+            } else {
+                None
+            }
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until head_timestamp or an error arrives for an awaited request
+    pub(crate) fn wait_for_head_timestamp(
+        &self,
+        req_id: i32,
+        timeout: Duration,
+    ) -> Result<String, IBKRApiLibError> {
+        self.wait(req_id, timeout, |state| {
+            state.head_timestamps.remove(&req_id)
+        })
+    }
+}
+
+//==================================================================================================
+/// A historical data, historical ticks or head timestamp request sent by send_historical_request.
+/// Its answer is waited on through the HistoricalDataTracker alone, so the client can be
+/// released, and its lock dropped, while waiting.
+#[derive(Debug)]
+pub struct PendingHistoricalData {
+    req_id: i32,
+    tracker: Arc<HistoricalDataTracker>,
+}
+
+impl PendingHistoricalData {
+    pub(crate) fn new(req_id: i32, tracker: Arc<HistoricalDataTracker>) -> Self {
+        PendingHistoricalData { req_id, tracker }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn req_id(&self) -> i32 {
+        self.req_id
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until historical_data_end or an error arrives, or until the timeout elapses
+    pub fn wait_for_bars(self, timeout: Duration) -> Result<Vec<BarData>, IBKRApiLibError> {
+        self.tracker.wait_for_bars(self.req_id, timeout)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until the ticks flagged done or an error arrive, or until the timeout elapses
+    pub fn wait_for_ticks(
+        self,
+        timeout: Duration,
+    ) -> Result<Option<HistoricalTicks>, IBKRApiLibError> {
+        self.tracker.wait_for_ticks(self.req_id, timeout)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until head_timestamp or an error arrives, or until the timeout elapses
+    pub fn wait_for_head_timestamp(self, timeout: Duration) -> Result<String, IBKRApiLibError> {
+        self.tracker.wait_for_head_timestamp(self.req_id, timeout)
+    }
+}
+
+impl Drop for PendingHistoricalData {
+    fn drop(&mut self) {
+        self.tracker.forget(self.req_id);
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Sends a request whose answer is kept by the HistoricalDataTracker, locking the client only
+/// while the request is sent.  The answer is delivered by the decoder thread, so the
+/// PendingHistoricalData returned must not be waited on from inside a Wrapper callback.
+///
+/// # Arguments
+/// * client - a connected client, not locked by the caller
+/// * req_id - the id the request is sent with
+/// * send - sends the request with the locked client
+pub fn send_historical_request<T, F>(
+    client: &Mutex<EClient<T>>,
+    req_id: i32,
+    send: F,
+) -> Result<PendingHistoricalData, IBKRApiLibError>
+where
+    T: Wrapper + Send + Sync + 'static,
+    F: FnOnce(&mut EClient<T>) -> Result<(), IBKRApiLibError>,
+{
+    let mut client = client.lock().expect(POISONED_MUTEX);
+    client.historical_data.expect(req_id);
+    let pending = PendingHistoricalData::new(req_id, client.historical_data.clone());
+    send(&mut client)?;
+    Ok(pending)
+}
+
+//==================================================================================================
+/// Keeps track of the historical data requests sent, to tell how long to wait before the next
+/// one so that TWS does not reject it with a pacing violation.  The limits default to the ones
+/// TWS applies to bars of 30 seconds or less.
+#[derive(Clone, Debug)]
+pub struct Pacer {
+    pub max_requests_per_window: usize,
+    pub pacing_window: Duration,
+    pub identical_request_interval: Duration,
+    pub max_same_contract_requests: usize,
+    pub same_contract_window: Duration,
+    /// Time, contract key and request key of the requests sent in the last pacing window
+    sent: VecDeque<(Instant, String, String)>,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Pacer {
+            max_requests_per_window: MAX_REQUESTS_PER_WINDOW,
+            pacing_window: PACING_WINDOW,
+            identical_request_interval: IDENTICAL_REQUEST_INTERVAL,
+            max_same_contract_requests: MAX_SAME_CONTRACT_REQUESTS,
+            same_contract_window: SAME_CONTRACT_WINDOW,
+            sent: VecDeque::new(),
+        }
+    }
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Pacer::default()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns how long to wait from now before sending a request
+    ///
+    /// # Arguments
+    /// * contract_key - identifies the contract, exchange and tick type of the request
+    /// * request_key - identifies the whole request, to detect identical requests
+    /// * now - the current time
+    pub fn delay(&self, contract_key: &str, request_key: &str, now: Instant) -> Duration {
+        let wait_until =
+            |sent: Instant, interval: Duration| (sent + interval).saturating_duration_since(now);
+        let mut delay = Duration::from_secs(0);
+
+        let count = self.sent.len();
+        if self.max_requests_per_window > 0 && count >= self.max_requests_per_window {
+            let (sent, _, _) = &self.sent[count - self.max_requests_per_window];
+            delay = delay.max(wait_until(*sent, self.pacing_window));
+        }
+
+        let same_contract: Vec<Instant> = self
+            .sent
+            .iter()
+            .filter(|(_, key, _)| key == contract_key)
+            .map(|(sent, _, _)| *sent)
+            .collect();
+        let count = same_contract.len();
+        if self.max_same_contract_requests > 0 && count >= self.max_same_contract_requests {
+            let sent = same_contract[count - self.max_same_contract_requests];
+            delay = delay.max(wait_until(sent, self.same_contract_window));
+        }
+
+        for (sent, _, key) in self.sent.iter() {
+            if key == request_key {
+                delay = delay.max(wait_until(*sent, self.identical_request_interval));
+            }
+        }
+        delay
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a request sent at a time
+    pub fn record(&mut self, contract_key: &str, request_key: &str, sent: Instant) {
+        let longest_window = self
+            .pacing_window
+            .max(self.identical_request_interval)
+            .max(self.same_contract_window);
+        while let Some((first, _, _)) = self.sent.front() {
+            if sent.saturating_duration_since(*first) < longest_window {
+                break;
+            }
+            self.sent.pop_front();
+        }
+        self.sent
+            .push_back((sent, contract_key.to_string(), request_key.to_string()));
+    }
+}
+
+//==================================================================================================
+/// A req_historical_data call covering part of the range to download
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    pub end: DateTime<Utc>,
+    pub duration_str: String,
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns the longest time span, and its duration_str, that TWS returns in one request for a
+/// bar size setting
+pub fn max_chunk(bar_size_setting: &str) -> Result<(chrono::Duration, String), IBKRApiLibError> {
+    let seconds = |seconds: i64| Ok((chrono::Duration::seconds(seconds), format!("{} S", seconds)));
+    let days = |days: i64| Ok((chrono::Duration::days(days), format!("{} D", days)));
+    let weeks = |weeks: i64| Ok((chrono::Duration::weeks(weeks), format!("{} W", weeks)));
+    match bar_size_setting.trim() {
+        "1 secs" => seconds(1800),
+        "5 secs" => seconds(3600),
+        "10 secs" | "15 secs" => seconds(14400),
+        "30 secs" => seconds(28800),
+        "1 min" => days(1),
+        "2 mins" => days(2),
+        "3 mins" | "5 mins" | "10 mins" | "15 mins" | "20 mins" | "30 mins" => weeks(1),
+        "1 hour" | "2 hours" | "3 hours" | "4 hours" | "8 hours" => weeks(4),
+        "1 day" | "1 week" | "1 month" => weeks(52),
+        other => Err(IBKRApiLibError::invalid_argument(
+            NO_VALID_ID,
+            &format!("Unsupported bar size setting: {}", other),
+        )),
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Splits a range in chunks of the longest span allowed for the bar size, from the most recent
+/// one backwards
+pub fn plan_chunks(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bar_size_setting: &str,
+) -> Result<Vec<Chunk>, IBKRApiLibError> {
+    let (span, duration_str) = max_chunk(bar_size_setting)?;
+    let mut chunks = vec![];
+    let mut chunk_end = end;
+    while chunk_end > start {
+        chunks.push(Chunk {
+            end: chunk_end,
+            duration_str: duration_str.clone(),
+        });
+        chunk_end -= span;
+    }
+    Ok(chunks)
+}
+
+//----------------------------------------------------------------------------------------------
+/// Sorts bars by time, drops the ones outside of [start, end) and the duplicates returned by
+/// overlapping chunks
+pub fn merge_bars(
+    chunks: Vec<Vec<BarData>>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<BarData> {
+    let mut by_time = BTreeMap::new();
+    for bar in chunks.into_iter().flatten() {
+        if start <= bar.date && bar.date < end {
+            by_time.entry(bar.date).or_insert(bar);
+        }
+    }
+    by_time.into_values().collect()
+}
+
+//==================================================================================================
+/// Downloads the bars of a contract over a range longer than a single req_historical_data call
+/// allows.  The range is split in chunks, starting no earlier than the head timestamp, that are
+/// requested one at a time within the pacing limits.  Chunks that fail are retried, and
+/// download can be called again after an error to fetch only the chunks still missing.
+///
+/// The client is locked only while each request is sent, not during the pacing delays nor
+/// while waiting for the answers, so it stays usable from other threads during a download.
+pub struct HistoricalDownloader {
+    pub contract: Contract,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub bar_size_setting: String,
+    pub what_to_show: String,
+    pub use_rth: i32,
+    /// How long to wait for each request to complete
+    pub timeout: Duration,
+    /// How many times a request that timed out or violated the pacing rules is sent again
+    /// before download returns the error
+    pub max_retries: u32,
+    /// How long to wait before sending again a request that violated the pacing rules, doubled
+    /// at every retry
+    pub pacing_backoff: Duration,
+    /// Pacing limits, which can be relaxed for bars longer than 30 seconds
+    pub pacer: Pacer,
+    next_req_id: i32,
+    head_timestamp: Option<DateTime<Utc>>,
+    chunks: Vec<Chunk>,
+    completed: BTreeMap<DateTime<Utc>, Vec<BarData>>,
+}
+
+impl HistoricalDownloader {
+    //----------------------------------------------------------------------------------------------
+    /// # Arguments
+    /// * contract - the contract to download bars for
+    /// * start - start of the range, clipped to the head timestamp of the contract
+    /// * end - end of the range, excluded
+    /// * bar_size_setting - a bar size accepted by req_historical_data, such as "1 min"
+    /// * what_to_show - the type of data, such as "TRADES" or "MIDPOINT"
+    /// * first_req_id - the first request id to use.  One id is used per request sent.
+    pub fn new(
+        contract: Contract,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bar_size_setting: &str,
+        what_to_show: &str,
+        first_req_id: i32,
+    ) -> Self {
+        HistoricalDownloader {
+            contract,
+            start,
+            end,
+            bar_size_setting: bar_size_setting.to_string(),
+            what_to_show: what_to_show.to_string(),
+            use_rth: 0,
+            timeout: Duration::from_secs(60),
+            max_retries: 3,
+            pacing_backoff: Duration::from_secs(10),
+            pacer: Pacer::new(),
+            next_req_id: first_req_id,
+            head_timestamp: None,
+            chunks: vec![],
+            completed: BTreeMap::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the chunks still to be downloaded
+    pub fn pending_chunks(&self) -> Vec<Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| !self.completed.contains_key(&chunk.end))
+            .cloned()
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn take_req_id(&mut self) -> i32 {
+        let req_id = self.next_req_id;
+        self.next_req_id += 1;
+        req_id
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn contract_key(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            self.contract.con_id,
+            self.contract.symbol,
+            self.contract.sec_type,
+            self.contract.exchange,
+            self.what_to_show
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits as long as the pacing rules require before a request
+    fn pace(&mut self, request_key: &str) {
+        let contract_key = self.contract_key();
+        let delay = self.pacer.delay(&contract_key, request_key, Instant::now());
+        if delay > Duration::from_secs(0) {
+            info!(
+                "Waiting {:?} before the next historical data request",
+                delay
+            );
+            thread::sleep(delay);
+        }
+        self.pacer
+            .record(&contract_key, request_key, Instant::now());
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Runs a request, retrying it up to max_retries times when it times out or violates the
+    /// pacing rules.  Other errors are returned at once.
+    fn with_retries<R, F>(
+        &mut self,
+        request_key: &str,
+        mut request: F,
+    ) -> Result<R, IBKRApiLibError>
+    where
+        F: FnMut(i32) -> Result<R, IBKRApiLibError>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.pace(request_key);
+            let req_id = self.take_req_id();
+            match request(req_id) {
+                Ok(result) => return Ok(result),
+                Err(err) if attempt < self.max_retries && is_retryable(&err) => {
+                    attempt += 1;
+                    warn!(
+                        "Historical data request {} failed, retrying ({}/{}): {}",
+                        req_id, attempt, self.max_retries, err
+                    );
+                    if is_pacing_violation(&err) {
+                        thread::sleep(self.pacing_backoff * 2u32.pow(attempt - 1));
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Finds the earliest data available with req_head_time_stamp and plans the chunks from
+    /// the later of it and start
+    fn plan<T>(&mut self, client: &Mutex<EClient<T>>) -> Result<(), IBKRApiLibError>
+    where
+        T: Wrapper + Send + Sync + 'static,
+    {
+        let contract = self.contract.clone();
+        let what_to_show = self.what_to_show.clone();
+        let use_rth = self.use_rth;
+        let timeout = self.timeout;
+        let time_zone = client.lock().expect(POISONED_MUTEX).tws_time_zone();
+        let request_key = format!("head:{}", self.contract_key());
+        let head_timestamp = self.with_retries(&request_key, |req_id| {
+            let pending = send_historical_request(client, req_id, |client| {
+                client.req_head_time_stamp(
+                    req_id,
+                    &contract,
+                    &what_to_show,
+                    use_rth,
+                    FORMAT_DATE_EPOCH,
+                )
+            })?;
+            let head_timestamp = match pending.wait_for_head_timestamp(timeout) {
+                Err(IBKRApiLibError::RecvTimeoutError(err)) => {
+                    client
+                        .lock()
+                        .expect(POISONED_MUTEX)
+                        .cancel_head_time_stamp(req_id)?;
+                    return Err(IBKRApiLibError::RecvTimeoutError(err));
+                }
+                result => result?,
+            };
+            parse_tws_time(&head_timestamp, FORMAT_DATE_EPOCH, &time_zone)
+        })?;
+
+        self.head_timestamp = Some(head_timestamp);
+        self.chunks = plan_chunks(
+            self.start.max(head_timestamp),
+            self.end,
+            &self.bar_size_setting,
+        )?;
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Downloads the missing chunks and returns all the bars of the range in time order,
+    /// without duplicates.  After an error, calling download again resumes with the chunks
+    /// that were not downloaded.
+    ///
+    /// # Arguments
+    /// * client - a connected client, not locked by the caller
+    pub fn download<T>(
+        &mut self,
+        client: &Mutex<EClient<T>>,
+    ) -> Result<Vec<BarData>, IBKRApiLibError>
+    where
+        T: Wrapper + Send + Sync + 'static,
+    {
+        if self.head_timestamp.is_none() {
+            self.plan(client)?;
+        }
+
+        for chunk in self.pending_chunks() {
+            let contract = self.contract.clone();
+            let end_date_time = chunk.end;
+            let bar_size_setting = self.bar_size_setting.clone();
+            let what_to_show = self.what_to_show.clone();
+            let use_rth = self.use_rth;
+            let timeout = self.timeout;
+            let request_key = format!(
+                "{}:{}:{}",
+                self.contract_key(),
+                format_tws_time(&end_date_time),
+                chunk.duration_str
+            );
+            let bars = self.with_retries(&request_key, |req_id| {
+                let pending = send_historical_request(client, req_id, |client| {
+                    client.req_historical_data(
+                        req_id,
+                        &contract,
+                        Some(end_date_time),
+                        &chunk.duration_str,
+                        &bar_size_setting,
+                        &what_to_show,
+                        use_rth,
+                        FORMAT_DATE_EPOCH,
+                        false,
+                        vec![],
+                    )
+                })?;
+                match pending.wait_for_bars(timeout) {
+                    // A chunk without any trading, such as a holiday, is not an error
+                    Err(ref err) if is_no_data_error(err) => Ok(vec![]),
+                    Err(IBKRApiLibError::RecvTimeoutError(err)) => {
+                        client
+                            .lock()
+                            .expect(POISONED_MUTEX)
+                            .cancel_historical_data(req_id)?;
+                        Err(IBKRApiLibError::RecvTimeoutError(err))
+                    }
+                    result => result,
+                }
+            })?;
+            self.completed.insert(chunk.end, bars);
+        }
+
+        Ok(merge_bars(
+            self.completed.values().cloned().collect(),
+            self.start,
+            self.end,
+        ))
+    }
+}
@@ -0,0 +1,358 @@
+//! Pages through historical ticks over a time range, 1000 ticks per req_historical_ticks call
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::*;
+
+use crate::core::client::EClient;
+use crate::core::common::{HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast};
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::historical_data::{send_historical_request, Pacer};
+use crate::core::timestamps::from_epoch_seconds;
+use crate::core::wrapper::Wrapper;
+
+/// Most ticks returned by one req_historical_ticks call
+pub const MAX_TICKS_PER_REQUEST: i32 = 1000;
+
+//==================================================================================================
+/// Ticks received for a req_historical_ticks request, by the callback they came from
+#[derive(Clone, Debug)]
+pub enum HistoricalTicks {
+    /// Sent to historical_ticks, when what_to_show is MIDPOINT
+    Midpoint(Vec<HistoricalTick>),
+    /// Sent to historical_ticks_bid_ask, when what_to_show is BID_ASK
+    BidAsk(Vec<HistoricalTickBidAsk>),
+    /// Sent to historical_ticks_last, when what_to_show is TRADES
+    Last(Vec<HistoricalTickLast>),
+}
+
+impl HistoricalTicks {
+    //----------------------------------------------------------------------------------------------
+    /// Appends the ticks of another message of the same request.  Ticks of another kind are
+    /// ignored.
+    pub fn extend(&mut self, other: HistoricalTicks) {
+        match (self, other) {
+            (HistoricalTicks::Midpoint(ticks), HistoricalTicks::Midpoint(other)) => {
+                ticks.extend(other)
+            }
+            (HistoricalTicks::BidAsk(ticks), HistoricalTicks::BidAsk(other)) => ticks.extend(other),
+            (HistoricalTicks::Last(ticks), HistoricalTicks::Last(other)) => ticks.extend(other),
+            (_, other) => warn!("Ignoring historical ticks of another kind: {:?}", other),
+        }
+    }
+}
+
+//==================================================================================================
+/// A kind of historical tick that can be paged through
+pub trait HistoricalTickData: Clone {
+    /// The what_to_show value requesting this kind of tick
+    const WHAT_TO_SHOW: &'static str;
+
+    /// Time of the tick
+    fn time(&self) -> DateTime<Utc>;
+
+    /// Extracts the ticks of this kind, or None if the ticks are of another kind
+    fn from_ticks(ticks: HistoricalTicks) -> Option<Vec<Self>>;
+}
+
+impl HistoricalTickData for HistoricalTick {
+    const WHAT_TO_SHOW: &'static str = "MIDPOINT";
+
+    fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    fn from_ticks(ticks: HistoricalTicks) -> Option<Vec<Self>> {
+        match ticks {
+            HistoricalTicks::Midpoint(ticks) => Some(ticks),
+            _ => None,
+        }
+    }
+}
+
+impl HistoricalTickData for HistoricalTickBidAsk {
+    const WHAT_TO_SHOW: &'static str = "BID_ASK";
+
+    fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    fn from_ticks(ticks: HistoricalTicks) -> Option<Vec<Self>> {
+        match ticks {
+            HistoricalTicks::BidAsk(ticks) => Some(ticks),
+            _ => None,
+        }
+    }
+}
+
+impl HistoricalTickData for HistoricalTickLast {
+    const WHAT_TO_SHOW: &'static str = "TRADES";
+
+    fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    fn from_ticks(ticks: HistoricalTicks) -> Option<Vec<Self>> {
+        match ticks {
+            HistoricalTicks::Last(ticks) => Some(ticks),
+            _ => None,
+        }
+    }
+}
+
+//==================================================================================================
+/// Order in which the ticks of a range are returned
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageDirection {
+    /// From the start of the range, oldest tick first
+    Forward,
+    /// From the end of the range, most recent tick first
+    Backward,
+}
+
+//==================================================================================================
+/// Iterates over the ticks of a contract in the range [start, end), requesting a page of ticks
+/// at a time.  Each page starts, or ends when going backward, at the second of the last tick
+/// returned, and the ticks of that second already returned are skipped, so that no tick is
+/// returned twice.  A page made only of ticks already returned, which happens when more than a
+/// page of ticks share a second, moves on to the next second.
+///
+/// The kind of tick is chosen by the item type: HistoricalTick for midpoints,
+/// HistoricalTickBidAsk for bids and asks, and HistoricalTickLast for trades.  Iteration ends
+/// at the edge of the range, or when TWS has no more ticks.  After an error, calling next
+/// again requests the same page again.
+///
+/// The client is locked only while each page is requested, not during the pacing delays nor
+/// while waiting for the ticks, so it stays usable from other threads between calls to next.
+pub struct HistoricalTicksPager<'a, T, K>
+where
+    T: Wrapper + Send + Sync + 'static,
+    K: HistoricalTickData,
+{
+    client: &'a Mutex<EClient<T>>,
+    pub contract: Contract,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub direction: PageDirection,
+    pub use_rth: i32,
+    pub ignore_size: bool,
+    /// Ticks requested per page, at most MAX_TICKS_PER_REQUEST
+    pub ticks_per_page: i32,
+    /// How long to wait for each page
+    pub timeout: Duration,
+    pub pacer: Pacer,
+    next_req_id: i32,
+    /// Second at which the next page starts, or ends
+    cursor: i64,
+    /// Ticks of the cursor second already returned
+    returned_at_cursor: usize,
+    page: VecDeque<K>,
+    finished: bool,
+}
+
+impl<'a, T, K> HistoricalTicksPager<'a, T, K>
+where
+    T: Wrapper + Send + Sync + 'static,
+    K: HistoricalTickData,
+{
+    //----------------------------------------------------------------------------------------------
+    /// # Arguments
+    /// * client - a connected client, not locked by the caller
+    /// * contract - the contract to request ticks for
+    /// * start - start of the range
+    /// * end - end of the range, excluded
+    /// * direction - whether to page forward from start or backward from end
+    /// * first_req_id - the first request id to use.  One id is used per page.
+    pub fn new(
+        client: &'a Mutex<EClient<T>>,
+        contract: Contract,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        direction: PageDirection,
+        first_req_id: i32,
+    ) -> Self {
+        let cursor = match direction {
+            PageDirection::Forward => start.timestamp(),
+            PageDirection::Backward => end.timestamp(),
+        };
+        HistoricalTicksPager {
+            client,
+            contract,
+            start,
+            end,
+            direction,
+            use_rth: 0,
+            ignore_size: false,
+            ticks_per_page: MAX_TICKS_PER_REQUEST,
+            timeout: Duration::from_secs(60),
+            pacer: Pacer::new(),
+            next_req_id: first_req_id,
+            cursor,
+            returned_at_cursor: 0,
+            page: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns true if the tick is past the edge of the range the pager moves towards
+    fn past_edge(&self, time: i64) -> bool {
+        match self.direction {
+            PageDirection::Forward => time >= self.end.timestamp(),
+            PageDirection::Backward => time < self.start.timestamp(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Waits as long as the pacing rules require before a request
+    fn pace(&mut self) {
+        let contract_key = format!(
+            "{}:{}:{}:{}:{}",
+            self.contract.con_id,
+            self.contract.symbol,
+            self.contract.sec_type,
+            self.contract.exchange,
+            K::WHAT_TO_SHOW
+        );
+        let request_key = format!("{}:{:?}:{}", contract_key, self.direction, self.cursor);
+        let delay = self
+            .pacer
+            .delay(&contract_key, &request_key, Instant::now());
+        if delay > Duration::from_secs(0) {
+            info!(
+                "Waiting {:?} before the next historical ticks request",
+                delay
+            );
+            thread::sleep(delay);
+        }
+        self.pacer
+            .record(&contract_key, &request_key, Instant::now());
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the page at the cursor and returns its ticks in iteration order
+    fn request_page(&mut self) -> Result<Vec<K>, IBKRApiLibError> {
+        self.pace();
+        let req_id = self.next_req_id;
+        self.next_req_id += 1;
+
+        let cursor = from_epoch_seconds(self.cursor)?;
+        let (start_date_time, end_date_time) = match self.direction {
+            PageDirection::Forward => (Some(cursor), None),
+            PageDirection::Backward => (None, Some(cursor)),
+        };
+
+        let pending = send_historical_request(self.client, req_id, |client| {
+            client.req_historical_ticks(
+                req_id,
+                &self.contract,
+                start_date_time,
+                end_date_time,
+                self.ticks_per_page,
+                K::WHAT_TO_SHOW,
+                self.use_rth,
+                self.ignore_size,
+                vec![],
+            )
+        })?;
+        let mut ticks = match pending.wait_for_ticks(self.timeout)? {
+            Some(ticks) => K::from_ticks(ticks).ok_or_else(|| {
+                IBKRApiLibError::invalid_argument(
+                    req_id,
+                    &format!(
+                        "Historical ticks are not of the kind requested: {}",
+                        K::WHAT_TO_SHOW
+                    ),
+                )
+            })?,
+            None => vec![],
+        };
+        if self.direction == PageDirection::Backward {
+            ticks.reverse();
+        }
+        Ok(ticks)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Requests the next page, keeps its new ticks inside the range and moves the cursor
+    fn fetch_page(&mut self) -> Result<(), IBKRApiLibError> {
+        let ticks = self.request_page()?;
+        let full = ticks.len() >= self.ticks_per_page as usize;
+        if ticks.is_empty() {
+            self.finished = true;
+            return Ok(());
+        }
+
+        // The page starts with the ticks of the cursor second returned by the previous page
+        let cursor = self.cursor;
+        let skip = ticks
+            .iter()
+            .take(self.returned_at_cursor)
+            .take_while(|tick| tick.time().timestamp() == cursor)
+            .count();
+        let new_ticks = &ticks[skip..];
+
+        match new_ticks.last() {
+            Some(last) => {
+                self.cursor = last.time().timestamp();
+                self.returned_at_cursor = ticks
+                    .iter()
+                    .filter(|tick| tick.time().timestamp() == self.cursor)
+                    .count();
+            }
+            None if full => {
+                warn!(
+                    "More than {} historical ticks at {}, moving on to the next second",
+                    self.ticks_per_page, self.cursor
+                );
+                self.cursor += match self.direction {
+                    PageDirection::Forward => 1,
+                    PageDirection::Backward => -1,
+                };
+                self.returned_at_cursor = 0;
+            }
+            None => (),
+        }
+
+        for tick in new_ticks {
+            let time = tick.time().timestamp();
+            if self.past_edge(time) {
+                self.finished = true;
+                break;
+            }
+            if self.start.timestamp() <= time && time < self.end.timestamp() {
+                self.page.push_back(tick.clone());
+            }
+        }
+        if !full || self.past_edge(self.cursor) {
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T, K> Iterator for HistoricalTicksPager<'a, T, K>
+where
+    T: Wrapper + Send + Sync + 'static,
+    K: HistoricalTickData,
+{
+    type Item = Result<K, IBKRApiLibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(tick) = self.page.pop_front() {
+                return Some(Ok(tick));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(err) = self.fetch_page() {
+                return Some(Err(err));
+            }
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! Folds the tick callbacks of market data subscriptions into a current snapshot per request
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::*;
+use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::POISONED_MUTEX;
+use crate::core::common::{MarketDataTypeEnum, TickAttrib, TickType};
+use crate::core::errors::IBKRApiLibError;
+use crate::core::generic_tick::{Dividends, FundamentalRatios, RtVolume, Shortability};
+
+//==================================================================================================
+/// Trading halt status sent in the halted generic tick
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum HaltStatus {
+    NotHalted,
+    Halted,
+    VolatilityHalt,
+}
+
+impl HaltStatus {
+    pub fn from_tick_value(value: f64) -> Option<Self> {
+        match value as i32 {
+            0 => Some(HaltStatus::NotHalted),
+            1 => Some(HaltStatus::Halted),
+            2 => Some(HaltStatus::VolatilityHalt),
+            _ => None,
+        }
+    }
+}
+
+//==================================================================================================
+/// Values of a tick_option_computation callback, None where TWS has not computed them
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct OptionComputation {
+    pub implied_vol: Option<f64>,
+    pub delta: Option<f64>,
+    pub opt_price: Option<f64>,
+    pub pv_dividend: Option<f64>,
+    pub gamma: Option<f64>,
+    pub vega: Option<f64>,
+    pub theta: Option<f64>,
+    pub und_price: Option<f64>,
+}
+
+impl OptionComputation {
+    //----------------------------------------------------------------------------------------------
+    /// Returns a value sent by TWS, or None if it is the sentinel of a value not computed: -1 for
+    /// the volatility and prices, -2 for the greeks.  f64::MAX, the sentinel passed to
+    /// tick_option_computation, is also None.
+    pub fn computed(value: f64, not_computed: f64) -> Option<f64> {
+        if value == not_computed || value == f64::MAX || value.is_nan() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+
+//==================================================================================================
+/// Values of a tick_efp callback
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct EfpComputation {
+    pub basis_points: f64,
+    pub formatted_basis_points: String,
+    pub implied_future: f64,
+    pub hold_days: i32,
+    pub future_last_trade_date: String,
+    pub dividend_impact: f64,
+    pub dividends_to_last_trade_date: f64,
+}
+
+impl EfpComputation {
+    pub fn new(
+        basis_points: f64,
+        formatted_basis_points: String,
+        implied_future: f64,
+        hold_days: i32,
+        future_last_trade_date: String,
+        dividend_impact: f64,
+        dividends_to_last_trade_date: f64,
+    ) -> Self {
+        EfpComputation {
+            basis_points,
+            formatted_basis_points,
+            implied_future,
+            hold_days,
+            future_last_trade_date,
+            dividend_impact,
+            dividends_to_last_trade_date,
+        }
+    }
+}
+
+//==================================================================================================
+/// Current state of a market data subscription.  Delayed ticks are stored in the same fields as
+/// their live counterparts, market_data_type tells which kind of data the values are.  Ticks
+/// without a dedicated field are kept in other_values and other_strings.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Ticker {
+    pub req_id: i32,
+    pub market_data_type: MarketDataTypeEnum,
+    pub bid: Option<f64>,
+    pub bid_size: Option<i32>,
+    pub ask: Option<f64>,
+    pub ask_size: Option<i32>,
+    pub last: Option<f64>,
+    pub last_size: Option<i32>,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub volume: Option<i32>,
+    pub mark_price: Option<f64>,
+    pub bid_exchange: Option<String>,
+    pub ask_exchange: Option<String>,
+    pub last_exchange: Option<String>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+    pub halted: Option<HaltStatus>,
+    pub shortable: Option<Shortability>,
+    pub shortable_shares: Option<f64>,
+    pub rt_volume: Option<RtVolume>,
+    pub rt_trade_volume: Option<RtVolume>,
+    pub dividends: Option<Dividends>,
+    pub fundamental_ratios: Option<FundamentalRatios>,
+    pub bid_attrib: TickAttrib,
+    pub ask_attrib: TickAttrib,
+    pub bid_option: Option<OptionComputation>,
+    pub ask_option: Option<OptionComputation>,
+    pub last_option: Option<OptionComputation>,
+    pub model_option: Option<OptionComputation>,
+    pub bid_efp: Option<EfpComputation>,
+    pub ask_efp: Option<EfpComputation>,
+    pub last_efp: Option<EfpComputation>,
+    pub other_values: HashMap<TickType, f64>,
+    pub other_strings: HashMap<TickType, String>,
+    /// Set once tick_snapshot_end has been received for a snapshot request
+    pub snapshot_complete: bool,
+}
+
+impl Ticker {
+    pub fn new(req_id: i32) -> Self {
+        Ticker {
+            req_id,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the midpoint of the bid and ask, if both are known
+    pub fn midpoint(&self) -> Option<f64> {
+        match (self.bid, self.ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the implied volatility of the model computation, else the average of those of the
+    /// bid and ask computations if both are known
+    pub fn implied_vol(&self) -> Option<f64> {
+        let implied_vol = |computation: Option<OptionComputation>| computation?.implied_vol;
+        implied_vol(self.model_option)
+            .or_else(|| Some((implied_vol(self.bid_option)? + implied_vol(self.ask_option)?) / 2.0))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns true if the values are delayed rather than real time
+    pub fn is_delayed(&self) -> bool {
+        matches!(
+            self.market_data_type,
+            MarketDataTypeEnum::Delayed | MarketDataTypeEnum::DelayedFrozen
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records that a delayed tick arrived, in case the market_data_type message has not
+    fn mark_delayed(&mut self) {
+        self.market_data_type = match self.market_data_type {
+            MarketDataTypeEnum::Frozen | MarketDataTypeEnum::DelayedFrozen => {
+                MarketDataTypeEnum::DelayedFrozen
+            }
+            _ => MarketDataTypeEnum::Delayed,
+        };
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_price(&mut self, tick_type: TickType, price: f64, attrib: &TickAttrib) {
+        // A bid or ask of -1 means that there is currently no quote
+        let quote = if price == -1.0 { None } else { Some(price) };
+        match tick_type {
+            TickType::Bid => {
+                self.bid = quote;
+                self.bid_attrib = attrib.clone();
+            }
+            TickType::Ask => {
+                self.ask = quote;
+                self.ask_attrib = attrib.clone();
+            }
+            TickType::Last => self.last = quote,
+            TickType::Open => self.open = Some(price),
+            TickType::High => self.high = Some(price),
+            TickType::Low => self.low = Some(price),
+            TickType::Close => self.close = Some(price),
+            TickType::MarkPrice => self.mark_price = Some(price),
+            _ => {
+                self.other_values.insert(tick_type, price);
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_size(&mut self, tick_type: TickType, size: i32) {
+        match tick_type {
+            TickType::BidSize => self.bid_size = Some(size),
+            TickType::AskSize => self.ask_size = Some(size),
+            TickType::LastSize => self.last_size = Some(size),
+            TickType::Volume => self.volume = Some(size),
+            TickType::ShortableShares => self.shortable_shares = Some(size as f64),
+            _ => {
+                self.other_values.insert(tick_type, size as f64);
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_generic(&mut self, tick_type: TickType, value: f64) {
+        match tick_type {
+            TickType::Halted => self.halted = HaltStatus::from_tick_value(value),
+            TickType::Shortable => self.shortable = Some(Shortability::from_tick_value(value)),
+            TickType::ShortableShares => self.shortable_shares = Some(value),
+            _ => {
+                self.other_values.insert(tick_type, value);
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_string(&mut self, tick_type: TickType, value: &str) {
+        match tick_type {
+            TickType::LastTimestamp => {
+                self.last_timestamp = value
+                    .trim()
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single());
+            }
+            TickType::BidExch => self.bid_exchange = Some(value.to_string()),
+            TickType::AskExch => self.ask_exchange = Some(value.to_string()),
+            TickType::LastExch => self.last_exchange = Some(value.to_string()),
+            TickType::RtVolume | TickType::RtTrdVolume | TickType::IbDividends => {
+                if let Err(err) = self.update_parsed_string(tick_type, value) {
+                    warn!("{}", err);
+                    self.other_strings.insert(tick_type, value.to_string());
+                }
+            }
+            TickType::FundamentalRatios => {
+                self.fundamental_ratios = Some(FundamentalRatios::parse(value))
+            }
+            _ => {
+                self.other_strings.insert(tick_type, value.to_string());
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_parsed_string(
+        &mut self,
+        tick_type: TickType,
+        value: &str,
+    ) -> Result<(), IBKRApiLibError> {
+        match tick_type {
+            TickType::RtVolume => self.rt_volume = Some(RtVolume::parse(value)?),
+            TickType::RtTrdVolume => self.rt_trade_volume = Some(RtVolume::parse(value)?),
+            TickType::IbDividends => self.dividends = Some(Dividends::parse(value)?),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_option_computation(&mut self, tick_type: TickType, values: OptionComputation) {
+        match tick_type {
+            TickType::BidOptionComputation => self.bid_option = Some(values),
+            TickType::AskOptionComputation => self.ask_option = Some(values),
+            TickType::LastOptionComputation => self.last_option = Some(values),
+            TickType::ModelOption | TickType::CustOptionComputation => {
+                self.model_option = Some(values)
+            }
+            _ => (),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn update_efp(&mut self, tick_type: TickType, values: EfpComputation) {
+        match tick_type {
+            TickType::BidEfpComputation => self.bid_efp = Some(values),
+            TickType::AskEfpComputation => self.ask_efp = Some(values),
+            TickType::LastEfpComputation => self.last_efp = Some(values),
+            _ => (),
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Maps a delayed tick type to its real time counterpart.  Returns the tick type and whether
+/// it was a delayed one.
+pub fn live_tick_type(tick_type: TickType) -> (TickType, bool) {
+    let live = match tick_type {
+        TickType::DelayedBid => TickType::Bid,
+        TickType::DelayedAsk => TickType::Ask,
+        TickType::DelayedLast => TickType::Last,
+        TickType::DelayedBidSize => TickType::BidSize,
+        TickType::DelayedAskSize => TickType::AskSize,
+        TickType::DelayedLastSize => TickType::LastSize,
+        TickType::DelayedHigh => TickType::High,
+        TickType::DelayedLow => TickType::Low,
+        TickType::DelayedVolume => TickType::Volume,
+        TickType::DelayedClose => TickType::Close,
+        TickType::DelayedOpen => TickType::Open,
+        TickType::DelayedBidOption => TickType::BidOptionComputation,
+        TickType::DelayedAskOption => TickType::AskOptionComputation,
+        TickType::DelayedLastOption => TickType::LastOptionComputation,
+        TickType::DelayedModelOption => TickType::ModelOption,
+        TickType::DelayedLastTimestamp => TickType::LastTimestamp,
+        _ => return (tick_type, false),
+    };
+    (live, true)
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct CacheState {
+    tickers: HashMap<i32, Ticker>,
+    subscribers: HashMap<i32, Vec<Sender<Ticker>>>,
+}
+
+impl CacheState {
+    //----------------------------------------------------------------------------------------------
+    /// Applies an update to the ticker of a request and sends the result to its subscribers
+    fn update<F: FnOnce(&mut Ticker)>(&mut self, req_id: i32, update: F) {
+        let ticker = self
+            .tickers
+            .entry(req_id)
+            .or_insert_with(|| Ticker::new(req_id));
+        update(ticker);
+
+        if let Some(subscribers) = self.subscribers.get_mut(&req_id) {
+            let ticker = &self.tickers[&req_id];
+            subscribers.retain(|subscriber| subscriber.send(ticker.clone()).is_ok());
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies a tick to the ticker, mapping delayed tick types to their live counterparts
+    fn update_tick<F: FnOnce(&mut Ticker, TickType)>(
+        &mut self,
+        req_id: i32,
+        tick_type: TickType,
+        update: F,
+    ) {
+        let (tick_type, delayed) = live_tick_type(tick_type);
+        self.update(req_id, |ticker| {
+            if delayed && !ticker.is_delayed() {
+                ticker.mark_delayed();
+            }
+            update(ticker, tick_type);
+        });
+    }
+}
+
+//==================================================================================================
+/// Thread safe store of Tickers keyed by the req_id of req_mkt_data.  The decoder updates it
+/// every time a tick arrives, before the matching Wrapper callback is called.
+#[derive(Debug, Default)]
+pub struct MarketDataCache {
+    state: Mutex<CacheState>,
+}
+
+impl MarketDataCache {
+    pub fn new() -> Self {
+        MarketDataCache {
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the current ticker of a request, if any tick has been received for it
+    pub fn ticker(&self, req_id: i32) -> Option<Ticker> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .tickers
+            .get(&req_id)
+            .cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of all the tickers
+    pub fn tickers(&self) -> Vec<Ticker> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .tickers
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a channel receiving a copy of the ticker every time it changes.  The channel is
+    /// closed when the subscription is cancelled.
+    pub fn subscribe(&self, req_id: i32) -> Receiver<Ticker> {
+        let (sender, receiver) = channel();
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .subscribers
+            .entry(req_id)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Clears the ticker of a request, keeping its subscribers.  Called when a request id is
+    /// reused for a new subscription.
+    pub fn reset(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .tickers
+            .remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes and returns the ticker of a request and closes the channels of its subscribers
+    pub fn remove(&self, req_id: i32) -> Option<Ticker> {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.subscribers.remove(&req_id);
+        state.tickers.remove(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the market data type reported by the market_data_type callback
+    pub fn update_market_data_type(&self, req_id: i32, market_data_type: i32) {
+        let market_data_type =
+            FromPrimitive::from_i32(market_data_type).unwrap_or(MarketDataTypeEnum::Unknown);
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .update(req_id, |ticker| ticker.market_data_type = market_data_type);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn update_price(&self, req_id: i32, tick_type: TickType, price: f64, attrib: &TickAttrib) {
+        self.state.lock().expect(POISONED_MUTEX).update_tick(
+            req_id,
+            tick_type,
+            |ticker, tick_type| ticker.update_price(tick_type, price, attrib),
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn update_size(&self, req_id: i32, tick_type: TickType, size: i32) {
+        self.state.lock().expect(POISONED_MUTEX).update_tick(
+            req_id,
+            tick_type,
+            |ticker, tick_type| ticker.update_size(tick_type, size),
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn update_generic(&self, req_id: i32, tick_type: TickType, value: f64) {
+        self.state.lock().expect(POISONED_MUTEX).update_tick(
+            req_id,
+            tick_type,
+            |ticker, tick_type| ticker.update_generic(tick_type, value),
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn update_string(&self, req_id: i32, tick_type: TickType, value: &str) {
+        self.state.lock().expect(POISONED_MUTEX).update_tick(
+            req_id,
+            tick_type,
+            |ticker, tick_type| ticker.update_string(tick_type, value),
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn update_option_computation(
+        &self,
+        req_id: i32,
+        tick_type: TickType,
+        values: OptionComputation,
+    ) {
+        self.state.lock().expect(POISONED_MUTEX).update_tick(
+            req_id,
+            tick_type,
+            |ticker, tick_type| ticker.update_option_computation(tick_type, values),
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn update_efp(&self, req_id: i32, tick_type: TickType, values: EfpComputation) {
+        self.state.lock().expect(POISONED_MUTEX).update_tick(
+            req_id,
+            tick_type,
+            |ticker, tick_type| ticker.update_efp(tick_type, values),
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Marks a snapshot request as complete
+    pub fn snapshot_end(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .update(req_id, |ticker| ticker.snapshot_complete = true);
+    }
+}
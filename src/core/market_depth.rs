@@ -0,0 +1,439 @@
+//! Rebuilds level 2 order books from the update_mkt_depth and update_mkt_depth_l2 callbacks
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+use std::sync::Mutex;
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::POISONED_MUTEX;
+use crate::core::common::TagValue;
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+
+//==================================================================================================
+/// Side of the book in a market depth update
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq)]
+pub enum DepthSide {
+    Ask = 0,
+    Bid = 1,
+}
+
+impl Display for DepthSide {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            DepthSide::Ask => write!(f, "ask"),
+            DepthSide::Bid => write!(f, "bid"),
+        }
+    }
+}
+
+//==================================================================================================
+/// How a market depth update changes the row at its position
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Clone, Copy, FromPrimitive, Debug, PartialEq)]
+pub enum DepthOperation {
+    Insert = 0,
+    Update = 1,
+    Delete = 2,
+}
+
+impl Display for DepthOperation {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            DepthOperation::Insert => write!(f, "insert"),
+            DepthOperation::Update => write!(f, "update"),
+            DepthOperation::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+//==================================================================================================
+/// A row of the book.  market_maker is empty for update_mkt_depth rows, and holds the exchange
+/// for smart depth rows.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DepthRow {
+    pub price: f64,
+    pub size: i32,
+    pub market_maker: String,
+}
+
+impl DepthRow {
+    pub fn new(price: f64, size: i32, market_maker: String) -> Self {
+        DepthRow {
+            price,
+            size,
+            market_maker,
+        }
+    }
+}
+
+//==================================================================================================
+/// Total size at a price, summed over all the rows quoting it
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PriceLevel {
+    pub price: f64,
+    pub size: i32,
+    pub row_count: usize,
+}
+
+//==================================================================================================
+/// Copy of both sides of a book taken at one point in time
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OrderBookSnapshot {
+    pub req_id: i32,
+    pub is_smart_depth: bool,
+    pub bids: Vec<DepthRow>,
+    pub asks: Vec<DepthRow>,
+}
+
+//==================================================================================================
+/// Order book of a req_mkt_depth request.  Rows are kept in the order TWS positions them, best
+/// price first.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OrderBook {
+    pub req_id: i32,
+    pub is_smart_depth: bool,
+    bids: Vec<DepthRow>,
+    asks: Vec<DepthRow>,
+    /// Set when an update could not be applied.  The book is cleared, ignores the updates that
+    /// follow and has to be requested again.
+    pub invalid: bool,
+    /// Set while a requested book waits for the first row of its stream, an insert at position
+    /// 0.  The updates received before it, such as those still in flight from a cancelled
+    /// request with the same id, are ignored.
+    pub awaiting_first_row: bool,
+}
+
+impl OrderBook {
+    pub fn new(req_id: i32) -> Self {
+        OrderBook {
+            req_id,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies a market depth update.  An update that does not match the current rows, or has
+    /// an unknown side or operation, clears the book, marks it invalid and returns an error.
+    /// Updates received once the book is invalid, or while it awaits its first row, are ignored.
+    ///
+    /// # Arguments
+    /// * position - the row being updated
+    /// * operation - 0 = insert, 1 = update, 2 = delete
+    /// * side - 0 for ask, 1 for bid
+    /// * row - price, size and market maker of the order, the market maker is empty for
+    ///   update_mkt_depth
+    /// * is_smart_depth - is SMART Depth request
+    pub fn apply(
+        &mut self,
+        position: i32,
+        operation: i32,
+        side: i32,
+        row: DepthRow,
+        is_smart_depth: bool,
+    ) -> Result<(), IBKRApiLibError> {
+        if self.invalid {
+            return Ok(());
+        }
+        if self.awaiting_first_row {
+            if position != 0 || operation != DepthOperation::Insert as i32 {
+                return Ok(());
+            }
+            self.awaiting_first_row = false;
+        }
+        let result = self.try_apply(position, operation, side, row, is_smart_depth);
+        if result.is_err() {
+            self.bids.clear();
+            self.asks.clear();
+            self.invalid = true;
+        }
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn try_apply(
+        &mut self,
+        position: i32,
+        operation: i32,
+        side: i32,
+        row: DepthRow,
+        is_smart_depth: bool,
+    ) -> Result<(), IBKRApiLibError> {
+        let req_id = self.req_id;
+        let invalid = move |message: String| IBKRApiLibError::invalid_argument(req_id, &message);
+        let side: DepthSide = FromPrimitive::from_i32(side)
+            .ok_or_else(|| invalid(format!("Unknown market depth side: {}", side)))?;
+        let operation: DepthOperation = FromPrimitive::from_i32(operation)
+            .ok_or_else(|| invalid(format!("Unknown market depth operation: {}", operation)))?;
+
+        self.is_smart_depth = is_smart_depth;
+        let rows = match side {
+            DepthSide::Ask => &mut self.asks,
+            DepthSide::Bid => &mut self.bids,
+        };
+        let index = position as usize;
+        let in_range = match operation {
+            DepthOperation::Insert => position >= 0 && index <= rows.len(),
+            _ => position >= 0 && index < rows.len(),
+        };
+        if !in_range {
+            let message = format!(
+                "Cannot {} {} row {} of a book with {} rows",
+                operation,
+                side,
+                position,
+                rows.len()
+            );
+            return Err(invalid(message));
+        }
+
+        match operation {
+            DepthOperation::Insert => rows.insert(index, row),
+            DepthOperation::Update => rows[index] = row,
+            DepthOperation::Delete => {
+                rows.remove(index);
+            }
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn rows(&self, side: DepthSide) -> &[DepthRow] {
+        match side {
+            DepthSide::Ask => &self.asks,
+            DepthSide::Bid => &self.bids,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn bids(&self) -> &[DepthRow] {
+        &self.bids
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn asks(&self) -> &[DepthRow] {
+        &self.asks
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the highest bid, with its size summed over the rows quoting it
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.levels(DepthSide::Bid).into_iter().next()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the lowest ask, with its size summed over the rows quoting it
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.levels(DepthSide::Ask).into_iter().next()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn spread(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some(ask.price - bid.price),
+            _ => None,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Aggregates the rows of a side by price, best price first.  With several market makers
+    /// or exchanges quoting the same price, their sizes are summed.
+    pub fn levels(&self, side: DepthSide) -> Vec<PriceLevel> {
+        let mut levels: Vec<PriceLevel> = vec![];
+        for row in self.rows(side) {
+            match levels.iter_mut().find(|level| level.price == row.price) {
+                Some(level) => {
+                    level.size += row.size;
+                    level.row_count += 1;
+                }
+                None => levels.push(PriceLevel {
+                    price: row.price,
+                    size: row.size,
+                    row_count: 1,
+                }),
+            }
+        }
+        match side {
+            DepthSide::Ask => levels.sort_by(|a, b| a.price.total_cmp(&b.price)),
+            DepthSide::Bid => levels.sort_by(|a, b| b.price.total_cmp(&a.price)),
+        }
+        levels
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the total size quoted at a price on one side
+    pub fn depth_at_price(&self, side: DepthSide, price: f64) -> i32 {
+        self.rows(side)
+            .iter()
+            .filter(|row| row.price == price)
+            .map(|row| row.size)
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the rows quoting a price on one side, one per market maker
+    pub fn market_makers_at(&self, side: DepthSide, price: f64) -> Vec<&DepthRow> {
+        self.rows(side)
+            .iter()
+            .filter(|row| row.price == price)
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns (bid size - ask size) / (bid size + ask size) over the best price levels of each
+    /// side, between -1 (all asks) and 1 (all bids)
+    ///
+    /// # Arguments
+    /// * num_levels - number of price levels of each side to include
+    pub fn imbalance(&self, num_levels: usize) -> Option<f64> {
+        let total = |side| -> f64 {
+            self.levels(side)
+                .iter()
+                .take(num_levels)
+                .map(|level| level.size as f64)
+                .sum()
+        };
+        let bid_size = total(DepthSide::Bid);
+        let ask_size = total(DepthSide::Ask);
+        if bid_size + ask_size > 0.0 {
+            Some((bid_size - ask_size) / (bid_size + ask_size))
+        } else {
+            None
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn snapshot(&self) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            req_id: self.req_id,
+            is_smart_depth: self.is_smart_depth,
+            bids: self.bids.clone(),
+            asks: self.asks.clone(),
+        }
+    }
+}
+
+//==================================================================================================
+/// Parameters of a req_mkt_depth call, kept to be able to request the book again
+#[derive(Clone, Debug, Default)]
+pub struct DepthSubscription {
+    pub contract: Contract,
+    pub num_rows: i32,
+    pub is_smart_depth: bool,
+    pub mkt_depth_options: Vec<TagValue>,
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct DepthState {
+    books: HashMap<i32, OrderBook>,
+    subscriptions: HashMap<i32, DepthSubscription>,
+}
+
+//==================================================================================================
+/// Thread safe store of OrderBooks keyed by the req_id of req_mkt_depth.  The decoder applies
+/// every depth update to it before the matching Wrapper callback is called.
+#[derive(Debug, Default)]
+pub struct MarketDepthCache {
+    state: Mutex<DepthState>,
+}
+
+impl MarketDepthCache {
+    pub fn new() -> Self {
+        MarketDepthCache {
+            state: Mutex::new(DepthState::default()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts a new empty book for a request, awaiting the first row of the stream
+    pub fn subscribe(&self, req_id: i32, subscription: DepthSubscription) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let mut book = OrderBook::new(req_id);
+        book.is_smart_depth = subscription.is_smart_depth;
+        book.awaiting_first_row = true;
+        state.books.insert(req_id, book);
+        state.subscriptions.insert(req_id, subscription);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes and returns the book of a request
+    pub fn remove(&self, req_id: i32) -> Option<OrderBook> {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.subscriptions.remove(&req_id);
+        state.books.remove(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Applies a depth update to the book of a request.  See OrderBook::apply: an error is only
+    /// returned by the update that makes the book invalid.
+    pub fn apply(
+        &self,
+        req_id: i32,
+        position: i32,
+        operation: i32,
+        side: i32,
+        row: DepthRow,
+        is_smart_depth: bool,
+    ) -> Result<(), IBKRApiLibError> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .books
+            .entry(req_id)
+            .or_insert_with(|| OrderBook::new(req_id))
+            .apply(position, operation, side, row, is_smart_depth)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the book of a request
+    pub fn order_book(&self, req_id: i32) -> Option<OrderBook> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .books
+            .get(&req_id)
+            .cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a consistent copy of both sides of the book of a request
+    pub fn snapshot(&self, req_id: i32) -> Option<OrderBookSnapshot> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .books
+            .get(&req_id)
+            .map(|book| book.snapshot())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the requests whose books became invalid and need to be requested again
+    pub fn invalid_books(&self) -> Vec<i32> {
+        let mut req_ids: Vec<i32> = self
+            .state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .books
+            .values()
+            .filter(|book| book.invalid)
+            .map(|book| book.req_id)
+            .collect();
+        req_ids.sort_unstable();
+        req_ids
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the parameters a book was requested with
+    pub fn subscription(&self, req_id: i32) -> Option<DepthSubscription> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .subscriptions
+            .get(&req_id)
+            .cloned()
+    }
+}
@@ -1,18 +1,38 @@
 //! Core structs, enums, and functions
+pub mod account;
 pub mod account_summary_tags;
+pub mod account_value;
 pub mod algo_params;
+pub mod algo_strategy;
+pub mod bar_builder;
 pub mod client;
 pub mod common;
 pub mod contract;
 pub mod decoder;
 pub mod errors;
 pub mod execution;
+pub mod fa;
+#[cfg(feature = "fundamentals")]
+pub mod fundamentals;
+pub mod generic_tick;
+pub mod historical_data;
+pub mod historical_ticks;
+pub mod market_data;
+pub mod market_depth;
 pub mod messages;
+pub mod option_chain;
+pub mod option_pricing;
 pub mod order;
 pub mod order_condition;
 pub mod order_decoder;
+pub mod order_tracker;
+pub mod pnl;
 pub mod reader;
 pub mod scanner;
 pub mod server_versions;
 pub mod streamer;
+pub mod timestamps;
+pub mod trade_blotter;
+pub mod trading_hours;
 pub mod wrapper;
+pub(crate) mod xml;
@@ -0,0 +1,571 @@
+//! Option chains gathered from the security_definition_option_parameter callbacks, with the option
+//! contracts they are made of
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::POISONED_MUTEX;
+use crate::core::common::NO_VALID_ID;
+use crate::core::contract::{Contract, ContractDetails};
+use crate::core::errors::IBKRApiLibError;
+
+//----------------------------------------------------------------------------------------------
+fn invalid_option(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(NO_VALID_ID, &format!("Invalid option: {}", message))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Parses an expiration such as "20240119"
+pub fn parse_expiration(expiration: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(expiration.get(..8)?, "%Y%m%d").ok()
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+impl Display for OptionRight {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            OptionRight::Call => write!(f, "C"),
+            OptionRight::Put => write!(f, "P"),
+        }
+    }
+}
+
+impl FromStr for OptionRight {
+    type Err = IBKRApiLibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "C" | "CALL" => Ok(OptionRight::Call),
+            "P" | "PUT" => Ok(OptionRight::Put),
+            _ => Err(invalid_option(format!("unknown right {}", s))),
+        }
+    }
+}
+
+//==================================================================================================
+/// Identifies an option of a chain, whatever the exchange it is traded on
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OptionKey {
+    pub trading_class: String,
+    pub expiration: String,
+    /// The strike, formatted without trailing zeros
+    pub strike: String,
+    pub right: OptionRight,
+}
+
+impl OptionKey {
+    pub fn new(trading_class: &str, expiration: &str, strike: f64, right: OptionRight) -> Self {
+        OptionKey {
+            trading_class: trading_class.to_string(),
+            expiration: expiration.to_string(),
+            strike: strike.to_string(),
+            right,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the key of an option contract, or None if it is not an option
+    pub fn of(contract: &Contract) -> Option<Self> {
+        Some(OptionKey::new(
+            &contract.trading_class,
+            contract.last_trade_date_or_contract_month.get(..8)?,
+            contract.strike,
+            contract.right.parse().ok()?,
+        ))
+    }
+}
+
+//==================================================================================================
+/// The expirations and strikes of a trading class on an exchange, as sent by a
+/// security_definition_option_parameter callback
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OptionChainParameters {
+    pub exchange: String,
+    pub underlying_con_id: i32,
+    pub trading_class: String,
+    pub multiplier: String,
+    /// Expirations such as "20240119", sorted
+    pub expirations: Vec<String>,
+    /// Strikes, sorted
+    pub strikes: Vec<f64>,
+}
+
+impl OptionChainParameters {
+    pub fn new(
+        exchange: &str,
+        underlying_con_id: i32,
+        trading_class: &str,
+        multiplier: &str,
+        expirations: impl IntoIterator<Item = String>,
+        strikes: impl IntoIterator<Item = f64>,
+    ) -> Self {
+        let expirations: BTreeSet<String> = expirations.into_iter().collect();
+        let mut strikes: Vec<f64> = strikes.into_iter().collect();
+        strikes.sort_by(f64::total_cmp);
+        strikes.dedup();
+        OptionChainParameters {
+            exchange: exchange.to_string(),
+            underlying_con_id,
+            trading_class: trading_class.to_string(),
+            multiplier: multiplier.to_string(),
+            expirations: expirations.into_iter().collect(),
+            strikes,
+        }
+    }
+}
+
+//==================================================================================================
+/// The options of an underlying, gathered from the security_definition_option_parameter callbacks
+/// of a req_sec_def_opt_params request until security_definition_option_parameter_end.
+///
+/// The contracts of the chain are built from its parameters without their con_id.  The con_ids
+/// are resolved on demand with EClient::req_option_con_id.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OptionChain {
+    pub req_id: i32,
+    pub underlying_symbol: String,
+    pub underlying_sec_type: String,
+    pub underlying_con_id: i32,
+    /// Parameters by exchange and trading class
+    pub parameters: Vec<OptionChainParameters>,
+    /// Whether security_definition_option_parameter_end was received
+    pub complete: bool,
+}
+
+impl OptionChain {
+    pub fn new(
+        req_id: i32,
+        underlying_symbol: &str,
+        underlying_sec_type: &str,
+        underlying_con_id: i32,
+    ) -> Self {
+        OptionChain {
+            req_id,
+            underlying_symbol: underlying_symbol.to_string(),
+            underlying_sec_type: underlying_sec_type.to_string(),
+            underlying_con_id,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the exchanges of the chain
+    pub fn exchanges(&self) -> BTreeSet<&str> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.exchange.as_str())
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the trading classes of the chain, such as SPX and SPXW
+    pub fn trading_classes(&self) -> BTreeSet<&str> {
+        self.parameters
+            .iter()
+            .map(|parameters| parameters.trading_class.as_str())
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the expirations of all the trading classes, sorted
+    pub fn expirations(&self) -> Vec<&str> {
+        let expirations: BTreeSet<&str> = self
+            .parameters
+            .iter()
+            .flat_map(|parameters| parameters.expirations.iter().map(String::as_str))
+            .collect();
+        expirations.into_iter().collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the strikes of all the trading classes, sorted
+    pub fn strikes(&self) -> Vec<f64> {
+        let mut strikes: Vec<f64> = self
+            .parameters
+            .iter()
+            .flat_map(|parameters| parameters.strikes.iter().copied())
+            .collect();
+        strikes.sort_by(f64::total_cmp);
+        strikes.dedup();
+        strikes
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the security type of the options: FOP on futures, else OPT
+    pub fn option_sec_type(&self) -> &'static str {
+        if self.underlying_sec_type == "FUT" {
+            "FOP"
+        } else {
+            "OPT"
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Selects the parameters of a trading class, preferring those of the SMART exchange
+    fn parameters_of(&self, trading_class: &str) -> Option<&OptionChainParameters> {
+        let mut candidates = self
+            .parameters
+            .iter()
+            .filter(|parameters| parameters.trading_class == trading_class);
+        let first = candidates.next()?;
+        Some(
+            std::iter::once(first)
+                .chain(candidates)
+                .find(|parameters| parameters.exchange == "SMART")
+                .unwrap_or(first),
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn build(
+        &self,
+        parameters: &OptionChainParameters,
+        expiration: &str,
+        strike: f64,
+        right: OptionRight,
+    ) -> Contract {
+        Contract {
+            symbol: self.underlying_symbol.clone(),
+            sec_type: self.option_sec_type().to_string(),
+            last_trade_date_or_contract_month: expiration.to_string(),
+            strike,
+            right: right.to_string(),
+            multiplier: parameters.multiplier.clone(),
+            exchange: parameters.exchange.clone(),
+            trading_class: parameters.trading_class.clone(),
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the contract of an option of the chain, on the SMART exchange if it is one of the
+    /// exchanges of its trading class
+    ///
+    /// # Arguments
+    /// * trading_class - the trading class, such as SPXW
+    /// * expiration - the expiration, such as "20240119"
+    /// * strike - the strike
+    /// * right - call or put
+    pub fn contract(
+        &self,
+        trading_class: &str,
+        expiration: &str,
+        strike: f64,
+        right: OptionRight,
+    ) -> Result<Contract, IBKRApiLibError> {
+        let parameters = self
+            .parameters_of(trading_class)
+            .ok_or_else(|| invalid_option(format!("unknown trading class {}", trading_class)))?;
+        if !parameters.expirations.iter().any(|e| e == expiration) {
+            return Err(invalid_option(format!(
+                "{} does not expire on {}",
+                trading_class, expiration
+            )));
+        }
+        if !parameters.strikes.contains(&strike) {
+            return Err(invalid_option(format!(
+                "{} has no strike {}",
+                trading_class, strike
+            )));
+        }
+        Ok(self.build(parameters, expiration, strike, right))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts a selection of the contracts of the chain
+    pub fn select(&self) -> OptionChainQuery<'_> {
+        OptionChainQuery {
+            chain: self,
+            trading_class: None,
+            rights: vec![OptionRight::Call, OptionRight::Put],
+            days_to_expiration: None,
+            moneyness: None,
+            delta: None,
+        }
+    }
+}
+
+//==================================================================================================
+/// A selection of the contracts of an option chain.  All the contracts are selected unless
+/// restricted.
+pub struct OptionChainQuery<'a> {
+    chain: &'a OptionChain,
+    trading_class: Option<String>,
+    rights: Vec<OptionRight>,
+    /// Trading day and range of days to expiration
+    days_to_expiration: Option<(NaiveDate, i64, i64)>,
+    /// Price of the underlying and range of strike / price
+    moneyness: Option<(f64, f64, f64)>,
+    #[allow(clippy::type_complexity)]
+    delta: Option<(Box<dyn Fn(&Contract) -> Option<f64> + 'a>, f64, f64)>,
+}
+
+impl<'a> OptionChainQuery<'a> {
+    //----------------------------------------------------------------------------------------------
+    pub fn trading_class(mut self, trading_class: &str) -> Self {
+        self.trading_class = Some(trading_class.to_string());
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn right(mut self, right: OptionRight) -> Self {
+        self.rights = vec![right];
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Selects the expirations between a number of days from a trading day, both included
+    pub fn days_to_expiration(mut self, today: NaiveDate, min: i64, max: i64) -> Self {
+        self.days_to_expiration = Some((today, min, max));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Selects the strikes whose ratio to the price of the underlying is within a range, such as
+    /// 0.9 to 1.1 for the strikes within 10% of the price
+    pub fn moneyness(mut self, underlying_price: f64, min: f64, max: f64) -> Self {
+        self.moneyness = Some((underlying_price, min, max));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Selects the options whose delta is within a range, such as -0.3 to 0.3.  Options whose delta
+    /// is unknown are not selected.
+    ///
+    /// # Arguments
+    /// * deltas - returns the delta of an option, such as the model_option delta of its Ticker
+    /// * min - the lowest delta
+    /// * max - the highest delta
+    pub fn delta<F: Fn(&Contract) -> Option<f64> + 'a>(
+        mut self,
+        deltas: F,
+        min: f64,
+        max: f64,
+    ) -> Self {
+        self.delta = Some((Box::new(deltas), min, max));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn expiration_selected(&self, expiration: &str) -> bool {
+        match self.days_to_expiration {
+            Some((today, min, max)) => parse_expiration(expiration).is_some_and(|date| {
+                let days = (date - today).num_days();
+                days >= min && days <= max
+            }),
+            None => true,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn strike_selected(&self, strike: f64) -> bool {
+        match self.moneyness {
+            Some((price, min, max)) if price > 0.0 => {
+                let moneyness = strike / price;
+                moneyness >= min && moneyness <= max
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the selected contracts by trading class, expiration, strike and right, on the SMART
+    /// exchange where possible
+    pub fn contracts(&self) -> Vec<Contract> {
+        let mut trading_classes: Vec<&str> = self.chain.trading_classes().into_iter().collect();
+        if let Some(selected) = &self.trading_class {
+            trading_classes.retain(|trading_class| trading_class == selected);
+        }
+        let mut contracts = vec![];
+        for trading_class in trading_classes {
+            let parameters = match self.chain.parameters_of(trading_class) {
+                Some(parameters) => parameters,
+                None => continue,
+            };
+            for expiration in parameters.expirations.iter() {
+                if !self.expiration_selected(expiration) {
+                    continue;
+                }
+                for strike in parameters.strikes.iter() {
+                    if !self.strike_selected(*strike) {
+                        continue;
+                    }
+                    for right in self.rights.iter() {
+                        let contract = self.chain.build(parameters, expiration, *strike, *right);
+                        let selected = match &self.delta {
+                            Some((deltas, min, max)) => deltas(&contract)
+                                .is_some_and(|delta| delta >= *min && delta <= *max),
+                            None => true,
+                        };
+                        if selected {
+                            contracts.push(contract);
+                        }
+                    }
+                }
+            }
+        }
+        contracts
+    }
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct OptionChainState {
+    chains: HashMap<i32, OptionChain>,
+    subscribers: HashMap<i32, Vec<Sender<OptionChain>>>,
+    /// The con_ids resolved by req_contract_details
+    con_ids: HashMap<OptionKey, i32>,
+    /// The req_ids of the req_contract_details requests resolving con_ids
+    resolving: HashSet<i32>,
+}
+
+//==================================================================================================
+/// Thread safe store of the OptionChain of each req_sec_def_opt_params request and of the con_ids
+/// of their options.  The decoder updates it before the matching Wrapper callback is called.
+#[derive(Debug, Default)]
+pub struct OptionChainStore {
+    state: Mutex<OptionChainState>,
+}
+
+impl OptionChainStore {
+    pub fn new() -> Self {
+        OptionChainStore {
+            state: Mutex::new(OptionChainState::default()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts the chain of a req_sec_def_opt_params request
+    pub(crate) fn expect_chain(&self, chain: OptionChain) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .chains
+            .insert(chain.req_id, chain);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds the parameters of a security_definition_option_parameter callback
+    pub fn add_parameters(&self, req_id: i32, parameters: OptionChainParameters) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let chain = state.chains.entry(req_id).or_insert_with(|| OptionChain {
+            req_id,
+            underlying_con_id: parameters.underlying_con_id,
+            ..Default::default()
+        });
+        chain.parameters.push(parameters);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Completes the chain of a request and sends it to the subscribers, whose channels are then
+    /// closed
+    pub fn end(&self, req_id: i32) -> OptionChain {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let chain = state.chains.entry(req_id).or_insert_with(|| OptionChain {
+            req_id,
+            ..Default::default()
+        });
+        chain.complete = true;
+        let chain = chain.clone();
+        for subscriber in state.subscribers.remove(&req_id).unwrap_or_default() {
+            subscriber.send(chain.clone()).ok();
+        }
+        chain
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a channel receiving the chain of a request once complete, at once if it already is
+    pub fn subscribe(&self, req_id: i32) -> Receiver<OptionChain> {
+        let (sender, receiver) = channel();
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        match state.chains.get(&req_id) {
+            Some(chain) if chain.complete => {
+                sender.send(chain.clone()).ok();
+            }
+            _ => state.subscribers.entry(req_id).or_default().push(sender),
+        }
+        receiver
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the chain of a request, complete or not
+    pub fn chain(&self, req_id: i32) -> Option<OptionChain> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .chains
+            .get(&req_id)
+            .cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes and returns the chain of a request
+    pub fn remove(&self, req_id: i32) -> Option<OptionChain> {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.subscribers.remove(&req_id);
+        state.chains.remove(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a req_contract_details request resolving the con_id of an option
+    pub(crate) fn expect_con_id(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .resolving
+            .insert(req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the con_id of the contract details of a request resolving a con_id
+    pub fn add_contract_details(&self, req_id: i32, details: &ContractDetails) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if !state.resolving.contains(&req_id) {
+            return;
+        }
+        if let Some(key) = OptionKey::of(&details.contract) {
+            state.con_ids.insert(key, details.contract.con_id);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub(crate) fn forget_con_id(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .resolving
+            .remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the con_id of an option, if resolved
+    pub fn con_id(&self, contract: &Contract) -> Option<i32> {
+        let key = OptionKey::of(contract)?;
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .con_ids
+            .get(&key)
+            .copied()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of an option contract with its con_id, if resolved
+    pub fn resolved(&self, contract: &Contract) -> Option<Contract> {
+        let mut contract = contract.clone();
+        contract.con_id = self.con_id(&contract)?;
+        Some(contract)
+    }
+}
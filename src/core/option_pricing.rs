@@ -0,0 +1,354 @@
+//! Prices and greeks of European options on stocks (Black-Scholes) and on futures (Black-76),
+//! computed locally in the units of the tick_option_computation callback
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::core::common::NO_VALID_ID;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::market_data::OptionComputation;
+use crate::core::option_chain::{parse_expiration, OptionRight};
+
+/// Days per year of the times to expiration
+pub const DAYS_PER_YEAR: f64 = 365.0;
+
+const MIN_VOLATILITY: f64 = 1e-6;
+const MAX_VOLATILITY: f64 = 10.0;
+const IMPLIED_VOLATILITY_TOLERANCE: f64 = 1e-10;
+const MAX_ITERATIONS: usize = 100;
+
+//----------------------------------------------------------------------------------------------
+fn invalid_pricing(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(NO_VALID_ID, &format!("Invalid option pricing: {}", message))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns the cumulative distribution of the standard normal distribution, with Hart's double
+/// precision approximation
+pub fn normal_cdf(x: f64) -> f64 {
+    let x_abs = x.abs();
+    let tail = if x_abs > 37.0 {
+        0.0
+    } else {
+        let e = (-x_abs * x_abs / 2.0).exp();
+        if x_abs < 7.071_067_811_865_47 {
+            let numerator = [
+                220.206_867_912_376,
+                221.213_596_169_931,
+                112.079_291_497_871,
+                33.912_866_078_383,
+                6.373_962_203_531_65,
+                0.700_383_064_443_688,
+                3.526_249_659_989_11e-2,
+            ]
+            .iter()
+            .rev()
+            .fold(0.0, |b, c| b * x_abs + c);
+            let denominator = [
+                440.413_735_824_752,
+                793.826_512_519_948,
+                637.333_633_378_831,
+                296.564_248_779_674,
+                86.780_732_202_946_1,
+                16.064_177_579_207,
+                1.755_667_163_182_64,
+                8.838_834_764_831_84e-2,
+            ]
+            .iter()
+            .rev()
+            .fold(0.0, |b, c| b * x_abs + c);
+            e * numerator / denominator
+        } else {
+            let b = x_abs + 0.65;
+            let b = x_abs + 4.0 / b;
+            let b = x_abs + 3.0 / b;
+            let b = x_abs + 2.0 / b;
+            let b = x_abs + 1.0 / b;
+            e / b / 2.506_628_274_631
+        }
+    };
+    if x > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns the density of the standard normal distribution
+pub fn normal_pdf(x: f64) -> f64 {
+    (-x * x / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns the time in years from a day to an expiration such as "20240119"
+pub fn years_to_expiration(today: NaiveDate, expiration: &str) -> Option<f64> {
+    let days = (parse_expiration(expiration)? - today).num_days();
+    Some(days.max(0) as f64 / DAYS_PER_YEAR)
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PricingModel {
+    /// Options on a stock or index, priced from its spot price
+    BlackScholes,
+    /// Options on a future, priced from its price
+    Black76,
+}
+
+//==================================================================================================
+/// A cash dividend paid before the expiration of an option
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Dividend {
+    /// Time in years to the ex date
+    pub time: f64,
+    pub amount: f64,
+}
+
+//==================================================================================================
+/// Price and greeks of an option.  As in tick_option_computation, vega is the change of price for
+/// a volatility 1% higher and theta the change of price after a day.  Rho is the change of price
+/// for a rate 1% higher.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct OptionGreeks {
+    pub price: f64,
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
+    pub rho: f64,
+}
+
+//==================================================================================================
+/// A European option to price with Black-Scholes or Black-76.  Rates and dividend yields are
+/// continuously compounded and times are in years.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OptionPricer {
+    pub model: PricingModel,
+    pub right: OptionRight,
+    /// Spot price of the stock, or price of the future
+    pub underlying_price: f64,
+    pub strike: f64,
+    pub time_to_expiration: f64,
+    pub rate: f64,
+    /// Dividend yield of the stock, ignored by Black-76
+    pub dividend_yield: f64,
+    /// Cash dividends of the stock, ignored by Black-76
+    pub dividends: Vec<Dividend>,
+}
+
+impl OptionPricer {
+    /// # Arguments
+    /// * model - Black-Scholes for options on stocks, Black-76 for options on futures
+    /// * right - call or put
+    /// * underlying_price - the price of the stock or future
+    /// * strike - the strike
+    /// * time_to_expiration - the time to expiration in years, see years_to_expiration
+    /// * rate - the risk free rate, such as 0.05 for 5%
+    pub fn new(
+        model: PricingModel,
+        right: OptionRight,
+        underlying_price: f64,
+        strike: f64,
+        time_to_expiration: f64,
+        rate: f64,
+    ) -> Self {
+        OptionPricer {
+            model,
+            right,
+            underlying_price,
+            strike,
+            time_to_expiration,
+            rate,
+            dividend_yield: 0.0,
+            dividends: vec![],
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn dividend_yield(mut self, dividend_yield: f64) -> Self {
+        self.dividend_yield = dividend_yield;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a cash dividend, whose present value is taken off the price of the stock
+    pub fn dividend(mut self, time: f64, amount: f64) -> Self {
+        self.dividends.push(Dividend { time, amount });
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the present value of the cash dividends paid before the expiration
+    pub fn pv_dividend(&self) -> f64 {
+        if self.model == PricingModel::Black76 {
+            return 0.0;
+        }
+        self.dividends
+            .iter()
+            .filter(|dividend| dividend.time > 0.0 && dividend.time <= self.time_to_expiration)
+            .map(|dividend| dividend.amount * (-self.rate * dividend.time).exp())
+            .sum()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the forward price of the underlying and its derivative by the underlying price
+    fn forward(&self) -> (f64, f64) {
+        match self.model {
+            PricingModel::BlackScholes => {
+                let growth = ((self.rate - self.dividend_yield) * self.time_to_expiration).exp();
+                (
+                    (self.underlying_price - self.pv_dividend()) * growth,
+                    growth,
+                )
+            }
+            PricingModel::Black76 => (self.underlying_price, 1.0),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the price, delta, gamma and vega by volatility point
+    fn black(&self, volatility: f64) -> (f64, f64, f64, f64) {
+        let (forward, forward_delta) = self.forward();
+        let discount = (-self.rate * self.time_to_expiration).exp();
+        let sign = match self.right {
+            OptionRight::Call => 1.0,
+            OptionRight::Put => -1.0,
+        };
+        let deviation = volatility * self.time_to_expiration.sqrt();
+        if deviation <= 0.0 || forward <= 0.0 || self.strike <= 0.0 {
+            let intrinsic = (sign * (forward - self.strike)).max(0.0);
+            let delta = if intrinsic > 0.0 { sign } else { 0.0 };
+            return (
+                discount * intrinsic,
+                discount * forward_delta * delta,
+                0.0,
+                0.0,
+            );
+        }
+        let d1 = ((forward / self.strike).ln() + deviation * deviation / 2.0) / deviation;
+        let d2 = d1 - deviation;
+        let price = discount
+            * sign
+            * (forward * normal_cdf(sign * d1) - self.strike * normal_cdf(sign * d2));
+        let delta = discount * forward_delta * sign * normal_cdf(sign * d1);
+        let gamma =
+            discount * forward_delta * forward_delta * normal_pdf(d1) / (forward * deviation);
+        let vega = discount * forward * normal_pdf(d1) * self.time_to_expiration.sqrt();
+        (price, delta, gamma, vega)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the option a number of days later, its dividends paid in the meantime removed
+    fn days_later(&self, days: f64) -> Self {
+        let elapsed = (days / DAYS_PER_YEAR).min(self.time_to_expiration);
+        let mut later = self.clone();
+        later.time_to_expiration -= elapsed;
+        later.dividends = self
+            .dividends
+            .iter()
+            .map(|dividend| Dividend {
+                time: dividend.time - elapsed,
+                amount: dividend.amount,
+            })
+            .filter(|dividend| dividend.time > 0.0)
+            .collect();
+        later
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn with_rate(&self, rate: f64) -> Self {
+        let mut bumped = self.clone();
+        bumped.rate = rate;
+        bumped
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the price of the option for a volatility, such as 0.2 for 20%
+    pub fn price(&self, volatility: f64) -> f64 {
+        self.black(volatility).0
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the price and greeks of the option for a volatility.  Theta and rho are computed by
+    /// repricing the option a day later and with rates 0.01% apart.
+    pub fn greeks(&self, volatility: f64) -> OptionGreeks {
+        let (price, delta, gamma, vega) = self.black(volatility);
+        let bump = 0.0001;
+        let rho = (self.with_rate(self.rate + bump).price(volatility)
+            - self.with_rate(self.rate - bump).price(volatility))
+            / (2.0 * bump)
+            / 100.0;
+        OptionGreeks {
+            price,
+            delta,
+            gamma,
+            vega: vega / 100.0,
+            theta: self.days_later(1.0).price(volatility) - price,
+            rho,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the volatility the option is worth a price at, by Newton's method safeguarded by
+    /// bisection.  Fails if the price is outside the bounds of the model.
+    pub fn implied_volatility(&self, option_price: f64) -> Result<f64, IBKRApiLibError> {
+        let low_price = self.price(MIN_VOLATILITY);
+        let high_price = self.price(MAX_VOLATILITY);
+        if option_price.is_nan() || option_price < low_price || option_price > high_price {
+            return Err(invalid_pricing(format!(
+                "price {} outside of {} to {}",
+                option_price, low_price, high_price
+            )));
+        }
+        let (mut low, mut high) = (MIN_VOLATILITY, MAX_VOLATILITY);
+        let mut volatility = 0.2;
+        for _ in 0..MAX_ITERATIONS {
+            let (price, _, _, vega) = self.black(volatility);
+            let error = price - option_price;
+            if error.abs() < IMPLIED_VOLATILITY_TOLERANCE {
+                return Ok(volatility);
+            }
+            if error > 0.0 {
+                high = volatility;
+            } else {
+                low = volatility;
+            }
+            let newton = volatility - error / vega;
+            volatility = if vega > 0.0 && newton > low && newton < high {
+                newton
+            } else {
+                (low + high) / 2.0
+            };
+        }
+        Ok(volatility)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the values calculate_option_price would send to tick_option_computation for a
+    /// volatility
+    pub fn option_computation(&self, volatility: f64) -> OptionComputation {
+        let greeks = self.greeks(volatility);
+        OptionComputation {
+            implied_vol: Some(volatility),
+            delta: Some(greeks.delta),
+            opt_price: Some(greeks.price),
+            pv_dividend: Some(self.pv_dividend()),
+            gamma: Some(greeks.gamma),
+            vega: Some(greeks.vega),
+            theta: Some(greeks.theta),
+            und_price: Some(self.underlying_price),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the values calculate_implied_volatility would send to tick_option_computation for
+    /// a price of the option
+    pub fn implied_option_computation(
+        &self,
+        option_price: f64,
+    ) -> Result<OptionComputation, IBKRApiLibError> {
+        let mut computation = self.option_computation(self.implied_volatility(option_price)?);
+        computation.opt_price = Some(option_price);
+        Ok(computation)
+    }
+}
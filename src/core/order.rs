@@ -1,18 +1,21 @@
 //! Types related to orders
 use std::fmt::{Display, Error, Formatter};
+use std::io;
 
 use num_derive::FromPrimitive;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::core::common::{TagValue, UNSET_DOUBLE, UNSET_INTEGER};
+use crate::core::errors::IBKRApiLibError;
 use crate::core::order::AuctionStrategy::AuctionUnset;
 use crate::core::order::Origin::Customer;
 use crate::core::order_condition::{Condition, OrderConditionEnum};
 
 //==================================================================================================
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, Debug, FromPrimitive, Copy)]
+#[derive(Serialize, Deserialize, Clone, Debug, FromPrimitive, Copy, PartialEq)]
 pub enum Origin {
     Customer = 0,
     Firm = 1,
@@ -28,7 +31,7 @@ impl Default for Origin {
 // enum AuctionStrategy
 //==================================================================================================
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, Debug, FromPrimitive, Copy)]
+#[derive(Serialize, Deserialize, Clone, Debug, FromPrimitive, Copy, PartialEq)]
 pub enum AuctionStrategy {
     AuctionUnset = 0,
     AuctionMatch = 1,
@@ -43,7 +46,7 @@ impl Default for AuctionStrategy {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct SoftDollarTier {
     pub name: String,
     pub val: String,
@@ -70,10 +73,111 @@ impl Display for SoftDollarTier {
     }
 }
 
+//==================================================================================================
+/// Status of an order as reported by TWS in order_status, open_order and completed_order
+/// messages.  Statuses this library does not know about are kept in Unknown.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum OrderStatus {
+    PendingSubmit,
+    PendingCancel,
+    PreSubmitted,
+    Submitted,
+    ApiCancelled,
+    Cancelled,
+    Filled,
+    Inactive,
+    Unknown(String),
+}
+
+impl OrderStatus {
+    //----------------------------------------------------------------------------------------------
+    /// True if the order can no longer change status
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::ApiCancelled | OrderStatus::Cancelled | OrderStatus::Filled
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// True if the order is working or may still start working
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::PendingSubmit
+                | OrderStatus::PendingCancel
+                | OrderStatus::PreSubmitted
+                | OrderStatus::Submitted
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks the transition from this status to `next` against the order life cycle.  Repeating
+    /// the same status is always valid, as are transitions from or to an unknown status.
+    pub fn can_transition_to(&self, next: &OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        if self == next {
+            return true;
+        }
+        match (self, next) {
+            (Unknown(_), _) | (_, Unknown(_)) => true,
+            (ApiCancelled, _) | (Cancelled, _) | (Filled, _) => false,
+            (_, PendingSubmit) => false,
+            (PendingSubmit, _) => true,
+            (PreSubmitted, _) => true,
+            (Submitted, PreSubmitted) => false,
+            (Submitted, _) => true,
+            (PendingCancel, Inactive) => false,
+            (PendingCancel, _) => true,
+            (Inactive, Filled) => false,
+            (Inactive, _) => true,
+        }
+    }
+}
+
+impl Default for OrderStatus {
+    fn default() -> Self {
+        OrderStatus::Unknown("".to_string())
+    }
+}
+
+impl From<&str> for OrderStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "PendingSubmit" => OrderStatus::PendingSubmit,
+            "PendingCancel" => OrderStatus::PendingCancel,
+            "PreSubmitted" => OrderStatus::PreSubmitted,
+            "Submitted" => OrderStatus::Submitted,
+            "ApiCancelled" => OrderStatus::ApiCancelled,
+            "Cancelled" => OrderStatus::Cancelled,
+            "Filled" => OrderStatus::Filled,
+            "Inactive" => OrderStatus::Inactive,
+            _ => OrderStatus::Unknown(status.to_string()),
+        }
+    }
+}
+
+impl Display for OrderStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            OrderStatus::PendingSubmit => write!(f, "PendingSubmit"),
+            OrderStatus::PendingCancel => write!(f, "PendingCancel"),
+            OrderStatus::PreSubmitted => write!(f, "PreSubmitted"),
+            OrderStatus::Submitted => write!(f, "Submitted"),
+            OrderStatus::ApiCancelled => write!(f, "ApiCancelled"),
+            OrderStatus::Cancelled => write!(f, "Cancelled"),
+            OrderStatus::Filled => write!(f, "Filled"),
+            OrderStatus::Inactive => write!(f, "Inactive"),
+            OrderStatus::Unknown(status) => write!(f, "{}", status),
+        }
+    }
+}
+
 //==================================================================================================
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct OrderState {
-    pub status: String,
+    pub status: OrderStatus,
     pub init_margin_before: String,
     pub maint_margin_before: String,
     pub equity_with_loan_before: String,
@@ -94,7 +198,7 @@ pub struct OrderState {
 
 impl OrderState {
     pub fn new(
-        status: String,
+        status: OrderStatus,
         init_margin_before: String,
         maint_margin_before: String,
         equity_with_loan_before: String,
@@ -189,7 +293,114 @@ impl Display for OrderState {
 }
 
 //==================================================================================================
+/// Before, change and after values of one of the margin figures returned for a what-if order.
+/// Values that TWS left empty or unset are None.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MarginImpact {
+    pub before: Option<f64>,
+    pub change: Option<f64>,
+    pub after: Option<f64>,
+}
+
+impl MarginImpact {
+    pub fn new(before: &str, change: &str, after: &str) -> Self {
+        MarginImpact {
+            before: parse_amount(before),
+            change: parse_amount(change),
+            after: parse_amount(after),
+        }
+    }
+}
+
+impl Display for MarginImpact {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(
+            f,
+            "before: {:?}, change: {:?}, after: {:?}",
+            self.before, self.change, self.after
+        )
+    }
+}
+
+//==================================================================================================
+/// Margin and commission impact of an order, as calculated by TWS for a what-if order
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OrderPreview {
+    pub status: OrderStatus,
+    pub init_margin: MarginImpact,
+    pub maint_margin: MarginImpact,
+    pub equity_with_loan: MarginImpact,
+    pub commission: Option<f64>,
+    pub min_commission: Option<f64>,
+    pub max_commission: Option<f64>,
+    pub commission_currency: String,
+    pub warning_text: String,
+}
+
+impl From<&OrderState> for OrderPreview {
+    fn from(order_state: &OrderState) -> Self {
+        OrderPreview {
+            status: order_state.status.clone(),
+            init_margin: MarginImpact::new(
+                &order_state.init_margin_before,
+                &order_state.init_margin_change,
+                &order_state.init_margin_after,
+            ),
+            maint_margin: MarginImpact::new(
+                &order_state.maint_margin_before,
+                &order_state.maint_margin_change,
+                &order_state.maint_margin_after,
+            ),
+            equity_with_loan: MarginImpact::new(
+                &order_state.equity_with_loan_before,
+                &order_state.equity_with_loan_change,
+                &order_state.equity_with_loan_after,
+            ),
+            commission: unset_to_none(order_state.commission),
+            min_commission: unset_to_none(order_state.min_commission),
+            max_commission: unset_to_none(order_state.max_commission),
+            commission_currency: order_state.commission_currency.clone(),
+            warning_text: order_state.warning_text.clone(),
+        }
+    }
+}
+
+impl Display for OrderPreview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(
+            f,
+            "status: {}, init_margin: ({}), maint_margin: ({}), equity_with_loan: ({}), \
+             commission: {:?}, min_commission: {:?}, max_commission: {:?}, \
+             commission_currency: {}, warning_text: {}",
+            self.status,
+            self.init_margin,
+            self.maint_margin,
+            self.equity_with_loan,
+            self.commission,
+            self.min_commission,
+            self.max_commission,
+            self.commission_currency,
+            self.warning_text,
+        )
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn unset_to_none(value: f64) -> Option<f64> {
+    if value == UNSET_DOUBLE || !value.is_finite() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn parse_amount(text: &str) -> Option<f64> {
+    text.trim().parse::<f64>().ok().and_then(unset_to_none)
+}
+
+//==================================================================================================
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct OrderComboLeg {
     pub(crate) price: f64, // type: float
 }
@@ -702,6 +913,75 @@ impl Order {
     }
 }
 
+impl Order {
+    //----------------------------------------------------------------------------------------------
+    /// Returns the names of the fields that differ between this order and `other`, sorted by
+    /// name.  The orders are compared through their serialized form so that every field is
+    /// covered, including fields added later.
+    pub fn changed_fields(&self, other: &Order) -> Result<Vec<String>, IBKRApiLibError> {
+        let fields = |order: &Order| match serde_json::to_value(order).map_err(io::Error::from)? {
+            Value::Object(fields) => Ok(fields),
+            _ => Err(IBKRApiLibError::invalid_argument(
+                order.order_id,
+                "The order does not serialize to a JSON object.",
+            )),
+        };
+        let old = fields(self)?;
+        let new = fields(other)?;
+        Ok(old
+            .iter()
+            .filter(|(name, value)| new.get(name.as_str()) != Some(value))
+            .map(|(name, _)| name.clone())
+            .collect())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the names of the fields that TWS allows to be changed on a working order of this
+    /// order's type.  Any other field must be left as it was when the order was placed.
+    pub fn modifiable_fields(&self) -> Vec<&'static str> {
+        let mut fields = vec![
+            "total_quantity",
+            "tif",
+            "good_after_time",
+            "good_till_date",
+            "active_start_time",
+            "active_stop_time",
+            "outside_rth",
+            "display_size",
+            "order_ref",
+            "transmit",
+            "conditions",
+            "conditions_cancel_order",
+            "conditions_ignore_rth",
+        ];
+
+        let type_specific: &[&'static str] = match self.order_type.as_str() {
+            "LMT" | "LOC" | "LOO" | "MIDPRICE" => &["lmt_price"],
+            "STP" | "MIT" => &["aux_price"],
+            "STP LMT" | "LIT" => &["lmt_price", "aux_price"],
+            "TRAIL" => &["aux_price", "trail_stop_price", "trailing_percent"],
+            "TRAIL LIMIT" => &[
+                "lmt_price",
+                "aux_price",
+                "trail_stop_price",
+                "trailing_percent",
+                "lmt_price_offset",
+            ],
+            "REL" | "PEG MKT" | "PEG MID" | "PEG PRIM" => {
+                &["lmt_price", "aux_price", "percent_offset"]
+            }
+            "VOL" => &["volatility", "volatility_type", "delta_neutral_aux_price"],
+            _ => &[],
+        };
+        fields.extend_from_slice(type_specific);
+
+        if !self.algo_strategy.is_empty() {
+            fields.push("algo_params");
+        }
+        fields
+    }
+}
+
 impl Display for Order {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         write!(
@@ -2,18 +2,28 @@
 use std::fmt::{Debug, Display, Error, Formatter};
 use std::slice::Iter;
 
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
 use serde::{Deserialize, Serialize};
 
-use crate::core::decoder::{decode_bool, decode_f64, decode_i32, decode_string};
+use crate::core::common::NO_VALID_ID;
+use crate::core::decoder::{decode_bool, decode_i32, decode_string};
 use crate::core::errors::IBKRApiLibError;
 use crate::core::messages::make_field;
+use crate::core::timestamps::format_tws_local_time;
+use crate::core::trading_hours::{local_to_utc, parse_time_zone};
+
+/// Format of the time in a time condition, optionally followed by a time zone
+const TIME_CONDITION_FORMAT: &str = "%Y%m%d %H:%M:%S";
+/// Format of a time condition expressed in UTC
+const TIME_CONDITION_UTC_FORMAT: &str = "%Y%m%d-%H:%M:%S";
 
 //==================================================================================================
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Copy)]
+#[derive(Serialize, Deserialize, Clone, FromPrimitive, Copy, PartialEq)]
 pub enum ConditionType {
     Price = 1,
     Time = 3,
@@ -50,7 +60,7 @@ impl Debug for ConditionType {
 
 //==================================================================================================
 #[repr(i32)]
-#[derive(Serialize, Deserialize, Clone, FromPrimitive, Copy)]
+#[derive(Serialize, Deserialize, Clone, FromPrimitive, Copy, PartialEq)]
 pub enum TriggerMethod {
     Default = 0,
     DoubleBidAsk = 1,
@@ -92,7 +102,7 @@ impl Debug for TriggerMethod {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum OrderConditionEnum {
     Price(PriceCondition),
     Time(TimeCondition),
@@ -139,7 +149,7 @@ impl Condition for OrderConditionEnum {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, text: String) {
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError> {
         match self {
             OrderConditionEnum::Execution(s) => s.set_value_from_string(text),
             OrderConditionEnum::Price(p) => p.set_value_from_string(text),
@@ -163,6 +173,46 @@ impl Condition for OrderConditionEnum {
     }
 }
 
+impl OrderConditionEnum {
+    //----------------------------------------------------------------------------------------------
+    /// Returns the part of the condition holding its type and connector
+    pub fn order_condition(&self) -> &OrderCondition {
+        match self {
+            OrderConditionEnum::Execution(s) => &s.order_condition,
+            OrderConditionEnum::Price(p) => {
+                &p.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::Margin(m) => &m.operator_condition.order_condition,
+            OrderConditionEnum::Time(t) => &t.operator_condition.order_condition,
+            OrderConditionEnum::Volume(v) => {
+                &v.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::PercentChange(pch) => {
+                &pch.contract_condition.operator_condition.order_condition
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the part of the condition holding its type and connector
+    pub fn order_condition_mut(&mut self) -> &mut OrderCondition {
+        match self {
+            OrderConditionEnum::Execution(s) => &mut s.order_condition,
+            OrderConditionEnum::Price(p) => {
+                &mut p.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::Margin(m) => &mut m.operator_condition.order_condition,
+            OrderConditionEnum::Time(t) => &mut t.operator_condition.order_condition,
+            OrderConditionEnum::Volume(v) => {
+                &mut v.contract_condition.operator_condition.order_condition
+            }
+            OrderConditionEnum::PercentChange(pch) => {
+                &mut pch.contract_condition.operator_condition.order_condition
+            }
+        }
+    }
+}
+
 impl Display for OrderConditionEnum {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
         write!(f, "{}", self.value_to_string())
@@ -191,12 +241,12 @@ pub trait Condition: Display + Debug + Serialize {
     fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError>;
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError>;
     fn value_to_string(&self) -> String;
-    fn set_value_from_string(&mut self, text: String);
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError>;
     fn get_type(&self) -> ConditionType;
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Copy, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, Default, PartialEq)]
 pub struct OrderCondition {
     pub cond_type: ConditionType,
     pub is_conjunction_connection: bool,
@@ -247,7 +297,7 @@ impl OrderCondition {
     }
 }
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct ExecutionCondition {
     pub sec_type: String,
     pub exchange: String,
@@ -294,8 +344,9 @@ impl Condition for ExecutionCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, _text: String) {
-        unimplemented!()
+    /// Execution conditions are not compared against a value, so the text is ignored
+    fn set_value_from_string(&mut self, _text: String) -> Result<(), IBKRApiLibError> {
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -320,13 +371,19 @@ impl Debug for ExecutionCondition {
 
 impl From<OrderConditionEnum> for ExecutionCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        ExecutionCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Execution(execution) => execution,
+            _ => ExecutionCondition::new(String::new(), String::new(), String::new()),
+        }
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Copy, Default)]
+/// Base of the conditions that compare a value against a threshold.  On the wire the connector
+/// and is_more flag are followed by the threshold, written by the concrete condition's
+/// value_to_string and read back by its set_value_from_string.
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, Default, PartialEq)]
 pub struct OperatorCondition {
     pub order_condition: OrderCondition,
     pub is_more: bool,
@@ -341,16 +398,7 @@ impl OperatorCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    pub fn value_to_string(&self) -> String {
-        unimplemented!();
-    }
-
-    //----------------------------------------------------------------------------------------------
-    pub fn set_value_from_string(&self, _text: &str) {
-        unimplemented!();
-    }
-
-    //----------------------------------------------------------------------------------------------
+    /// Decodes the connector and is_more flag
     pub fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
         self.order_condition.decode(fields_iter)?;
         self.is_more = decode_bool(fields_iter)?;
@@ -358,7 +406,8 @@ impl OperatorCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
+    /// Encodes the connector and is_more flag
+    pub fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
         let mut flds = self.order_condition.make_fields()?;
         flds.push(make_field(&self.is_more)?);
         Ok(flds)
@@ -366,7 +415,7 @@ impl OperatorCondition {
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
 pub struct MarginCondition {
     pub operator_condition: OperatorCondition,
     pub percent: f64,
@@ -386,25 +435,29 @@ impl Condition for MarginCondition {
     //----------------------------------------------------------------------------------------------
     fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
         self.operator_condition.decode(fields_iter)?;
-        self.percent = decode_f64(fields_iter).unwrap();
+        self.set_value_from_string(decode_string(fields_iter)?)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
         let mut flds = self.operator_condition.make_fields()?;
-        flds.push(make_field(&self.percent)?);
+        flds.push(make_field(&self.value_to_string())?);
         Ok(flds)
     }
 
+    //----------------------------------------------------------------------------------------------
     fn value_to_string(&self) -> String {
         self.percent.to_string()
     }
 
-    fn set_value_from_string(&mut self, text: String) {
-        self.percent = text.parse().unwrap();
+    //----------------------------------------------------------------------------------------------
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError> {
+        self.percent = text.parse()?;
+        Ok(())
     }
 
+    //----------------------------------------------------------------------------------------------
     fn get_type(&self) -> ConditionType {
         self.operator_condition.order_condition.cond_type
     }
@@ -426,12 +479,18 @@ impl Debug for MarginCondition {
 
 impl From<OrderConditionEnum> for MarginCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        MarginCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Margin(margin) => margin,
+            _ => MarginCondition::new(false, 0.0),
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Default)]
+//==================================================================================================
+/// Base of the conditions on the market data of a contract.  On the wire the contract id and
+/// exchange follow the threshold of the operator condition.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct ContractCondition {
     pub operator_condition: OperatorCondition,
     pub con_id: i32,
@@ -447,37 +506,31 @@ impl ContractCondition {
             exchange: exchange.to_string(),
         }
     }
-}
 
-impl Condition for ContractCondition {
     //----------------------------------------------------------------------------------------------
-    fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
-        self.operator_condition.decode(fields_iter)?;
+    /// Decodes the contract id and exchange
+    pub fn decode_contract(
+        &mut self,
+        fields_iter: &mut Iter<String>,
+    ) -> Result<(), IBKRApiLibError> {
         self.con_id = decode_i32(fields_iter)?;
         self.exchange = decode_string(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
-    fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
-        let mut flds = self.operator_condition.make_fields()?;
-        flds.push(make_field(&self.con_id)?);
-        flds.push(make_field(&self.exchange)?);
-        Ok(flds)
+    /// Encodes the contract id and exchange
+    pub fn make_contract_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
+        Ok(vec![make_field(&self.con_id)?, make_field(&self.exchange)?])
     }
 
     //----------------------------------------------------------------------------------------------
-    fn value_to_string(&self) -> String {
+    pub fn value_to_string(&self) -> String {
         format!("contract id: {}, Exchange: {}", self.con_id, self.exchange)
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, _text: String) {
-        unimplemented!()
-    }
-
-    //----------------------------------------------------------------------------------------------
-    fn get_type(&self) -> ConditionType {
+    pub fn get_type(&self) -> ConditionType {
         self.operator_condition.order_condition.cond_type
     }
 }
@@ -498,50 +551,103 @@ impl Debug for ContractCondition {
 
 impl From<OrderConditionEnum> for ContractCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        ContractCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Price(price) => price.contract_condition,
+            OrderConditionEnum::Volume(volume) => volume.contract_condition,
+            OrderConditionEnum::PercentChange(pch) => pch.contract_condition,
+            _ => ContractCondition::default(),
+        }
     }
 }
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+/// Triggers once the current time is after (is_more) or before the given time.  When time_zone
+/// is None the time is interpreted in the TWS time zone.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct TimeCondition {
     pub operator_condition: OperatorCondition,
-    pub time: String,
+    pub time: NaiveDateTime,
+    pub time_zone: Option<Tz>,
 }
 
 impl TimeCondition {
     //----------------------------------------------------------------------------------------------
-    pub fn new(is_more: bool, time: String) -> Self {
+    pub fn new(is_more: bool, time: NaiveDateTime, time_zone: Option<Tz>) -> Self {
         TimeCondition {
             operator_condition: OperatorCondition::new(ConditionType::Time, is_more),
             time: time,
+            time_zone,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Creates a time condition on an instant, sent to TWS in UTC
+    pub fn from_utc<Z: TimeZone>(is_more: bool, time: DateTime<Z>) -> Self {
+        TimeCondition::new(is_more, time.with_timezone(&Utc).naive_utc(), Some(Tz::UTC))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the time as an instant if the condition has a time zone
+    pub fn utc_time(&self) -> Option<DateTime<Utc>> {
+        self.time_zone
+            .map(|time_zone| local_to_utc(&time_zone, &self.time))
+    }
 }
 
 impl Condition for TimeCondition {
     //----------------------------------------------------------------------------------------------
     fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
         self.operator_condition.decode(fields_iter)?;
-        self.time = decode_string(fields_iter).unwrap();
+        self.set_value_from_string(decode_string(fields_iter)?)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
         let mut flds = self.operator_condition.make_fields()?;
-        flds.push(make_field(&self.time)?);
+        flds.push(make_field(&self.value_to_string())?);
         Ok(flds)
     }
 
     //----------------------------------------------------------------------------------------------
+    /// Formats the time as "yyyymmdd-hh:mm:ss" for UTC, otherwise as "yyyymmdd hh:mm:ss"
+    /// followed by the time zone if there is one
     fn value_to_string(&self) -> String {
-        self.time.clone()
+        match &self.time_zone {
+            Some(time_zone) => format_tws_local_time(&self.time, time_zone),
+            None => self.time.format(TIME_CONDITION_FORMAT).to_string(),
+        }
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, text: String) {
-        self.time = text.to_string()
+    /// Parses a time in any of the formats produced by value_to_string
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError> {
+        let text = text.trim();
+        let invalid_time = || {
+            IBKRApiLibError::invalid_argument(
+                NO_VALID_ID,
+                &format!("Invalid time condition: {}", text),
+            )
+        };
+        let (date_time, time_zone) = match (text.get(..17), text.get(17..)) {
+            (Some(date_time), Some(time_zone)) => (date_time, time_zone.trim()),
+            _ => return Err(invalid_time()),
+        };
+
+        if date_time.as_bytes()[8] == b'-' && time_zone.is_empty() {
+            self.time = NaiveDateTime::parse_from_str(date_time, TIME_CONDITION_UTC_FORMAT)
+                .map_err(|_| invalid_time())?;
+            self.time_zone = Some(Tz::UTC);
+        } else {
+            self.time = NaiveDateTime::parse_from_str(date_time, TIME_CONDITION_FORMAT)
+                .map_err(|_| invalid_time())?;
+            self.time_zone = if time_zone.is_empty() {
+                None
+            } else {
+                Some(parse_time_zone(time_zone)?)
+            };
+        }
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -568,13 +674,16 @@ impl Debug for TimeCondition {
 //==================================================================================================
 impl From<OrderConditionEnum> for TimeCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        TimeCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Time(time) => time,
+            _ => TimeCondition::new(false, NaiveDateTime::default(), None),
+        }
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct PriceCondition {
     pub contract_condition: ContractCondition,
     pub price: f64,
@@ -606,25 +715,20 @@ impl PriceCondition {
 impl Condition for PriceCondition {
     //----------------------------------------------------------------------------------------------
     fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
-        self.price = decode_f64(fields_iter)?;
-        self.contract_condition.decode(fields_iter)?;
-        self.trigger_method = FromPrimitive::from_i32(decode_i32(fields_iter)?).unwrap();
+        self.contract_condition
+            .operator_condition
+            .decode(fields_iter)?;
+        self.set_value_from_string(decode_string(fields_iter)?)?;
+        self.contract_condition.decode_contract(fields_iter)?;
+        self.trigger_method = FromPrimitive::from_i32(decode_i32(fields_iter)?).unwrap_or_default();
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
-        let mut flds = self
-            .contract_condition
-            .operator_condition
-            .order_condition
-            .make_fields()?;
-        flds.push(make_field(
-            &(self.contract_condition.operator_condition.is_more as i32),
-        )?);
-        flds.push(make_field(&(self.price))?);
-        flds.push(make_field(&(self.contract_condition.con_id))?);
-        flds.push(make_field(&(self.contract_condition.exchange))?);
+        let mut flds = self.contract_condition.operator_condition.make_fields()?;
+        flds.push(make_field(&self.value_to_string())?);
+        flds.extend(self.contract_condition.make_contract_fields()?);
         flds.push(make_field(&(self.trigger_method as i32))?);
 
         Ok(flds)
@@ -636,8 +740,9 @@ impl Condition for PriceCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, text: String) {
-        self.price = text.parse().unwrap();
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError> {
+        self.price = text.parse()?;
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -663,13 +768,16 @@ impl Display for PriceCondition {
 
 impl From<OrderConditionEnum> for PriceCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        PriceCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Price(price) => price,
+            _ => PriceCondition::new(TriggerMethod::Default, 0, "", false, 0.0),
+        }
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct PercentChangeCondition {
     pub contract_condition: ContractCondition,
     pub change_percent: f64,
@@ -693,24 +801,19 @@ impl PercentChangeCondition {
 impl Condition for PercentChangeCondition {
     //----------------------------------------------------------------------------------------------
     fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
-        self.change_percent = decode_f64(fields_iter)?;
-        self.contract_condition.decode(fields_iter)?;
+        self.contract_condition
+            .operator_condition
+            .decode(fields_iter)?;
+        self.set_value_from_string(decode_string(fields_iter)?)?;
+        self.contract_condition.decode_contract(fields_iter)?;
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
-        let mut flds = self
-            .contract_condition
-            .operator_condition
-            .order_condition
-            .make_fields()?;
-        flds.push(make_field(
-            &(self.contract_condition.operator_condition.is_more as i32),
-        )?);
-        flds.push(make_field(&(self.change_percent))?);
-        flds.push(make_field(&(self.contract_condition.con_id))?);
-        flds.push(make_field(&(self.contract_condition.exchange))?);
+        let mut flds = self.contract_condition.operator_condition.make_fields()?;
+        flds.push(make_field(&self.value_to_string())?);
+        flds.extend(self.contract_condition.make_contract_fields()?);
 
         Ok(flds)
     }
@@ -721,8 +824,9 @@ impl Condition for PercentChangeCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, text: String) {
-        self.change_percent = text.parse().unwrap();
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError> {
+        self.change_percent = text.parse()?;
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -749,13 +853,16 @@ impl Debug for PercentChangeCondition {
 
 impl From<OrderConditionEnum> for PercentChangeCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        PercentChangeCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::PercentChange(pch) => pch,
+            _ => PercentChangeCondition::new(0, String::new(), false, 0.0),
+        }
     }
 }
 
 //==================================================================================================
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct VolumeCondition {
     pub contract_condition: ContractCondition,
     pub volume: i32,
@@ -779,25 +886,20 @@ impl VolumeCondition {
 impl Condition for VolumeCondition {
     //----------------------------------------------------------------------------------------------
     fn decode(&mut self, fields_iter: &mut Iter<String>) -> Result<(), IBKRApiLibError> {
-        self.contract_condition.decode(fields_iter)?;
-        self.volume = decode_i32(fields_iter)?;
+        self.contract_condition
+            .operator_condition
+            .decode(fields_iter)?;
+        self.set_value_from_string(decode_string(fields_iter)?)?;
+        self.contract_condition.decode_contract(fields_iter)?;
 
         Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
     fn make_fields(&self) -> Result<Vec<String>, IBKRApiLibError> {
-        let mut flds = self
-            .contract_condition
-            .operator_condition
-            .order_condition
-            .make_fields()?;
-        flds.push(make_field(
-            &(self.contract_condition.operator_condition.is_more as i32),
-        )?);
-        flds.push(make_field(&(self.volume))?);
-        flds.push(make_field(&(self.contract_condition.con_id))?);
-        flds.push(make_field(&(self.contract_condition.exchange))?);
+        let mut flds = self.contract_condition.operator_condition.make_fields()?;
+        flds.push(make_field(&self.value_to_string())?);
+        flds.extend(self.contract_condition.make_contract_fields()?);
         Ok(flds)
     }
 
@@ -807,8 +909,9 @@ impl Condition for VolumeCondition {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn set_value_from_string(&mut self, text: String) {
-        self.volume = text.parse().unwrap();
+    fn set_value_from_string(&mut self, text: String) -> Result<(), IBKRApiLibError> {
+        self.volume = text.parse()?;
+        Ok(())
     }
 
     //----------------------------------------------------------------------------------------------
@@ -836,21 +939,290 @@ impl Debug for VolumeCondition {
 
 impl From<OrderConditionEnum> for VolumeCondition {
     //----------------------------------------------------------------------------------------------
-    fn from(_: OrderConditionEnum) -> Self {
-        VolumeCondition::default()
+    fn from(condition: OrderConditionEnum) -> Self {
+        match condition {
+            OrderConditionEnum::Volume(volume) => volume,
+            _ => VolumeCondition::new(0, "", false, 0),
+        }
     }
 }
 
 //----------------------------------------------------------------------------------------------
 pub fn create_condition<'a>(cond_type: ConditionType) -> OrderConditionEnum {
     match cond_type {
-        ConditionType::Execution => OrderConditionEnum::Execution(ExecutionCondition::default()),
-        ConditionType::Margin => OrderConditionEnum::Margin(MarginCondition::default()),
-        ConditionType::PercentChange => {
-            OrderConditionEnum::PercentChange(PercentChangeCondition::default())
+        ConditionType::Execution => OrderConditionEnum::Execution(ExecutionCondition::new(
+            String::new(),
+            String::new(),
+            String::new(),
+        )),
+        ConditionType::Margin => OrderConditionEnum::Margin(MarginCondition::new(false, 0.0)),
+        ConditionType::PercentChange => OrderConditionEnum::PercentChange(
+            PercentChangeCondition::new(0, String::new(), false, 0.0),
+        ),
+        ConditionType::Price => OrderConditionEnum::Price(PriceCondition::new(
+            TriggerMethod::Default,
+            0,
+            "",
+            false,
+            0.0,
+        )),
+        ConditionType::Time => {
+            OrderConditionEnum::Time(TimeCondition::new(false, NaiveDateTime::default(), None))
         }
-        ConditionType::Price => OrderConditionEnum::Price(PriceCondition::default()),
-        ConditionType::Time => OrderConditionEnum::Time(TimeCondition::default()),
-        ConditionType::Volume => OrderConditionEnum::Volume(VolumeCondition::default()),
+        ConditionType::Volume => OrderConditionEnum::Volume(VolumeCondition::new(0, "", false, 0)),
+    }
+}
+
+//==================================================================================================
+/// Conditions joined by and/or connectors, in the order they are sent to TWS.  The connector of
+/// each condition joins it to the one that follows it.  Build one with the condition builder
+/// functions, e.g. `price(con_id, "SMART").above(101.5).and(time().after(t))`, and convert it
+/// with `into()` to set Order::conditions.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OrderConditions {
+    pub conditions: Vec<OrderConditionEnum>,
+}
+
+impl OrderConditions {
+    pub fn new() -> Self {
+        OrderConditions { conditions: vec![] }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Appends conditions, which must hold together with the preceding condition
+    pub fn and<C: Into<OrderConditions>>(self, next: C) -> Self {
+        self.join(true, next.into())
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Appends conditions, of which either these or the preceding condition must hold
+    pub fn or<C: Into<OrderConditions>>(self, next: C) -> Self {
+        self.join(false, next.into())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn join(mut self, is_conjunction: bool, next: OrderConditions) -> Self {
+        if let Some(last) = self.conditions.last_mut() {
+            last.order_condition_mut().is_conjunction_connection = is_conjunction;
+        }
+        self.conditions.extend(next.conditions);
+        self
+    }
+}
+
+impl From<OrderConditionEnum> for OrderConditions {
+    fn from(condition: OrderConditionEnum) -> Self {
+        OrderConditions {
+            conditions: vec![condition],
+        }
+    }
+}
+
+impl From<OrderConditions> for Vec<OrderConditionEnum> {
+    fn from(conditions: OrderConditions) -> Self {
+        conditions.conditions
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Lets a single condition be wrapped in an OrderConditionEnum and start a chain of conditions
+macro_rules! chainable_condition {
+    ($condition:ident, $variant:ident) => {
+        impl From<$condition> for OrderConditionEnum {
+            fn from(condition: $condition) -> Self {
+                OrderConditionEnum::$variant(condition)
+            }
+        }
+
+        impl From<$condition> for OrderConditions {
+            fn from(condition: $condition) -> Self {
+                OrderConditions::from(OrderConditionEnum::$variant(condition))
+            }
+        }
+
+        impl $condition {
+            //--------------------------------------------------------------------------------------
+            /// Joins this condition and the next ones with an and connector
+            pub fn and<C: Into<OrderConditions>>(self, next: C) -> OrderConditions {
+                OrderConditions::from(self).and(next)
+            }
+
+            //--------------------------------------------------------------------------------------
+            /// Joins this condition and the next ones with an or connector
+            pub fn or<C: Into<OrderConditions>>(self, next: C) -> OrderConditions {
+                OrderConditions::from(self).or(next)
+            }
+        }
+    };
+}
+
+chainable_condition!(PriceCondition, Price);
+chainable_condition!(TimeCondition, Time);
+chainable_condition!(MarginCondition, Margin);
+chainable_condition!(ExecutionCondition, Execution);
+chainable_condition!(VolumeCondition, Volume);
+chainable_condition!(PercentChangeCondition, PercentChange);
+
+//==================================================================================================
+/// Builds a condition on the price of a contract
+#[derive(Clone, Debug)]
+pub struct PriceConditionBuilder {
+    con_id: i32,
+    exchange: String,
+    trigger_method: TriggerMethod,
+}
+
+impl PriceConditionBuilder {
+    //----------------------------------------------------------------------------------------------
+    /// Sets how the price is evaluated.  Defaults to TriggerMethod::Default
+    pub fn trigger_method(mut self, trigger_method: TriggerMethod) -> Self {
+        self.trigger_method = trigger_method;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn above(self, price: f64) -> PriceCondition {
+        PriceCondition::new(
+            self.trigger_method,
+            self.con_id,
+            &self.exchange,
+            true,
+            price,
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn below(self, price: f64) -> PriceCondition {
+        PriceCondition::new(
+            self.trigger_method,
+            self.con_id,
+            &self.exchange,
+            false,
+            price,
+        )
+    }
+}
+
+//==================================================================================================
+/// Builds a condition on the percent change of a contract's price since the last close
+#[derive(Clone, Debug)]
+pub struct PercentChangeConditionBuilder {
+    con_id: i32,
+    exchange: String,
+}
+
+impl PercentChangeConditionBuilder {
+    //----------------------------------------------------------------------------------------------
+    pub fn above(self, change_percent: f64) -> PercentChangeCondition {
+        PercentChangeCondition::new(self.con_id, self.exchange, true, change_percent)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn below(self, change_percent: f64) -> PercentChangeCondition {
+        PercentChangeCondition::new(self.con_id, self.exchange, false, change_percent)
+    }
+}
+
+//==================================================================================================
+/// Builds a condition on the traded volume of a contract
+#[derive(Clone, Debug)]
+pub struct VolumeConditionBuilder {
+    con_id: i32,
+    exchange: String,
+}
+
+impl VolumeConditionBuilder {
+    //----------------------------------------------------------------------------------------------
+    pub fn above(self, volume: i32) -> VolumeCondition {
+        VolumeCondition::new(self.con_id, &self.exchange, true, volume)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn below(self, volume: i32) -> VolumeCondition {
+        VolumeCondition::new(self.con_id, &self.exchange, false, volume)
+    }
+}
+
+//==================================================================================================
+/// Builds a condition on the margin cushion of the account
+#[derive(Clone, Debug)]
+pub struct MarginConditionBuilder;
+
+impl MarginConditionBuilder {
+    //----------------------------------------------------------------------------------------------
+    pub fn above(self, percent: f64) -> MarginCondition {
+        MarginCondition::new(true, percent)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn below(self, percent: f64) -> MarginCondition {
+        MarginCondition::new(false, percent)
+    }
+}
+
+//==================================================================================================
+/// Builds a condition on the current time
+#[derive(Clone, Debug)]
+pub struct TimeConditionBuilder;
+
+impl TimeConditionBuilder {
+    //----------------------------------------------------------------------------------------------
+    pub fn after<Z: TimeZone>(self, time: DateTime<Z>) -> TimeCondition {
+        TimeCondition::from_utc(true, time)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn before<Z: TimeZone>(self, time: DateTime<Z>) -> TimeCondition {
+        TimeCondition::from_utc(false, time)
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Starts a condition on the price of a contract, e.g. `price(con_id, "SMART").above(101.5)`
+pub fn price(con_id: i32, exchange: &str) -> PriceConditionBuilder {
+    PriceConditionBuilder {
+        con_id,
+        exchange: exchange.to_string(),
+        trigger_method: TriggerMethod::Default,
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Starts a condition on the percent change of a contract's price since the last close
+pub fn percent_change(con_id: i32, exchange: &str) -> PercentChangeConditionBuilder {
+    PercentChangeConditionBuilder {
+        con_id,
+        exchange: exchange.to_string(),
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Starts a condition on the traded volume of a contract
+pub fn volume(con_id: i32, exchange: &str) -> VolumeConditionBuilder {
+    VolumeConditionBuilder {
+        con_id,
+        exchange: exchange.to_string(),
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Starts a condition on the margin cushion of the account, in percent
+pub fn margin() -> MarginConditionBuilder {
+    MarginConditionBuilder
+}
+
+//----------------------------------------------------------------------------------------------
+/// Starts a condition on the current time, e.g. `time().after(Utc::now())`
+pub fn time() -> TimeConditionBuilder {
+    TimeConditionBuilder
+}
+
+//----------------------------------------------------------------------------------------------
+/// Creates a condition that triggers on an execution of the given symbol
+pub fn execution(symbol: &str, sec_type: &str, exchange: &str) -> ExecutionCondition {
+    ExecutionCondition::new(
+        sec_type.to_string(),
+        exchange.to_string(),
+        symbol.to_string(),
+    )
 }
@@ -10,7 +10,7 @@ use crate::core::decoder::{
     decode_string,
 };
 use crate::core::errors::IBKRApiLibError;
-use crate::core::order::{Order, OrderComboLeg, OrderState, SoftDollarTier};
+use crate::core::order::{Order, OrderComboLeg, OrderState, OrderStatus, SoftDollarTier};
 use crate::core::order_condition::{create_condition, Condition};
 use crate::core::server_versions::{
     MIN_SERVER_VER_AUTO_PRICE_FOR_HEDGE, MIN_SERVER_VER_CASH_QTY, MIN_SERVER_VER_D_PEG_ORDERS,
@@ -789,7 +789,7 @@ impl<'a> OrderDecoder<'a> {
         &mut self,
         fields_iter: &mut Iter<String>,
     ) -> Result<(), IBKRApiLibError> {
-        self.order_state.status = decode_string(fields_iter)?;
+        self.order_state.status = OrderStatus::from(decode_string(fields_iter)?.as_str());
         Ok(())
     }
 
@@ -0,0 +1,323 @@
+//! Keeps the latest open order snapshots sent by TWS so that working orders can be modified
+//! without re-populating every field by hand, and so that what-if orders can be waited on
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::{POISONED_MUTEX, PREVIEW_ORDER_TIMEOUT};
+use crate::core::contract::Contract;
+use crate::core::errors::{IBKRApiLibError, TwsApiReportableError, TwsError};
+use crate::core::order::{Order, OrderPreview, OrderState, OrderStatus};
+
+/// Number of filled or cancelled orders whose final status is kept, so that status messages
+/// arriving late for them are still recognized as out of order
+pub const FINISHED_ORDERS_KEPT: usize = 1000;
+
+//==================================================================================================
+/// The contract, order and order state from the last open_order message received for an order
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OpenOrderSnapshot {
+    pub contract: Contract,
+    pub order: Order,
+    pub order_state: OrderState,
+}
+
+impl OpenOrderSnapshot {
+    pub fn new(contract: Contract, order: Order, order_state: OrderState) -> Self {
+        OpenOrderSnapshot {
+            contract,
+            order,
+            order_state,
+        }
+    }
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct TrackerState {
+    snapshots: HashMap<i32, OpenOrderSnapshot>,
+    statuses: HashMap<i32, OrderStatus>,
+    finished: VecDeque<(i32, OrderStatus)>,
+    awaited: HashSet<i32>,
+    errors: HashMap<i32, TwsApiReportableError>,
+}
+
+impl TrackerState {
+    //----------------------------------------------------------------------------------------------
+    fn status(&self, order_id: i32) -> Option<&OrderStatus> {
+        self.statuses.get(&order_id).or_else(|| {
+            self.finished
+                .iter()
+                .find(|(id, _)| *id == order_id)
+                .map(|(_, status)| status)
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn remove_status(&mut self, order_id: i32) {
+        self.statuses.remove(&order_id);
+        self.finished.retain(|(id, _)| *id != order_id);
+    }
+}
+
+//==================================================================================================
+/// Thread safe store of open order snapshots and order statuses keyed by order id.  The decoder
+/// updates it every time an open_order, order_status or order related error
+/// message arrives and the client reads from it.
+#[derive(Debug, Default)]
+pub struct OrderTracker {
+    state: Mutex<TrackerState>,
+    changed: Condvar,
+}
+
+impl OrderTracker {
+    pub fn new() -> Self {
+        OrderTracker {
+            state: Mutex::new(TrackerState::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the latest open order details for an order.  No snapshot is kept once the order
+    /// is filled or cancelled.  The status of the order state is checked as in update_status: a
+    /// stale one is not recorded, the snapshot keeps the current one, and the
+    /// TwsError::OutOfOrderStatus error is returned after the snapshot has been updated.
+    pub fn update_open_order(
+        &self,
+        order_id: i32,
+        contract: &Contract,
+        order: &Order,
+        order_state: &OrderState,
+    ) -> Result<(), IBKRApiLibError> {
+        let mut snapshot =
+            OpenOrderSnapshot::new(contract.clone(), order.clone(), order_state.clone());
+        let result = self.update_status(order_id, &order_state.status);
+
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if let Some(status) = state.status(order_id) {
+            snapshot.order_state.status = status.clone();
+        }
+        if !snapshot.order_state.status.is_terminal() || state.awaited.contains(&order_id) {
+            state.snapshots.insert(order_id, snapshot);
+        }
+        drop(state);
+        self.changed.notify_all();
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a status reported for an order, checking it against the last status seen.
+    /// TWS sometimes delivers status messages out of order.  A status the last one cannot
+    /// transition to is not recorded, and a TwsError::OutOfOrderStatus error is returned.  The
+    /// snapshot of an order that is filled or cancelled is removed, and its status is only kept
+    /// among the last FINISHED_ORDERS_KEPT finished orders.
+    pub fn update_status(
+        &self,
+        order_id: i32,
+        status: &OrderStatus,
+    ) -> Result<(), IBKRApiLibError> {
+        if let OrderStatus::Unknown(text) = status {
+            if text.is_empty() {
+                return Ok(());
+            }
+        }
+
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if let Some(current) = state.status(order_id) {
+            if !current.can_transition_to(status) {
+                return Err(IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                    order_id,
+                    TwsError::OutOfOrderStatus.code().to_string(),
+                    format!(
+                        "{} Order {} received status {} after {}",
+                        TwsError::OutOfOrderStatus.message(),
+                        order_id,
+                        status,
+                        current
+                    ),
+                )));
+            }
+        }
+
+        if status.is_terminal() {
+            state.remove_status(order_id);
+            state.finished.push_back((order_id, status.clone()));
+            if state.finished.len() > FINISHED_ORDERS_KEPT {
+                state.finished.pop_front();
+            }
+        } else {
+            state.statuses.insert(order_id, status.clone());
+        }
+        if status.is_terminal() && !state.awaited.contains(&order_id) {
+            state.snapshots.remove(&order_id);
+        } else if let Some(snapshot) = state.snapshots.get_mut(&order_id) {
+            snapshot.order_state.status = status.clone();
+        }
+        Ok(())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the last status recorded for the order, if any
+    pub fn order_status(&self, order_id: i32) -> Option<OrderStatus> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .status(order_id)
+            .cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Replaces the order details of an existing snapshot, leaving the contract and order state
+    /// untouched.  Used after an order has been resent so that consecutive modifications build
+    /// on each other before TWS has echoed the change back.
+    pub(crate) fn update_order(&self, order_id: i32, order: &Order) {
+        if let Some(snapshot) = self
+            .state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .snapshots
+            .get_mut(&order_id)
+        {
+            snapshot.order = order.clone();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the last snapshot seen for the order, if any
+    pub fn snapshot(&self, order_id: i32) -> Option<OpenOrderSnapshot> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .snapshots
+            .get(&order_id)
+            .cloned()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes and returns the snapshot for the order
+    pub fn remove(&self, order_id: i32) -> Option<OpenOrderSnapshot> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .snapshots
+            .remove(&order_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records an error sent by TWS if someone is waiting on the order it refers to.
+    /// Informational warnings are ignored.
+    pub fn record_error(&self, req_id: i32, error_code: i32, error_string: &str) {
+        if error_code == 399 || (2100..2200).contains(&error_code) {
+            return;
+        }
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        if state.awaited.contains(&req_id) {
+            state.errors.insert(
+                req_id,
+                TwsApiReportableError::new(
+                    req_id,
+                    error_code.to_string(),
+                    error_string.to_string(),
+                ),
+            );
+            drop(state);
+            self.changed.notify_all();
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Marks an order as awaited so that errors sent for it are kept.  Must be called before
+    /// the order is sent.
+    pub(crate) fn expect_open_order(&self, order_id: i32) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.awaited.insert(order_id);
+        state.errors.remove(&order_id);
+        state.snapshots.remove(&order_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Stops waiting for an order previously passed to expect_open_order
+    pub(crate) fn forget_open_order(&self, order_id: i32) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.awaited.remove(&order_id);
+        state.errors.remove(&order_id);
+        state.snapshots.remove(&order_id);
+        state.remove_status(order_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until an open_order message or an error arrives for an order previously passed to
+    /// expect_open_order, or until the timeout elapses.  The snapshot is removed from the store.
+    pub(crate) fn wait_for_open_order(
+        &self,
+        order_id: i32,
+        timeout: Duration,
+    ) -> Result<OpenOrderSnapshot, IBKRApiLibError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let result = loop {
+            if let Some(err) = state.errors.remove(&order_id) {
+                break Err(IBKRApiLibError::ApiError(err));
+            }
+            if let Some(snapshot) = state.snapshots.remove(&order_id) {
+                break Ok(snapshot);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break Err(IBKRApiLibError::RecvTimeoutError(RecvTimeoutError::Timeout));
+            }
+            state = self
+                .changed
+                .wait_timeout(state, deadline - now)
+                .expect(POISONED_MUTEX)
+                .0;
+        };
+        state.awaited.remove(&order_id);
+        state.remove_status(order_id);
+        result
+    }
+}
+
+//==================================================================================================
+/// A what-if order sent by EClient::preview_order.  Its answer is waited on through the
+/// OrderTracker alone, so the client can be released, and its lock dropped, while waiting.
+#[derive(Debug)]
+pub struct PendingOrderPreview {
+    order_id: i32,
+    tracker: Arc<OrderTracker>,
+}
+
+impl PendingOrderPreview {
+    pub(crate) fn new(order_id: i32, tracker: Arc<OrderTracker>) -> Self {
+        PendingOrderPreview { order_id, tracker }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn order_id(&self) -> i32 {
+        self.order_id
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Blocks until TWS answers with the open_order event of the what-if order or an error, or
+    /// until PREVIEW_ORDER_TIMEOUT elapses
+    pub fn wait(self) -> Result<OrderPreview, IBKRApiLibError> {
+        self.wait_timeout(PREVIEW_ORDER_TIMEOUT)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Same as wait, waiting at most timeout for the answer from TWS
+    pub fn wait_timeout(self, timeout: Duration) -> Result<OrderPreview, IBKRApiLibError> {
+        let snapshot = self.tracker.wait_for_open_order(self.order_id, timeout)?;
+        Ok(OrderPreview::from(&snapshot.order_state))
+    }
+}
+
+impl Drop for PendingOrderPreview {
+    fn drop(&mut self) {
+        self.tracker.forget_open_order(self.order_id);
+    }
+}
@@ -0,0 +1,596 @@
+//! Local profit and loss of executions, marked to market with the prices of market data
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::account::AccountPosition;
+use crate::core::common::{CommissionReport, UNSET_DOUBLE};
+use crate::core::contract::Contract;
+use crate::core::execution::Execution;
+use crate::core::market_data::Ticker;
+use crate::core::trade_blotter::{split_exec_id, Fill};
+
+//----------------------------------------------------------------------------------------------
+/// Returns the quantity of an execution, negative for sales
+fn signed_shares(execution: &Execution) -> f64 {
+    if execution.side == "SLD" {
+        -execution.shares
+    } else {
+        execution.shares
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+fn multiplier(contract: &Contract) -> f64 {
+    contract
+        .multiplier
+        .parse::<f64>()
+        .ok()
+        .filter(|multiplier| *multiplier > 0.0)
+        .unwrap_or(1.0)
+}
+
+//==================================================================================================
+/// How the cost of the closed quantity is computed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CostMethod {
+    /// Closes the oldest lots first
+    Fifo,
+    /// Closes at the average cost of the position
+    Average,
+}
+
+//==================================================================================================
+/// The positions the P&L is computed for: a contract in an account, traded with an order ref
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PnlKey {
+    pub account: String,
+    pub con_id: i32,
+    pub order_ref: String,
+}
+
+impl PnlKey {
+    pub fn new(account: &str, con_id: i32, order_ref: &str) -> Self {
+        PnlKey {
+            account: account.to_string(),
+            con_id,
+            order_ref: order_ref.to_string(),
+        }
+    }
+}
+
+//==================================================================================================
+/// Profit and loss of a position, or of several added together.  The unrealized and daily P&L
+/// are only known once the contracts are marked.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PnlValues {
+    pub position: f64,
+    /// Cost of the open position, per unit of the contract
+    pub average_cost: f64,
+    /// P&L of the closed quantities, less all the commissions
+    pub realized: f64,
+    pub unrealized: Option<f64>,
+    /// P&L since the previous close, less the commissions of the day
+    pub daily: Option<f64>,
+    pub commissions: f64,
+    pub market_value: Option<f64>,
+}
+
+impl PnlValues {
+    //----------------------------------------------------------------------------------------------
+    /// Adds the P&L of another position.  The average cost of positions of different contracts is
+    /// meaningless and set to 0.
+    pub fn add(&mut self, other: &PnlValues, same_contract: bool) {
+        let cost = self.average_cost * self.position + other.average_cost * other.position;
+        self.position += other.position;
+        self.average_cost = if same_contract && self.position != 0.0 {
+            cost / self.position
+        } else {
+            0.0
+        };
+        self.realized += other.realized;
+        self.commissions += other.commissions;
+        let add = |a: Option<f64>, b: Option<f64>| Some(a? + b?);
+        self.unrealized = add(self.unrealized, other.unrealized);
+        self.daily = add(self.daily, other.daily);
+        self.market_value = add(self.market_value, other.market_value);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Compares to the P&L TWS computed for the same account and contract, as received by
+    /// pnl_single and kept by the AccountStore
+    pub fn compare(&self, tws: &AccountPosition) -> PnlComparison {
+        let difference =
+            |local: Option<f64>, tws: Option<f64>| Some(local? - tws?).filter(|d| d.is_finite());
+        PnlComparison {
+            position: self.position - tws.position,
+            daily: difference(self.daily, tws.daily_pnl),
+            unrealized: difference(self.unrealized, tws.unrealized_pnl),
+            realized: difference(Some(self.realized), tws.realized_pnl),
+            market_value: difference(self.market_value, tws.market_value),
+        }
+    }
+}
+
+//==================================================================================================
+/// Differences between the local P&L and the P&L of TWS, None where either is unknown
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PnlComparison {
+    pub position: f64,
+    pub daily: Option<f64>,
+    pub unrealized: Option<f64>,
+    pub realized: Option<f64>,
+    pub market_value: Option<f64>,
+}
+
+impl PnlComparison {
+    //----------------------------------------------------------------------------------------------
+    /// Returns whether all the known differences are within a tolerance
+    pub fn within(&self, tolerance: f64) -> bool {
+        self.position.abs() <= tolerance
+            && [
+                self.daily,
+                self.unrealized,
+                self.realized,
+                self.market_value,
+            ]
+            .iter()
+            .flatten()
+            .all(|difference| difference.abs() <= tolerance)
+    }
+}
+
+//==================================================================================================
+/// A quantity bought or sold at a price by an order ref, negative for short lots
+#[derive(Clone, Debug)]
+struct Lot {
+    order_ref: String,
+    quantity: f64,
+    price: f64,
+}
+
+//==================================================================================================
+/// An execution, or a position held before the executions, as added to a Book
+#[derive(Clone, Debug)]
+struct Entry {
+    /// Exec id whose commission applies, empty for opening positions
+    exec_id: String,
+    lot: Lot,
+    /// Day the entry was added on, counted by roll_day
+    day: u32,
+    opening: bool,
+}
+
+//==================================================================================================
+/// The lots of a contract in an account, whatever the order refs that traded it.  Closing a lot
+/// realizes its P&L for the order ref that opened it.
+#[derive(Clone, Debug, Default)]
+struct Book {
+    multiplier: f64,
+    entries: Vec<Entry>,
+    lots: VecDeque<Lot>,
+    /// Realized P&L by order ref, before commissions
+    realized: HashMap<String, f64>,
+    /// Lots and realized P&L at the start of the day
+    opening_lots: Vec<Lot>,
+    opening_realized: HashMap<String, f64>,
+}
+
+impl Book {
+    //----------------------------------------------------------------------------------------------
+    fn average_cost(lots: &[&Lot]) -> (f64, f64) {
+        let position: f64 = lots.iter().map(|lot| lot.quantity).sum();
+        if position == 0.0 {
+            (0.0, 0.0)
+        } else {
+            let cost: f64 = lots.iter().map(|lot| lot.quantity * lot.price).sum();
+            (position, cost / position)
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a quantity, closing the open lots of the other side first
+    fn trade(&mut self, method: CostMethod, lot: &Lot) {
+        let mut quantity = lot.quantity;
+        while quantity != 0.0 {
+            let open = match self.lots.front_mut() {
+                Some(open) if open.quantity.signum() != quantity.signum() => open,
+                _ => break,
+            };
+            let closed = quantity.abs().min(open.quantity.abs()) * open.quantity.signum();
+            *self.realized.entry(open.order_ref.clone()).or_default() +=
+                closed * (lot.price - open.price) * self.multiplier;
+            open.quantity -= closed;
+            quantity += closed;
+            if open.quantity == 0.0 {
+                self.lots.pop_front();
+            }
+        }
+        if quantity != 0.0 {
+            self.lots.push_back(Lot {
+                order_ref: lot.order_ref.clone(),
+                quantity,
+                price: lot.price,
+            });
+            if method == CostMethod::Average {
+                let (_, average_cost) = Self::average_cost(&self.lots.iter().collect::<Vec<_>>());
+                for open in self.lots.iter_mut() {
+                    open.price = average_cost;
+                }
+            }
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts the day with the current lots and realized P&L
+    fn open_day(&mut self) {
+        self.opening_lots = self.lots.iter().cloned().collect();
+        self.opening_realized = self.realized.clone();
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Computes the lots again from the entries, after one of them was replaced
+    fn rebuild(&mut self, method: CostMethod, day: u32) {
+        self.lots.clear();
+        self.realized.clear();
+        let (before, today): (Vec<Entry>, Vec<Entry>) = self
+            .entries
+            .iter()
+            .cloned()
+            .partition(|entry| entry.opening || entry.day < day);
+        for entry in &before {
+            self.trade(method, &entry.lot);
+        }
+        self.open_day();
+        for entry in &today {
+            self.trade(method, &entry.lot);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn order_refs(&self) -> HashSet<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.lot.order_ref.clone())
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of the lots of an order ref
+    fn values(
+        &self,
+        order_ref: &str,
+        day: u32,
+        commissions: &HashMap<String, f64>,
+        mark: Option<f64>,
+        previous_close: Option<f64>,
+    ) -> PnlValues {
+        let lots: Vec<&Lot> = self
+            .lots
+            .iter()
+            .filter(|lot| lot.order_ref == order_ref)
+            .collect();
+        let opening_lots: Vec<&Lot> = self
+            .opening_lots
+            .iter()
+            .filter(|lot| lot.order_ref == order_ref)
+            .collect();
+        let unrealized = |lots: &[&Lot], price: f64| -> f64 {
+            lots.iter()
+                .map(|lot| lot.quantity * (price - lot.price) * self.multiplier)
+                .sum()
+        };
+        let (mut commissions_total, mut commissions_today) = (0.0, 0.0);
+        for entry in self
+            .entries
+            .iter()
+            .filter(|entry| entry.lot.order_ref == order_ref)
+        {
+            if let Some(commission) = commissions.get(&entry.exec_id) {
+                commissions_total += commission;
+                if entry.day == day && !entry.opening {
+                    commissions_today += commission;
+                }
+            }
+        }
+        let realized = self.realized.get(order_ref).copied().unwrap_or(0.0);
+        let opening_realized = self.opening_realized.get(order_ref).copied().unwrap_or(0.0);
+
+        let (position, average_cost) = Self::average_cost(&lots);
+        let daily = mark.and_then(|mark| {
+            let opening = if opening_lots.is_empty() {
+                0.0
+            } else {
+                unrealized(&opening_lots, previous_close?)
+            };
+            Some(
+                realized - opening_realized + unrealized(&lots, mark) - opening - commissions_today,
+            )
+        });
+        PnlValues {
+            position,
+            average_cost,
+            realized: realized - commissions_total,
+            unrealized: mark.map(|mark| unrealized(&lots, mark)),
+            daily,
+            commissions: commissions_total,
+            market_value: mark.map(|mark| mark * position * self.multiplier),
+        }
+    }
+}
+
+//==================================================================================================
+/// Computes the realized, unrealized and daily P&L of executions, by contract, account and order
+/// ref, from their prices, their commission reports and the marks of the contracts.
+///
+/// The executions of a contract in an account close each other in a single book, whatever their
+/// order refs, and the P&L of a lot is attributed to the order ref that opened it.  Executions
+/// are counted once by exec id, and a correction replaces the execution it corrects.  Positions
+/// held before the day are given with add_opening_position, and are marked from the previous
+/// close for the daily P&L.
+#[derive(Clone, Debug)]
+pub struct PnlEngine {
+    method: CostMethod,
+    /// Days rolled since the engine was created
+    day: u32,
+    /// Book of each account and contract id
+    books: HashMap<(String, i32), Book>,
+    /// Book and revision of each exec id without its revision
+    executions: HashMap<String, ((String, i32), u32)>,
+    /// Commission of each exec id, which may arrive before its execution
+    commissions: HashMap<String, f64>,
+    marks: HashMap<i32, f64>,
+    previous_closes: HashMap<i32, f64>,
+}
+
+impl PnlEngine {
+    pub fn new(method: CostMethod) -> Self {
+        PnlEngine {
+            method,
+            day: 0,
+            books: HashMap::new(),
+            executions: HashMap::new(),
+            commissions: HashMap::new(),
+            marks: HashMap::new(),
+            previous_closes: HashMap::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn book(&mut self, account: &str, contract: &Contract) -> &mut Book {
+        self.books
+            .entry((account.to_string(), contract.con_id))
+            .or_insert_with(|| Book {
+                multiplier: multiplier(contract),
+                ..Default::default()
+            })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a position held before the executions of the day
+    ///
+    /// # Arguments
+    /// * key - the account, contract id and order ref of the position
+    /// * contract - the contract, for its multiplier
+    /// * position - the position, negative if short
+    /// * average_cost - the cost of the position per unit of the contract
+    pub fn add_opening_position(
+        &mut self,
+        key: PnlKey,
+        contract: &Contract,
+        position: f64,
+        average_cost: f64,
+    ) {
+        let (method, day) = (self.method, self.day);
+        let book = self.book(&key.account, contract);
+        book.entries.push(Entry {
+            exec_id: "".to_string(),
+            lot: Lot {
+                order_ref: key.order_ref,
+                quantity: position,
+                price: average_cost,
+            },
+            day,
+            opening: true,
+        });
+        book.rebuild(method, day);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds an execution of the exec_details callback.  Executions already added are ignored, and
+    /// a correction replaces the execution it corrects.
+    pub fn add_execution(&mut self, contract: &Contract, execution: &Execution) {
+        let (base, revision) = split_exec_id(&execution.exec_id);
+        let book_key = (execution.acct_number.clone(), contract.con_id);
+        let (method, current_day) = (self.method, self.day);
+        let mut day = current_day;
+        let entry = |day| Entry {
+            exec_id: execution.exec_id.clone(),
+            lot: Lot {
+                order_ref: execution.order_ref.clone(),
+                quantity: signed_shares(execution),
+                price: execution.price,
+            },
+            day,
+            opening: false,
+        };
+
+        if let Some((corrected_book_key, current_revision)) = self.executions.get(base).cloned() {
+            if revision <= current_revision {
+                // A replay, or an execution corrected since
+                return;
+            }
+            if let Some(book) = self.books.get_mut(&corrected_book_key) {
+                if let Some(index) = book
+                    .entries
+                    .iter()
+                    .position(|entry| split_exec_id(&entry.exec_id).0 == base)
+                {
+                    day = book.entries[index].day;
+                    if corrected_book_key == book_key {
+                        book.entries[index] = entry(day);
+                        book.rebuild(method, current_day);
+                        self.executions
+                            .insert(base.to_string(), (book_key, revision));
+                        return;
+                    }
+                    book.entries.remove(index);
+                    book.rebuild(method, current_day);
+                }
+            }
+        }
+
+        self.executions
+            .insert(base.to_string(), (book_key, revision));
+        let book = self.book(&execution.acct_number, contract);
+        let added = entry(day);
+        let lot = added.lot.clone();
+        book.entries.push(added);
+        if day == current_day {
+            book.trade(method, &lot);
+        } else {
+            book.rebuild(method, current_day);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a report of the commission_report callback, which may arrive before its execution.
+    /// The commission of a corrected execution no longer applies once its correction is added.
+    pub fn add_commission_report(&mut self, report: &CommissionReport) {
+        if report.commission != UNSET_DOUBLE {
+            self.commissions
+                .insert(report.exec_id.clone(), report.commission);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a fill of the TradeBlotter, with its commission report if it has one
+    pub fn add_fill(&mut self, fill: &Fill) {
+        self.add_execution(&fill.contract, &fill.execution);
+        if let Some(report) = &fill.commission_report {
+            self.add_commission_report(report);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the price the positions of a contract are marked at
+    pub fn update_mark(&mut self, con_id: i32, price: f64) {
+        self.marks.insert(con_id, price);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the close of the previous day, the daily P&L of the opening positions is computed from
+    pub fn set_previous_close(&mut self, con_id: i32, price: f64) {
+        self.previous_closes.insert(con_id, price);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Marks a contract with the market data of a Ticker: its mark price, else its last price,
+    /// else its midpoint.  Its close is taken as the previous close.
+    pub fn update_from_ticker(&mut self, con_id: i32, ticker: &Ticker) {
+        if let Some(mark) = ticker
+            .mark_price
+            .or(ticker.last)
+            .or_else(|| ticker.midpoint())
+        {
+            self.update_mark(con_id, mark);
+        }
+        if let Some(close) = ticker.close {
+            self.set_previous_close(con_id, close);
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts a new day: the positions become opening positions, and the trades and commissions
+    /// of the day no longer count in the daily P&L.  They are kept, so that the total realized
+    /// P&L and commissions include them and a later correction of one of their executions can
+    /// be replayed.  The marks become the previous closes.
+    pub fn roll_day(&mut self) {
+        self.day += 1;
+        for book in self.books.values_mut() {
+            book.open_day();
+        }
+        self.previous_closes = self.marks.clone();
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of a position
+    pub fn pnl(&self, key: &PnlKey) -> Option<PnlValues> {
+        let book = self.books.get(&(key.account.clone(), key.con_id))?;
+        book.order_refs().contains(&key.order_ref).then(|| {
+            book.values(
+                &key.order_ref,
+                self.day,
+                &self.commissions,
+                self.marks.get(&key.con_id).copied(),
+                self.previous_closes.get(&key.con_id).copied(),
+            )
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of each position
+    pub fn positions(&self) -> BTreeMap<PnlKey, PnlValues> {
+        self.books
+            .iter()
+            .flat_map(|((account, con_id), book)| {
+                book.order_refs()
+                    .into_iter()
+                    .map(move |order_ref| PnlKey::new(account, *con_id, &order_ref))
+            })
+            .filter_map(|key| Some((key.clone(), self.pnl(&key)?)))
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of an account and contract, whatever the order refs, comparable to the
+    /// P&L sent by pnl_single
+    pub fn pnl_single(&self, account: &str, con_id: i32) -> Option<PnlValues> {
+        self.rolled_up(|key| (key.account == account && key.con_id == con_id).then_some(()))
+            .remove(&())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of each contract, over all the accounts
+    pub fn by_contract(&self) -> BTreeMap<i32, PnlValues> {
+        self.rolled_up(|key| Some(key.con_id))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of each account
+    pub fn by_account(&self) -> BTreeMap<String, PnlValues> {
+        self.rolled_up(|key| Some(key.account.clone()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the P&L of each order ref, such as the strategy which placed the orders
+    pub fn by_order_ref(&self) -> BTreeMap<String, PnlValues> {
+        self.rolled_up(|key| Some(key.order_ref.clone()))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds the P&L of the positions with the same group, skipping those without group
+    fn rolled_up<G: Ord, F: Fn(&PnlKey) -> Option<G>>(&self, group: F) -> BTreeMap<G, PnlValues> {
+        let mut groups: BTreeMap<G, (Option<i32>, PnlValues)> = BTreeMap::new();
+        for (key, values) in self.positions() {
+            if let Some(group) = group(&key) {
+                match groups.get_mut(&group) {
+                    Some((con_id, total)) => {
+                        let same_contract = *con_id == Some(key.con_id);
+                        if !same_contract {
+                            *con_id = None;
+                        }
+                        total.add(&values, same_contract);
+                    }
+                    None => {
+                        groups.insert(group, (Some(key.con_id), values));
+                    }
+                }
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(group, (_, values))| (group, values))
+            .collect()
+    }
+}
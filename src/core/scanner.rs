@@ -1,9 +1,20 @@
 //! Types for dealing with scanner data and scanner subscriptions
+use std::collections::HashMap;
 use std::fmt::{Display, Error, Formatter};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
 
+use roxmltree::Node;
 use serde::{Deserialize, Serialize};
 
+use crate::core::client::POISONED_MUTEX;
+use crate::core::common::{TagValue, NO_VALID_ID, UNSET_DOUBLE, UNSET_INTEGER};
 use crate::core::contract::ContractDetails;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::xml::{child_text, children, parse_document};
+
+/// Maximum number of rows of a scan
+pub const MAX_SCANNER_ROWS: i32 = 50;
 
 //==================================================================================================
 
@@ -119,6 +130,38 @@ impl ScannerSubscription {
             stock_type_filter,
         }
     }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts a subscription that is validated against the scanner parameters when built
+    ///
+    /// # Arguments
+    /// * instrument - the instrument type, such as "STK" or "STOCK.EU"
+    /// * location_code - the location, such as "STK.US.MAJOR"
+    /// * scan_code - the scan, such as "TOP_PERC_GAIN"
+    pub fn builder(
+        instrument: &str,
+        location_code: &str,
+        scan_code: &str,
+    ) -> ScannerSubscriptionBuilder {
+        ScannerSubscriptionBuilder {
+            subscription: ScannerSubscription {
+                number_of_rows: UNSET_INTEGER,
+                instrument: instrument.to_string(),
+                location_code: location_code.to_string(),
+                scan_code: scan_code.to_string(),
+                above_price: UNSET_DOUBLE,
+                below_price: UNSET_DOUBLE,
+                above_volume: UNSET_INTEGER,
+                market_cap_above: UNSET_DOUBLE,
+                market_cap_below: UNSET_DOUBLE,
+                coupon_rate_above: UNSET_DOUBLE,
+                coupon_rate_below: UNSET_DOUBLE,
+                average_option_volume_above: UNSET_INTEGER,
+                ..Default::default()
+            },
+            filter_options: vec![],
+        }
+    }
 }
 
 impl Display for ScannerSubscription {
@@ -130,3 +173,600 @@ impl Display for ScannerSubscription {
         )
     }
 }
+
+//----------------------------------------------------------------------------------------------
+fn invalid_scan(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(
+        NO_VALID_ID,
+        &format!("Invalid scanner subscription: {}", message),
+    )
+}
+
+//----------------------------------------------------------------------------------------------
+/// Splits a comma separated list of the scanner parameters
+fn list(node: Node, name: &str) -> Vec<String> {
+    child_text(node, name)
+        .map(|items| {
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+//==================================================================================================
+/// An instrument type that can be scanned
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScanInstrument {
+    pub name: String,
+    /// The instrument of ScannerSubscription, such as "STK" or "FUT.EU"
+    pub instrument_type: String,
+    /// Ids of the filters available for the instrument
+    pub filters: Vec<String>,
+}
+
+//==================================================================================================
+/// A location that can be scanned, such as an exchange or a group of exchanges
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScanLocation {
+    /// The location_code of ScannerSubscription, such as "STK.US.MAJOR"
+    pub code: String,
+    pub display_name: String,
+    /// Instrument types available at the location
+    pub instruments: Vec<String>,
+}
+
+//==================================================================================================
+/// A scan, such as the top percent gainers
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScanType {
+    /// The scan_code of ScannerSubscription, such as "TOP_PERC_GAIN"
+    pub code: String,
+    pub display_name: String,
+    /// Instrument types the scan applies to
+    pub instruments: Vec<String>,
+}
+
+//==================================================================================================
+/// A field of a filter, set by its code in the filter options of a subscription
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScanFilterField {
+    /// Tag of the filter option, such as "priceAbove"
+    pub code: String,
+    pub display_name: String,
+    /// Java type of the field, such as "scanner.filter.DoubleField"
+    pub field_type: String,
+}
+
+//==================================================================================================
+/// A filter of the scans, such as PRICE with the fields priceAbove and priceBelow
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScanFilter {
+    pub id: String,
+    pub category: String,
+    pub fields: Vec<ScanFilterField>,
+}
+
+//==================================================================================================
+/// The instruments, locations, scans and filters of the XML document sent to scanner_parameters
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScannerParameters {
+    pub instruments: Vec<ScanInstrument>,
+    /// Locations by code
+    pub locations: HashMap<String, ScanLocation>,
+    /// Scans by code
+    pub scan_types: HashMap<String, ScanType>,
+    /// Filters by id
+    pub filters: HashMap<String, ScanFilter>,
+    /// Ids of the filters by the code of their fields
+    filter_tags: HashMap<String, String>,
+}
+
+impl ScannerParameters {
+    //----------------------------------------------------------------------------------------------
+    /// Parses the XML document sent to scanner_parameters
+    pub fn parse(xml: &str) -> Result<Self, IBKRApiLibError> {
+        let document = parse_document(xml, "ScanParameterResponse")?;
+        let root = document.root_element();
+        let mut parameters = ScannerParameters::default();
+
+        for instrument_list in root
+            .descendants()
+            .filter(|node| node.has_tag_name("InstrumentList"))
+        {
+            for instrument in children(instrument_list, "Instrument") {
+                parameters.instruments.push(ScanInstrument {
+                    name: child_text(instrument, "name").unwrap_or_default(),
+                    instrument_type: child_text(instrument, "type").unwrap_or_default(),
+                    filters: list(instrument, "filters"),
+                });
+            }
+        }
+
+        // Locations are nested in location trees, down to the exchanges
+        for location in root
+            .descendants()
+            .filter(|node| node.has_tag_name("Location"))
+        {
+            if let Some(code) = child_text(location, "locationCode") {
+                parameters.locations.insert(
+                    code.clone(),
+                    ScanLocation {
+                        code,
+                        display_name: child_text(location, "displayName").unwrap_or_default(),
+                        instruments: list(location, "instruments"),
+                    },
+                );
+            }
+        }
+
+        for scan_type in root
+            .descendants()
+            .filter(|node| node.has_tag_name("ScanType"))
+        {
+            if let Some(code) = child_text(scan_type, "scanCode") {
+                parameters.scan_types.insert(
+                    code.clone(),
+                    ScanType {
+                        code,
+                        display_name: child_text(scan_type, "displayName").unwrap_or_default(),
+                        instruments: list(scan_type, "instruments"),
+                    },
+                );
+            }
+        }
+
+        for filter_list in root
+            .descendants()
+            .filter(|node| node.has_tag_name("FilterList"))
+        {
+            for filter in filter_list.children().filter(Node::is_element) {
+                let id = match child_text(filter, "id") {
+                    Some(id) => id,
+                    None => continue,
+                };
+                let fields: Vec<ScanFilterField> = filter
+                    .descendants()
+                    .filter(|node| node.has_tag_name("AbstractField"))
+                    .filter_map(|field| {
+                        Some(ScanFilterField {
+                            code: child_text(field, "code")?,
+                            display_name: child_text(field, "displayName").unwrap_or_default(),
+                            field_type: field.attribute("type").unwrap_or_default().to_string(),
+                        })
+                    })
+                    .collect();
+                for field in fields.iter() {
+                    parameters
+                        .filter_tags
+                        .entry(field.code.clone())
+                        .or_insert_with(|| id.clone());
+                }
+                parameters.filters.insert(
+                    id.clone(),
+                    ScanFilter {
+                        id,
+                        category: child_text(filter, "category").unwrap_or_default(),
+                        fields,
+                    },
+                );
+            }
+        }
+        Ok(parameters)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn instrument(&self, instrument_type: &str) -> Option<&ScanInstrument> {
+        self.instruments
+            .iter()
+            .find(|instrument| instrument.instrument_type == instrument_type)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn location(&self, code: &str) -> Option<&ScanLocation> {
+        self.locations.get(code)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn scan_type(&self, code: &str) -> Option<&ScanType> {
+        self.scan_types.get(code)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the filter that has a field with the code of a filter option, such as "priceAbove"
+    pub fn filter_for_tag(&self, tag: &str) -> Option<&ScanFilter> {
+        self.filters.get(self.filter_tags.get(tag)?)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the locations where an instrument type can be scanned
+    pub fn locations_for<'a>(
+        &'a self,
+        instrument_type: &'a str,
+    ) -> impl Iterator<Item = &'a ScanLocation> + 'a {
+        self.locations.values().filter(move |location| {
+            location
+                .instruments
+                .iter()
+                .any(|instrument| instrument == instrument_type)
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the scans that apply to an instrument type
+    pub fn scan_types_for<'a>(
+        &'a self,
+        instrument_type: &'a str,
+    ) -> impl Iterator<Item = &'a ScanType> + 'a {
+        self.scan_types.values().filter(move |scan_type| {
+            scan_type
+                .instruments
+                .iter()
+                .any(|instrument| instrument == instrument_type)
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Checks that the instrument, location and scan of a subscription exist and go together, and
+    /// that the tags of the filter options are fields of filters available for the instrument
+    pub fn validate(
+        &self,
+        subscription: &ScannerSubscription,
+        filter_options: &[TagValue],
+    ) -> Result<(), IBKRApiLibError> {
+        let instrument_type = subscription.instrument.as_str();
+        let instrument = self
+            .instrument(instrument_type)
+            .ok_or_else(|| invalid_scan(format!("unknown instrument {}", instrument_type)))?;
+
+        let location = self.location(&subscription.location_code).ok_or_else(|| {
+            invalid_scan(format!("unknown location {}", subscription.location_code))
+        })?;
+        if !location.instruments.iter().any(|i| i == instrument_type) {
+            return Err(invalid_scan(format!(
+                "location {} has no {} instruments",
+                location.code, instrument_type
+            )));
+        }
+
+        let scan_type = self
+            .scan_type(&subscription.scan_code)
+            .ok_or_else(|| invalid_scan(format!("unknown scan code {}", subscription.scan_code)))?;
+        if !scan_type.instruments.iter().any(|i| i == instrument_type) {
+            return Err(invalid_scan(format!(
+                "scan code {} does not apply to {} instruments",
+                scan_type.code, instrument_type
+            )));
+        }
+
+        for option in filter_options.iter() {
+            let filter = self
+                .filter_for_tag(&option.tag)
+                .ok_or_else(|| invalid_scan(format!("unknown filter tag {}", option.tag)))?;
+            if !instrument.filters.contains(&filter.id) {
+                return Err(invalid_scan(format!(
+                    "filter tag {} is not available for {} instruments",
+                    option.tag, instrument_type
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+//==================================================================================================
+/// A subscription and its filter options, validated against the scanner parameters and ready to be
+/// sent with req_scanner_subscription
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScannerRequest {
+    pub subscription: ScannerSubscription,
+    pub filter_options: Vec<TagValue>,
+}
+
+//==================================================================================================
+/// Builds a ScannerSubscription, started with ScannerSubscription::builder
+#[derive(Clone, Debug)]
+pub struct ScannerSubscriptionBuilder {
+    subscription: ScannerSubscription,
+    filter_options: Vec<TagValue>,
+}
+
+impl ScannerSubscriptionBuilder {
+    //----------------------------------------------------------------------------------------------
+    /// Sets the number of rows of the scan, at most MAX_SCANNER_ROWS
+    pub fn number_of_rows(mut self, number_of_rows: i32) -> Self {
+        self.subscription.number_of_rows = number_of_rows;
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Sets the type of stocks scanned, such as "CORP", "ADR", "ETF" or "REIT"
+    pub fn stock_type_filter(mut self, stock_type_filter: &str) -> Self {
+        self.subscription.stock_type_filter = stock_type_filter.to_string();
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a filter option, such as `filter("priceAbove", 5)`
+    ///
+    /// # Arguments
+    /// * tag - the code of a field of a filter of the scanner parameters
+    /// * value - the value of the field
+    pub fn filter<V: Display>(mut self, tag: &str, value: V) -> Self {
+        self.filter_options
+            .push(TagValue::new(tag.to_string(), value.to_string()));
+        self
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Validates the subscription against the scanner parameters
+    pub fn build(self, parameters: &ScannerParameters) -> Result<ScannerRequest, IBKRApiLibError> {
+        let rows = self.subscription.number_of_rows;
+        if rows != UNSET_INTEGER && !(1..=MAX_SCANNER_ROWS).contains(&rows) {
+            return Err(invalid_scan(format!(
+                "number of rows {} is not between 1 and {}",
+                rows, MAX_SCANNER_ROWS
+            )));
+        }
+        parameters.validate(&self.subscription, &self.filter_options)?;
+        Ok(ScannerRequest {
+            subscription: self.subscription,
+            filter_options: self.filter_options,
+        })
+    }
+}
+
+//==================================================================================================
+/// A change between two consecutive results of a scan
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ScannerEvent {
+    /// A contract that was not in the previous results
+    Entered { rank: i32, data: ScanData },
+    /// A contract of the previous results that is no longer there
+    Exited { previous_rank: i32, data: ScanData },
+    /// A contract whose rank changed
+    Moved {
+        previous_rank: i32,
+        rank: i32,
+        data: ScanData,
+    },
+}
+
+//==================================================================================================
+/// The results of a scan once scanner_data_end is received, with their changes since the previous
+/// results
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScannerRefresh {
+    pub req_id: i32,
+    /// Rows ordered by rank
+    pub rows: Vec<ScanData>,
+    pub events: Vec<ScannerEvent>,
+}
+
+impl ScannerRefresh {
+    //----------------------------------------------------------------------------------------------
+    /// Returns the contracts that entered the results, ordered by rank
+    pub fn entered(&self) -> Vec<&ScanData> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                ScannerEvent::Entered { data, .. } => Some(data),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Identifies the contract of a row across refreshes.  Rows without contract id, such as the
+/// combos of EFP scans, are identified by their symbols and legs.
+fn row_key(data: &ScanData) -> (i32, String) {
+    let contract = &data.contract.contract;
+    if contract.con_id != 0 {
+        (contract.con_id, "".to_string())
+    } else {
+        (
+            0,
+            format!(
+                "{}|{}|{}",
+                contract.symbol, contract.local_symbol, data.legs
+            ),
+        )
+    }
+}
+
+//==================================================================================================
+/// Assembles the scanner_data rows of a scan into results, one refresh at a time.  TWS sends the
+/// full list again on every refresh, so consecutive results are diffed to find the contracts that
+/// entered, exited or moved.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ScannerSession {
+    pub req_id: i32,
+    /// Results of the last complete refresh, ordered by rank
+    results: Vec<ScanData>,
+    /// Rows of the refresh being received
+    pending: Vec<ScanData>,
+    /// Number of complete refreshes
+    refreshes: usize,
+}
+
+impl ScannerSession {
+    pub fn new(req_id: i32) -> Self {
+        ScannerSession {
+            req_id,
+            ..Default::default()
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Adds a row of the refresh being received
+    pub fn add_row(&mut self, data: ScanData) {
+        self.pending.push(data);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Completes the refresh being received and returns it with its changes since the previous
+    /// one.  All the rows of the first refresh are entered.
+    pub fn end(&mut self) -> ScannerRefresh {
+        let mut rows = std::mem::take(&mut self.pending);
+        rows.sort_by_key(|data| data.rank);
+
+        let previous: HashMap<(i32, String), &ScanData> = self
+            .results
+            .iter()
+            .map(|data| (row_key(data), data))
+            .collect();
+        let mut events = vec![];
+        for data in rows.iter() {
+            match previous.get(&row_key(data)) {
+                None => events.push(ScannerEvent::Entered {
+                    rank: data.rank,
+                    data: data.clone(),
+                }),
+                Some(before) if before.rank != data.rank => events.push(ScannerEvent::Moved {
+                    previous_rank: before.rank,
+                    rank: data.rank,
+                    data: data.clone(),
+                }),
+                _ => (),
+            }
+        }
+
+        let current: HashMap<(i32, String), i32> =
+            rows.iter().map(|data| (row_key(data), data.rank)).collect();
+        for data in self.results.iter() {
+            if !current.contains_key(&row_key(data)) {
+                events.push(ScannerEvent::Exited {
+                    previous_rank: data.rank,
+                    data: data.clone(),
+                });
+            }
+        }
+
+        self.results = rows;
+        self.refreshes += 1;
+        ScannerRefresh {
+            req_id: self.req_id,
+            rows: self.results.clone(),
+            events,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the results of the last complete refresh, ordered by rank
+    pub fn results(&self) -> &[ScanData] {
+        &self.results
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn refreshes(&self) -> usize {
+        self.refreshes
+    }
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct ScannerState {
+    sessions: HashMap<i32, ScannerSession>,
+    subscribers: HashMap<i32, Vec<Sender<ScannerRefresh>>>,
+}
+
+//==================================================================================================
+/// Thread safe store of ScannerSessions keyed by the req_id of req_scanner_subscription.  The
+/// decoder adds every row to it before the matching Wrapper callback is called.
+#[derive(Debug, Default)]
+pub struct ScannerCache {
+    state: Mutex<ScannerState>,
+}
+
+impl ScannerCache {
+    pub fn new() -> Self {
+        ScannerCache {
+            state: Mutex::new(ScannerState::default()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Starts a new session for a request, keeping its subscribers
+    pub fn reset(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .sessions
+            .insert(req_id, ScannerSession::new(req_id));
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Removes and returns the session of a request and closes the channels of its subscribers
+    pub fn remove(&self, req_id: i32) -> Option<ScannerSession> {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.subscribers.remove(&req_id);
+        state.sessions.remove(&req_id)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a channel receiving every refresh of a request.  The channel is closed when the
+    /// subscription is cancelled.
+    pub fn subscribe(&self, req_id: i32) -> Receiver<ScannerRefresh> {
+        let (sender, receiver) = channel();
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .subscribers
+            .entry(req_id)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn add_row(&self, req_id: i32, data: ScanData) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .sessions
+            .entry(req_id)
+            .or_insert_with(|| ScannerSession::new(req_id))
+            .add_row(data);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Completes the refresh of a request and sends it to the subscribers
+    pub fn end(&self, req_id: i32) -> ScannerRefresh {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let refresh = state
+            .sessions
+            .entry(req_id)
+            .or_insert_with(|| ScannerSession::new(req_id))
+            .end();
+        if let Some(subscribers) = state.subscribers.get_mut(&req_id) {
+            subscribers.retain(|subscriber| subscriber.send(refresh.clone()).is_ok());
+        }
+        refresh
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the results of the last complete refresh of a request
+    pub fn results(&self, req_id: i32) -> Option<Vec<ScanData>> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .sessions
+            .get(&req_id)
+            .map(|session| session.results().to_vec())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of the session of a request
+    pub fn session(&self, req_id: i32) -> Option<ScannerSession> {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .sessions
+            .get(&req_id)
+            .cloned()
+    }
+}
@@ -0,0 +1,105 @@
+//! Conversions between the date and time strings of the TWS messages and chrono date times
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::core::common::NO_VALID_ID;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::trading_hours::{local_to_utc, parse_time_zone};
+
+/// Value of format_date for dates and times sent as "yyyymmdd  hh:mm:ss"
+pub const FORMAT_DATE_TEXT: i32 = 1;
+/// Value of format_date for dates and times sent as seconds since the epoch
+pub const FORMAT_DATE_EPOCH: i32 = 2;
+
+//----------------------------------------------------------------------------------------------
+fn invalid_time(text: &str) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(NO_VALID_ID, &format!("Invalid date and time: {}", text))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Converts a number of seconds since the epoch to a date time
+pub fn from_epoch_seconds(seconds: i64) -> Result<DateTime<Utc>, IBKRApiLibError> {
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .ok_or_else(|| invalid_time(&seconds.to_string()))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Parses a date and time sent by TWS in the format_date it was requested with:
+///
+/// * with FORMAT_DATE_EPOCH, seconds since the epoch, as sent for intraday bars and real time
+///   bars
+/// * "yyyymmdd", the start of a day, as sent for daily and longer bars whatever the format_date
+/// * with FORMAT_DATE_TEXT, "yyyymmdd  hh:mm:ss", as sent for intraday bars and executions,
+///   optionally followed by the id of its time zone, or "yyyymmdd-hh:mm:ss" in UTC
+///
+/// # Arguments
+/// * text - the date and time to parse
+/// * format_date - FORMAT_DATE_TEXT or FORMAT_DATE_EPOCH
+/// * time_zone - the time zone of dates without one, which is the time zone of TWS
+pub fn parse_tws_time(
+    text: &str,
+    format_date: i32,
+    time_zone: &Tz,
+) -> Result<DateTime<Utc>, IBKRApiLibError> {
+    let text = text.trim();
+    if format_date == FORMAT_DATE_EPOCH && text.len() != 8 {
+        return from_epoch_seconds(text.parse::<i64>().map_err(|_| invalid_time(text))?);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y%m%d") {
+        return Ok(local_to_utc(time_zone, &date.and_hms_opt(0, 0, 0).unwrap()));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, "%Y%m%d-%H:%M:%S") {
+        return Ok(Utc.from_utc_datetime(&time));
+    }
+
+    let mut parts = text.split_whitespace();
+    let (date, time) = match (parts.next(), parts.next()) {
+        (Some(date), Some(time)) => (date, time),
+        _ => return Err(invalid_time(text)),
+    };
+    let local = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y%m%d %H:%M:%S")
+        .map_err(|_| invalid_time(text))?;
+    let zone_id = parts.collect::<Vec<&str>>().join(" ");
+    let time_zone = if zone_id.is_empty() {
+        *time_zone
+    } else {
+        parse_time_zone(&zone_id)?
+    };
+    Ok(local_to_utc(&time_zone, &local))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Formats a date time for a request, such as the end_date_time of req_historical_data, in the
+/// UTC format "yyyymmdd-hh:mm:ss" accepted by TWS whatever its time zone
+pub fn format_tws_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%d-%H:%M:%S").to_string()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Formats a local date time for a request as "yyyymmdd hh:mm:ss" followed by its time zone,
+/// such as "20200312 10:00:00 US/Eastern".  A time in UTC is formatted as by format_tws_time.
+pub fn format_tws_local_time(time: &NaiveDateTime, time_zone: &Tz) -> String {
+    match time_zone {
+        Tz::UTC => format_tws_time(&Utc.from_utc_datetime(time)),
+        _ => format!("{} {}", time.format("%Y%m%d %H:%M:%S"), time_zone.name()),
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Formats an optional date time for a request, None being sent as an empty string
+pub fn format_optional_tws_time(time: &Option<DateTime<Utc>>) -> String {
+    time.as_ref().map(format_tws_time).unwrap_or_default()
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns the time zone of TWS from the connection time sent when connecting, such as
+/// "20200312 10:00:00 EST"
+pub fn connection_time_zone(connection_time: &str) -> Result<Tz, IBKRApiLibError> {
+    let zone_id = connection_time
+        .split_whitespace()
+        .skip(2)
+        .collect::<Vec<&str>>()
+        .join(" ");
+    parse_time_zone(&zone_id)
+}
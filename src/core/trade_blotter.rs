@@ -0,0 +1,329 @@
+//! Fills of the day, joining the exec_details and commission_report callbacks, with exports to CSV
+//! and JSON
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::client::POISONED_MUTEX;
+use crate::core::common::{CommissionReport, UNSET_DOUBLE};
+use crate::core::contract::Contract;
+use crate::core::errors::IBKRApiLibError;
+use crate::core::execution::{Execution, ExecutionFilter};
+
+//----------------------------------------------------------------------------------------------
+/// Splits an exec id such as "0000e0d5.5f0ed1a4.01.01" into the id shared by its corrections and
+/// its correction number
+pub(crate) fn split_exec_id(exec_id: &str) -> (&str, u32) {
+    match exec_id.rsplit_once('.') {
+        Some((base, revision)) => match u32::from_str_radix(revision, 16) {
+            Ok(revision) => (base, revision),
+            Err(_) => (exec_id, 0),
+        },
+        None => (exec_id, 0),
+    }
+}
+
+//==================================================================================================
+/// An execution with its contract and, once received, its commission report
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Fill {
+    pub contract: Contract,
+    pub execution: Execution,
+    pub commission_report: Option<CommissionReport>,
+    /// Exec ids of the executions this fill corrects, oldest first
+    pub corrected_exec_ids: Vec<String>,
+}
+
+impl Fill {
+    //----------------------------------------------------------------------------------------------
+    pub fn commission(&self) -> Option<f64> {
+        self.commission_report
+            .as_ref()
+            .map(|report| report.commission)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the realized P&L of the commission report, if TWS sent one
+    pub fn realized_pnl(&self) -> Option<f64> {
+        self.commission_report
+            .as_ref()
+            .map(|report| report.realized_pnl)
+            .filter(|pnl| *pnl != UNSET_DOUBLE)
+    }
+}
+
+//==================================================================================================
+/// A fill flattened to a row of the CSV export
+#[derive(Serialize)]
+struct FillRow<'a> {
+    time: DateTime<Utc>,
+    account: &'a str,
+    model_code: &'a str,
+    exec_id: &'a str,
+    order_id: i32,
+    perm_id: i32,
+    client_id: i32,
+    order_ref: &'a str,
+    side: &'a str,
+    shares: f64,
+    price: f64,
+    cum_qty: f64,
+    avg_price: f64,
+    exchange: &'a str,
+    last_liquidity: i32,
+    con_id: i32,
+    symbol: &'a str,
+    sec_type: &'a str,
+    last_trade_date_or_contract_month: &'a str,
+    strike: f64,
+    right: &'a str,
+    multiplier: &'a str,
+    primary_exchange: &'a str,
+    currency: &'a str,
+    local_symbol: &'a str,
+    trading_class: &'a str,
+    commission: Option<f64>,
+    commission_currency: Option<&'a str>,
+    realized_pnl: Option<f64>,
+    corrected_exec_ids: String,
+}
+
+impl<'a> From<&'a Fill> for FillRow<'a> {
+    fn from(fill: &'a Fill) -> Self {
+        let contract = &fill.contract;
+        let execution = &fill.execution;
+        FillRow {
+            time: execution.time,
+            account: &execution.acct_number,
+            model_code: &execution.model_code,
+            exec_id: &execution.exec_id,
+            order_id: execution.order_id,
+            perm_id: execution.perm_id,
+            client_id: execution.client_id,
+            order_ref: &execution.order_ref,
+            side: &execution.side,
+            shares: execution.shares,
+            price: execution.price,
+            cum_qty: execution.cum_qty,
+            avg_price: execution.avg_price,
+            exchange: &execution.exchange,
+            last_liquidity: execution.last_liquidity,
+            con_id: contract.con_id,
+            symbol: &contract.symbol,
+            sec_type: &contract.sec_type,
+            last_trade_date_or_contract_month: &contract.last_trade_date_or_contract_month,
+            strike: contract.strike,
+            right: &contract.right,
+            multiplier: &contract.multiplier,
+            primary_exchange: &contract.primary_exchange,
+            currency: &contract.currency,
+            local_symbol: &contract.local_symbol,
+            trading_class: &contract.trading_class,
+            commission: fill.commission(),
+            commission_currency: fill
+                .commission_report
+                .as_ref()
+                .map(|report| report.currency.as_str()),
+            realized_pnl: fill.realized_pnl(),
+            corrected_exec_ids: fill.corrected_exec_ids.join(" "),
+        }
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Writes fills as CSV, one row per fill with its contract and commission, after a header row
+pub fn write_fills_csv<W: io::Write>(fills: &[Fill], writer: W) -> Result<(), IBKRApiLibError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for fill in fills {
+        writer
+            .serialize(FillRow::from(fill))
+            .map_err(io::Error::from)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns fills as CSV, see write_fills_csv
+pub fn fills_to_csv(fills: &[Fill]) -> Result<String, IBKRApiLibError> {
+    let mut csv = Vec::new();
+    write_fills_csv(fills, &mut csv)?;
+    Ok(String::from_utf8_lossy(&csv).into_owned())
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns fills as a JSON array, with all the fields of their contract, execution and commission
+/// report
+pub fn fills_to_json(fills: &[Fill]) -> Result<String, IBKRApiLibError> {
+    Ok(serde_json::to_string_pretty(fills).map_err(io::Error::from)?)
+}
+
+//==================================================================================================
+#[derive(Debug, Default)]
+struct BlotterState {
+    /// Fills by the exec id they share with their corrections
+    fills: HashMap<String, Fill>,
+    commission_reports: HashMap<String, CommissionReport>,
+    /// Filters of the req_executions requests, applied again to the executions they return
+    execution_filters: HashMap<i32, ExecutionFilter>,
+}
+
+//==================================================================================================
+/// Thread safe blotter of the fills received by exec_details, joined to the commission_report
+/// callbacks by exec id.  The decoder updates it before the matching Wrapper callback is called.
+///
+/// A correction of an execution, sent with the same exec id but a higher last number, replaces it.
+/// Executions received again, such as the replay of req_executions after a reconnection, are
+/// only kept once.
+#[derive(Debug, Default)]
+pub struct TradeBlotter {
+    state: Mutex<BlotterState>,
+}
+
+impl TradeBlotter {
+    pub fn new() -> Self {
+        TradeBlotter {
+            state: Mutex::new(BlotterState::default()),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records an execution of the exec_details callback
+    pub fn add_execution(&self, contract: Contract, execution: Execution) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        let (base, revision) = split_exec_id(&execution.exec_id);
+        let base = base.to_string();
+        let mut corrected_exec_ids = vec![];
+        if let Some(current) = state.fills.get(&base) {
+            let current_exec_id = &current.execution.exec_id;
+            let (_, current_revision) = split_exec_id(current_exec_id);
+            if revision < current_revision {
+                // A replay of an execution corrected since
+                return;
+            }
+            corrected_exec_ids = current.corrected_exec_ids.clone();
+            if revision > current_revision {
+                corrected_exec_ids.push(current_exec_id.clone());
+            }
+        }
+        state.fills.insert(
+            base,
+            Fill {
+                contract,
+                execution,
+                commission_report: None,
+                corrected_exec_ids,
+            },
+        );
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records a report of the commission_report callback, which may arrive before its execution
+    pub fn add_commission_report(&self, report: CommissionReport) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .commission_reports
+            .insert(report.exec_id.clone(), report);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the fill of an exec id, or of the correction replacing it
+    pub fn fill(&self, exec_id: &str) -> Option<Fill> {
+        let state = self.state.lock().expect(POISONED_MUTEX);
+        let (base, _) = split_exec_id(exec_id);
+        state.fills.get(base).map(|fill| Self::joined(&state, fill))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns all the fills, ordered by time then exec id
+    pub fn fills(&self) -> Vec<Fill> {
+        self.fills_matching(|_| true)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the fills of a day in a time zone, ordered by time then exec id
+    ///
+    /// # Arguments
+    /// * date - the day
+    /// * time_zone - the time zone of the day, such as chrono_tz::America::New_York
+    pub fn fills_on<Tz: TimeZone>(&self, date: NaiveDate, time_zone: &Tz) -> Vec<Fill> {
+        self.fills_matching(|fill| {
+            fill.execution.time.with_timezone(time_zone).date_naive() == date
+        })
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the fills matching an execution filter, ordered by time then exec id
+    pub fn filtered(&self, filter: &ExecutionFilter) -> Vec<Fill> {
+        self.fills_matching(|fill| filter.matches(&fill.contract, &fill.execution))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Records the filter of a req_executions request
+    pub(crate) fn expect_executions(&self, req_id: i32, filter: ExecutionFilter) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .execution_filters
+            .insert(req_id, filter);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub(crate) fn forget_executions(&self, req_id: i32) {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .execution_filters
+            .remove(&req_id);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns whether an execution returned for a req_executions request matches its filter.
+    /// Executions of other requests, such as those of orders being filled, are always accepted.
+    pub fn accepts(&self, req_id: i32, contract: &Contract, execution: &Execution) -> bool {
+        self.state
+            .lock()
+            .expect(POISONED_MUTEX)
+            .execution_filters
+            .get(&req_id)
+            .map_or(true, |filter| filter.matches(contract, execution))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Forgets all the fills and commission reports, such as at the start of a day
+    pub fn clear(&self) {
+        let mut state = self.state.lock().expect(POISONED_MUTEX);
+        state.fills.clear();
+        state.commission_reports.clear();
+    }
+
+    //----------------------------------------------------------------------------------------------
+    fn fills_matching<F: Fn(&Fill) -> bool>(&self, predicate: F) -> Vec<Fill> {
+        let state = self.state.lock().expect(POISONED_MUTEX);
+        let mut fills: Vec<Fill> = state
+            .fills
+            .values()
+            .filter(|fill| predicate(fill))
+            .map(|fill| Self::joined(&state, fill))
+            .collect();
+        fills.sort_by(|a, b| {
+            (a.execution.time, &a.execution.exec_id).cmp(&(b.execution.time, &b.execution.exec_id))
+        });
+        fills
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns a copy of a fill with its commission report
+    fn joined(state: &BlotterState, fill: &Fill) -> Fill {
+        let mut fill = fill.clone();
+        fill.commission_report = state
+            .commission_reports
+            .get(&fill.execution.exec_id)
+            .cloned();
+        fill
+    }
+}
@@ -0,0 +1,172 @@
+//! Exchange time zones and trading session calendars built from ContractDetails
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::core::common::NO_VALID_ID;
+use crate::core::contract::ContractDetails;
+use crate::core::errors::IBKRApiLibError;
+
+//----------------------------------------------------------------------------------------------
+fn invalid_argument(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(NO_VALID_ID, &message)
+}
+
+//----------------------------------------------------------------------------------------------
+/// Converts a time zone id sent by TWS, such as "US/Eastern", "EST", "JST" or the Windows name
+/// "Eastern Standard Time", to a time zone.
+/// The abbreviations TWS uses for exchanges that observe daylight saving time are mapped to
+/// the matching region, so that "EST" follows New York in the summer too.
+pub fn parse_time_zone(time_zone_id: &str) -> Result<Tz, IBKRApiLibError> {
+    let name = match time_zone_id.trim() {
+        "EST" | "EDT" | "EST5EDT" | "Eastern Standard Time" => "America/New_York",
+        "CST" | "CDT" | "CST6CDT" | "Central Standard Time" => "America/Chicago",
+        "MST" | "MDT" | "MST7MDT" | "Mountain Standard Time" => "America/Denver",
+        "PST" | "PDT" | "PST8PDT" | "Pacific Standard Time" => "America/Los_Angeles",
+        "JST" | "Tokyo Standard Time" => "Asia/Tokyo",
+        "HKT" => "Asia/Hong_Kong",
+        "AEST" | "AEDT" => "Australia/Sydney",
+        "CET" | "CEST" | "MET" => "Europe/Berlin",
+        "BST" | "GMT Standard Time" => "Europe/London",
+        "China Standard Time" => "Asia/Shanghai",
+        other => other,
+    };
+    name.parse::<Tz>()
+        .map_err(|_| invalid_argument(format!("Unknown time zone: {}", time_zone_id)))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Converts a local time of a time zone to UTC.  Ambiguous times take the earlier instant, and
+/// times skipped by a daylight saving change are moved forward by the size of the gap.
+pub fn local_to_utc(time_zone: &Tz, local: &NaiveDateTime) -> DateTime<Utc> {
+    match time_zone.from_local_datetime(local) {
+        LocalResult::Single(time) => time.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => local_to_utc(time_zone, &(*local + Duration::hours(1))),
+    }
+}
+
+//==================================================================================================
+/// A period during which an exchange trades a contract
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Session { start, end }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn contains(&self, time: &DateTime<Utc>) -> bool {
+        self.start <= *time && *time < self.end
+    }
+}
+
+//==================================================================================================
+/// Trading sessions of a contract, in the time zone of its exchange
+#[derive(Clone, Debug)]
+pub struct SessionCalendar {
+    pub time_zone: Tz,
+    sessions: Vec<Session>,
+}
+
+impl SessionCalendar {
+    pub fn new(time_zone: Tz, mut sessions: Vec<Session>) -> Self {
+        sessions.sort_by_key(|session| session.start);
+        SessionCalendar {
+            time_zone,
+            sessions,
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Builds the calendar from the trading_hours, or liquid_hours when liquid_hours_only is
+    /// set, and time_zone_id of contract details
+    pub fn from_contract_details(
+        details: &ContractDetails,
+        liquid_hours_only: bool,
+    ) -> Result<Self, IBKRApiLibError> {
+        let hours = if liquid_hours_only {
+            &details.liquid_hours
+        } else {
+            &details.trading_hours
+        };
+        SessionCalendar::parse(hours, parse_time_zone(&details.time_zone_id)?)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Parses trading hours in either of the formats sent by TWS:
+    /// "20180323:0400-20180323:2000;20180324:CLOSED" or "20090507:0700-1830,1830-2330;20090508:CLOSED".
+    /// A session whose end is not after its start ends on the next day.
+    pub fn parse(hours: &str, time_zone: Tz) -> Result<Self, IBKRApiLibError> {
+        let mut sessions = vec![];
+        for day in hours
+            .split(';')
+            .map(str::trim)
+            .filter(|day| !day.is_empty())
+        {
+            let (date, ranges) = split_date(day)
+                .ok_or_else(|| invalid_argument(format!("Invalid trading hours: {}", day)))?;
+            if ranges == "CLOSED" {
+                continue;
+            }
+            for range in ranges.split(',') {
+                let invalid = || invalid_argument(format!("Invalid trading hours: {}", range));
+                let mut bounds = range.splitn(2, '-');
+                let start =
+                    parse_bound(date, bounds.next().ok_or_else(invalid)?).ok_or_else(invalid)?;
+                let mut end =
+                    parse_bound(date, bounds.next().ok_or_else(invalid)?).ok_or_else(invalid)?;
+                if end <= start {
+                    end += Duration::days(1);
+                }
+                sessions.push(Session::new(
+                    local_to_utc(&time_zone, &start),
+                    local_to_utc(&time_zone, &end),
+                ));
+            }
+        }
+        Ok(SessionCalendar::new(time_zone, sessions))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the session trading at a time, if any
+    pub fn session_at(&self, time: &DateTime<Utc>) -> Option<&Session> {
+        self.sessions.iter().find(|session| session.contains(time))
+    }
+
+    //----------------------------------------------------------------------------------------------
+    pub fn is_open(&self, time: &DateTime<Utc>) -> bool {
+        self.session_at(time).is_some()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    /// Returns the first session starting after a time
+    pub fn next_session(&self, time: &DateTime<Utc>) -> Option<&Session> {
+        self.sessions.iter().find(|session| session.start > *time)
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+/// Splits "20180323:0400-..." in its date and the rest
+fn split_date(day: &str) -> Option<(NaiveDate, &str)> {
+    let (date, rest) = day.split_at(day.find(':')?);
+    Some((NaiveDate::parse_from_str(date, "%Y%m%d").ok()?, &rest[1..]))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Parses "0400" as a time of date, or "20180323:2000" as a date and time
+fn parse_bound(date: NaiveDate, bound: &str) -> Option<NaiveDateTime> {
+    let (date, time) = match split_date(bound) {
+        Some((date, time)) => (date, time),
+        None => (date, bound),
+    };
+    Some(date.and_time(NaiveTime::parse_from_str(time, "%H%M").ok()?))
+}
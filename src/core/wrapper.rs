@@ -12,7 +12,8 @@ use crate::core::common::{
 };
 use crate::core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract};
 use crate::core::execution::Execution;
-use crate::core::order::{Order, OrderState, SoftDollarTier};
+use crate::core::fa::FaConfig;
+use crate::core::order::{Order, OrderState, OrderStatus, SoftDollarTier};
 
 /// A trait that clients will implement that declares callback functions that get called when the application receives messages from the Trader WorkStation or IB Gateway
 pub trait Wrapper: Send + Sync {
@@ -93,11 +94,12 @@ pub trait Wrapper: Send + Sync {
     /// # Arguments
     /// * order_id - The order ID that was specified previously in the
     ///            call to placeOrder()
-    /// * status - The order status. Possible values include:
+    /// * status - The order status. Statuses not listed below are passed as OrderStatus::Unknown. Possible values include:
     ///     * PendingSubmit - indicates that you have transmitted the order, but have not  yet received confirmation that it has been accepted by the order destination. NOTE: This order status is not sent by TWS and should be explicitly set by the API developer when an order is submitted.
     ///     * PendingCancel - indicates that you have sent a request to cancel the order but have not yet received cancel confirmation from the order destination. At this point, your order is not confirmed canceled. You may still receive an execution while your cancellation request is pending. NOTE: This order status is not sent by TWS and should be explicitly set by the API developer when an order is canceled.
     ///     * PreSubmitted - indicates that a simulated order type has been accepted by the IB system and that this order has yet to be elected. The order is held in the IB system until the election criteria are met. At that time the order is transmitted to the order destination as specified.
     ///     * Submitted - indicates that your order has been accepted at the order destination and is working.
+    ///     * ApiCancelled - after an order has been submitted and before it has been acknowledged, an API client client can request its cancelation, producing this state.
     ///     * Cancelled - indicates that the balance of your order has been confirmed canceled by the IB system. This could occur unexpectedly when IB or the destination has rejected your order.
     ///     * Filled - indicates that the order has been completely filled.
     ///     * Inactive - indicates that the order has been accepted by the system (simulated orders) or an exchange (native orders) but that currently the order is inactive due to system, exchange or other issues.
@@ -113,7 +115,7 @@ pub trait Wrapper: Send + Sync {
     fn order_status(
         &mut self,
         order_id: i32,
-        status: &str,
+        status: OrderStatus,
         filled: f64,
         remaining: f64,
         avg_fill_price: f64,
@@ -299,7 +301,8 @@ pub trait Wrapper: Send + Sync {
     ///     * Account Aliases: let you easily identify the accounts by meaningful
     ///               names rather than account numbers.
     ///     * faXmlData -  the xml-formatted configuration
-    fn receive_fa(&mut self, fa_data: FaDataType, cxml: &str);
+    /// * config - the configuration parsed from the XML, or None if it could not be parsed
+    fn receive_fa(&mut self, fa_data: FaDataType, cxml: &str, config: Option<FaConfig>);
 
     //----------------------------------------------------------------------------------------------
     ///  returns the requested historical data bars
@@ -0,0 +1,73 @@
+//! Helpers to read the XML documents sent by TWS, such as scanner parameters and fundamental data
+use roxmltree::{Document, Node};
+
+use crate::core::common::NO_VALID_ID;
+use crate::core::errors::IBKRApiLibError;
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn invalid_xml(message: String) -> IBKRApiLibError {
+    IBKRApiLibError::invalid_argument(NO_VALID_ID, &format!("Invalid XML document: {}", message))
+}
+
+/// Parses a document and checks that its root element is the one expected
+pub(crate) fn parse_document<'a>(
+    xml: &'a str,
+    root: &str,
+) -> Result<Document<'a>, IBKRApiLibError> {
+    let document = Document::parse(xml).map_err(|err| invalid_xml(err.to_string()))?;
+    let name = document.root_element().tag_name().name();
+    if name != root {
+        return Err(invalid_xml(format!(
+            "expected a {} document, got {}",
+            root, name
+        )));
+    }
+    Ok(document)
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
+
+//----------------------------------------------------------------------------------------------
+/// Returns the trimmed text of a node, or None if it is empty
+pub(crate) fn text(node: Node) -> Option<String> {
+    let text = node.text()?.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+//----------------------------------------------------------------------------------------------
+pub(crate) fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(text)
+}
+
+//----------------------------------------------------------------------------------------------
+/// Escapes the characters of a text which are special in XML
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -16,7 +16,8 @@ use crate::core::common::{
 };
 use crate::core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract};
 use crate::core::execution::Execution;
-use crate::core::order::{Order, OrderState, SoftDollarTier};
+use crate::core::fa::FaConfig;
+use crate::core::order::{Order, OrderState, OrderStatus, SoftDollarTier};
 use crate::core::wrapper::Wrapper;
 
 //==================================================================================================
@@ -130,7 +131,7 @@ impl Wrapper for DefaultWrapper {
     fn order_status(
         &mut self,
         order_id: i32,
-        status: &str,
+        status: OrderStatus,
         filled: f64,
         remaining: f64,
         avg_fill_price: f64,
@@ -310,8 +311,11 @@ impl Wrapper for DefaultWrapper {
     }
 
     //----------------------------------------------------------------------------------------------
-    fn receive_fa(&mut self, fa_data: FaDataType, cxml: &str) {
-        info!("receive_fa -- fa_data: {}, cxml: {}", fa_data, cxml);
+    fn receive_fa(&mut self, fa_data: FaDataType, cxml: &str, config: Option<FaConfig>) {
+        info!(
+            "receive_fa -- fa_data: {}, cxml: {}, config: {:?}",
+            fa_data, cxml, config
+        );
     }
 
     //----------------------------------------------------------------------------------------------
@@ -1,89 +1,60 @@
-//! Example FA allocation XML request data
+//! Example FA allocation configurations
 
-///Replace all with your own accountIds
+use crate::core::fa::{
+    FaAllocation, FaAllocationMethod, FaConfig, FaGroup, FaProfile, FaProfileType,
+};
 
 //==================================================================================================
-pub const FA_ONE_GROUP: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?> \
-                                <ListOfGroups> \
-                                <Group> \
-                                <name>Equal_Quantity</name> \
-                                <ListOfAccts varName=\"list\"> \
-                                <String>DU119915</String> \
-                                <String>DU119916</String> \
-                                </ListOfAccts> \
-                                <defaultMethod>EqualQuantity</defaultMethod> \
-                                </Group> \
-                                </ListOfGroups>";
+/// A group allocating the same quantity to each of the accounts
+pub fn fa_one_group(accounts: &[&str]) -> FaConfig {
+    FaConfig::Groups(vec![FaGroup::new(
+        "Equal_Quantity",
+        accounts,
+        FaAllocationMethod::EqualQuantity,
+    )])
+}
 
 //==================================================================================================
-pub const FA_TWO_GROUPS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?> \
-                            <ListOfGroups> \
-                            <Group> \
-                            <name>Equal_Quantity</name> \
-                            <ListOfAccts varName=\"list\"> \
-                            <String>DU119915</String> \
-                            <String>DU119916</String> \
-                            </ListOfAccts> \
-                            <defaultMethod>EqualQuantity</defaultMethod> \
-                            </Group> \
-                            <Group> \
-                            <name>Pct_Change</name> \
-                            <ListOfAccts varName=\"list\">
-                            <String>DU119915</String> \
-                            <String>DU119916</String> \
-                            </ListOfAccts> \
-                            <defaultMethod>PctChange</defaultMethod> \
-                            </Group> \
-                            </ListOfGroups>";
+pub fn fa_two_groups(accounts: &[&str]) -> FaConfig {
+    FaConfig::Groups(vec![
+        FaGroup::new(
+            "Equal_Quantity",
+            accounts,
+            FaAllocationMethod::EqualQuantity,
+        ),
+        FaGroup::new("Pct_Change", accounts, FaAllocationMethod::PctChange),
+    ])
+}
 
 //==================================================================================================
-pub const FA_ONE_PROFILE: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?> \
-                                <ListOfAllocationProfiles> \
-                                <AllocationProfile> \
-                                <name>Percent_60_40</name> \
-                                <type>1</type> \
-                                <ListOfAllocations varName=\"listOfAllocations\"> \
-                                <Allocation>
-                                <acct>DU119915</acct> \
-                                <amount>60.0</amount> \
-                                </Allocation> \
-                                <Allocation> \
-                                <acct>DU119916</acct> \
-                                <amount>40.0</amount> \
-                                </Allocation> \
-                                </ListOfAllocations> \
-                                </AllocationProfile> \
-                                </ListOfAllocationProfiles>";
+/// A profile allocating 60% to the first account and 40% to the second
+pub fn fa_one_profile(first_account: &str, second_account: &str) -> FaConfig {
+    FaConfig::Profiles(vec![percent_60_40(first_account, second_account)])
+}
 
 //==================================================================================================
-pub const FA_TWO_PROFILES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?> \
-                                <ListOfAllocationProfiles> \
-                                <AllocationProfile> \
-                                <name>Percent_60_40</name> \
-                                <type>1</type> \
-                                <ListOfAllocations varName=\"listOfAllocations\"> \
-                                <Allocation> \
-                                <acct>DU119915</acct> \
-                                <amount>60.0</amount> \
-                                </Allocation> \
-                                <Allocation> \
-                                <acct>DU119916</acct> \
-                                <amount>40.0</amount> \
-                                </Allocation> \
-                                </ListOfAllocations> \
-                                </AllocationProfile> \
-                                <AllocationProfile> \
-                                <name>Ratios_2_1</name> \
-                                <type>1</type> \
-                                <ListOfAllocations varName=\"listOfAllocations\"> \
-                                <Allocation> \
-                                <acct>DU119915</acct> \
-                                <amount>2.0</amount> \
-                                </Allocation> \
-                                <Allocation>
-                                <acct>DU119916</acct> \
-                                <amount>1.0</amount> \
-                                </Allocation> \
-                                </ListOfAllocations> \
-                                </AllocationProfile> \
-                                </ListOfAllocationProfiles>";
+pub fn fa_two_profiles(first_account: &str, second_account: &str) -> FaConfig {
+    FaConfig::Profiles(vec![
+        percent_60_40(first_account, second_account),
+        FaProfile::new(
+            "Ratios_2_1",
+            FaProfileType::FinancialRatios,
+            vec![
+                FaAllocation::new(first_account, 2.0),
+                FaAllocation::new(second_account, 1.0),
+            ],
+        ),
+    ])
+}
+
+//----------------------------------------------------------------------------------------------
+fn percent_60_40(first_account: &str, second_account: &str) -> FaProfile {
+    FaProfile::new(
+        "Percent_60_40",
+        FaProfileType::Percentages,
+        vec![
+            FaAllocation::new(first_account, 60.0),
+            FaAllocation::new(second_account, 40.0),
+        ],
+    )
+}
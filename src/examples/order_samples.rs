@@ -1,5 +1,6 @@
 //! Examples of populating fields of various order types
 
+use chrono::NaiveDateTime;
 use num_traits::FromPrimitive;
 
 use crate::core::common::TagValue;
@@ -1051,12 +1052,12 @@ pub fn percentage_change_condition(
 }
 
 //==================================================================================================
-pub fn time_condition(time: &str, is_more: bool, is_conjunction: bool) -> TimeCondition {
+pub fn time_condition(time: NaiveDateTime, is_more: bool, is_conjunction: bool) -> TimeCondition {
     let mut time_condition: TimeCondition = create_condition(ConditionType::Time).into();
     // Before or after...
     time_condition.operator_condition.is_more = is_more;
     // this time..
-    time_condition.time = time.to_string();
+    time_condition.time = time;
     // AND | OR next condition (will be ignored if no more conditions are added)
     time_condition
         .operator_condition
@@ -10,9 +10,11 @@ use crate::{
     core::contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract},
     core::errors::IBKRApiLibError,
     core::execution::{Execution, ExecutionFilter},
+    core::fa::FaConfig,
+    core::generic_tick::{GenericTick, GenericTickList},
     core::{
         account_summary_tags::AccountSummaryTags,
-        order::{Order, OrderState, SoftDollarTier},
+        order::{Order, OrderState, OrderStatus, SoftDollarTier},
         order_condition::TriggerMethod,
         wrapper::Wrapper,
     },
@@ -33,7 +35,7 @@ use crate::{
 };
 use bigdecimal::BigDecimal;
 use chrono;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
 use log::*;
 use std::borrow::Borrow;
 use std::collections::HashSet;
@@ -43,6 +45,8 @@ use std::time::{Duration, UNIX_EPOCH};
 
 const CLIENT_POISONED_MUTEX: &str = "Client mutex was poisoned";
 const CLIENT_IS_NONE: &str = "Client must be assigned!";
+/// Accounts of the FA configuration samples, to replace with your own account ids
+const FA_ACCOUNTS: [&str; 2] = ["DU119915", "DU119916"];
 
 //==================================================================================================
 /// Example implementation of the Wrapper callback trait.  Just logs callback methods
@@ -775,7 +779,14 @@ impl<T: Streamer> TestWrapper<T> {
         );
         mkt.conditions
             .push(crate::core::order_condition::OrderConditionEnum::Time(
-                order_samples::time_condition("20160118 23:59:59", true, false),
+                order_samples::time_condition(
+                    NaiveDate::from_ymd_opt(2016, 1, 18)
+                        .unwrap()
+                        .and_hms_opt(23, 59, 59)
+                        .unwrap(),
+                    true,
+                    false,
+                ),
             ));
         mkt.conditions
             .push(crate::core::order_condition::OrderConditionEnum::Volume(
@@ -1397,8 +1408,7 @@ impl<T: Streamer> TestWrapper<T> {
                 1,
             )?;
 
-        let dt = Utc::now();
-        let query_time = dt.format("%Y%m%d %H:%M:%S").to_string();
+        let query_time = Utc::now();
         info!("Request Time:  {}", query_time);
         self.client
             .as_ref()
@@ -1408,7 +1418,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_data(
                 4102,
                 contract_samples::simple_future().borrow(),
-                query_time.as_str(),
+                Some(query_time),
                 "1 M",
                 "1 day",
                 "MIDPOINT",
@@ -1425,7 +1435,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_data(
                 4103,
                 contract_samples::simple_future().borrow(),
-                query_time.as_str(),
+                Some(query_time),
                 "10 D",
                 "1 min",
                 "TRADES",
@@ -1442,7 +1452,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_data(
                 4104,
                 contract_samples::eur_gbp_fx().borrow(),
-                "",
+                None,
                 "1 M",
                 "1 day",
                 "MIDPOINT",
@@ -1489,7 +1499,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1000,
                 contract_samples::us_stock_at_smart().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -1502,7 +1512,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1001,
                 contract_samples::stock_combo_contract().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -1516,7 +1526,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1002,
                 contract_samples::future_combo_contract().borrow(),
-                "",
+                &GenericTickList::default(),
                 true,
                 false,
                 vec![],
@@ -1531,7 +1541,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1003,
                 contract_samples::usstock().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 true,
                 vec![],
@@ -1546,7 +1556,11 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1004,
                 contract_samples::us_stock_at_smart().borrow(),
-                "233,236,258",
+                &GenericTickList::new(&[
+                    GenericTick::RtVolume,
+                    GenericTick::Shortable,
+                    GenericTick::FundamentalRatios,
+                ]),
                 false,
                 false,
                 vec![],
@@ -1561,7 +1575,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1005,
                 contract_samples::us_stock_at_smart().borrow(),
-                "100",
+                &GenericTickList::new(&[GenericTick::OptionVolume]),
                 false,
                 false,
                 vec![],
@@ -1574,7 +1588,9 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1006,
                 contract_samples::us_stock_at_smart().borrow(),
-                "mdoff,292:BRFG+DJNL",
+                &GenericTickList::default()
+                    .news(&["BRFG", "DJNL"])
+                    .market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1587,7 +1603,9 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1007,
                 contract_samples::us_stock_at_smart().borrow(),
-                "mdoff,292:BRFUPDN",
+                &GenericTickList::default()
+                    .news(&["BRFUPDN"])
+                    .market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1600,7 +1618,9 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1008,
                 contract_samples::us_stock_at_smart().borrow(),
-                "mdoff,292:DJ-RT",
+                &GenericTickList::default()
+                    .news(&["DJ-RT"])
+                    .market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1614,7 +1634,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1009,
                 contract_samples::brfgbroadtape_news_feed().borrow(),
-                "mdoff,292",
+                &GenericTickList::new(&[GenericTick::News]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1627,7 +1647,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1010,
                 contract_samples::djnlbroadtape_news_feed().borrow(),
-                "mdoff,292",
+                &GenericTickList::new(&[GenericTick::News]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1640,7 +1660,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1011,
                 contract_samples::djtopbroadtape_news_feed().borrow(),
-                "mdoff,292",
+                &GenericTickList::new(&[GenericTick::News]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1653,7 +1673,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1012,
                 contract_samples::brfupdnbroadtape_news_feed().borrow(),
-                "mdoff,292",
+                &GenericTickList::new(&[GenericTick::News]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1668,7 +1688,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1013,
                 contract_samples::option_with_local_symbol().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -1681,7 +1701,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1014,
                 contract_samples::futures_on_options().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -1695,7 +1715,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1015,
                 contract_samples::simple_future().borrow(),
-                "mdoff,588",
+                &GenericTickList::new(&[GenericTick::FuturesOpenInterest]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -1709,7 +1729,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1016,
                 contract_samples::simple_future().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -1723,7 +1743,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 1017,
                 contract_samples::us_stock_at_smart().borrow(),
-                "mdoff,105",
+                &GenericTickList::new(&[GenericTick::AverageOptionVolume]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -2154,8 +2174,8 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_ticks(
                 18001,
                 contract_samples::usstock().borrow(),
-                "20170712 21:39:33",
-                "",
+                Some(Utc.with_ymd_and_hms(2017, 7, 13, 1, 39, 33).unwrap()),
+                None,
                 10,
                 "TRADES",
                 1,
@@ -2170,8 +2190,8 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_ticks(
                 18002,
                 contract_samples::us_stock_at_smart().borrow(),
-                "20170712 21:39:33",
-                "",
+                Some(Utc.with_ymd_and_hms(2017, 7, 13, 1, 39, 33).unwrap()),
+                None,
                 10,
                 "BID_ASK",
                 1,
@@ -2186,8 +2206,8 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_ticks(
                 18003,
                 contract_samples::us_stock_at_smart().borrow(),
-                "20170712 21:39:33",
-                "",
+                Some(Utc.with_ymd_and_hms(2017, 7, 13, 1, 39, 33).unwrap()),
+                None,
                 10,
                 "MIDPOINT",
                 1,
@@ -2232,7 +2252,7 @@ impl<T: Streamer> TestWrapper<T> {
             .expect(CLIENT_POISONED_MUTEX)
             .req_contract_details(18001, contract_samples::cont_fut().borrow())?;
 
-        let time_str = Utc::now().format("%Y%m%d %H:%M:%S");
+        let time = Utc::now();
         self.client
             .as_ref()
             .expect(CLIENT_IS_NONE)
@@ -2241,7 +2261,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_historical_data(
                 18002,
                 contract_samples::cont_fut().borrow(),
-                time_str.to_string().as_str(),
+                Some(time),
                 "1 Y",
                 "1 month",
                 "TRADES",
@@ -2337,7 +2357,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 16001,
                 contract_samples::usstock_cfd().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -2350,7 +2370,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 16002,
                 contract_samples::european_stock_cfd().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -2363,7 +2383,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 16003,
                 contract_samples::cash_cfd().borrow(),
-                "",
+                &GenericTickList::default(),
                 false,
                 false,
                 vec![],
@@ -2435,35 +2455,41 @@ impl<T: Streamer> TestWrapper<T> {
             .expect(CLIENT_POISONED_MUTEX)
             .request_fa(FaDataType::PROFILES)?;
 
-        // Replacing FA information - Fill in with the appropriate XML string.
+        // Replacing FA information - Fill in FA_ACCOUNTS with your own account ids.
 
         self.client
             .as_ref()
             .expect(CLIENT_IS_NONE)
             .lock()
             .expect(CLIENT_POISONED_MUTEX)
-            .replace_fa(FaDataType::GROUPS, fa_allocation_samples::FA_ONE_GROUP)?;
+            .replace_fa(&fa_allocation_samples::fa_one_group(&FA_ACCOUNTS))?;
 
         self.client
             .as_ref()
             .expect(CLIENT_IS_NONE)
             .lock()
             .expect(CLIENT_POISONED_MUTEX)
-            .replace_fa(FaDataType::GROUPS, fa_allocation_samples::FA_TWO_GROUPS)?;
+            .replace_fa(&fa_allocation_samples::fa_two_groups(&FA_ACCOUNTS))?;
 
         self.client
             .as_ref()
             .expect(CLIENT_IS_NONE)
             .lock()
             .expect(CLIENT_POISONED_MUTEX)
-            .replace_fa(FaDataType::PROFILES, fa_allocation_samples::FA_ONE_PROFILE)?;
+            .replace_fa(&fa_allocation_samples::fa_one_profile(
+                FA_ACCOUNTS[0],
+                FA_ACCOUNTS[1],
+            ))?;
 
         self.client
             .as_ref()
             .expect(CLIENT_IS_NONE)
             .lock()
             .expect(CLIENT_POISONED_MUTEX)
-            .replace_fa(FaDataType::PROFILES, fa_allocation_samples::FA_TWO_PROFILES)?;
+            .replace_fa(&fa_allocation_samples::fa_two_profiles(
+                FA_ACCOUNTS[0],
+                FA_ACCOUNTS[1],
+            ))?;
 
         self.client
             .as_ref()
@@ -2487,7 +2513,7 @@ impl<T: Streamer> TestWrapper<T> {
             .req_mkt_data(
                 10001,
                 contract_samples::usstock().borrow(),
-                "mdoff,258",
+                &GenericTickList::new(&[GenericTick::FundamentalRatios]).market_data_off(),
                 false,
                 false,
                 vec![],
@@ -3117,7 +3143,7 @@ where
     fn order_status(
         &mut self,
         order_id: i32,
-        status: &str,
+        status: OrderStatus,
         filled: f64,
         remaining: f64,
         avg_fill_price: f64,
@@ -3303,8 +3329,11 @@ where
     }
 
     //----------------------------------------------------------------------------------------------
-    fn receive_fa(&mut self, fa_data: FaDataType, cxml: &str) {
-        info!("receive_fa -- fa_data: {}, cxml: {}", fa_data, cxml);
+    fn receive_fa(&mut self, fa_data: FaDataType, cxml: &str, config: Option<FaConfig>) {
+        info!(
+            "receive_fa -- fa_data: {}, cxml: {}, config: {:?}",
+            fa_data, cxml, config
+        );
     }
 
     //----------------------------------------------------------------------------------------------
@@ -0,0 +1,50 @@
+//! Factories of the contracts, executions and commission reports shared by the tests
+#![cfg(test)]
+use chrono::{TimeZone, Utc};
+
+use crate::core::common::{CommissionReport, UNSET_DOUBLE};
+use crate::core::contract::Contract;
+use crate::core::execution::Execution;
+
+//------------------------------------------------------------------------------------------------
+/// Returns a USD stock
+pub(crate) fn contract(con_id: i32, symbol: &str) -> Contract {
+    let mut contract = Contract::default();
+    contract.con_id = con_id;
+    contract.symbol = symbol.to_string();
+    contract.sec_type = "STK".to_string();
+    contract.currency = "USD".to_string();
+    contract
+}
+
+//------------------------------------------------------------------------------------------------
+/// Returns a fill of account DU1
+pub(crate) fn execution(exec_id: &str, side: &str, shares: f64, price: f64) -> Execution {
+    let mut execution = Execution::default();
+    execution.exec_id = exec_id.to_string();
+    execution.acct_number = "DU1".to_string();
+    execution.side = side.to_string();
+    execution.shares = shares;
+    execution.price = price;
+    execution
+}
+
+//------------------------------------------------------------------------------------------------
+/// Returns the execution, filled on 2020-03-02 at a time of day in UTC
+pub(crate) fn executed_at(mut execution: Execution, hour: u32, minute: u32) -> Execution {
+    execution.time = Utc.with_ymd_and_hms(2020, 3, 2, hour, minute, 0).unwrap();
+    execution
+}
+
+//------------------------------------------------------------------------------------------------
+/// Returns a USD commission report without realized P&L
+pub(crate) fn commission(exec_id: &str, commission: f64) -> CommissionReport {
+    CommissionReport::new(
+        exec_id.to_string(),
+        commission,
+        "USD".to_string(),
+        UNSET_DOUBLE,
+        UNSET_DOUBLE,
+        "".to_string(),
+    )
+}
@@ -1,2 +1,23 @@
+pub(crate) mod helpers;
 pub(crate) mod test_eclient;
 pub(crate) mod test_messages;
+pub(crate) mod test_order;
+pub(crate) mod test_order_condition;
+pub(crate) mod test_algo_strategy;
+pub(crate) mod test_market_data;
+pub(crate) mod test_market_depth;
+pub(crate) mod test_bar_builder;
+pub(crate) mod test_historical_data;
+pub(crate) mod test_timestamps;
+pub(crate) mod test_generic_tick;
+pub(crate) mod test_scanner;
+pub(crate) mod test_account;
+pub(crate) mod test_account_value;
+pub(crate) mod test_fa;
+pub(crate) mod test_trade_blotter;
+pub(crate) mod test_execution;
+pub(crate) mod test_pnl;
+pub(crate) mod test_option_chain;
+pub(crate) mod test_option_pricing;
+#[cfg(feature = "fundamentals")]
+pub(crate) mod test_fundamentals;
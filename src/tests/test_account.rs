@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::account::{AccountEvent, AccountKey, AccountPosition, AccountStore};
+    use crate::core::common::UNSET_DOUBLE;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::fa::{FaAllocationMethod, FaGroup};
+    use crate::tests::helpers::contract;
+
+    const FA_GROUPS: &str = include_str!("fixtures/fa_groups.xml");
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_updates() {
+        let store = AccountStore::new();
+        let events = store.subscribe();
+        store.account_updates_requested("DU123");
+
+        store.update_account_value("NetLiquidation", "100000.5", "USD", "DU123");
+        store.update_account_value("CashBalance", "2500", "EUR", "DU123");
+        store.update_account_value("CashBalance", "-300", "USD", "DU123");
+        store.update_account_value("AccountType", "INDIVIDUAL", "", "DU123");
+        store.update_portfolio(
+            "DU123",
+            AccountPosition::from_portfolio(
+                contract(265598, "AAPL"),
+                100.0,
+                180.0,
+                18000.0,
+                150.0,
+                3000.0,
+                UNSET_DOUBLE,
+            ),
+        );
+        store.update_account_time("15:32");
+        store.account_download_end("DU123");
+
+        let key = AccountKey::account("DU123");
+        let snapshot = store.snapshot(&key).unwrap();
+        assert_eq!(Some(100000.5), snapshot.balance("NetLiquidation", "USD"));
+        assert_eq!(Some("INDIVIDUAL"), snapshot.value("AccountType", ""));
+        let cash = snapshot.balances("CashBalance");
+        assert_eq!(2, cash.len());
+        assert_eq!(Some(&2500.0), cash.get("EUR"));
+        assert_eq!(Some(&-300.0), cash.get("USD"));
+
+        let aapl = snapshot.position(265598).unwrap();
+        assert_eq!("AAPL", aapl.contract.symbol);
+        assert_eq!(Some(18000.0), aapl.market_value);
+        assert_eq!(Some(3000.0), aapl.unrealized_pnl);
+        assert_eq!(None, aapl.realized_pnl);
+        assert_eq!("15:32", snapshot.account_time);
+        assert!(snapshot.download_complete);
+        assert!(snapshot.last_update.is_some());
+
+        let received: Vec<AccountEvent> = events.try_iter().collect();
+        assert_eq!(7, received.len());
+        assert!(
+            matches!(&received[4], AccountEvent::Position { position, .. }
+            if position.position == 100.0)
+        );
+        assert!(
+            matches!(&received[5], AccountEvent::Time { account_time, .. }
+            if account_time == "15:32")
+        );
+        assert!(matches!(&received[6], AccountEvent::DownloadEnd { key }
+            if key.account == "DU123"));
+
+        store.update_portfolio(
+            "DU123",
+            AccountPosition::from_portfolio(
+                contract(265598, "AAPL"),
+                0.0,
+                180.0,
+                0.0,
+                0.0,
+                0.0,
+                200.0,
+            ),
+        );
+        assert!(store.snapshot(&key).unwrap().positions.is_empty());
+        assert!(
+            matches!(events.try_recv().unwrap(), AccountEvent::Position { position, .. }
+            if position.position == 0.0 && position.realized_pnl == Some(200.0))
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_positions_and_models() {
+        let store = AccountStore::new();
+        store.update_position(AccountKey::account("DU1"), contract(1, "AAA"), 10.0, 5.0);
+        store.update_position(AccountKey::account("DU2"), contract(1, "AAA"), -3.0, 6.0);
+        store.update_position(
+            AccountKey::new("DU1", "GROWTH"),
+            contract(2, "BBB"),
+            7.0,
+            8.0,
+        );
+        store.update_account_multi("DU1", "GROWTH", "NetLiquidation", "5000", "USD");
+        store.update_account_summary("DU2", "BuyingPower", "40000", "USD");
+
+        assert_eq!(
+            vec![
+                AccountKey::account("DU1"),
+                AccountKey::new("DU1", "GROWTH"),
+                AccountKey::account("DU2"),
+            ],
+            store.accounts()
+        );
+        assert_eq!("DU1/GROWTH", AccountKey::new("DU1", "GROWTH").to_string());
+
+        let snapshots = store.snapshots();
+        assert_eq!(3, snapshots.len());
+        assert_eq!(Some(5000.0), snapshots[1].balance("NetLiquidation", "USD"));
+        assert_eq!(Some(7.0), snapshots[1].position(2).map(|p| p.position));
+        assert_eq!(Some(40000.0), snapshots[2].balance("BuyingPower", "USD"));
+        assert_eq!(Some(-3.0), snapshots[2].position(1).map(|p| p.position));
+        assert_eq!(None, snapshots[2].position(1).unwrap().market_value);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_pnl() {
+        let store = AccountStore::new();
+        let key = AccountKey::account("DU1");
+        store.update_position(key.clone(), contract(1, "AAA"), 10.0, 5.0);
+
+        // Updates of requests the store does not know are ignored
+        store.update_pnl(9001, 10.0, 20.0, 30.0);
+        assert_eq!(None, store.snapshot(&key).unwrap().pnl.daily);
+
+        store.expect_pnl(9001, key.clone(), None);
+        store.expect_pnl(9002, key.clone(), Some(1));
+        store.update_pnl(9001, 10.0, 20.0, UNSET_DOUBLE);
+        store.update_pnl_single(9002, 10, 1.5, 2.5, 3.5, 60.0);
+
+        let snapshot = store.snapshot(&key).unwrap();
+        assert_eq!(Some(10.0), snapshot.pnl.daily);
+        assert_eq!(Some(20.0), snapshot.pnl.unrealized);
+        assert_eq!(None, snapshot.pnl.realized);
+        let position = snapshot.position(1).unwrap();
+        assert_eq!("AAA", position.contract.symbol);
+        assert_eq!(5.0, position.average_cost);
+        assert_eq!(Some(1.5), position.daily_pnl);
+        assert_eq!(Some(60.0), position.market_value);
+
+        store.forget_pnl(9001);
+        store.update_pnl(9001, 99.0, 99.0, 99.0);
+        assert_eq!(Some(10.0), store.snapshot(&key).unwrap().pnl.daily);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_rollups() -> Result<(), IBKRApiLibError> {
+        let store = AccountStore::new();
+        let groups = FaGroup::parse_list(FA_GROUPS)?;
+        assert_eq!(2, groups.len());
+        assert_eq!(
+            vec!["DU1".to_string(), "DU2".to_string()],
+            groups[0].accounts
+        );
+        assert_eq!(
+            FaAllocationMethod::AvailableEquity,
+            groups[1].default_method
+        );
+        store.update_fa_groups(groups);
+
+        store.update_position(AccountKey::account("DU1"), contract(1, "AAA"), 10.0, 5.0);
+        store.update_position(AccountKey::account("DU2"), contract(1, "AAA"), 30.0, 9.0);
+        store.update_position(AccountKey::account("DU3"), contract(2, "BBB"), 4.0, 2.0);
+        // Positions of the models of DU1 are already counted in its own positions
+        store.update_position(
+            AccountKey::new("DU1", "GROWTH"),
+            contract(1, "AAA"),
+            6.0,
+            5.0,
+        );
+        store.update_position(
+            AccountKey::new("DU4", "GROWTH"),
+            contract(1, "AAA"),
+            2.0,
+            4.0,
+        );
+        store.expect_pnl(1, AccountKey::account("DU1"), None);
+        store.expect_pnl(2, AccountKey::account("DU2"), None);
+        store.update_pnl(1, 10.0, 100.0, UNSET_DOUBLE);
+        store.update_pnl(2, -4.0, UNSET_DOUBLE, UNSET_DOUBLE);
+
+        let rollups = store.rollups();
+        assert_eq!(4, rollups.by_account.len());
+        assert_eq!(
+            Some(10.0),
+            rollups.by_account["DU1"].position(1).map(|p| p.position)
+        );
+        assert_eq!(
+            Some(2.0),
+            rollups.by_account["DU4"].position(1).map(|p| p.position)
+        );
+
+        let growth = &rollups.by_model["GROWTH"];
+        assert_eq!(2, growth.keys.len());
+        assert_eq!(Some(8.0), growth.position(1).map(|p| p.position));
+
+        let smith = &rollups.by_household["Smith"];
+        assert_eq!(
+            vec![AccountKey::account("DU1"), AccountKey::account("DU2")],
+            smith.keys
+        );
+        let aaa = smith.position(1).unwrap();
+        assert_eq!(40.0, aaa.position);
+        assert_eq!(8.0, aaa.average_cost);
+        assert_eq!(Some(6.0), smith.pnl.daily);
+        assert_eq!(Some(100.0), smith.pnl.unrealized);
+        assert_eq!(None, smith.pnl.realized);
+        assert_eq!(
+            Some(4.0),
+            rollups.by_household["Jones"]
+                .position(2)
+                .map(|p| p.position)
+        );
+        Ok(())
+    }
+}
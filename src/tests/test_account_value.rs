@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::account::{AccountEvent, AccountKey, AccountStore};
+    use crate::core::account_value::{
+        AccountSegment, AccountValue, AccountValueKey, AccountValueName,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_value_name() {
+        let name = AccountValueName::parse("NetLiquidation-S");
+        assert_eq!(AccountValueKey::NetLiquidation, name.key);
+        assert_eq!(AccountSegment::Securities, name.segment);
+        assert_eq!("NetLiquidation-S", name.to_string());
+
+        let name = AccountValueName::parse("DayTradesRemainingT+1");
+        assert_eq!(AccountValueKey::DayTradesRemainingT1, name.key);
+        assert_eq!(AccountSegment::Total, name.segment);
+
+        let name = AccountValueName::parse("SomeNewKey-C");
+        assert_eq!(AccountValueKey::Other("SomeNewKey".to_string()), name.key);
+        assert_eq!(AccountSegment::Commodities, name.segment);
+        assert_eq!("SomeNewKey-C", name.to_string());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_account_value_parse() {
+        let parse = |name: &str, value: &str, currency: &str| {
+            AccountValue::parse(&AccountValueName::parse(name), value, currency)
+        };
+        assert_eq!(
+            AccountValue::Amount {
+                value: 1250.5,
+                currency: "BASE".to_string()
+            },
+            parse("CashBalance", "1250.50", "BASE")
+        );
+        assert_eq!(AccountValue::Number(0.93), parse("Cushion", "0.93", ""));
+        assert_eq!(
+            AccountValue::Number(1.1),
+            parse("ExchangeRate", "1.1", "EUR")
+        );
+        assert_eq!(
+            AccountValue::Boolean(true),
+            parse("AccountReady", "true", "")
+        );
+        assert_eq!(
+            AccountValue::Text("USD".to_string()),
+            parse("Currency", "USD", "USD")
+        );
+        assert_eq!(
+            AccountValue::Text("".to_string()),
+            parse("NetLiquidation-C", "", "USD")
+        );
+        assert_eq!(
+            AccountValue::Amount {
+                value: 3.0,
+                currency: "USD".to_string()
+            },
+            parse("Unheard", "3", "USD")
+        );
+        assert_eq!(
+            AccountValue::Text("x".to_string()),
+            parse("Unheard", "x", "")
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_typed_account_values() {
+        let store = AccountStore::new();
+        let events = store.subscribe();
+        store.update_account_summary("DU123", "TotalCashBalance", "500", "EUR");
+        store.update_account_summary("DU123", "SegmentTitle-S", "US Securities", "");
+        store.update_account_summary("DU123", "Unheard", "7", "");
+
+        match events.try_recv().unwrap() {
+            AccountEvent::Value { name, value, .. } => {
+                assert_eq!(
+                    AccountValueName::from(AccountValueKey::TotalCashBalance),
+                    name
+                );
+                assert_eq!(Some(500.0), value.as_f64());
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let snapshot = store.snapshot(&AccountKey::account("DU123")).unwrap();
+        assert_eq!(
+            Some(AccountValue::Text("US Securities".to_string())),
+            snapshot.typed_value(&AccountValueName::parse("SegmentTitle-S"), "")
+        );
+        let values = snapshot.typed_values();
+        assert_eq!(3, values.len());
+        assert!(values.iter().any(|(name, _, value)| {
+            name.key == AccountValueKey::Other("Unheard".to_string())
+                && *value == AccountValue::Number(7.0)
+        }));
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::core::algo_params::{
+        fill_accumulate_distribute_params, fill_arrival_price_params, fill_jefferies_vwapparams,
+        fill_vwap_params,
+    };
+    use crate::core::algo_strategy::{
+        AccumulateDistributeParams, AdaptiveParams, AdaptivePriority, AlgoStrategy, AlgoTime,
+        ArrivalPriceParams, MinImpactParams, PriceVariantPctVolParams, RiskAversion,
+        TimeVariantPctVolParams, TwapParams, TwapStrategyType, VariantPctVolParams, VwapParams,
+    };
+    use crate::core::common::TagValue;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::order::Order;
+
+    //------------------------------------------------------------------------------------------------
+    fn time_of_day(hour: u32, time_zone: &str) -> Option<AlgoTime> {
+        Some(AlgoTime::new(
+            None,
+            NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+            Some(time_zone.to_string()),
+        ))
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_algo_round_trip() -> Result<(), IBKRApiLibError> {
+        let algos: Vec<AlgoStrategy> = vec![
+            AdaptiveParams {
+                priority: AdaptivePriority::Patient,
+            }
+            .into(),
+            TwapParams {
+                strategy_type: TwapStrategyType::MatchingMidpoint,
+                start_time: time_of_day(9, "US/Eastern"),
+                end_time: time_of_day(16, "US/Eastern"),
+                allow_past_end_time: true,
+                monetary_value: None,
+            }
+            .into(),
+            PriceVariantPctVolParams {
+                pct_vol: 0.1,
+                delta_pct_vol: Some(0.05),
+                min_pct_vol_4px: Some(0.01),
+                max_pct_vol_4px: Some(0.2),
+                start_time: None,
+                end_time: None,
+                no_take_liq: true,
+                monetary_value: Some(100000.0),
+            }
+            .into(),
+            TimeVariantPctVolParams(VariantPctVolParams {
+                start_pct_vol: 0.2,
+                end_pct_vol: 0.4,
+                start_time: time_of_day(12, "EST"),
+                end_time: time_of_day(14, "EST"),
+                no_take_liq: false,
+                monetary_value: None,
+            })
+            .into(),
+            MinImpactParams { max_pct_vol: 0.3 }.into(),
+            AlgoStrategy::Other {
+                strategy: "STROBE".to_string(),
+                params: vec![TagValue::new(
+                    "benchmark".to_string(),
+                    "Arrival".to_string(),
+                )],
+            },
+        ];
+
+        for algo in algos {
+            let mut order = Order::default();
+            algo.apply_to(&mut order)?;
+            assert_eq!(algo.strategy(), order.algo_strategy);
+            assert_eq!(Some(algo), AlgoStrategy::from_order(&order)?);
+        }
+        assert_eq!(None, AlgoStrategy::from_order(&Order::default())?);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_algo_from_filled_order() -> Result<(), IBKRApiLibError> {
+        let mut order = Order::default();
+        fill_arrival_price_params(
+            &mut order,
+            0.1,
+            "Aggressive",
+            "09:00:00 CET",
+            "16:00:00 CET",
+            true,
+            true,
+            100000,
+        );
+        assert_eq!(
+            Some(AlgoStrategy::ArrivalPrice(ArrivalPriceParams {
+                max_pct_vol: Some(0.1),
+                risk_aversion: RiskAversion::Aggressive,
+                start_time: time_of_day(9, "CET"),
+                end_time: time_of_day(16, "CET"),
+                force_completion: true,
+                allow_past_end_time: true,
+                monetary_value: Some(100000.0),
+            })),
+            AlgoStrategy::from_order(&order)?
+        );
+
+        let mut order = Order::default();
+        fill_accumulate_distribute_params(
+            &mut order,
+            10,
+            60,
+            true,
+            true,
+            1,
+            true,
+            true,
+            "20161010-12:00:00 GMT",
+            "20161010-16:00:00 GMT",
+        );
+        match AlgoStrategy::from_order(&order)? {
+            Some(AlgoStrategy::AccumulateDistribute(AccumulateDistributeParams {
+                component_size,
+                active_time_start: Some(start),
+                ..
+            })) => {
+                assert_eq!(10, component_size);
+                assert_eq!(NaiveDate::from_ymd_opt(2016, 10, 10), start.date);
+                assert_eq!("20161010-12:00:00 GMT", start.to_string());
+            }
+            other => panic!("expected accumulate/distribute params, got {:?}", other),
+        }
+
+        let mut order = Order::default();
+        fill_jefferies_vwapparams(
+            &mut order,
+            "10:00:00 EST",
+            "16:00:00 EST",
+            10.0,
+            10.0,
+            "Exclude_Both",
+            130.0,
+            135.0,
+            1,
+            0.4,
+            "Patient",
+            false,
+            "IBM",
+        );
+        match AlgoStrategy::from_order(&order)? {
+            Some(AlgoStrategy::Other { strategy, params }) => {
+                assert_eq!("VWAP", strategy);
+                assert_eq!(order.algo_params, params);
+            }
+            other => panic!("expected a third party algo, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_algo_validation() {
+        let vwap = VwapParams {
+            max_pct_vol: Some(20.0),
+            start_time: None,
+            end_time: None,
+            allow_past_end_time: false,
+            no_take_liq: false,
+            monetary_value: None,
+        };
+        assert!(AlgoStrategy::from(vwap.clone()).validate().is_err());
+
+        let mut order = Order::default();
+        let backwards = VwapParams {
+            max_pct_vol: Some(0.2),
+            start_time: time_of_day(16, "CET"),
+            end_time: time_of_day(9, "CET"),
+            ..vwap
+        };
+        assert!(AlgoStrategy::from(backwards).apply_to(&mut order).is_err());
+        assert!(order.algo_strategy.is_empty());
+
+        // Times in different time zones cannot be compared
+        let mut order = Order::default();
+        fill_vwap_params(
+            &mut order,
+            0.2,
+            "09:00:00 CET",
+            "08:00:00 EST",
+            true,
+            true,
+            100000.0,
+        );
+        assert!(AlgoStrategy::from_order(&order).is_ok());
+
+        order.algo_strategy = "Adaptive".to_string();
+        order.algo_params = vec![TagValue::new(
+            "adaptivePriority".to_string(),
+            "Fast".to_string(),
+        )];
+        assert!(AlgoStrategy::from_order(&order).is_err());
+
+        order.algo_params = vec![];
+        assert!(AlgoStrategy::from_order(&order).is_err());
+    }
+}
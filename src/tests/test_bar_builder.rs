@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::core::bar_builder::{BarBuilder, BarSize, Trade};
+    use crate::core::common::{BarData, RealTimeBar};
+    use crate::core::contract::ContractDetails;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::trading_hours::{parse_time_zone, SessionCalendar};
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(hour: u32, minute: u32, second: u32, price: f64, size: i64) -> Trade {
+        Trade::new(
+            Utc.with_ymd_and_hms(2020, 3, 12, hour, minute, second)
+                .unwrap(),
+            price,
+            size,
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn utc(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2020, 3, 12, hour, minute, 0).unwrap()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn add_all(builder: &mut BarBuilder, trades: &[Trade]) -> Vec<BarData> {
+        trades
+            .iter()
+            .flat_map(|trade| builder.add_trade(trade))
+            .collect()
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_time_bars_align_to_exchange_time_zone() {
+        // Kolkata is 5:30 ahead of UTC, so hourly bars start at half past the UTC hour
+        let mut builder = BarBuilder::new(BarSize::Time(Duration::hours(1)), Tz::Asia__Kolkata);
+        let bars = add_all(
+            &mut builder,
+            &[
+                trade(4, 0, 0, 10.0, 100),
+                trade(4, 20, 0, 12.0, 300),
+                trade(4, 31, 0, 11.0, 100),
+                trade(4, 45, 0, 9.0, 100),
+            ],
+        );
+
+        assert_eq!(1, bars.len());
+        assert_eq!(utc(3, 30), bars[0].date);
+        assert_eq!(10.0, bars[0].open);
+        assert_eq!(12.0, bars[0].high);
+        assert_eq!(12.0, bars[0].close);
+        assert_eq!(400, bars[0].volume);
+        assert_eq!(2, bars[0].bar_count);
+        assert_eq!(11.5, bars[0].average);
+
+        assert!(builder
+            .update_time(&Utc.with_ymd_and_hms(2020, 3, 12, 5, 29, 59).unwrap())
+            .is_none());
+        let last = builder
+            .update_time(&Utc.with_ymd_and_hms(2020, 3, 12, 5, 30, 0).unwrap())
+            .unwrap();
+        assert_eq!(utc(4, 30), last.date);
+        assert_eq!(9.0, last.low);
+        assert!(builder.flush().is_none());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tick_volume_and_dollar_bars() {
+        let trades = [
+            trade(14, 0, 0, 10.0, 100),
+            trade(14, 0, 1, 11.0, 200),
+            trade(14, 0, 2, 12.0, 300),
+            trade(14, 0, 3, 13.0, 100),
+        ];
+
+        let mut builder = BarBuilder::new(BarSize::Ticks(3), Tz::America__New_York);
+        let bars = add_all(&mut builder, &trades);
+        assert_eq!(1, bars.len());
+        assert_eq!(utc(14, 0), bars[0].date);
+        assert_eq!(3, bars[0].bar_count);
+        assert_eq!(600, bars[0].volume);
+        assert_eq!(13.0, builder.flush().unwrap().close);
+
+        let mut builder = BarBuilder::new(BarSize::Volume(300), Tz::America__New_York);
+        let volumes: Vec<i64> = add_all(&mut builder, &trades)
+            .iter()
+            .map(|bar| bar.volume)
+            .collect();
+        assert_eq!(vec![300, 300], volumes);
+
+        let mut builder = BarBuilder::new(BarSize::Dollar(3000.0), Tz::America__New_York);
+        let bars = add_all(&mut builder, &trades);
+        assert_eq!(2, bars.len());
+        assert_eq!(3200.0 / 300.0, bars[0].average);
+        assert_eq!(12.0, bars[1].average);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bars_respect_sessions() -> Result<(), IBKRApiLibError> {
+        let mut details = ContractDetails::default();
+        details.time_zone_id = "US/Eastern".to_string();
+        details.liquid_hours = "20200312:0930-20200312:1600;20200313:CLOSED".to_string();
+        let calendar = SessionCalendar::from_contract_details(&details, true)?;
+        assert_eq!(1, calendar.sessions().len());
+        assert_eq!(
+            Utc.with_ymd_and_hms(2020, 3, 12, 13, 30, 0).unwrap(),
+            calendar.sessions()[0].start
+        );
+
+        // 45 minute bars start at 9:30 and the last one is cut at the close
+        let mut builder = BarBuilder::with_calendar(BarSize::Time(Duration::minutes(45)), calendar);
+        let bars = add_all(
+            &mut builder,
+            &[
+                trade(13, 0, 0, 1.0, 1),
+                trade(13, 31, 0, 10.0, 1),
+                trade(19, 50, 0, 11.0, 1),
+                trade(20, 0, 0, 12.0, 1),
+            ],
+        );
+        assert_eq!(1, bars.len());
+        assert_eq!(utc(13, 30), bars[0].date);
+        assert_eq!(10.0, bars[0].open);
+        assert_eq!(1, bars[0].bar_count);
+
+        let last = builder.flush().unwrap();
+        assert_eq!(utc(19, 30), last.date);
+        assert_eq!(11.0, last.close);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_trading_hours_formats() -> Result<(), IBKRApiLibError> {
+        let time_zone = parse_time_zone("EST")?;
+        let calendar = SessionCalendar::parse(
+            "20090507:0700-1830,1830-2330;20090508:CLOSED;20090509:2200-0200",
+            time_zone,
+        )?;
+        let sessions = calendar.sessions();
+        assert_eq!(3, sessions.len());
+        assert_eq!(
+            Utc.with_ymd_and_hms(2009, 5, 7, 11, 0, 0).unwrap(),
+            sessions[0].start
+        );
+        assert_eq!(
+            Utc.with_ymd_and_hms(2009, 5, 10, 6, 0, 0).unwrap(),
+            sessions[2].end
+        );
+        assert!(calendar.is_open(&Utc.with_ymd_and_hms(2009, 5, 8, 3, 0, 0).unwrap()));
+        assert!(!calendar.is_open(&Utc.with_ymd_and_hms(2009, 5, 8, 12, 0, 0).unwrap()));
+
+        assert!(parse_time_zone("Mars/Olympus").is_err());
+        assert!(SessionCalendar::parse("20090507:0700", time_zone).is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_bars_from_real_time_bars() {
+        let mut builder = BarBuilder::new(BarSize::Time(Duration::seconds(15)), Tz::UTC);
+        let start = utc(14, 0);
+        let mut bars = vec![];
+        for (i, price) in [10.0, 11.0, 9.0, 10.0].iter().enumerate() {
+            let bar = RealTimeBar::new(
+                start + Duration::seconds(5 * i as i64),
+                *price,
+                price + 0.5,
+                price - 0.5,
+                *price,
+                10,
+                *price,
+                2,
+            );
+            bars.extend(builder.add_real_time_bar(&bar));
+        }
+
+        assert_eq!(1, bars.len());
+        assert_eq!(11.5, bars[0].high);
+        assert_eq!(8.5, bars[0].low);
+        assert_eq!(30, bars[0].volume);
+        assert_eq!(6, bars[0].bar_count);
+        assert_eq!(10.0, bars[0].average);
+    }
+}
@@ -4,14 +4,17 @@ mod tests {
 
     use crate::core::{
         common::{
-            BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode,
+            Action, BarData, CommissionReport, DepthMktDataDescription, FaDataType, FamilyCode,
             HistogramData, HistoricalTick, HistoricalTickBidAsk, HistoricalTickLast, NewsProvider,
             PriceIncrement, RealTimeBar, SmartComponent, TickAttrib, TickAttribBidAsk,
-            TickAttribLast, TickByTickType, TickType,
+            TickAttribLast, TickByTickType, TickType, UNSET_DOUBLE,
         },
         contract::{Contract, ContractDescription, ContractDetails, DeltaNeutralContract},
         execution::{Execution, ExecutionFilter},
-        order::{Order, SoftDollarTier},
+        fa::FaConfig,
+        historical_data::HistoricalDownloader,
+        historical_ticks::{HistoricalTicks, HistoricalTicksPager, PageDirection},
+        order::{Order, OrderStatus, SoftDollarTier},
         streamer::{Streamer, TestStreamer},
         wrapper::Wrapper,
     };
@@ -22,8 +25,13 @@ mod tests {
             order::OrderState,
         },
         examples::contract_samples::simple_future,
+        examples::contract_samples::usstock,
+        examples::order_samples::limit_order,
     };
+    use chrono::{DateTime, TimeZone, Utc};
     use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
 
     pub struct DummyTestWrapper {}
 
@@ -84,7 +92,7 @@ mod tests {
         fn order_status(
             &mut self,
             _order_id: i32,
-            _status: &str,
+            _status: OrderStatus,
             _filled: f64,
             _remaining: f64,
             _avg_fill_price: f64,
@@ -194,7 +202,7 @@ mod tests {
         fn managed_accounts(&mut self, _accounts_list: &str) {
             todo!()
         }
-        fn receive_fa(&mut self, _fa_data: FaDataType, _cxml: &str) {
+        fn receive_fa(&mut self, _fa_data: FaDataType, _cxml: &str, _config: Option<FaConfig>) {
             todo!()
         }
         fn historical_data(&mut self, _req_id: i32, _bar: BarData) {
@@ -517,6 +525,356 @@ mod tests {
         }
     }
 
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_modify_order() -> Result<(), IBKRApiLibError> {
+        let wrapper = Arc::new(Mutex::new(DummyTestWrapper::new()));
+        let app = Arc::new(Mutex::new(EClient::<DummyTestWrapper>::new(
+            wrapper.clone(),
+        )));
+
+        let order_id = 7;
+        let mut order = limit_order("BUY", 100.0, 50.0);
+        order.order_id = order_id;
+        let mut buf = Vec::<u8>::new();
+
+        let mut locked_app = app.lock().expect("EClient mutex was poisoned");
+
+        locked_app.connect_test();
+        locked_app.order_tracker.update_open_order(
+            order_id,
+            &usstock(),
+            &order,
+            &OrderState::default(),
+        )?;
+
+        locked_app.modify_order(order_id, |order| order.lmt_price = 51.5)?;
+        locked_app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+
+        let msg_data = read_msg(buf.as_slice())?;
+        let fields = read_fields(&msg_data.1);
+
+        assert_eq!(
+            OutgoingMessageIds::PlaceOrder as u8,
+            fields[0].parse::<u8>().unwrap()
+        );
+        assert_eq!(order_id, fields[1].parse::<i32>().unwrap());
+        assert!(fields.contains(&"51.5".to_string()));
+        assert_eq!(
+            51.5,
+            locked_app
+                .open_order_snapshot(order_id)
+                .unwrap()
+                .order
+                .lmt_price
+        );
+
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_modify_order_rejects_unmodifiable_fields() -> Result<(), IBKRApiLibError> {
+        let wrapper = Arc::new(Mutex::new(DummyTestWrapper::new()));
+        let app = Arc::new(Mutex::new(EClient::<DummyTestWrapper>::new(
+            wrapper.clone(),
+        )));
+
+        let order_id = 8;
+        let mut order = limit_order("BUY", 100.0, 50.0);
+        order.order_id = order_id;
+        let mut buf = Vec::<u8>::new();
+
+        let mut locked_app = app.lock().expect("EClient mutex was poisoned");
+
+        locked_app.connect_test();
+        assert!(locked_app.modify_order(order_id, |_| ()).is_err());
+
+        locked_app.order_tracker.update_open_order(
+            order_id,
+            &usstock(),
+            &order,
+            &OrderState::default(),
+        )?;
+
+        let result = locked_app.modify_order(order_id, |order| {
+            order.action = "SELL".to_string();
+            order.aux_price = 49.0;
+        });
+        match result {
+            Err(IBKRApiLibError::ApiError(err)) => {
+                assert!(err.description.contains("action"));
+                assert!(err.description.contains("aux_price"));
+            }
+            _ => panic!("modify_order should reject changes to action and aux_price"),
+        }
+
+        locked_app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_preview_order() -> Result<(), IBKRApiLibError> {
+        let wrapper = Arc::new(Mutex::new(DummyTestWrapper::new()));
+        let app = Arc::new(Mutex::new(EClient::<DummyTestWrapper>::new(
+            wrapper.clone(),
+        )));
+
+        let order = limit_order("BUY", 100.0, 50.0);
+        let mut buf = Vec::<u8>::new();
+
+        let mut locked_app = app.lock().expect("EClient mutex was poisoned");
+
+        locked_app.connect_test();
+
+        let tracker = locked_app.order_tracker.clone();
+        let answer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let mut what_if_order = limit_order("BUY", 100.0, 50.0);
+            what_if_order.what_if = true;
+            let mut order_state = OrderState::default();
+            order_state.status = OrderStatus::PreSubmitted;
+            order_state.init_margin_before = "1000.5".to_string();
+            order_state.init_margin_change = "2500".to_string();
+            order_state.init_margin_after = "3500.5".to_string();
+            order_state.maint_margin_change = UNSET_DOUBLE.to_string();
+            order_state.commission = 1.0;
+            order_state.min_commission = UNSET_DOUBLE;
+            tracker
+                .update_open_order(20, &usstock(), &what_if_order, &order_state)
+                .unwrap();
+        });
+
+        // The client is released while waiting for the answer
+        let pending = locked_app.preview_order(20, &usstock(), &order)?;
+        drop(locked_app);
+        assert_eq!(20, pending.order_id());
+        let preview = pending.wait()?;
+        answer.join().unwrap();
+        let mut locked_app = app.lock().expect("EClient mutex was poisoned");
+
+        assert_eq!(Some(1000.5), preview.init_margin.before);
+        assert_eq!(Some(2500.0), preview.init_margin.change);
+        assert_eq!(Some(3500.5), preview.init_margin.after);
+        assert_eq!(None, preview.maint_margin.change);
+        assert_eq!(None, preview.equity_with_loan.after);
+        assert_eq!(Some(1.0), preview.commission);
+        assert_eq!(None, preview.min_commission);
+        assert!(locked_app.open_order_snapshot(20).is_none());
+
+        locked_app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let msg_data = read_msg(buf.as_slice())?;
+        let fields = read_fields(&msg_data.1);
+        assert_eq!(
+            OutgoingMessageIds::PlaceOrder as u8,
+            fields[0].parse::<u8>().unwrap()
+        );
+        assert_eq!(20, fields[1].parse::<i32>().unwrap());
+
+        let tracker = locked_app.order_tracker.clone();
+        let answer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tracker.record_error(21, 201, "Order rejected - reason: no trading permissions");
+        });
+
+        let result = locked_app
+            .preview_order(21, &usstock(), &order)?
+            .wait_timeout(Duration::from_secs(1));
+        answer.join().unwrap();
+        match result {
+            Err(IBKRApiLibError::ApiError(err)) => {
+                assert_eq!(21, err.req_id);
+                assert_eq!("201", err.code);
+            }
+            _ => panic!("preview_order should return the error sent for the what-if order"),
+        }
+
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_historical_downloader() -> Result<(), IBKRApiLibError> {
+        let wrapper = Arc::new(Mutex::new(DummyTestWrapper::new()));
+        let app = Arc::new(Mutex::new(EClient::<DummyTestWrapper>::new(
+            wrapper.clone(),
+        )));
+        app.lock().expect("EClient mutex was poisoned").connect_test();
+
+        let day = |day: u32, hour: u32, minute: u32| {
+            Utc.with_ymd_and_hms(2020, 3, day, hour, minute, 0).unwrap()
+        };
+        let bar = |date| BarData::new(date, 1.0, 1.0, 1.0, 1.0, 100, 1, 1.0);
+
+        // Answers each request once the downloader waits on it.  The client is not locked while
+        // the downloader waits.
+        let tracker = app.lock().expect("EClient mutex was poisoned").historical_data.clone();
+        let (d, b) = (day, bar);
+        let app_answers = app.clone();
+        let answers = thread::spawn(move || {
+            let wait = |req_id| {
+                while !tracker.is_awaited(req_id) {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            };
+            wait(1000);
+            let unlocked = (0..200).any(|_| {
+                let unlocked = app_answers.try_lock().is_ok();
+                if !unlocked {
+                    thread::sleep(Duration::from_millis(5));
+                }
+                unlocked
+            });
+            assert!(unlocked, "The client is locked while the downloader waits");
+            tracker.head_timestamp(1000, &d(10, 12, 0).timestamp().to_string());
+            wait(1001);
+            for date in vec![d(11, 0, 0), d(11, 23, 58), d(11, 23, 59)] {
+                tracker.add_bar(1001, &b(date));
+            }
+            tracker.end(1001);
+            wait(1002);
+            tracker.record_error(1002, 366, "No historical data query found for ticker id");
+            wait(1003);
+            for date in vec![d(10, 12, 0), d(11, 0, 0)] {
+                tracker.add_bar(1003, &b(date));
+            }
+            tracker.end(1003);
+        });
+
+        let mut downloader = HistoricalDownloader::new(
+            usstock(),
+            Utc.with_ymd_and_hms(2020, 3, 10, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2020, 3, 12, 0, 0, 0).unwrap(),
+            "1 min",
+            "TRADES",
+            1000,
+        );
+        downloader.pacer.identical_request_interval = Duration::from_millis(0);
+
+        // The second chunk fails with an error that is not retried, and is the only one requested
+        // again by the next download
+        assert!(downloader.download(&app).is_err());
+        let pending = downloader.pending_chunks();
+        assert_eq!(1, pending.len());
+        assert_eq!(day(11, 0, 0), pending[0].end);
+
+        let bars = downloader.download(&app)?;
+        answers.join().unwrap();
+        let dates: Vec<DateTime<Utc>> = bars.iter().map(|bar| bar.date).collect();
+        assert_eq!(
+            vec![day(10, 12, 0), day(11, 0, 0), day(11, 23, 58), day(11, 23, 59)],
+            dates
+        );
+
+        let mut locked_app = app.lock().expect("EClient mutex was poisoned");
+        let mut buf = Vec::<u8>::new();
+        locked_app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let msg_data = read_msg(buf.as_slice())?;
+        let fields = read_fields(&msg_data.1);
+        assert_eq!(
+            OutgoingMessageIds::ReqHeadTimestamp as u8,
+            fields[0].parse::<u8>().unwrap()
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_historical_ticks_pager() -> Result<(), IBKRApiLibError> {
+        let wrapper = Arc::new(Mutex::new(DummyTestWrapper::new()));
+        let app = Arc::new(Mutex::new(EClient::<DummyTestWrapper>::new(
+            wrapper.clone(),
+        )));
+        app.lock().expect("EClient mutex was poisoned").connect_test();
+
+        let start = Utc.with_ymd_and_hms(2020, 3, 12, 10, 0, 0).unwrap();
+        let end = start + chrono::Duration::seconds(10);
+        let at = move |time: i64| start + chrono::Duration::seconds(time);
+        let last = move |time: i64| {
+            HistoricalTickLast::new(
+                at(time),
+                TickAttribLast::default(),
+                1.0,
+                100,
+                "ISLAND".to_string(),
+                "".to_string(),
+            )
+        };
+        let midpoint = move |time: i64| HistoricalTick::new(at(time), 1.0, 0);
+
+        // Answers each page once the pager waits on it.  Pages hold 3 ticks.
+        let tracker = app.lock().expect("EClient mutex was poisoned").historical_data.clone();
+        let answers = thread::spawn(move || {
+            let wait = |req_id| {
+                while !tracker.is_awaited(req_id) {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            };
+            let pages = vec![
+                (2000, vec![last(0), last(1), last(1)]),
+                (2001, vec![last(1), last(1), last(2)]),
+                (2002, vec![last(2), last(9), last(10)]),
+            ];
+            for (req_id, ticks) in pages {
+                wait(req_id);
+                tracker.add_ticks(req_id, HistoricalTicks::Last(ticks), true);
+            }
+            let pages = vec![
+                (3000, vec![midpoint(8), midpoint(10), midpoint(10)]),
+                (3001, vec![midpoint(-1), midpoint(0), midpoint(8)]),
+            ];
+            for (req_id, ticks) in pages {
+                wait(req_id);
+                tracker.add_ticks(req_id, HistoricalTicks::Midpoint(ticks), true);
+            }
+        });
+
+        let mut pager = HistoricalTicksPager::<_, HistoricalTickLast>::new(
+            &app,
+            usstock(),
+            start,
+            end,
+            PageDirection::Forward,
+            2000,
+        );
+        pager.ticks_per_page = 3;
+        let times = pager
+            .map(|tick| tick.map(|tick| (tick.time - start).num_seconds()))
+            .collect::<Result<Vec<i64>, IBKRApiLibError>>()?;
+        assert_eq!(vec![0, 1, 1, 2, 9], times);
+
+        let mut pager = HistoricalTicksPager::<_, HistoricalTick>::new(
+            &app,
+            usstock(),
+            start,
+            end,
+            PageDirection::Backward,
+            3000,
+        );
+        pager.ticks_per_page = 3;
+        let times = pager
+            .map(|tick| tick.map(|tick| (tick.time - start).num_seconds()))
+            .collect::<Result<Vec<i64>, IBKRApiLibError>>()?;
+        assert_eq!(vec![8, 0], times);
+        answers.join().unwrap();
+
+        let mut locked_app = app.lock().expect("EClient mutex was poisoned");
+        let mut buf = Vec::<u8>::new();
+        locked_app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
+        let msg_data = read_msg(buf.as_slice())?;
+        let fields = read_fields(&msg_data.1);
+        assert_eq!(
+            OutgoingMessageIds::ReqHistoricalTicks as u8,
+            fields[0].parse::<u8>().unwrap()
+        );
+        assert_eq!("20200312-10:00:00", fields[15]);
+        assert_eq!("TRADES", fields[18]);
+        Ok(())
+    }
+
     //------------------------------------------------------------------------------------------------
     #[test]
     fn test_req_account_summary() -> Result<(), IBKRApiLibError> {
@@ -847,40 +1205,41 @@ mod tests {
         let client_id = 0;
         let acct_code = "D54321";
 
-        //Time from which the executions will be returned yyyymmdd hh:mm:ss Only those executions reported after the specified time will be returned.
-        let time = "";
+        //Time from which the executions will be returned, sent in the time zone of the filter
+        let time = Utc.with_ymd_and_hms(2020, 7, 2, 18, 55, 0).unwrap();
         let symbol = "ES";
         let sec_type = "FUT";
         let exchange = "GLOBEX";
-        let side = "BUY";
+        let side = Action::BUY;
         let mut buf = Vec::<u8>::new();
 
         let mut locked_app = app.lock().expect("EClient mutex was poisoned");
 
         locked_app.connect_test();
 
-        let exec_filter = ExecutionFilter::new(
-            client_id,
-            acct_code.to_string(),
-            time.to_string(),
-            symbol.to_string().to_string(),
-            sec_type.to_string(),
-            exchange.to_string(),
-            side.to_string(),
-        );
+        let exec_filter = ExecutionFilter::builder()
+            .client_id(client_id)
+            .account(acct_code)
+            .since(time)
+            .time_zone(chrono_tz::US::Eastern)
+            .symbol(symbol)
+            .sec_type(sec_type)
+            .exchange(exchange)
+            .side(side)
+            .build();
         locked_app.req_executions(req_id, &exec_filter)?;
         locked_app.stream.as_mut().unwrap().read_to_end(&mut buf)?;
 
-        let expected: [u8; 40] = [
-            0, 0, 0, 36, 55, 0, 51, 0, 49, 48, 50, 0, 48, 0, 68, 53, 52, 51, 50, 49, 0, 0, 69, 83,
-            0, 70, 85, 84, 0, 71, 76, 79, 66, 69, 88, 0, 66, 85, 89, 0,
-        ];
+        let mut expected = vec![0, 0, 0, 64];
+        expected.extend_from_slice(
+            b"7\x003\x00102\x000\x00D54321\x0020200702 14:55:00 US/Eastern\x00ES\x00FUT\x00GLOBEX\x00BUY\x00",
+        );
 
         let msg_data = read_msg(buf.as_slice())?;
         //println!("read message: {:?}", read_msg(buf.as_slice())?);
         let fields = read_fields(&msg_data.1);
         //println!("read fields: {:?}", read_fields(&msg_data.1));
-        assert_eq!(expected.as_ref(), buf.as_slice());
+        assert_eq!(expected.as_slice(), buf.as_slice());
         assert_eq!(
             OutgoingMessageIds::ReqExecutions as u8,
             fields[0].parse::<u8>().unwrap()
@@ -889,11 +1248,11 @@ mod tests {
         assert_eq!(req_id, fields[2].parse::<i32>().unwrap());
         assert_eq!(client_id, fields[3].parse::<i32>().unwrap());
         assert_eq!(acct_code, fields[4]);
-        assert_eq!(time, fields[5]);
+        assert_eq!("20200702 14:55:00 US/Eastern", fields[5]);
         assert_eq!(symbol, fields[6]);
         assert_eq!(sec_type, fields[7]);
         assert_eq!(exchange, fields[8]);
-        assert_eq!(side, fields[9]);
+        assert_eq!("BUY", fields[9]);
 
         Ok(())
     }
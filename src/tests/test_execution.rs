@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::core::common::Action;
+    use crate::core::contract::Contract;
+    use crate::core::execution::{Execution, ExecutionFilter};
+    use crate::core::trade_blotter::TradeBlotter;
+    use crate::tests::helpers::{contract, executed_at, execution};
+
+    //------------------------------------------------------------------------------------------------
+    fn fill(exec_id: &str, symbol: &str, side: &str, hour: u32) -> (Contract, Execution) {
+        let mut execution = executed_at(execution(exec_id, side, 100.0, 10.0), hour, 0);
+        execution.exchange = "ISLAND".to_string();
+        execution.client_id = 7;
+        (contract(0, symbol), execution)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_execution_filter_encoding() {
+        let filter = ExecutionFilter::default();
+        assert_eq!("", filter.encoded_time());
+        assert_eq!("", filter.encoded_side());
+
+        let time = Utc.with_ymd_and_hms(2020, 1, 15, 14, 30, 5).unwrap();
+        let filter = ExecutionFilter::builder()
+            .since(time)
+            .side(Action::SSHORT)
+            .build();
+        assert_eq!("20200115-14:30:05", filter.encoded_time());
+        assert_eq!("SELL", filter.encoded_side());
+
+        let filter = ExecutionFilter::builder()
+            .since(time.with_timezone(&chrono_tz::Europe::London))
+            .time_zone(chrono_tz::Asia::Tokyo)
+            .build();
+        assert_eq!(Some(time), filter.time);
+        assert_eq!("20200115 23:30:05 Asia/Tokyo", filter.encoded_time());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_execution_filter_matches() {
+        let (contract, execution) = fill("1.01", "AAPL", "BOT", 15);
+        assert!(ExecutionFilter::default().matches(&contract, &execution));
+
+        let filter = ExecutionFilter::builder()
+            .client_id(7)
+            .account("du1")
+            .symbol("aapl")
+            .sec_type("STK")
+            .exchange("ISLAND")
+            .side(Action::BUY)
+            .since(Utc.with_ymd_and_hms(2020, 3, 2, 15, 0, 0).unwrap())
+            .build();
+        assert!(filter.matches(&contract, &execution));
+
+        let mismatches = vec![
+            ExecutionFilter::builder().client_id(8).build(),
+            ExecutionFilter::builder().account("DU2").build(),
+            ExecutionFilter::builder().symbol("MSFT").build(),
+            ExecutionFilter::builder().exchange("ARCA").build(),
+            ExecutionFilter::builder().side(Action::SELL).build(),
+            ExecutionFilter::builder()
+                .since(Utc.with_ymd_and_hms(2020, 3, 2, 15, 0, 1).unwrap())
+                .build(),
+        ];
+        for filter in mismatches {
+            assert!(!filter.matches(&contract, &execution), "{:?}", filter);
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_execution_post_filter() {
+        let blotter = TradeBlotter::new();
+        let (aapl, aapl_execution) = fill("1.01", "AAPL", "BOT", 15);
+        let (msft, msft_execution) = fill("2.01", "MSFT", "SLD", 16);
+
+        let filter = ExecutionFilter::builder().symbol("AAPL").build();
+        blotter.expect_executions(5, filter.clone());
+        assert!(blotter.accepts(5, &aapl, &aapl_execution));
+        assert!(!blotter.accepts(5, &msft, &msft_execution));
+        // Executions of orders being filled are sent with req_id -1
+        assert!(blotter.accepts(-1, &msft, &msft_execution));
+        blotter.forget_executions(5);
+        assert!(blotter.accepts(5, &msft, &msft_execution));
+
+        blotter.add_execution(aapl, aapl_execution);
+        blotter.add_execution(msft, msft_execution);
+        let fills = blotter.filtered(&filter);
+        assert_eq!(1, fills.len());
+        assert_eq!("AAPL", fills[0].contract.symbol);
+        let sells = ExecutionFilter::builder().side(Action::SELL).build();
+        assert_eq!("MSFT", blotter.filtered(&sells)[0].contract.symbol);
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::FaDataType;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::fa::{
+        FaAlias, FaAllocation, FaAllocationMethod, FaConfig, FaGroup, FaProfile, FaProfileType,
+    };
+
+    const FA_GROUPS: &str = include_str!("fixtures/fa_groups.xml");
+    const FA_PROFILES: &str = include_str!("fixtures/fa_profiles.xml");
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fa_round_trip() -> Result<(), IBKRApiLibError> {
+        let groups = FaConfig::parse(FaDataType::GROUPS, FA_GROUPS)?;
+        assert_eq!(FaDataType::GROUPS, groups.data_type());
+        assert_eq!(
+            groups,
+            FaConfig::parse(FaDataType::GROUPS, &groups.to_xml())?
+        );
+
+        // Methods of newer TWS versions are kept as sent
+        let ratio = FaConfig::parse(FaDataType::GROUPS, &FA_GROUPS.replace("NetLiq", "Ratio"))?;
+        match &ratio {
+            FaConfig::Groups(groups) => assert_eq!(
+                FaAllocationMethod::Other("Ratio".to_string()),
+                groups[0].default_method
+            ),
+            other => panic!("unexpected configuration: {:?}", other),
+        }
+        assert!(ratio
+            .to_xml()
+            .contains("<defaultMethod>Ratio</defaultMethod>"));
+        assert!(FaConfig::parse(FaDataType::GROUPS, &FA_GROUPS.replace("NetLiq", "")).is_err());
+
+        let profiles = FaConfig::parse(FaDataType::PROFILES, FA_PROFILES)?;
+        match &profiles {
+            FaConfig::Profiles(profiles) => {
+                assert_eq!(2, profiles.len());
+                assert_eq!(FaProfileType::Shares, profiles[1].profile_type);
+                assert_eq!(FaAllocation::new("DU2", 40.0), profiles[0].allocations[1]);
+            }
+            other => panic!("unexpected configuration: {:?}", other),
+        }
+        profiles.validate()?;
+        assert_eq!(
+            profiles,
+            FaConfig::parse(FaDataType::PROFILES, &profiles.to_xml())?
+        );
+
+        let aliases = FaConfig::Aliases(vec![
+            FaAlias::new("DU1", "Smith & Sons <main>"),
+            FaAlias::new("DU2", ""),
+        ]);
+        assert_eq!(
+            aliases,
+            FaConfig::parse(FaDataType::ALIASES, &aliases.to_xml())?
+        );
+
+        assert!(FaConfig::parse(FaDataType::PROFILES, FA_GROUPS).is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_fa_validation() {
+        let profile = |amounts: &[f64]| {
+            FaProfile::new(
+                "P",
+                FaProfileType::Percentages,
+                amounts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, amount)| FaAllocation::new(&format!("DU{}", i), *amount))
+                    .collect(),
+            )
+        };
+        assert!(profile(&[60.0, 40.0]).validate().is_ok());
+        assert!(profile(&[60.0, 30.0]).validate().is_err());
+        assert!(profile(&[110.0, -10.0]).validate().is_err());
+        assert!(profile(&[]).validate().is_err());
+
+        let mut ratios = profile(&[2.0, 1.0]);
+        ratios.profile_type = FaProfileType::FinancialRatios;
+        assert!(ratios.validate().is_ok());
+
+        let group = FaGroup::new("G", &["DU1", "DU1"], FaAllocationMethod::NetLiq);
+        assert!(group.validate().is_err());
+        let group = FaGroup::new("", &["DU1"], FaAllocationMethod::NetLiq);
+        assert!(group.validate().is_err());
+
+        let group = FaGroup::new("G", &["DU1"], FaAllocationMethod::NetLiq);
+        assert!(FaConfig::Groups(vec![group.clone()]).validate().is_ok());
+        assert!(FaConfig::Groups(vec![group.clone(), group])
+            .validate()
+            .is_err());
+    }
+}
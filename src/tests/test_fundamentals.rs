@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::core::common::FundamentalType;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::fundamentals::{
+        parse_fundamental_data, CompanySnapshot, Estimates, FinancialStatements, FinancialSummary,
+        FundamentalReport, RatioValue, RatiosReport, StatementType,
+    };
+
+    const REPORT_SNAPSHOT: &str = include_str!("fixtures/report_snapshot.xml");
+    const FIN_SUMMARY: &str = include_str!("fixtures/fin_summary.xml");
+    const RATIOS: &str = include_str!("fixtures/ratios.xml");
+    const FIN_STATEMENTS: &str = include_str!("fixtures/fin_statements.xml");
+    const RESC: &str = include_str!("fixtures/resc.xml");
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_company_snapshot() -> Result<(), IBKRApiLibError> {
+        let snapshot = CompanySnapshot::parse(REPORT_SNAPSHOT)?;
+        let company = &snapshot.company;
+        assert_eq!(Some("05680".to_string()), company.rep_no);
+        assert_eq!(Some("Apple Inc.".to_string()), company.name);
+        assert_eq!(Some("AAPL".to_string()), company.ticker);
+        assert_eq!(Some("NASD".to_string()), company.exchange);
+        assert_eq!(Some(137000), company.employees);
+        assert_eq!(Some(4519180000.0), company.shares_outstanding);
+        assert_eq!(Some(4514435304.0), company.float_shares);
+        assert_eq!(Some("USD".to_string()), company.reporting_currency);
+        assert!(company
+            .business_summary
+            .as_ref()
+            .unwrap()
+            .starts_with("Apple Inc. designs"));
+        assert_eq!(Some("https://www.apple.com/".to_string()), company.web_site);
+        assert_eq!(2, company.industries.len());
+        assert_eq!("TRBC", company.industries[0].classification);
+        assert_eq!("Phones & Smart Phones", company.industries[0].description);
+        assert_eq!(2, company.officers.len());
+        assert_eq!("Cook", company.officers[0].last_name);
+        assert_eq!(Some(58), company.officers[0].age);
+
+        assert_eq!(Some(255.82), snapshot.ratios.ratio("NPRICE"));
+        assert_eq!(Some(-99999.99), snapshot.ratios.ratio("PEEXCLXOR"));
+        assert_eq!(
+            Some(&RatioValue::Date(
+                NaiveDate::from_ymd_opt(2019, 11, 1).unwrap()
+            )),
+            snapshot.ratios.values.get("PDATE")
+        );
+        assert_eq!(None, snapshot.ratios.ratio("PDATE"));
+        assert_eq!(Some(&262.415), snapshot.forecasts.get("TargetPrice"));
+        assert_eq!(3, snapshot.forecasts.len());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_financial_summary() -> Result<(), IBKRApiLibError> {
+        let summary = FinancialSummary::parse(FIN_SUMMARY)?;
+        assert_eq!(Some("USD".to_string()), summary.currency);
+        assert_eq!(3, summary.eps.len());
+        assert_eq!("TTM", summary.eps[1].report_type);
+        assert_eq!("12M", summary.eps[1].period);
+        assert_eq!(11.89, summary.eps[1].value);
+        assert_eq!(2, summary.total_revenues.len());
+        assert_eq!(
+            Some(NaiveDate::from_ymd_opt(2019, 6, 29).unwrap()),
+            summary.total_revenues[1].as_of_date
+        );
+        assert_eq!(1, summary.dividends_per_share.len());
+        assert_eq!(2, summary.dividends.len());
+        assert_eq!("CD", summary.dividends[0].kind);
+        assert_eq!(
+            Some(NaiveDate::from_ymd_opt(2019, 11, 7).unwrap()),
+            summary.dividends[0].ex_date
+        );
+        assert_eq!(0.77, summary.dividends[0].amount);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ratios_report() -> Result<(), IBKRApiLibError> {
+        let report = RatiosReport::parse(RATIOS)?;
+        assert_eq!(Some("AAPL".to_string()), report.company.ticker);
+        assert_eq!(
+            Some(NaiveDate::from_ymd_opt(2019, 9, 28).unwrap()),
+            report.company.latest_annual
+        );
+        assert_eq!(Some("USD".to_string()), report.ratios.price_currency);
+        assert_eq!(Some(21.5148), report.ratios.ratio("PEEXCLXOR"));
+        assert_eq!(
+            Some(&RatioValue::Text("USD".to_string())),
+            report.ratios.values.get("CURRENCY")
+        );
+        assert_eq!(5, report.ratios.values.len());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_financial_statements() -> Result<(), IBKRApiLibError> {
+        let statements = FinancialStatements::parse(FIN_STATEMENTS)?;
+        assert_eq!(Some("Revenue"), statements.label("SREV"));
+        assert_eq!(2, statements.annual.len());
+        assert_eq!(1, statements.interim.len());
+
+        let year = &statements.annual[0];
+        assert_eq!(Some(2019), year.fiscal_year);
+        assert_eq!(3, year.statements.len());
+        let income = year.statement(&StatementType::Income).unwrap();
+        assert_eq!(Some(52), income.period_length);
+        assert_eq!(Some("W".to_string()), income.period_unit);
+        assert_eq!(Some(&260174.0), income.line_items.get("SREV"));
+        let balance_sheet = year.statement(&StatementType::BalanceSheet).unwrap();
+        assert_eq!(None, balance_sheet.period_length);
+        assert_eq!(Some(&338516.0), balance_sheet.line_items.get("ATOT"));
+
+        let quarter = &statements.interim[0];
+        assert_eq!(Some(4), quarter.period_number);
+        assert_eq!(
+            Some(&13686.0),
+            quarter
+                .statement(&StatementType::Income)
+                .unwrap()
+                .line_items
+                .get("NINC")
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_estimates() -> Result<(), IBKRApiLibError> {
+        let estimates = Estimates::parse(RESC)?;
+        assert_eq!(Some("AAPL".to_string()), estimates.company.ticker);
+        assert_eq!(3, estimates.actuals.len());
+        assert_eq!("Q", estimates.actuals[1].period_type);
+        assert_eq!(3.03, estimates.actuals[1].value);
+
+        let eps = estimates.estimate("EPS", 2020).unwrap();
+        assert_eq!(Some(12.98943), eps.mean);
+        assert_eq!(Some(12.985), eps.median);
+        assert_eq!(Some(14.25), eps.high);
+        assert_eq!(Some(11.81), eps.low);
+        assert_eq!(Some(38), eps.num_estimates);
+        let revenue = estimates.estimate("REVENUE", 2020).unwrap();
+        assert_eq!(Some(276521.5), revenue.mean);
+        assert_eq!(None, revenue.high);
+        assert!(estimates.estimate("EPS", 2021).is_none());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_fundamental_data() {
+        match parse_fundamental_data(&FundamentalType::ReportRatios, RATIOS) {
+            Ok(FundamentalReport::Ratios(report)) => {
+                assert_eq!(Some(255.82), report.ratios.ratio("NPRICE"))
+            }
+            other => panic!("unexpected report: {:?}", other),
+        }
+        assert!(parse_fundamental_data(&FundamentalType::ReportSnapshot, RATIOS).is_err());
+        assert!(parse_fundamental_data(&FundamentalType::RESC, "<REarnEstCons").is_err());
+        assert!(parse_fundamental_data(&FundamentalType::CalendarReport, RATIOS).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::core::common::TickType;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::generic_tick::{
+        Dividends, FundamentalRatios, GenericTick, GenericTickList, RtVolume, Shortability,
+    };
+    use crate::core::market_data::MarketDataCache;
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_generic_tick_list() {
+        let list = GenericTickList::new(&[
+            GenericTick::FundamentalRatios,
+            GenericTick::RtVolume,
+            GenericTick::Shortable,
+            GenericTick::RtVolume,
+        ]);
+        assert_eq!("233,236,258", list.to_string());
+        assert_eq!(3, list.ticks().len());
+
+        let news = GenericTickList::default()
+            .news(&["BRFG", "DJNL"])
+            .market_data_off();
+        assert_eq!("mdoff,292:BRFG+DJNL", news.to_string());
+
+        assert_eq!("", GenericTickList::default().to_string());
+        assert!(GenericTickList::default().is_empty());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_rt_volume() -> Result<(), IBKRApiLibError> {
+        let trade = RtVolume::parse("701.28;1;1348075471534;67854;701.46918464;true")?;
+        assert_eq!(Some(701.28), trade.price);
+        assert_eq!(Some(1.0), trade.size);
+        assert_eq!(
+            Utc.timestamp_opt(1348075471, 534_000_000).unwrap(),
+            trade.time
+        );
+        assert_eq!(67854.0, trade.total_volume);
+        assert_eq!(701.46918464, trade.vwap);
+        assert!(trade.single_trade);
+
+        // Volume corrections have no price and size
+        let correction = RtVolume::parse(";;1348075471534;67900;701.46;false")?;
+        assert_eq!(None, correction.price);
+        assert_eq!(None, correction.size);
+        assert!(!correction.single_trade);
+
+        assert!(RtVolume::parse("701.28;1;1348075471534").is_err());
+        assert!(RtVolume::parse("x;1;1348075471534;67854;701.46;true").is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_dividends_ratios_and_shortability() -> Result<(), IBKRApiLibError> {
+        let dividends = Dividends::parse("0.83,0.92,20130219,0.23")?;
+        assert_eq!(Some(0.83), dividends.past_12_months);
+        assert_eq!(Some(0.92), dividends.next_12_months);
+        assert_eq!(NaiveDate::from_ymd_opt(2013, 2, 19), dividends.next_date);
+        assert_eq!(Some(0.23), dividends.next_amount);
+        assert_eq!(Dividends::default(), Dividends::parse(",,,")?);
+        assert!(Dividends::parse("0.83,0.92").is_err());
+
+        let ratios = FundamentalRatios::parse("TTMNPMGN=16.1298;NLOW=80.6;CURRENCY=USD;");
+        assert_eq!(Some(80.6), ratios.ratio("NLOW"));
+        assert_eq!(None, ratios.ratio("CURRENCY"));
+        assert_eq!(Some(&"USD".to_string()), ratios.values.get("CURRENCY"));
+
+        assert_eq!(Shortability::Available, Shortability::from_tick_value(3.0));
+        assert_eq!(
+            Shortability::HardToBorrow,
+            Shortability::from_tick_value(2.0)
+        );
+        assert_eq!(
+            Shortability::NotAvailable,
+            Shortability::from_tick_value(1.0)
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticker_decodes_string_ticks() {
+        let cache = MarketDataCache::new();
+        cache.update_string(
+            1,
+            TickType::RtVolume,
+            "701.28;1;1348075471534;67854;701.46918464;true",
+        );
+        cache.update_string(1, TickType::IbDividends, "0.83,0.92,20130219,0.23");
+        cache.update_string(1, TickType::FundamentalRatios, "NLOW=80.6");
+        cache.update_string(1, TickType::RtTrdVolume, "garbage");
+
+        let ticker = cache.ticker(1).unwrap();
+        assert_eq!(Some(701.28), ticker.rt_volume.unwrap().price);
+        assert_eq!(Some(0.23), ticker.dividends.unwrap().next_amount);
+        assert_eq!(Some(80.6), ticker.fundamental_ratios.unwrap().ratio("NLOW"));
+        assert_eq!(None, ticker.rt_trade_volume);
+        assert_eq!(
+            Some(&"garbage".to_string()),
+            ticker.other_strings.get(&TickType::RtTrdVolume)
+        );
+    }
+}
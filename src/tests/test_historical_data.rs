@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::{Duration, Instant};
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::core::common::BarData;
+    use crate::core::errors::{IBKRApiLibError, TwsApiReportableError};
+    use crate::core::historical_data::{
+        is_no_data_error, is_pacing_violation, is_retryable, max_chunk, merge_bars, plan_chunks,
+        HistoricalDataTracker, Pacer,
+    };
+    use crate::core::timestamps::{FORMAT_DATE_EPOCH, FORMAT_DATE_TEXT};
+
+    //------------------------------------------------------------------------------------------------
+    fn bar(date: DateTime<Utc>, close: f64) -> BarData {
+        BarData::new(date, close, close, close, close, 100, 1, close)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_pacer() {
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+        let mut pacer = Pacer::new();
+
+        // Identical requests must be 15 seconds apart
+        pacer.record("AAPL", "AAPL:1", at(0));
+        assert_eq!(
+            Duration::from_secs(10),
+            pacer.delay("AAPL", "AAPL:1", at(5))
+        );
+        assert_eq!(
+            Duration::from_secs(0),
+            pacer.delay("AAPL", "AAPL:1", at(15))
+        );
+
+        // No more than 5 requests for the same contract in 2 seconds
+        for i in 2..6 {
+            pacer.record("AAPL", &format!("AAPL:{}", i), at(1));
+        }
+        assert_eq!(Duration::from_secs(1), pacer.delay("AAPL", "AAPL:7", at(1)));
+        assert_eq!(Duration::from_secs(0), pacer.delay("MSFT", "MSFT:1", at(1)));
+
+        // No more than 60 requests in 10 minutes
+        let mut pacer = Pacer::new();
+        for i in 0..60 {
+            pacer.record(&i.to_string(), &i.to_string(), at(i * 5));
+        }
+        assert_eq!(Duration::from_secs(300), pacer.delay("x", "x", at(300)));
+        pacer.record("x", "x", at(600));
+        assert_eq!(Duration::from_secs(0), pacer.delay("y", "y", at(605)));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_plan_chunks() -> Result<(), IBKRApiLibError> {
+        let start = Utc.with_ymd_and_hms(2020, 3, 9, 12, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2020, 3, 12, 0, 0, 0).unwrap();
+        let chunks = plan_chunks(start, end, "1 min")?;
+
+        let ends: Vec<DateTime<Utc>> = chunks.iter().map(|chunk| chunk.end).collect();
+        let day = |day: u32| Utc.with_ymd_and_hms(2020, 3, day, 0, 0, 0).unwrap();
+        assert_eq!(vec![day(12), day(11), day(10)], ends);
+        assert!(chunks.iter().all(|chunk| chunk.duration_str == "1 D"));
+        assert_eq!("3600 S", max_chunk("5 secs")?.1);
+        assert_eq!("52 W", max_chunk("1 day")?.1);
+        assert!(max_chunk("7 mins").is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_merge_bars() {
+        let start = Utc.with_ymd_and_hms(2020, 3, 12, 0, 0, 0).unwrap();
+        let end = start + chrono::Duration::minutes(3);
+        let minute = |i: i64| start + chrono::Duration::minutes(i);
+
+        let merged = merge_bars(
+            vec![
+                vec![
+                    bar(minute(1), 2.0),
+                    bar(minute(2), 3.0),
+                    bar(minute(3), 4.0),
+                ],
+                vec![
+                    bar(minute(-1), 0.0),
+                    bar(minute(0), 1.0),
+                    bar(minute(1), 2.0),
+                ],
+            ],
+            start,
+            end,
+        );
+        let closes: Vec<f64> = merged.iter().map(|bar| bar.close).collect();
+        assert_eq!(vec![1.0, 2.0, 3.0], closes);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_tracker_only_keeps_awaited_requests() -> Result<(), IBKRApiLibError> {
+        let tracker = HistoricalDataTracker::new();
+        tracker.add_bar(
+            1,
+            &bar(Utc.with_ymd_and_hms(2020, 3, 12, 0, 0, 0).unwrap(), 1.0),
+        );
+        tracker.end(1);
+
+        tracker.expect(2);
+        tracker.add_bar(
+            2,
+            &bar(Utc.with_ymd_and_hms(2020, 3, 12, 0, 0, 0).unwrap(), 1.0),
+        );
+        tracker.add_bar(
+            2,
+            &bar(Utc.with_ymd_and_hms(2020, 3, 13, 0, 0, 0).unwrap(), 2.0),
+        );
+        tracker.end(2);
+        assert_eq!(
+            2,
+            tracker.wait_for_bars(2, Duration::from_millis(10))?.len()
+        );
+        assert!(!tracker.is_awaited(2));
+
+        tracker.expect(1);
+        assert!(tracker.wait_for_bars(1, Duration::from_millis(10)).is_err());
+
+        tracker.expect(3);
+        tracker.record_error(3, 2106, "HMDS data farm connection is OK");
+        tracker.record_error(3, 162, "Historical Market Data Service error message");
+        match tracker.wait_for_head_timestamp(3, Duration::from_millis(10)) {
+            Err(IBKRApiLibError::ApiError(err)) => assert_eq!("162", err.code),
+            _ => panic!("expected the error sent for the request"),
+        }
+
+        tracker.expect_format_date(4, FORMAT_DATE_EPOCH);
+        assert_eq!(FORMAT_DATE_EPOCH, tracker.format_date(4));
+        assert_eq!(FORMAT_DATE_TEXT, tracker.format_date(5));
+        tracker.forget_format_date(4);
+        assert_eq!(FORMAT_DATE_TEXT, tracker.format_date(4));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_error_classification() {
+        let error = |code: &str, description: &str| {
+            IBKRApiLibError::ApiError(TwsApiReportableError::new(
+                1,
+                code.to_string(),
+                description.to_string(),
+            ))
+        };
+        let no_data = error(
+            "162",
+            "Historical Market Data Service error message:HMDS query returned no data: AAPL@SMART Trades",
+        );
+        let pacing = error(
+            "162",
+            "Historical Market Data Service error message:Historical data request pacing violation",
+        );
+        let permission = error(
+            "162",
+            "Historical Market Data Service error message:No market data permissions for NYSE STK",
+        );
+
+        assert!(is_no_data_error(&no_data));
+        assert!(!is_no_data_error(&pacing));
+        assert!(!is_no_data_error(&permission));
+        assert!(!is_no_data_error(&error(
+            "200",
+            "HMDS query returned no data"
+        )));
+
+        assert!(is_pacing_violation(&pacing));
+        assert!(is_retryable(&pacing));
+        assert!(is_retryable(&IBKRApiLibError::RecvTimeoutError(
+            RecvTimeoutError::Timeout
+        )));
+        assert!(!is_retryable(&no_data));
+        assert!(!is_retryable(&permission));
+        assert!(!is_retryable(&error(
+            "366",
+            "No historical data query found for ticker id"
+        )));
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::core::common::{MarketDataTypeEnum, TickAttrib, TickType};
+    use crate::core::generic_tick::Shortability;
+    use crate::core::market_data::{HaltStatus, MarketDataCache, OptionComputation};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticker_folds_ticks() {
+        let cache = MarketDataCache::new();
+        let attrib = TickAttrib::new(true, false, false);
+
+        cache.update_market_data_type(1, 1);
+        cache.update_price(1, TickType::Bid, 100.25, &attrib);
+        cache.update_size(1, TickType::BidSize, 300);
+        cache.update_price(1, TickType::Ask, 100.5, &attrib);
+        cache.update_price(1, TickType::Last, 100.3, &attrib);
+        cache.update_price(1, TickType::High, 101.0, &attrib);
+        cache.update_size(1, TickType::Volume, 12000);
+        cache.update_generic(1, TickType::Halted, 2.0);
+        cache.update_generic(1, TickType::Shortable, 3.0);
+        cache.update_string(1, TickType::LastTimestamp, "1600000000");
+        cache.update_string(1, TickType::LastExch, "ISLAND");
+        cache.update_generic(1, TickType::OptionImpliedVol, 0.25);
+        cache.update_option_computation(
+            1,
+            TickType::ModelOption,
+            OptionComputation {
+                implied_vol: OptionComputation::computed(0.3, -1.0),
+                delta: OptionComputation::computed(0.5, -2.0),
+                opt_price: OptionComputation::computed(2.1, -1.0),
+                pv_dividend: OptionComputation::computed(-1.0, -1.0),
+                gamma: OptionComputation::computed(-2.0, -2.0),
+                vega: Some(0.1),
+                theta: Some(-0.02),
+                und_price: Some(100.3),
+            },
+        );
+        cache.update_option_computation(
+            1,
+            TickType::BidOptionComputation,
+            OptionComputation {
+                implied_vol: Some(0.28),
+                ..Default::default()
+            },
+        );
+        cache.update_option_computation(
+            1,
+            TickType::AskOptionComputation,
+            OptionComputation {
+                implied_vol: Some(0.32),
+                delta: OptionComputation::computed(f64::MAX, -2.0),
+                ..Default::default()
+            },
+        );
+
+        let ticker = cache.ticker(1).unwrap();
+        assert_eq!(MarketDataTypeEnum::Realtime, ticker.market_data_type);
+        assert_eq!(Some(100.25), ticker.bid);
+        assert_eq!(Some(300), ticker.bid_size);
+        assert!(ticker.bid_attrib.can_auto_execute);
+        assert_eq!(Some(100.375), ticker.midpoint());
+        assert_eq!(Some(100.3), ticker.last);
+        assert_eq!(Some(101.0), ticker.high);
+        assert_eq!(None, ticker.low);
+        assert_eq!(Some(12000), ticker.volume);
+        assert_eq!(Some(HaltStatus::VolatilityHalt), ticker.halted);
+        assert_eq!(Some(Shortability::Available), ticker.shortable);
+        assert_eq!(
+            Some(Utc.timestamp_opt(1600000000, 0).unwrap()),
+            ticker.last_timestamp
+        );
+        assert_eq!(Some("ISLAND".to_string()), ticker.last_exchange);
+        assert_eq!(
+            Some(&0.25),
+            ticker.other_values.get(&TickType::OptionImpliedVol)
+        );
+        let model = ticker.model_option.unwrap();
+        assert_eq!(Some(0.5), model.delta);
+        assert_eq!(Some(2.1), model.opt_price);
+        assert_eq!(None, model.pv_dividend);
+        assert_eq!(None, model.gamma);
+        assert_eq!(Some(0.28), ticker.bid_option.unwrap().implied_vol);
+        assert_eq!(None, ticker.ask_option.unwrap().delta);
+        assert_eq!(Some(0.3), ticker.implied_vol());
+        let mut quoted = ticker.clone();
+        quoted.model_option = None;
+        assert!((quoted.implied_vol().unwrap() - 0.3).abs() < 1e-12);
+        assert_eq!(None, cache.ticker(2).map(|ticker| ticker.req_id));
+
+        // A bid of -1 means that the quote has gone away
+        cache.update_price(1, TickType::Bid, -1.0, &attrib);
+        assert_eq!(None, cache.ticker(1).unwrap().bid);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticker_delayed_ticks() {
+        let cache = MarketDataCache::new();
+        let attrib = TickAttrib::default();
+
+        cache.update_price(4, TickType::DelayedLast, 50.0, &attrib);
+        cache.update_size(4, TickType::DelayedVolume, 10);
+        cache.update_string(4, TickType::DelayedLastTimestamp, "1600000000");
+        let ticker = cache.ticker(4).unwrap();
+        assert_eq!(MarketDataTypeEnum::Delayed, ticker.market_data_type);
+        assert!(ticker.is_delayed());
+        assert_eq!(Some(50.0), ticker.last);
+        assert_eq!(Some(10), ticker.volume);
+        assert!(ticker.last_timestamp.is_some());
+
+        // Switching to frozen data keeps the values but reports the new type
+        cache.update_market_data_type(4, MarketDataTypeEnum::DelayedFrozen as i32);
+        cache.update_price(4, TickType::DelayedClose, 49.0, &attrib);
+        let ticker = cache.ticker(4).unwrap();
+        assert_eq!(MarketDataTypeEnum::DelayedFrozen, ticker.market_data_type);
+        assert_eq!(Some(49.0), ticker.close);
+
+        cache.update_market_data_type(4, MarketDataTypeEnum::Realtime as i32);
+        cache.update_price(4, TickType::Last, 51.0, &attrib);
+        let ticker = cache.ticker(4).unwrap();
+        assert_eq!(MarketDataTypeEnum::Realtime, ticker.market_data_type);
+        assert_eq!(Some(51.0), ticker.last);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_ticker_subscribe() {
+        let cache = MarketDataCache::new();
+        let receiver = cache.subscribe(7);
+
+        cache.update_price(7, TickType::Ask, 10.0, &TickAttrib::default());
+        cache.update_price(8, TickType::Ask, 20.0, &TickAttrib::default());
+        cache.snapshot_end(7);
+
+        let first = receiver.recv().unwrap();
+        assert_eq!(Some(10.0), first.ask);
+        assert!(!first.snapshot_complete);
+        assert!(receiver.recv().unwrap().snapshot_complete);
+        assert!(receiver.try_recv().is_err());
+
+        assert_eq!(7, cache.remove(7).unwrap().req_id);
+        assert!(receiver.recv().is_err());
+        assert_eq!(1, cache.tickers().len());
+    }
+}
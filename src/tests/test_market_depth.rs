@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::market_depth::{
+        DepthRow, DepthSide, DepthSubscription, MarketDepthCache, OrderBook, PriceLevel,
+    };
+
+    const INSERT: i32 = 0;
+    const UPDATE: i32 = 1;
+    const DELETE: i32 = 2;
+    const ASK: i32 = 0;
+    const BID: i32 = 1;
+
+    //------------------------------------------------------------------------------------------------
+    fn row(price: f64, size: i32, market_maker: &str) -> DepthRow {
+        DepthRow::new(price, size, market_maker.to_string())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_book_operations() -> Result<(), IBKRApiLibError> {
+        let mut book = OrderBook::new(1);
+        book.apply(0, INSERT, BID, row(99.0, 100, ""), false)?;
+        book.apply(0, INSERT, BID, row(100.0, 200, ""), false)?;
+        book.apply(0, INSERT, ASK, row(101.0, 300, ""), false)?;
+        book.apply(1, INSERT, ASK, row(102.0, 400, ""), false)?;
+        book.apply(1, UPDATE, BID, row(99.5, 150, ""), false)?;
+        book.apply(1, DELETE, ASK, row(102.0, 400, ""), false)?;
+
+        assert_eq!(vec![row(100.0, 200, ""), row(99.5, 150, "")], book.bids());
+        assert_eq!(vec![row(101.0, 300, "")], book.asks());
+        assert_eq!(
+            Some(PriceLevel {
+                price: 100.0,
+                size: 200,
+                row_count: 1
+            }),
+            book.best_bid()
+        );
+        assert_eq!(Some(101.0), book.best_ask().map(|level| level.price));
+        assert_eq!(Some(1.0), book.spread());
+        assert_eq!(150, book.depth_at_price(DepthSide::Bid, 99.5));
+        assert_eq!(0, book.depth_at_price(DepthSide::Ask, 99.5));
+        // 350 on the bid side against 300 on the ask side
+        assert_eq!(Some(50.0 / 650.0), book.imbalance(5));
+        assert_eq!(Some(-100.0 / 500.0), book.imbalance(1));
+        assert!(!book.invalid);
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_book_market_makers() -> Result<(), IBKRApiLibError> {
+        let mut book = OrderBook::new(2);
+        book.apply(0, INSERT, ASK, row(10.01, 100, "ARCA"), true)?;
+        book.apply(1, INSERT, ASK, row(10.01, 200, "NSDQ"), true)?;
+        book.apply(2, INSERT, ASK, row(10.02, 500, "BATS"), true)?;
+
+        assert!(book.is_smart_depth);
+        assert_eq!(300, book.depth_at_price(DepthSide::Ask, 10.01));
+        let market_makers: Vec<&str> = book
+            .market_makers_at(DepthSide::Ask, 10.01)
+            .iter()
+            .map(|row| row.market_maker.as_str())
+            .collect();
+        assert_eq!(vec!["ARCA", "NSDQ"], market_makers);
+        assert_eq!(
+            vec![
+                PriceLevel {
+                    price: 10.01,
+                    size: 300,
+                    row_count: 2
+                },
+                PriceLevel {
+                    price: 10.02,
+                    size: 500,
+                    row_count: 1
+                },
+            ],
+            book.levels(DepthSide::Ask)
+        );
+        assert_eq!(None, book.best_bid());
+        assert_eq!(Some(-1.0), book.imbalance(10));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_book_invalid_operations() {
+        let cache = MarketDepthCache::new();
+        cache.subscribe(
+            3,
+            DepthSubscription {
+                num_rows: 5,
+                ..Default::default()
+            },
+        );
+        cache.subscribe(4, DepthSubscription::default());
+
+        assert!(cache
+            .apply(3, 0, INSERT, BID, row(5.0, 1, ""), false)
+            .is_ok());
+        assert!(cache
+            .apply(4, 0, INSERT, ASK, row(6.0, 1, ""), false)
+            .is_ok());
+        // Deleting a row that does not exist
+        assert!(cache
+            .apply(3, 3, DELETE, BID, row(5.0, 1, ""), false)
+            .is_err());
+        assert!(cache.apply(4, 0, 7, ASK, row(6.0, 1, ""), false).is_err());
+        // An invalid book ignores the updates that follow
+        assert!(cache.apply(4, 0, INSERT, 2, row(6.0, 1, ""), false).is_ok());
+        assert!(cache
+            .apply(4, 0, INSERT, ASK, row(6.0, 1, ""), false)
+            .is_ok());
+        assert!(cache.order_book(4).unwrap().asks().is_empty());
+
+        let book = cache.order_book(3).unwrap();
+        assert!(book.invalid);
+        assert!(book.bids().is_empty());
+        assert_eq!(vec![3, 4], cache.invalid_books());
+        assert_eq!(5, cache.subscription(3).unwrap().num_rows);
+
+        cache.subscribe(3, cache.subscription(3).unwrap());
+        assert_eq!(vec![4], cache.invalid_books());
+        // Updates still in flight from the cancelled request are ignored until the first row
+        assert!(cache
+            .apply(3, 1, DELETE, BID, row(5.0, 1, ""), false)
+            .is_ok());
+        assert!(cache
+            .apply(3, 0, UPDATE, ASK, row(6.0, 1, ""), false)
+            .is_ok());
+        assert!(cache.order_book(3).unwrap().awaiting_first_row);
+        assert!(cache
+            .apply(3, 0, INSERT, BID, row(5.5, 2, ""), false)
+            .is_ok());
+        let book = cache.order_book(3).unwrap();
+        assert!(!book.awaiting_first_row && !book.invalid);
+        assert_eq!(vec![row(5.5, 2, "")], book.bids());
+        assert!(cache.remove(4).is_some());
+        assert!(cache.invalid_books().is_empty());
+        assert_eq!(None, cache.snapshot(4));
+        assert_eq!(Some(3), cache.snapshot(3).map(|snapshot| snapshot.req_id));
+    }
+}
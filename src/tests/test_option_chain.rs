@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::core::contract::ContractDetails;
+    use crate::core::option_chain::{
+        OptionChain, OptionChainParameters, OptionChainStore, OptionRight,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    fn parameters(
+        exchange: &str,
+        trading_class: &str,
+        expirations: &[&str],
+    ) -> OptionChainParameters {
+        OptionChainParameters::new(
+            exchange,
+            416904,
+            trading_class,
+            "100",
+            expirations.iter().map(|expiration| expiration.to_string()),
+            vec![4100.0, 3900.0, 4000.0, 4200.0],
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn spx_chain(store: &OptionChainStore) -> OptionChain {
+        store.expect_chain(OptionChain::new(7, "SPX", "IND", 416904));
+        store.add_parameters(7, parameters("CBOE", "SPX", &["20240119", "20240216"]));
+        store.add_parameters(7, parameters("SMART", "SPX", &["20240216", "20240119"]));
+        store.add_parameters(7, parameters("SMART", "SPXW", &["20240105", "20240112"]));
+        store.end(7)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_chain() {
+        let store = OptionChainStore::new();
+        let receiver = store.subscribe(7);
+        let chain = spx_chain(&store);
+        assert!(receiver.recv().unwrap().complete);
+        assert!(store.subscribe(7).try_recv().is_ok());
+
+        assert_eq!(
+            chain.exchanges().into_iter().collect::<Vec<_>>(),
+            ["CBOE", "SMART"]
+        );
+        assert_eq!(
+            chain.expirations(),
+            ["20240105", "20240112", "20240119", "20240216"]
+        );
+        assert_eq!(chain.strikes(), [3900.0, 4000.0, 4100.0, 4200.0]);
+
+        let contract = chain
+            .contract("SPX", "20240119", 4000.0, OptionRight::Put)
+            .unwrap();
+        assert_eq!(contract.symbol, "SPX");
+        assert_eq!(contract.sec_type, "OPT");
+        assert_eq!(contract.exchange, "SMART");
+        assert_eq!(contract.right, "P");
+        assert_eq!(contract.multiplier, "100");
+        assert!(chain
+            .contract("SPX", "20240105", 4000.0, OptionRight::Put)
+            .is_err());
+        assert!(chain
+            .contract("SPXW", "20240105", 4050.0, OptionRight::Call)
+            .is_err());
+
+        let today = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let contracts = chain
+            .select()
+            .right(OptionRight::Call)
+            .days_to_expiration(today, 0, 14)
+            .moneyness(4000.0, 0.99, 1.03)
+            .contracts();
+        let selected: Vec<(&str, &str, f64)> = contracts
+            .iter()
+            .map(|contract| {
+                (
+                    contract.trading_class.as_str(),
+                    contract.last_trade_date_or_contract_month.as_str(),
+                    contract.strike,
+                )
+            })
+            .collect();
+        assert_eq!(
+            selected,
+            [
+                ("SPXW", "20240105", 4000.0),
+                ("SPXW", "20240105", 4100.0),
+                ("SPXW", "20240112", 4000.0),
+                ("SPXW", "20240112", 4100.0),
+            ]
+        );
+
+        let contracts = chain
+            .select()
+            .trading_class("SPX")
+            .delta(
+                |contract| Some((4100.0 - contract.strike) / 400.0),
+                0.2,
+                0.5,
+            )
+            .contracts();
+        assert_eq!(contracts.len(), 2 * 2 * 2);
+        assert!(contracts
+            .iter()
+            .all(|contract| contract.strike == 3900.0 || contract.strike == 4000.0));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_con_id_resolution() {
+        let store = OptionChainStore::new();
+        let chain = spx_chain(&store);
+        let contract = chain
+            .contract("SPXW", "20240112", 4100.0, OptionRight::Call)
+            .unwrap();
+        assert_eq!(store.con_id(&contract), None);
+
+        let mut details = ContractDetails::default();
+        details.contract = contract.clone();
+        details.contract.last_trade_date_or_contract_month =
+            "20240112 16:00 US/Central".to_string();
+        details.contract.right = "C".to_string();
+        details.contract.con_id = 678_523_190;
+        // Details of requests not resolving con_ids are ignored
+        store.add_contract_details(3, &details);
+        assert_eq!(store.con_id(&contract), None);
+
+        store.expect_con_id(4);
+        store.add_contract_details(4, &details);
+        store.forget_con_id(4);
+        assert_eq!(store.resolved(&contract).unwrap().con_id, 678_523_190);
+    }
+}
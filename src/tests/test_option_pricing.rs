@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::core::option_chain::OptionRight;
+    use crate::core::option_pricing::{
+        normal_cdf, years_to_expiration, OptionPricer, PricingModel,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not {} within {}",
+            actual,
+            expected,
+            tolerance
+        );
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_black_scholes() {
+        assert_eq!(normal_cdf(0.0), 0.5);
+        assert_close(normal_cdf(1.96), 0.975_002_104_851_78, 1e-14);
+        assert_close(normal_cdf(-8.0), 6.220_960_574_271_78e-16, 1e-23);
+
+        // Hull, Options, Futures and Other Derivatives, example 15.6
+        let call = OptionPricer::new(
+            PricingModel::BlackScholes,
+            OptionRight::Call,
+            42.0,
+            40.0,
+            0.5,
+            0.1,
+        );
+        let put = OptionPricer {
+            right: OptionRight::Put,
+            ..call.clone()
+        };
+        assert_close(call.price(0.2), 4.7594, 1e-4);
+        assert_close(put.price(0.2), 0.8086, 1e-4);
+
+        let greeks = call.greeks(0.2);
+        let bumped = |price: f64| OptionPricer {
+            underlying_price: price,
+            ..call.clone()
+        };
+        let h = 0.01;
+        assert_close(
+            greeks.delta,
+            (bumped(42.0 + h).price(0.2) - bumped(42.0 - h).price(0.2)) / (2.0 * h),
+            1e-6,
+        );
+        assert_close(
+            greeks.gamma,
+            (bumped(42.0 + h).price(0.2) - 2.0 * greeks.price + bumped(42.0 - h).price(0.2))
+                / (h * h),
+            1e-4,
+        );
+        assert_close(
+            greeks.vega,
+            (call.price(0.21) - call.price(0.19)) / 2.0,
+            1e-4,
+        );
+        assert!(greeks.theta < 0.0);
+        assert!(greeks.rho > 0.0);
+        assert_close(greeks.delta - put.greeks(0.2).delta, 1.0, 1e-12);
+
+        // Put-call parity with a yield and a cash dividend
+        let call = call.dividend_yield(0.01).dividend(0.25, 0.5);
+        let put = OptionPricer {
+            right: OptionRight::Put,
+            ..call.clone()
+        };
+        let parity =
+            (42.0 - call.pv_dividend()) * (-0.01f64 * 0.5).exp() - 40.0 * (-0.1f64 * 0.5).exp();
+        assert_close(call.price(0.3) - put.price(0.3), parity, 1e-10);
+        assert_close(call.pv_dividend(), 0.5 * (-0.1f64 * 0.25).exp(), 1e-12);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_black_76_and_implied_volatility() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let years = years_to_expiration(today, "20240502").unwrap();
+        assert_close(years, 121.0 / 365.0, 1e-12);
+
+        // Hull, example 18.7: a put on a future at 20 with 4 months to expiration
+        let put = OptionPricer::new(
+            PricingModel::Black76,
+            OptionRight::Put,
+            20.0,
+            20.0,
+            4.0 / 12.0,
+            0.09,
+        );
+        assert_close(put.price(0.25), 1.1166, 1e-4);
+
+        let computation = put.implied_option_computation(1.1166).unwrap();
+        assert_close(computation.implied_vol.unwrap(), 0.25, 1e-4);
+        assert_eq!(computation.opt_price, Some(1.1166));
+        assert_eq!(computation.und_price, Some(20.0));
+        assert_eq!(computation.pv_dividend, Some(0.0));
+        assert!(computation.delta.unwrap() < 0.0);
+
+        for volatility in [0.05, 0.4, 1.5] {
+            let price = put.price(volatility);
+            assert_close(put.implied_volatility(price).unwrap(), volatility, 1e-8);
+        }
+        assert!(put.implied_volatility(20.0).is_err());
+
+        let expired = OptionPricer {
+            time_to_expiration: 0.0,
+            strike: 21.0,
+            ..put.clone()
+        };
+        let greeks = expired.greeks(0.25);
+        assert_eq!(greeks.price, 1.0);
+        assert_eq!(greeks.delta, -1.0);
+        assert_eq!(greeks.theta, 0.0);
+    }
+}
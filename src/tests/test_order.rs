@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::contract::Contract;
+    use crate::core::errors::{IBKRApiLibError, TwsError};
+    use crate::core::order::{Order, OrderState, OrderStatus};
+    use crate::core::order_condition::{MarginCondition, OrderConditionEnum};
+    use crate::core::order_tracker::{OrderTracker, FINISHED_ORDERS_KEPT};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_status_from_str() {
+        assert_eq!(OrderStatus::PreSubmitted, OrderStatus::from("PreSubmitted"));
+        assert_eq!(OrderStatus::ApiCancelled, OrderStatus::from("ApiCancelled"));
+        assert_eq!(
+            OrderStatus::Unknown("ApiPending".to_string()),
+            OrderStatus::from("ApiPending")
+        );
+        assert_eq!("Filled", OrderStatus::Filled.to_string());
+        assert_eq!("ApiPending", OrderStatus::from("ApiPending").to_string());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_status_transitions() {
+        assert!(OrderStatus::PendingSubmit.can_transition_to(&OrderStatus::Submitted));
+        assert!(OrderStatus::PreSubmitted.can_transition_to(&OrderStatus::Submitted));
+        assert!(OrderStatus::Submitted.can_transition_to(&OrderStatus::Filled));
+        assert!(OrderStatus::Submitted.can_transition_to(&OrderStatus::Submitted));
+        assert!(OrderStatus::PendingCancel.can_transition_to(&OrderStatus::Cancelled));
+        assert!(OrderStatus::Inactive.can_transition_to(&OrderStatus::Submitted));
+
+        assert!(!OrderStatus::Filled.can_transition_to(&OrderStatus::Submitted));
+        assert!(!OrderStatus::Cancelled.can_transition_to(&OrderStatus::PendingCancel));
+        assert!(!OrderStatus::Submitted.can_transition_to(&OrderStatus::PreSubmitted));
+        assert!(!OrderStatus::Submitted.can_transition_to(&OrderStatus::PendingSubmit));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_tracker_rejects_out_of_order_status() -> Result<(), IBKRApiLibError> {
+        let tracker = OrderTracker::new();
+        let mut order_state = OrderState::default();
+        order_state.status = OrderStatus::Submitted;
+        tracker.update_open_order(1, &Contract::default(), &Order::default(), &order_state)?;
+
+        assert!(tracker.update_status(1, &OrderStatus::Filled).is_ok());
+        match tracker.update_status(1, &OrderStatus::Submitted) {
+            Err(IBKRApiLibError::ApiError(err)) => {
+                assert_eq!(1, err.req_id);
+                assert_eq!(TwsError::OutOfOrderStatus.code().to_string(), err.code);
+            }
+            _ => panic!("a status after Filled should be rejected"),
+        }
+        assert_eq!(Some(OrderStatus::Filled), tracker.order_status(1));
+
+        // A stale status does not replace the current one
+        assert!(tracker.update_status(2, &OrderStatus::Submitted).is_ok());
+        assert!(tracker
+            .update_status(2, &OrderStatus::PreSubmitted)
+            .is_err());
+        assert_eq!(Some(OrderStatus::Submitted), tracker.order_status(2));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_order_tracker_drops_finished_orders() -> Result<(), IBKRApiLibError> {
+        let tracker = OrderTracker::new();
+        let mut order_state = OrderState::default();
+        order_state.status = OrderStatus::Submitted;
+        tracker.update_open_order(1, &Contract::default(), &Order::default(), &order_state)?;
+        tracker.update_open_order(2, &Contract::default(), &Order::default(), &order_state)?;
+        assert_eq!(
+            OrderStatus::Submitted,
+            tracker.snapshot(1).unwrap().order_state.status
+        );
+
+        assert!(tracker.update_status(1, &OrderStatus::Filled).is_ok());
+        assert!(tracker.snapshot(1).is_none());
+
+        // An open_order echo of the cancellation does not bring the snapshot back
+        order_state.status = OrderStatus::Cancelled;
+        tracker.update_open_order(2, &Contract::default(), &Order::default(), &order_state)?;
+        assert!(tracker.snapshot(2).is_none());
+        assert_eq!(Some(OrderStatus::Cancelled), tracker.order_status(2));
+
+        // A stale open_order is reported, and neither its status nor its snapshot is kept
+        order_state.status = OrderStatus::Submitted;
+        assert!(tracker
+            .update_open_order(2, &Contract::default(), &Order::default(), &order_state)
+            .is_err());
+        assert!(tracker.snapshot(2).is_none());
+        assert_eq!(Some(OrderStatus::Cancelled), tracker.order_status(2));
+
+        // Only the statuses of the last finished orders are kept
+        for order_id in 3..FINISHED_ORDERS_KEPT as i32 + 2 {
+            tracker.update_status(order_id, &OrderStatus::Filled)?;
+        }
+        assert_eq!(None, tracker.order_status(1));
+        assert_eq!(Some(OrderStatus::Cancelled), tracker.order_status(2));
+        tracker.update_status(FINISHED_ORDERS_KEPT as i32 + 2, &OrderStatus::Filled)?;
+        assert_eq!(None, tracker.order_status(2));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_changed_fields() -> Result<(), IBKRApiLibError> {
+        let order = Order::default();
+        assert!(order.changed_fields(&order.clone())?.is_empty());
+
+        let mut modified = order.clone();
+        modified.lmt_price = 10.0;
+        modified.use_price_mgmt_algo = true;
+        modified
+            .conditions
+            .push(OrderConditionEnum::Margin(MarginCondition::default()));
+        assert_eq!(
+            vec!["conditions", "lmt_price", "use_price_mgmt_algo"],
+            order.changed_fields(&modified)?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use num_traits::FromPrimitive;
+
+    use crate::core::decoder::decode_i32;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::messages::{make_field, read_fields};
+    use crate::core::order_condition::{
+        create_condition, execution, margin, percent_change, price, time, volume, Condition,
+        OrderConditionEnum, TimeCondition, TriggerMethod,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    /// Encodes conditions the way place_order does and decodes them the way the open order
+    /// decoder does
+    fn round_trip(
+        conditions: &[OrderConditionEnum],
+    ) -> Result<Vec<OrderConditionEnum>, IBKRApiLibError> {
+        let mut msg = String::new();
+        for condition in conditions {
+            msg.push_str(&make_field(&(condition.get_type() as i32))?);
+            msg.push_str(&condition.make_fields()?.concat());
+        }
+        let fields = read_fields(&msg);
+        let mut fields_iter = fields.iter();
+
+        let mut decoded = vec![];
+        for _ in 0..conditions.len() {
+            let condition_type = FromPrimitive::from_i32(decode_i32(&mut fields_iter)?).unwrap();
+            let mut condition = create_condition(condition_type);
+            condition.decode(&mut fields_iter)?;
+            decoded.push(condition);
+        }
+        assert_eq!(None, fields_iter.next());
+        Ok(decoded)
+    }
+
+    //------------------------------------------------------------------------------------------------
+    /// Small deterministic generator so that the property tests are reproducible
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        fn price(&mut self) -> f64 {
+            self.below(1_000_000) as f64 / 100.0
+        }
+
+        fn flag(&mut self) -> bool {
+            self.below(2) == 1
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn random_condition(rng: &mut Lcg) -> OrderConditionEnum {
+        let con_id = rng.below(1_000_000_000) as i32;
+        let exchange = ["SMART", "ISLAND", "IDEALPRO", ""][rng.below(4) as usize];
+        let is_more = rng.flag();
+        let mut condition: OrderConditionEnum = match rng.below(6) {
+            0 => {
+                let trigger_method = FromPrimitive::from_u64(rng.below(9)).unwrap();
+                let builder = price(con_id, exchange).trigger_method(trigger_method);
+                let value = rng.price();
+                if is_more {
+                    builder.above(value).into()
+                } else {
+                    builder.below(value).into()
+                }
+            }
+            1 => {
+                let instant = Utc
+                    .timestamp_opt(rng.below(4_000_000_000) as i64, 0)
+                    .unwrap();
+                match rng.below(3) {
+                    0 => time().after(instant).into(),
+                    1 => TimeCondition::new(is_more, instant.naive_utc(), None).into(),
+                    _ => TimeCondition::new(
+                        is_more,
+                        instant.naive_utc(),
+                        Some(chrono_tz::US::Eastern),
+                    )
+                    .into(),
+                }
+            }
+            2 => margin().above(rng.below(100) as f64).into(),
+            3 => execution("EUR.USD", "CASH", exchange).into(),
+            4 => volume(con_id, exchange)
+                .below(rng.below(i32::MAX as u64) as i32)
+                .into(),
+            _ => percent_change(con_id, exchange).above(rng.price()).into(),
+        };
+        condition.order_condition_mut().is_conjunction_connection = rng.flag();
+        condition
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_condition_builder() {
+        let after = Utc.with_ymd_and_hms(2021, 3, 4, 14, 30, 0).unwrap();
+        let conditions: Vec<OrderConditionEnum> = price(265598, "SMART")
+            .above(101.5)
+            .and(time().after(after))
+            .or(volume(265598, "SMART").below(1000))
+            .into();
+
+        assert_eq!(3, conditions.len());
+        match &conditions[0] {
+            OrderConditionEnum::Price(price) => {
+                assert_eq!(101.5, price.price);
+                assert_eq!(265598, price.contract_condition.con_id);
+                assert_eq!("SMART", price.contract_condition.exchange);
+                assert!(price.contract_condition.operator_condition.is_more);
+                assert!(price.trigger_method == TriggerMethod::Default);
+            }
+            other => panic!("expected a price condition, got {:?}", other),
+        }
+        assert!(conditions[0].order_condition().is_conjunction_connection);
+        assert!(!conditions[1].order_condition().is_conjunction_connection);
+        assert_eq!("20210304-14:30:00", conditions[1].value_to_string());
+        match &conditions[2] {
+            OrderConditionEnum::Volume(volume) => {
+                assert_eq!(1000, volume.volume);
+                assert!(!volume.contract_condition.operator_condition.is_more);
+            }
+            other => panic!("expected a volume condition, got {:?}", other),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_condition_wire_format() -> Result<(), IBKRApiLibError> {
+        let condition: OrderConditionEnum = price(265598, "SMART")
+            .trigger_method(TriggerMethod::Last)
+            .below(101.5)
+            .into();
+        assert_eq!(
+            vec!["o", "0", "101.5", "265598", "SMART", "2"],
+            read_fields(&condition.make_fields()?.concat())
+        );
+
+        let condition: OrderConditionEnum = TimeCondition::new(
+            true,
+            NaiveDate::from_ymd_opt(2016, 1, 18)
+                .unwrap()
+                .and_hms_opt(23, 59, 59)
+                .unwrap(),
+            Some(chrono_tz::US::Eastern),
+        )
+        .into();
+        assert_eq!(
+            vec!["o", "1", "20160118 23:59:59 US/Eastern"],
+            read_fields(&condition.make_fields()?.concat())
+        );
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_condition_round_trip() -> Result<(), IBKRApiLibError> {
+        let mut rng = Lcg(29);
+        for _ in 0..500 {
+            let conditions: Vec<OrderConditionEnum> = (0..=rng.below(4))
+                .map(|_| random_condition(&mut rng))
+                .collect();
+            assert_eq!(conditions, round_trip(&conditions)?);
+        }
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_time_condition_from_string() -> Result<(), IBKRApiLibError> {
+        let mut condition = TimeCondition::default();
+
+        condition.set_value_from_string("20160118 23:59:59".to_string())?;
+        assert_eq!(None, condition.time_zone);
+        assert_eq!(None, condition.utc_time());
+
+        condition.set_value_from_string("20160118-23:59:59".to_string())?;
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2016, 1, 18, 23, 59, 59).unwrap()),
+            condition.utc_time()
+        );
+
+        condition.set_value_from_string("20160118 23:59:59 Europe/London".to_string())?;
+        assert_eq!(Some(chrono_tz::Europe::London), condition.time_zone);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2016, 1, 18, 23, 59, 59).unwrap()),
+            condition.utc_time()
+        );
+        assert_eq!("20160118 23:59:59 Europe/London", condition.to_string());
+
+        assert!(condition
+            .set_value_from_string("20160118 23:59:59 Mars/Olympus".to_string())
+            .is_err());
+
+        assert!(condition
+            .set_value_from_string("2016-01-18".to_string())
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::account::AccountPosition;
+    use crate::core::execution::Execution;
+    use crate::core::pnl::{CostMethod, PnlEngine, PnlKey};
+    use crate::tests::helpers::{commission, contract, execution};
+
+    //------------------------------------------------------------------------------------------------
+    fn fill(exec_id: &str, order_ref: &str, side: &str, shares: f64, price: f64) -> Execution {
+        let mut execution = execution(exec_id, side, shares, price);
+        execution.order_ref = order_ref.to_string();
+        execution
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn trade(engine: &mut PnlEngine) {
+        let stock = contract(1, "AAA");
+        engine.add_execution(&stock, &fill("e1", "momentum", "BOT", 100.0, 10.0));
+        engine.add_execution(&stock, &fill("e2", "momentum", "BOT", 100.0, 12.0));
+        engine.add_execution(&stock, &fill("e3", "momentum", "SLD", 150.0, 13.0));
+        // Received again, such as after a reconnection
+        engine.add_execution(&stock, &fill("e3", "momentum", "SLD", 150.0, 13.0));
+        for exec_id in ["e1", "e2", "e3"] {
+            engine.add_commission_report(&commission(exec_id, 1.0));
+        }
+        engine.update_mark(1, 14.0);
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_cost_methods() {
+        let key = PnlKey::new("DU1", 1, "momentum");
+
+        let mut fifo = PnlEngine::new(CostMethod::Fifo);
+        trade(&mut fifo);
+        let pnl = fifo.pnl(&key).unwrap();
+        assert_eq!(pnl.position, 50.0);
+        assert_eq!(pnl.average_cost, 12.0);
+        // 100 * (13 - 10) + 50 * (13 - 12) - 3
+        assert_eq!(pnl.realized, 347.0);
+        assert_eq!(pnl.unrealized, Some(100.0));
+        assert_eq!(pnl.daily, Some(447.0));
+        assert_eq!(pnl.market_value, Some(700.0));
+
+        let mut average = PnlEngine::new(CostMethod::Average);
+        trade(&mut average);
+        let pnl = average.pnl(&key).unwrap();
+        assert_eq!(pnl.average_cost, 11.0);
+        assert_eq!(pnl.realized, 297.0);
+        assert_eq!(pnl.unrealized, Some(150.0));
+        assert_eq!(pnl.daily, Some(447.0));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_opening_positions_and_rollups() {
+        let mut engine = PnlEngine::new(CostMethod::Fifo);
+        let mut future = contract(2, "ES");
+        future.sec_type = "FUT".to_string();
+        future.multiplier = "50".to_string();
+        engine.add_opening_position(PnlKey::new("DU1", 2, ""), &future, -2.0, 3000.0);
+        engine.add_execution(&future, &fill("f1", "hedge", "BOT", 1.0, 2990.0));
+        // The commission report arrives before its execution
+        engine.add_commission_report(&commission("f2", 2.5));
+        engine.add_execution(&future, &fill("f2", "hedge", "BOT", 1.0, 2995.0));
+        assert_eq!(
+            engine.pnl(&PnlKey::new("DU1", 2, "hedge")).unwrap().daily,
+            None
+        );
+
+        engine.set_previous_close(2, 2985.0);
+        engine.update_mark(2, 2980.0);
+        // The hedge closed the short 2 opened at 3000, at 2990 and 2995
+        let single = engine.pnl_single("DU1", 2).unwrap();
+        assert_eq!(single.position, 0.0);
+        assert_eq!(single.realized, 500.0 + 250.0 - 2.5);
+        assert_eq!(single.unrealized, Some(0.0));
+        // Short 2 from 2985 to 2980, then bought at 2990 and 2995
+        assert_eq!(single.daily, Some(500.0 - 500.0 - 750.0 - 2.5));
+
+        // The lots closed are those of the opening position
+        let by_ref = engine.by_order_ref();
+        assert_eq!(by_ref.len(), 2);
+        assert_eq!(by_ref[""].position, 0.0);
+        assert_eq!(by_ref[""].realized, 750.0);
+        assert_eq!(by_ref[""].daily, Some(-750.0));
+        assert_eq!(by_ref["hedge"].position, 0.0);
+        assert_eq!(by_ref["hedge"].realized, -2.5);
+        assert_eq!(by_ref["hedge"].daily, Some(-2.5));
+        assert_eq!(engine.by_account()["DU1"].position, 0.0);
+
+        let mut tws = AccountPosition::default();
+        tws.daily_pnl = Some(-752.0);
+        tws.unrealized_pnl = Some(0.0);
+        tws.realized_pnl = Some(747.5);
+        let comparison = single.compare(&tws);
+        assert_eq!(comparison.daily, Some(-0.5));
+        assert_eq!(comparison.unrealized, Some(0.0));
+        assert_eq!(comparison.realized, Some(0.0));
+        assert!(comparison.within(1.0));
+        assert!(!comparison.within(0.1));
+
+        engine.roll_day();
+        let single = engine.pnl_single("DU1", 2).unwrap();
+        assert_eq!(single.daily, Some(0.0));
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_shared_book_and_corrections() {
+        let mut engine = PnlEngine::new(CostMethod::Fifo);
+        let stock = contract(3, "AAA");
+        engine.add_execution(
+            &stock,
+            &fill("0000e0d5.5f0ed1a4.01.01", "a", "BOT", 100.0, 10.0),
+        );
+        engine.add_commission_report(&commission("0000e0d5.5f0ed1a4.01.01", 1.0));
+        // A correction of the price, then a replay of the corrected execution
+        engine.add_execution(
+            &stock,
+            &fill("0000e0d5.5f0ed1a4.01.02", "a", "BOT", 100.0, 10.5),
+        );
+        engine.add_execution(
+            &stock,
+            &fill("0000e0d5.5f0ed1a4.01.01", "a", "BOT", 100.0, 10.0),
+        );
+        engine.add_commission_report(&commission("0000e0d5.5f0ed1a4.01.02", 1.25));
+        // Another order ref sells part of the position
+        engine.add_execution(
+            &stock,
+            &fill("0000e0d5.5f0ed1a5.01.01", "b", "SLD", 60.0, 12.0),
+        );
+        engine.update_mark(3, 11.0);
+
+        let single = engine.pnl_single("DU1", 3).unwrap();
+        assert_eq!(single.position, 40.0);
+        assert_eq!(single.average_cost, 10.5);
+        assert_eq!(single.commissions, 1.25);
+        assert_eq!(single.realized, 60.0 * 1.5 - 1.25);
+        assert_eq!(single.unrealized, Some(40.0 * 0.5));
+
+        let a = engine.pnl(&PnlKey::new("DU1", 3, "a")).unwrap();
+        assert_eq!(a.position, 40.0);
+        assert_eq!(a.realized, 90.0 - 1.25);
+        assert_eq!(a.unrealized, Some(20.0));
+        let b = engine.pnl(&PnlKey::new("DU1", 3, "b")).unwrap();
+        assert_eq!(b.position, 0.0);
+        assert_eq!(b.realized, 0.0);
+        assert_eq!(b.unrealized, Some(0.0));
+        assert_eq!(a.daily.unwrap() + b.daily.unwrap(), single.daily.unwrap());
+        assert!(engine.pnl(&PnlKey::new("DU1", 3, "c")).is_none());
+    }
+}
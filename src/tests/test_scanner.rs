@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod tests {
+    use crate::core::common::{UNSET_DOUBLE, UNSET_INTEGER};
+    use crate::core::contract::ContractDetails;
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::scanner::{
+        ScanData, ScannerCache, ScannerEvent, ScannerParameters, ScannerSession,
+        ScannerSubscription,
+    };
+
+    const SCANNER_PARAMETERS: &str = include_str!("fixtures/scanner_parameters.xml");
+
+    //------------------------------------------------------------------------------------------------
+    fn row(rank: i32, con_id: i32, symbol: &str) -> ScanData {
+        let mut contract = ContractDetails::default();
+        contract.contract.con_id = con_id;
+        contract.contract.symbol = symbol.to_string();
+        ScanData::new(
+            contract,
+            rank,
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+        )
+    }
+
+    //------------------------------------------------------------------------------------------------
+    fn error_message(result: Result<impl std::fmt::Debug, IBKRApiLibError>) -> String {
+        match result {
+            Err(IBKRApiLibError::ApiError(err)) => err.description,
+            other => panic!("expected an api error, got {:?}", other),
+        }
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_scanner_parameters() -> Result<(), IBKRApiLibError> {
+        let parameters = ScannerParameters::parse(SCANNER_PARAMETERS)?;
+        assert_eq!(3, parameters.instruments.len());
+        assert_eq!(
+            vec!["PRICE", "VOLUME"],
+            parameters.instrument("STOCK.EU").unwrap().filters
+        );
+        assert_eq!(6, parameters.locations.len());
+        assert_eq!(
+            "NASDAQ",
+            parameters.location("STK.NASDAQ").unwrap().display_name
+        );
+        assert_eq!(3, parameters.scan_types.len());
+        assert_eq!(
+            vec!["STK", "STOCK.EU"],
+            parameters.scan_type("TOP_PERC_GAIN").unwrap().instruments
+        );
+
+        let price = parameters.filter_for_tag("priceBelow").unwrap();
+        assert_eq!("PRICE", price.id);
+        assert_eq!("Price", price.category);
+        assert_eq!(2, price.fields.len());
+        assert_eq!("scanner.filter.DoubleField", price.fields[0].field_type);
+        assert!(parameters.filter_for_tag("priceAround").is_none());
+
+        let mut futures_scans: Vec<&str> = parameters
+            .scan_types_for("FUT.US")
+            .map(|scan_type| scan_type.code.as_str())
+            .collect();
+        futures_scans.sort_unstable();
+        assert_eq!(vec!["HOT_BY_VOLUME"], futures_scans);
+        assert_eq!(2, parameters.locations_for("STOCK.EU").count());
+
+        assert!(ScannerParameters::parse("<ReportSnapshot/>").is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_scanner_subscription_builder() -> Result<(), IBKRApiLibError> {
+        let parameters = ScannerParameters::parse(SCANNER_PARAMETERS)?;
+
+        let request = ScannerSubscription::builder("STK", "STK.US.MAJOR", "TOP_PERC_GAIN")
+            .number_of_rows(20)
+            .filter("priceAbove", 5)
+            .filter("marketCapAbove1e6", 1000.5)
+            .build(&parameters)?;
+        assert_eq!(20, request.subscription.number_of_rows);
+        assert_eq!("STK.US.MAJOR", request.subscription.location_code);
+        assert_eq!(UNSET_DOUBLE, request.subscription.above_price);
+        assert_eq!(UNSET_INTEGER, request.subscription.above_volume);
+        assert_eq!(2, request.filter_options.len());
+        assert_eq!("marketCapAbove1e6", request.filter_options[1].tag);
+        assert_eq!("1000.5", request.filter_options[1].value);
+
+        let unset_rows =
+            ScannerSubscription::builder("FUT.US", "FUT.US", "HOT_BY_VOLUME").build(&parameters)?;
+        assert_eq!(UNSET_INTEGER, unset_rows.subscription.number_of_rows);
+
+        let unknown_scan = ScannerSubscription::builder("STK", "STK.US.MAJOR", "TOP_PERC_GAINERS")
+            .build(&parameters);
+        assert!(error_message(unknown_scan).contains("unknown scan code TOP_PERC_GAINERS"));
+
+        let wrong_instrument =
+            ScannerSubscription::builder("FUT.US", "FUT.US", "TOP_PERC_GAIN").build(&parameters);
+        assert!(error_message(wrong_instrument)
+            .contains("scan code TOP_PERC_GAIN does not apply to FUT.US instruments"));
+
+        let wrong_location =
+            ScannerSubscription::builder("STK", "STK.EU.IBIS", "TOP_PERC_GAIN").build(&parameters);
+        assert!(error_message(wrong_location).contains("location STK.EU.IBIS has no STK"));
+
+        let unknown_tag = ScannerSubscription::builder("STK", "STK.US", "TOP_PERC_GAIN")
+            .filter("priceAround", 5)
+            .build(&parameters);
+        assert!(error_message(unknown_tag).contains("unknown filter tag priceAround"));
+
+        let unavailable_tag =
+            ScannerSubscription::builder("STOCK.EU", "STK.EU.IBIS", "TOP_PERC_GAIN")
+                .filter("avgOptVolumeAbove", 1000)
+                .build(&parameters);
+        assert!(error_message(unavailable_tag)
+            .contains("filter tag avgOptVolumeAbove is not available for STOCK.EU instruments"));
+
+        let too_many_rows = ScannerSubscription::builder("STK", "STK.US", "TOP_PERC_GAIN")
+            .number_of_rows(51)
+            .build(&parameters);
+        assert!(error_message(too_many_rows).contains("number of rows 51"));
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_scanner_session() {
+        let mut session = ScannerSession::new(7001);
+        session.add_row(row(1, 11, "BBB"));
+        session.add_row(row(0, 10, "AAA"));
+        let first = session.end();
+        assert_eq!(
+            vec![0, 1],
+            first
+                .rows
+                .iter()
+                .map(|data| data.rank)
+                .collect::<Vec<i32>>()
+        );
+        assert_eq!(2, first.entered().len());
+        assert_eq!("AAA", first.entered()[0].contract.contract.symbol);
+
+        session.add_row(row(0, 11, "BBB"));
+        session.add_row(row(1, 12, "CCC"));
+        session.add_row(row(2, 10, "AAA"));
+        let second = session.end();
+        assert_eq!(2, session.refreshes());
+        assert_eq!(3, session.results().len());
+        assert_eq!(3, second.events.len());
+        match &second.events[0] {
+            ScannerEvent::Moved {
+                previous_rank,
+                rank,
+                data,
+            } => {
+                assert_eq!((1, 0), (*previous_rank, *rank));
+                assert_eq!("BBB", data.contract.contract.symbol);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        let entered = second.entered();
+        assert_eq!(1, entered.len());
+        assert_eq!("CCC", entered[0].contract.contract.symbol);
+        assert!(matches!(
+            &second.events[2],
+            ScannerEvent::Moved {
+                previous_rank: 0,
+                rank: 2,
+                ..
+            }
+        ));
+
+        session.add_row(row(0, 12, "CCC"));
+        let third = session.end();
+        assert_eq!(3, third.events.len());
+        assert!(matches!(
+            &third.events[0],
+            ScannerEvent::Moved {
+                previous_rank: 1,
+                rank: 0,
+                ..
+            }
+        ));
+        let exited: Vec<(i32, &str)> = third
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                ScannerEvent::Exited {
+                    previous_rank,
+                    data,
+                } => Some((*previous_rank, data.contract.contract.symbol.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![(0, "BBB"), (2, "AAA")], exited);
+
+        session.add_row(row(0, 12, "CCC"));
+        let unchanged = session.end();
+        assert!(unchanged.events.is_empty());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_scanner_cache() {
+        let cache = ScannerCache::new();
+        cache.reset(7002);
+        let refreshes = cache.subscribe(7002);
+
+        cache.add_row(7002, row(0, 10, "AAA"));
+        assert!(cache.results(7002).unwrap().is_empty());
+        cache.end(7002);
+        let refresh = refreshes.try_recv().unwrap();
+        assert_eq!(7002, refresh.req_id);
+        assert_eq!(1, refresh.entered().len());
+        assert_eq!(1, cache.results(7002).unwrap().len());
+
+        cache.add_row(7002, row(0, 10, "AAA"));
+        cache.end(7002);
+        assert!(refreshes.try_recv().unwrap().events.is_empty());
+
+        assert_eq!(2, cache.remove(7002).unwrap().refreshes());
+        assert!(refreshes.recv().is_err());
+        assert!(cache.session(7002).is_none());
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::timestamps::{
+        connection_time_zone, format_optional_tws_time, format_tws_time, parse_tws_time,
+        FORMAT_DATE_EPOCH, FORMAT_DATE_TEXT,
+    };
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_parse_tws_time() -> Result<(), IBKRApiLibError> {
+        let new_york = Tz::America__New_York;
+        let expected = Utc.with_ymd_and_hms(2020, 3, 12, 14, 0, 0).unwrap();
+
+        let text = |text: &str| parse_tws_time(text, FORMAT_DATE_TEXT, &new_york);
+        let epoch = |text: &str| parse_tws_time(text, FORMAT_DATE_EPOCH, &new_york);
+
+        assert_eq!(expected, epoch("1584021600")?);
+        assert_eq!(expected, text("20200312  10:00:00")?);
+        assert_eq!(expected, text("20200312 10:00:00")?);
+        assert_eq!(expected, text("20200312 15:00:00 Europe/Berlin")?);
+        assert_eq!(expected, text("20200312-14:00:00")?);
+        // Daily bars are sent as dates whatever the format_date
+        let day = Utc.with_ymd_and_hms(2020, 3, 12, 4, 0, 0).unwrap();
+        assert_eq!(day, text("20200312")?);
+        assert_eq!(day, epoch("20200312")?);
+
+        // Seconds are only read as such when requested
+        assert!(text("1584021600").is_err());
+        assert!(epoch("20200312  10:00:00").is_err());
+        assert!(text("").is_err());
+        assert!(epoch("").is_err());
+        assert!(text("2020-03-12").is_err());
+        assert!(text("20200312 10:00:00 Mars/Olympus").is_err());
+        Ok(())
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_format_tws_time() -> Result<(), IBKRApiLibError> {
+        let time = Utc.with_ymd_and_hms(2020, 3, 12, 14, 0, 0).unwrap();
+        assert_eq!("20200312-14:00:00", format_tws_time(&time));
+        assert_eq!("20200312-14:00:00", format_optional_tws_time(&Some(time)));
+        assert_eq!("", format_optional_tws_time(&None));
+
+        assert_eq!(
+            Tz::America__New_York,
+            connection_time_zone("20200312 10:00:00 EST")?
+        );
+        assert_eq!(
+            Tz::America__Chicago,
+            connection_time_zone("20200312 09:00:00 Central Standard Time")?
+        );
+        assert!(connection_time_zone("20200312 10:00:00").is_err());
+        Ok(())
+    }
+}
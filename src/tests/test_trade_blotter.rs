@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::core::errors::IBKRApiLibError;
+    use crate::core::trade_blotter::{fills_to_csv, fills_to_json, TradeBlotter};
+    use crate::tests::helpers::{commission, contract, executed_at, execution};
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_corrections_and_replays() {
+        let blotter = TradeBlotter::new();
+        // The commission report of the second fill arrives first
+        blotter.add_commission_report(commission("0000e0d5.5f0ed1a5.01.01", 0.5));
+        blotter.add_execution(
+            contract(1, "AAA"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a4.01.01", "BOT", 100.0, 10.0),
+                15,
+                30,
+            ),
+        );
+        blotter.add_execution(
+            contract(2, "BBB"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a5.01.01", "BOT", 50.0, 20.0),
+                14,
+                30,
+            ),
+        );
+        blotter.add_commission_report(commission("0000e0d5.5f0ed1a4.01.01", 1.0));
+
+        // A correction of the first fill, then a replay of the original after a reconnection
+        blotter.add_execution(
+            contract(1, "AAA"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a4.01.02", "BOT", 100.0, 10.5),
+                15,
+                30,
+            ),
+        );
+        blotter.add_execution(
+            contract(1, "AAA"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a4.01.01", "BOT", 100.0, 10.0),
+                15,
+                30,
+            ),
+        );
+        blotter.add_execution(
+            contract(2, "BBB"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a5.01.01", "BOT", 50.0, 20.0),
+                14,
+                30,
+            ),
+        );
+
+        let fills = blotter.fills();
+        assert_eq!(2, fills.len());
+        assert_eq!("BBB", fills[0].contract.symbol);
+        assert_eq!(Some(0.5), fills[0].commission());
+        assert_eq!(None, fills[0].realized_pnl());
+
+        let corrected = &fills[1];
+        assert_eq!("0000e0d5.5f0ed1a4.01.02", corrected.execution.exec_id);
+        assert_eq!(10.5, corrected.execution.price);
+        assert_eq!(
+            vec!["0000e0d5.5f0ed1a4.01.01".to_string()],
+            corrected.corrected_exec_ids
+        );
+        // The commission of the corrected execution does not apply to the correction
+        assert_eq!(None, corrected.commission());
+        blotter.add_commission_report(commission("0000e0d5.5f0ed1a4.01.02", 1.25));
+        assert_eq!(
+            Some(1.25),
+            blotter
+                .fill("0000e0d5.5f0ed1a4.01.01")
+                .unwrap()
+                .commission()
+        );
+
+        let new_york = chrono_tz::America::New_York;
+        assert_eq!(
+            2,
+            blotter
+                .fills_on(NaiveDate::from_ymd_opt(2020, 3, 2).unwrap(), &new_york)
+                .len()
+        );
+        assert!(blotter
+            .fills_on(NaiveDate::from_ymd_opt(2020, 3, 1).unwrap(), &new_york)
+            .is_empty());
+        blotter.clear();
+        assert!(blotter.fills().is_empty());
+    }
+
+    //------------------------------------------------------------------------------------------------
+    #[test]
+    fn test_export() -> Result<(), IBKRApiLibError> {
+        let blotter = TradeBlotter::new();
+        blotter.add_execution(
+            contract(1, "AAA"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a4.01.01", "BOT", 100.0, 10.0),
+                15,
+                30,
+            ),
+        );
+        blotter.add_execution(
+            contract(2, "B,B"),
+            executed_at(
+                execution("0000e0d5.5f0ed1a5.01.01", "BOT", 50.0, 20.0),
+                16,
+                30,
+            ),
+        );
+        blotter.add_commission_report(commission("0000e0d5.5f0ed1a4.01.01", 1.0));
+        let fills = blotter.fills();
+
+        let csv = fills_to_csv(&fills)?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(3, lines.len());
+        assert!(lines[0].starts_with("time,account,model_code,exec_id,"));
+        assert!(
+            lines[0].ends_with(",commission,commission_currency,realized_pnl,corrected_exec_ids")
+        );
+        assert!(lines[1].starts_with("2020-03-02T15:30:00Z,DU1,,0000e0d5.5f0ed1a4.01.01,"));
+        assert!(lines[1].ends_with(",1.0,USD,,"));
+        assert!(lines[2].contains(",\"B,B\",STK,"));
+
+        let json = fills_to_json(&fills)?;
+        assert!(json.contains("\"exec_id\": \"0000e0d5.5f0ed1a5.01.01\""));
+        assert!(json.contains("\"symbol\": \"AAA\""));
+        assert!(json.contains("\"commission_report\": null"));
+        Ok(())
+    }
+}